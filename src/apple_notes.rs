@@ -0,0 +1,410 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::{DateTime, Local};
+use regex::Regex;
+
+use crate::{dry_run, frontmatter, import, log};
+
+/// Folder Notes.app keeps deleted notes in; never imported.
+const DELETED_FOLDER: &str = "Recently Deleted";
+
+/// JXA script printing every note as fields separated by U+001F and notes
+/// separated by U+001E; a note's folder path is joined with U+001D.
+const EXPORT_SCRIPT: &str = r#"
+const Notes = Application("Notes");
+const out = [];
+Notes.accounts().forEach(account => {
+  account.folders().forEach(folder => {
+    const path = [folder.name()];
+    let parent = folder.container();
+    while (parent.class() === "folder") {
+      path.unshift(parent.name());
+      parent = parent.container();
+    }
+    folder.notes().forEach(note => {
+      out.push([path.join("\u001d"), note.name(), note.creationDate().toISOString(),
+        note.modificationDate().toISOString(), note.body()].join("\u001f"));
+    });
+  });
+});
+out.join("\u001e");
+"#;
+
+/// A note pulled from Apple Notes, with its body still in HTML.
+#[derive(Debug, PartialEq)]
+pub struct Note {
+    pub folders: Vec<String>,
+    pub title: String,
+    pub html: String,
+    pub created: Option<DateTime<Local>>,
+    pub modified: Option<DateTime<Local>>,
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Local))
+}
+
+fn parse_export(output: &str) -> Vec<Note> {
+    output
+        .trim_end_matches('\n')
+        .split('\u{1e}')
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.splitn(5, '\u{1f}').collect();
+            let [folders, title, created, modified, html] = fields[..] else {
+                return None;
+            };
+            Some(Note {
+                folders: folders.split('\u{1d}').map(str::to_string).collect(),
+                title: title.to_string(),
+                html: html.to_string(),
+                created: parse_time(created),
+                modified: parse_time(modified),
+            })
+        })
+        .filter(|note| note.folders.first().is_none_or(|f| f != DELETED_FOLDER))
+        .collect()
+}
+
+/// Every note in Notes.app, read through `osascript`. macOS asks for
+/// permission to control Notes the first time.
+pub fn from_notes_app() -> Result<Vec<Note>, String> {
+    if !cfg!(target_os = "macos") {
+        return Err("reading Notes.app needs macOS; import exported HTML with --html".to_string());
+    }
+    log::debug!("osascript -l JavaScript (export Notes.app)");
+    let output = process::Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", EXPORT_SCRIPT])
+        .output()
+        .map_err(|e| format!("failed to run osascript: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("osascript failed: {}", stderr.trim()));
+    }
+    Ok(parse_export(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn collect_html(root: &Path, dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("failed to read {}: {e}", dir.display()))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let ext = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        if path.is_dir() {
+            collect_html(root, &path, out)?;
+        } else if matches!(ext.as_deref(), Some("html" | "htm")) {
+            out.push(path.strip_prefix(root).unwrap().to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// Notes from a tree of exported `.html` files, one per note, with
+/// directories as folders. The title comes from `<title>` or the file name.
+pub fn from_html_dir(dir: &Path) -> Result<Vec<Note>, String> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()));
+    }
+    let mut files = Vec::new();
+    collect_html(dir, dir, &mut files)?;
+    files.sort();
+
+    let title_tag = Regex::new(r"(?is)<title>(.*?)</title>").expect("valid regex");
+    let mut notes = Vec::new();
+    for rel in files {
+        let path = dir.join(&rel);
+        let html = fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+        let title = match title_tag.captures(&html) {
+            Some(caps) if !caps[1].trim().is_empty() => decode_entities(caps[1].trim()),
+            _ => rel
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        };
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        notes.push(Note {
+            folders: rel
+                .parent()
+                .into_iter()
+                .flatten()
+                .map(|c| c.to_string_lossy().into_owned())
+                .collect(),
+            title,
+            html,
+            created: None,
+            modified: modified.map(DateTime::from),
+        });
+    }
+    Ok(notes)
+}
+
+fn decode_entities(text: &str) -> String {
+    let entity = Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").expect("valid regex");
+    entity
+        .replace_all(text, |caps: &regex::Captures| {
+            let name = &caps[1];
+            let decoded = match name {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ if name.starts_with("#x") || name.starts_with("#X") => {
+                    u32::from_str_radix(&name[2..], 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                }
+                _ if name.starts_with('#') => name[1..].parse().ok().and_then(char::from_u32),
+                _ => None,
+            };
+            decoded.map_or(caps[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+/// Convert the HTML Notes.app produces (divs per line, headings, bold and
+/// italics, lists and links) to markdown. Anything else is reduced to its
+/// text; embedded images are dropped.
+pub fn to_markdown(html: &str) -> String {
+    let token = Regex::new(r"(?s)<(/?)([a-zA-Z][a-zA-Z0-9]*)([^>]*)>|<!--.*?-->|([^<]+)")
+        .expect("valid regex");
+    let href = Regex::new(r#"(?i)href\s*=\s*["']([^"']*)["']"#).expect("valid regex");
+
+    let mut out = String::new();
+    let mut lists: Vec<(bool, usize)> = Vec::new();
+    let mut links: Vec<String> = Vec::new();
+    let mut skipping: Option<String> = None;
+    let new_line = |out: &mut String| {
+        if !out.is_empty() && !out.ends_with('\n') {
+            out.push('\n');
+        }
+    };
+    let blank_line = |out: &mut String| {
+        new_line(out);
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+    };
+
+    for caps in token.captures_iter(html) {
+        if let Some(text) = caps.get(4) {
+            if skipping.is_none() {
+                let text = text.as_str().replace(['\n', '\r'], " ");
+                let at_line_start = out.is_empty() || out.ends_with('\n');
+                match at_line_start {
+                    true => out.push_str(decode_entities(&text).trim_start()),
+                    false => out.push_str(&decode_entities(&text)),
+                }
+            }
+            continue;
+        }
+        let Some(name) = caps.get(2) else { continue };
+        let name = name.as_str().to_lowercase();
+        let closing = &caps[1] == "/";
+        if let Some(skipped) = &skipping {
+            if closing && *skipped == name {
+                skipping = None;
+            }
+            continue;
+        }
+        match (name.as_str(), closing) {
+            ("head" | "style" | "script" | "title", false) => skipping = Some(name),
+            ("br", _) => out.push('\n'),
+            ("div" | "p" | "tr", false) => new_line(&mut out),
+            ("div" | "tr", true) => new_line(&mut out),
+            ("p", true) => blank_line(&mut out),
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                blank_line(&mut out);
+                let level = name[1..].parse().unwrap_or(1);
+                out.push_str(&"#".repeat(level));
+                out.push(' ');
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => blank_line(&mut out),
+            ("b" | "strong", _) => out.push_str("**"),
+            ("i" | "em", _) => out.push('*'),
+            ("strike" | "s" | "del", _) => out.push_str("~~"),
+            ("tt" | "code", _) => out.push('`'),
+            ("td", true) => out.push(' '),
+            ("a", false) => {
+                let target = href.captures(&caps[3]).map(|c| decode_entities(&c[1]));
+                links.push(target.unwrap_or_default());
+                out.push('[');
+            }
+            ("a", true) => {
+                let target = links.pop().unwrap_or_default();
+                out.push_str(&format!("]({target})"));
+            }
+            ("ul" | "ol", false) => {
+                new_line(&mut out);
+                lists.push((name == "ol", 0));
+            }
+            ("ul" | "ol", true) => {
+                lists.pop();
+                match lists.is_empty() {
+                    true => blank_line(&mut out),
+                    false => new_line(&mut out),
+                }
+            }
+            ("li", false) => {
+                new_line(&mut out);
+                let depth = lists.len().saturating_sub(1);
+                out.push_str(&"  ".repeat(depth));
+                match lists.last_mut() {
+                    Some((true, n)) => {
+                        *n += 1;
+                        out.push_str(&format!("{n}. "));
+                    }
+                    _ => out.push_str("- "),
+                }
+            }
+            ("li", true) => new_line(&mut out),
+            _ => {}
+        }
+    }
+
+    let lines: Vec<&str> = out.lines().map(str::trim_end).collect();
+    let mut markdown = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if line.is_empty() && (i == 0 || lines[i - 1].is_empty()) {
+            continue;
+        }
+        markdown.push_str(line);
+        markdown.push('\n');
+    }
+    markdown.trim().to_string()
+}
+
+/// `markdown` without its first line when that line just repeats the title,
+/// as Notes.app bodies do.
+fn without_title_line(markdown: &str, title: &str) -> String {
+    let (first, rest) = markdown.split_once('\n').unwrap_or((markdown, ""));
+    let first = first
+        .trim_start_matches('#')
+        .trim()
+        .trim_matches('*')
+        .trim();
+    match first == title.trim() {
+        true => rest.trim_start_matches('\n').to_string(),
+        false => markdown.to_string(),
+    }
+}
+
+/// Write `notes` as markdown under `into`, one directory per folder. Notes
+/// never overwrite anything: a name that's taken is numbered instead.
+/// Returns the paths written, relative to the notes dir.
+pub fn import(notes: &[Note], notes_dir: &Path, into: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut taken = HashSet::new();
+    let mut written = Vec::new();
+    for note in notes {
+        let mut dir = into.to_path_buf();
+        for folder in &note.folders {
+            dir.push(import::normalize_name(folder));
+        }
+        let name = format!("{}.md", import::normalize_name(&note.title));
+        let rel = import::unique_path(notes_dir, dir.join(name), &mut taken);
+
+        let title = match note.title.trim().is_empty() {
+            true => "Untitled",
+            false => note.title.trim(),
+        };
+        let body = without_title_line(&to_markdown(&note.html), title);
+        let mut content = match body.is_empty() {
+            true => format!("# {title}\n"),
+            false => format!("# {title}\n\n{body}\n"),
+        };
+        if let Some(created) = note.created {
+            content =
+                frontmatter::set(&content, "created", &created.format("%Y-%m-%d").to_string());
+        }
+
+        if dry_run::skip(format_args!("import {title} as {}", rel.display())) {
+            written.push(rel);
+            continue;
+        }
+        let path = notes_dir.join(&rel);
+        fs::create_dir_all(path.parent().unwrap())
+            .map_err(|e| format!("failed to create {}: {e}", path.display()))?;
+        fs::write(&path, content).map_err(|e| format!("failed to write {}: {e}", rel.display()))?;
+        if let Some(modified) = note.modified {
+            let _ = fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|f| f.set_modified(modified.into()));
+        }
+        log::debug!("imported {title} as {}", rel.display());
+        written.push(rel);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_to_markdown() {
+        let html = "<div><h1>Trip &amp; Plans</h1></div>\n<div>Pack <b>light</b>, see <a href=\"https://example.com/?a=1&amp;b=2\">the guide</a><br></div>\n<div><br></div>\n<ul>\n<li>Passport</li>\n<li>Tickets<ol><li>Train</li><li>Ferry</li></ol></li>\n</ul>\n<div><i>done</i></div>";
+        assert_eq!(
+            to_markdown(html),
+            "# Trip & Plans\n\nPack **light**, see [the guide](https://example.com/?a=1&b=2)\n\n- Passport\n- Tickets\n  1. Train\n  2. Ferry\n\n*done*"
+        );
+        assert_eq!(
+            to_markdown(
+                "<html><head><title>x</title><style>p{}</style></head><body><p>One</p><p>Two &#8212; &#x41;</p></body></html>"
+            ),
+            "One\n\nTwo — A"
+        );
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let output = "Work\u{1d}Acme\u{1f}Kickoff\u{1f}2024-03-01T09:00:00.000Z\u{1f}2024-03-02T10:00:00.000Z\u{1f}<div>Kickoff</div>\u{1e}Recently Deleted\u{1f}Old\u{1f}\u{1f}\u{1f}<div>gone</div>\n";
+        let notes = parse_export(output);
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].folders, ["Work", "Acme"]);
+        assert_eq!(notes[0].title, "Kickoff");
+        let created = chrono::Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+        assert_eq!(notes[0].created, Some(created.with_timezone(&Local)));
+    }
+
+    #[test]
+    fn test_import_from_html_dir() {
+        let tmp = TempDir::new().unwrap();
+        let (export, notes) = (tmp.path().join("export"), tmp.path().join("notes"));
+        fs::create_dir_all(export.join("Work Stuff")).unwrap();
+        fs::write(
+            export.join("Work Stuff/Kickoff.html"),
+            "<html><head><title>Kickoff Meeting</title></head><body><div><b>Kickoff Meeting</b></div><div>Agenda</div></body></html>",
+        )
+        .unwrap();
+        fs::write(export.join("Groceries.html"), "<div>Milk</div>").unwrap();
+        fs::create_dir_all(notes.join("apple")).unwrap();
+        fs::write(notes.join("apple/groceries.md"), "mine\n").unwrap();
+
+        let apple = from_html_dir(&export).unwrap();
+        let written = import(&apple, &notes, Path::new("apple")).unwrap();
+        assert_eq!(
+            written,
+            [
+                PathBuf::from("apple/groceries-2.md"),
+                PathBuf::from("apple/work-stuff/kickoff-meeting.md")
+            ]
+        );
+        let read = |rel: &str| fs::read_to_string(notes.join(rel)).unwrap();
+        assert_eq!(read("apple/groceries.md"), "mine\n");
+        assert_eq!(read("apple/groceries-2.md"), "# Groceries\n\nMilk\n");
+        assert_eq!(
+            read("apple/work-stuff/kickoff-meeting.md"),
+            "# Kickoff Meeting\n\nAgenda\n"
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Deserialize;
+
+use crate::vault;
+
+/// Notes under `path` that haven't been modified for `after_days` days get
+/// archived by `kno archive --auto`.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub path: String,
+    pub after_days: u64,
+}
+
+/// Where `note` goes in archive dir `dir`: the same relative path under it.
+pub fn target(dir: &str, note: &Path) -> PathBuf {
+    Path::new(dir).join(note)
+}
+
+/// Notes that `rules` say are due for archiving at `now`, paired with where
+/// they go. Notes already in the archive dir are left alone.
+pub fn due(
+    notes_dir: &Path,
+    dir: &str,
+    rules: &[Rule],
+    now: SystemTime,
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let mut moves = Vec::new();
+    for note in vault::notes(notes_dir) {
+        if note.starts_with(dir) {
+            continue;
+        }
+        let Some(rule) = rules
+            .iter()
+            .find(|rule| note.starts_with(rule.path.trim_end_matches('/')))
+        else {
+            continue;
+        };
+        let Some(modified) = vault::modified(&notes_dir.join(&note)) else {
+            continue;
+        };
+        let age = Duration::from_secs(rule.after_days * 24 * 60 * 60);
+        if now.duration_since(modified).is_ok_and(|idle| idle >= age) {
+            let to = target(dir, &note);
+            if notes_dir.join(&to).exists() {
+                return Err(format!(
+                    "can't archive {}: {} already exists",
+                    note.display(),
+                    to.display()
+                ));
+            }
+            moves.push((note, to));
+        }
+    }
+    Ok(moves)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write(root: &Path, rel: &str, days_ago: u64) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "# x\n").unwrap();
+        let time = SystemTime::now() - Duration::from_secs(days_ago * 24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_due() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "clippings/old.md", 200);
+        write(tmp.path(), "clippings/new.md", 10);
+        write(tmp.path(), "clippingsx/old.md", 200);
+        write(tmp.path(), "sql/joins.md", 400);
+        write(tmp.path(), "archive/clippings/older.md", 900);
+        let rules = [Rule {
+            path: "clippings/".into(),
+            after_days: 180,
+        }];
+
+        let moves = due(tmp.path(), "archive", &rules, SystemTime::now()).unwrap();
+        assert_eq!(
+            moves,
+            [(
+                PathBuf::from("clippings/old.md"),
+                PathBuf::from("archive/clippings/old.md")
+            )]
+        );
+
+        write(tmp.path(), "archive/clippings/old.md", 0);
+        let err = due(tmp.path(), "archive", &rules, SystemTime::now()).unwrap_err();
+        assert!(err.contains("archive/clippings/old.md already exists"));
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{links, vault};
+
+/// Directory inside the notes dir holding attachments.
+pub const ASSETS_DIR_NAME: &str = "assets";
+
+fn collect(notes_dir: &Path, dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        match path.is_dir() {
+            true => collect(notes_dir, &path, out),
+            false => out.push(path.strip_prefix(notes_dir).unwrap().to_path_buf()),
+        }
+    }
+}
+
+/// Every file under `assets/`, relative to the notes dir, sorted.
+pub fn files(notes_dir: &Path) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    collect(notes_dir, &notes_dir.join(ASSETS_DIR_NAME), &mut out);
+    out.sort();
+    out
+}
+
+/// Attachments no note references. A file counts as referenced if a note
+/// links to or embeds it, or mentions its file name anywhere (in raw HTML,
+/// say), so nothing still in use is reported.
+pub fn unused(notes_dir: &Path) -> Vec<PathBuf> {
+    let mut linked = HashSet::new();
+    let mut contents = Vec::new();
+    for rel in vault::notes(notes_dir) {
+        let content = fs::read_to_string(notes_dir.join(&rel)).unwrap_or_default();
+        linked.extend(links::targets(&rel, &content));
+        contents.push(content);
+    }
+
+    files(notes_dir)
+        .into_iter()
+        .filter(|asset| !linked.contains(asset))
+        .filter(|asset| {
+            let name = asset.file_name().unwrap_or_default().to_string_lossy();
+            !contents.iter().any(|c| c.contains(&*name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_unused() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "sql/joins.md", "![er](../assets/er.png)\n");
+        write(tmp.path(), "index.md", "[spec](assets/docs/spec.pdf)\n");
+        write(tmp.path(), "raw.md", "<img src=\"assets/logo.svg\">\n");
+        write(tmp.path(), "assets/er.png", "");
+        write(tmp.path(), "assets/docs/spec.pdf", "");
+        write(tmp.path(), "assets/logo.svg", "");
+        write(tmp.path(), "assets/old/scan.jpg", "");
+        write(tmp.path(), "assets/.DS_Store", "");
+
+        assert_eq!(files(tmp.path()).len(), 4);
+        assert_eq!(unused(tmp.path()), [PathBuf::from("assets/old/scan.jpg")]);
+    }
+
+    #[test]
+    fn test_unused_matches_escaped_links() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "trip.md", "![scan](assets/my%20scan.png)\n");
+        write(tmp.path(), "assets/my scan.png", "");
+        write(tmp.path(), "assets/other scan.png", "");
+        assert_eq!(unused(tmp.path()), [PathBuf::from("assets/other scan.png")]);
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Component, Path, PathBuf};
+use std::process;
+
+use chrono::{DateTime, Local};
+use serde::Deserialize;
+
+use crate::{dry_run, events, log};
+
+const ARCHIVE_PREFIX: &str = "kno-";
+const ARCHIVE_SUFFIX: &str = ".tar.gz";
+
+/// Name of the systemd units / launchd agent installed by `--install-timer`.
+const TIMER_NAME: &str = "kno-backup";
+const LAUNCHD_LABEL: &str = "com.kno.backup";
+
+/// How often the installed timer runs `kno backup`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Schedule {
+    Hourly,
+    #[default]
+    Daily,
+    Weekly,
+}
+
+impl Schedule {
+    fn systemd_calendar(self) -> &'static str {
+        match self {
+            Schedule::Hourly => "hourly",
+            Schedule::Daily => "daily",
+            Schedule::Weekly => "weekly",
+        }
+    }
+
+    fn seconds(self) -> u32 {
+        match self {
+            Schedule::Hourly => 60 * 60,
+            Schedule::Daily => 24 * 60 * 60,
+            Schedule::Weekly => 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// Where archives go when `backup.dir` isn't set: a sibling of the notes dir,
+/// so `~/.kno` is backed up to `~/.kno-backups`.
+pub fn default_dir(notes_dir: &Path) -> PathBuf {
+    let name = notes_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    notes_dir.with_file_name(format!("{name}-backups"))
+}
+
+fn archive_name(now: DateTime<Local>) -> String {
+    format!(
+        "{ARCHIVE_PREFIX}{}{ARCHIVE_SUFFIX}",
+        now.format("%Y%m%d-%H%M%S")
+    )
+}
+
+/// kno archives in `dir`, oldest first.
+pub fn archives(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with(ARCHIVE_PREFIX) && name.ends_with(ARCHIVE_SUFFIX)
+        })
+        .collect();
+    // Timestamped names sort chronologically
+    archives.sort();
+    archives
+}
+
+/// Write a gzipped tarball of the whole notes dir (including `.git`) to `dir`.
+pub fn create(notes_dir: &Path, dir: &Path) -> Result<PathBuf, String> {
+    let archive = dir.join(archive_name(Local::now()));
+    if dry_run::skip(format_args!(
+        "archive {} to {}",
+        notes_dir.display(),
+        archive.display()
+    )) {
+        return Ok(archive);
+    }
+
+    fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+    log::debug!(
+        "tar -czf {} -C {} .",
+        archive.display(),
+        notes_dir.display()
+    );
+    let status = process::Command::new("tar")
+        .arg("-czf")
+        .arg(&archive)
+        .arg("-C")
+        .arg(notes_dir)
+        // `kno events` may be listening; tar can't archive a socket anyway
+        .arg(format!("--exclude=./{}", events::EVENTS_SOCKET_NAME))
+        .arg(".")
+        .status()
+        .map_err(|e| format!("failed to run tar: {e}"))?;
+    match status.success() {
+        true => Ok(archive),
+        false => Err(format!("tar exited with {status}")),
+    }
+}
+
+/// Delete the oldest archives in `dir` so at most `keep` remain. Returns the
+/// archives removed.
+pub fn prune(dir: &Path, keep: usize) -> Vec<PathBuf> {
+    let archives = archives(dir);
+    let excess = archives.len().saturating_sub(keep);
+    let removed = archives[..excess].to_vec();
+    for archive in &removed {
+        if dry_run::skip(format_args!("delete {}", archive.display())) {
+            continue;
+        }
+        fs::remove_file(archive).expect("failed to delete old backup");
+    }
+    removed
+}
+
+/// What a restore changed (or, in dry-run mode, would change) relative to
+/// the existing vault.
+#[derive(Debug, Default, PartialEq)]
+pub struct RestoreSummary {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+    /// Where the replaced vault was moved to, when `--force` replaced one.
+    pub previous: Option<PathBuf>,
+}
+
+/// Content hashes of every file under `dir` except `.git`, by relative path.
+fn snapshot(root: &Path, dir: &Path, out: &mut BTreeMap<PathBuf, u64>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if entry.file_name() != ".git" {
+                snapshot(root, &path, out);
+            }
+            continue;
+        }
+        let mut hasher = DefaultHasher::new();
+        fs::read(&path).unwrap_or_default().hash(&mut hasher);
+        out.insert(
+            path.strip_prefix(root).unwrap().to_path_buf(),
+            hasher.finish(),
+        );
+    }
+}
+
+fn diff(before: &Path, after: &Path) -> RestoreSummary {
+    let (mut old, mut new) = (BTreeMap::new(), BTreeMap::new());
+    snapshot(before, before, &mut old);
+    snapshot(after, after, &mut new);
+
+    let mut summary = RestoreSummary::default();
+    for (path, hash) in &new {
+        match old.get(path) {
+            None => summary.added.push(path.clone()),
+            Some(old_hash) if old_hash != hash => summary.changed.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    summary.removed = old.into_keys().filter(|p| !new.contains_key(p)).collect();
+    summary
+}
+
+/// Reject archives with absolute paths or `..` entries that could write
+/// outside the restore directory.
+fn check_entries(archive: &Path) -> Result<(), String> {
+    let output = process::Command::new("tar")
+        .arg("-tzf")
+        .arg(archive)
+        .output()
+        .map_err(|e| format!("failed to run tar: {e}"))?;
+    if !output.status.success() {
+        return Err(format!("{} is not a readable backup", archive.display()));
+    }
+
+    let listing = String::from_utf8_lossy(&output.stdout);
+    match listing.lines().map(Path::new).find(|entry| {
+        entry.components().any(|c| {
+            matches!(
+                c,
+                Component::RootDir | Component::ParentDir | Component::Prefix(_)
+            )
+        })
+    }) {
+        Some(entry) => Err(format!("unsafe path in archive: {}", entry.display())),
+        None => Ok(()),
+    }
+}
+
+fn is_empty_dir(dir: &Path) -> bool {
+    fs::read_dir(dir).map_or(true, |mut entries| entries.next().is_none())
+}
+
+/// Unpack `archive` into `into`. The archive is extracted next to `into`
+/// first and only swapped in once that succeeded; an existing, non-empty
+/// `into` is refused unless `force`, in which case it's moved aside rather
+/// than deleted. In dry-run mode the summary is computed but nothing changes.
+pub fn restore(archive: &Path, into: &Path, force: bool) -> Result<RestoreSummary, String> {
+    if !archive.is_file() {
+        return Err(format!("{} not found", archive.display()));
+    }
+    if !is_empty_dir(into) && !force {
+        return Err(format!(
+            "{} already exists; use --force to replace it",
+            into.display()
+        ));
+    }
+    check_entries(archive)?;
+
+    let name = into
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let staging = into.with_file_name(format!(".{name}-restore"));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .map_err(|e| format!("failed to clear {}: {e}", staging.display()))?;
+    }
+    fs::create_dir_all(&staging)
+        .map_err(|e| format!("failed to create {}: {e}", staging.display()))?;
+
+    log::debug!("tar -xzf {} -C {}", archive.display(), staging.display());
+    let extracted = process::Command::new("tar")
+        .arg("-xzf")
+        .arg(archive)
+        .arg("-C")
+        .arg(&staging)
+        .status()
+        .map_err(|e| format!("failed to run tar: {e}"))?;
+    if !extracted.success() {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(format!("tar exited with {extracted}"));
+    }
+
+    let mut summary = diff(into, &staging);
+    if dry_run::skip(format_args!(
+        "restore {} into {}",
+        archive.display(),
+        into.display()
+    )) {
+        let _ = fs::remove_dir_all(&staging);
+        return Ok(summary);
+    }
+
+    if into.exists() {
+        match is_empty_dir(into) {
+            true => fs::remove_dir(into),
+            false => {
+                let stamp = Local::now().format("%Y%m%d-%H%M%S");
+                let previous = into.with_file_name(format!("{name}-before-restore-{stamp}"));
+                let moved = fs::rename(into, &previous);
+                summary.previous = Some(previous);
+                moved
+            }
+        }
+        .map_err(|e| format!("failed to move {} aside: {e}", into.display()))?;
+    }
+    fs::rename(&staging, into)
+        .map_err(|e| format!("failed to move restored notes into place: {e}"))?;
+    Ok(summary)
+}
+
+fn systemd_units(exe: &Path, schedule: Schedule) -> (String, String) {
+    let service = format!(
+        "[Unit]\nDescription=Back up kno notes\n\n[Service]\nType=oneshot\nExecStart={} --quiet backup\n",
+        exe.display()
+    );
+    let timer = format!(
+        "[Unit]\nDescription=Back up kno notes {}\n\n[Timer]\nOnCalendar={}\nPersistent=true\n\n[Install]\nWantedBy=timers.target\n",
+        schedule.systemd_calendar(),
+        schedule.systemd_calendar()
+    );
+    (service, timer)
+}
+
+fn launchd_plist(exe: &Path, schedule: Schedule) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LAUNCHD_LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+        <string>--quiet</string>
+        <string>backup</string>
+    </array>
+    <key>StartInterval</key>
+    <integer>{}</integer>
+</dict>
+</plist>
+"#,
+        exe.display(),
+        schedule.seconds()
+    )
+}
+
+fn write_file(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    fs::write(path, content).map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+    log::info!("Wrote {}", path.display());
+    Ok(())
+}
+
+fn run(program: &str, args: &[&str]) -> Result<(), String> {
+    log::debug!("{program} {}", args.join(" "));
+    let status = process::Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| format!("failed to run {program}: {e}"))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!("{program} {} failed", args.join(" "))),
+    }
+}
+
+/// Install and start a systemd user timer (Linux) or launchd agent (macOS)
+/// that runs `kno backup` on `schedule`.
+pub fn install_timer(home: &Path, schedule: Schedule) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("can't find the kno binary: {e}"))?;
+
+    if cfg!(target_os = "macos") {
+        let plist = home
+            .join("Library/LaunchAgents")
+            .join(format!("{LAUNCHD_LABEL}.plist"));
+        if dry_run::skip(format_args!("install launchd agent {}", plist.display())) {
+            return Ok(());
+        }
+        write_file(&plist, &launchd_plist(&exe, schedule))?;
+        let plist = plist.to_string_lossy();
+        // Unload first so re-running picks up a changed schedule
+        let _ = run("launchctl", &["unload", &plist]);
+        return run("launchctl", &["load", &plist]);
+    }
+
+    let units = home.join(".config/systemd/user");
+    if dry_run::skip(format_args!(
+        "install systemd timer {TIMER_NAME}.timer in {}",
+        units.display()
+    )) {
+        return Ok(());
+    }
+    let (service, timer) = systemd_units(&exe, schedule);
+    write_file(&units.join(format!("{TIMER_NAME}.service")), &service)?;
+    write_file(&units.join(format!("{TIMER_NAME}.timer")), &timer)?;
+    run("systemctl", &["--user", "daemon-reload"])?;
+    run(
+        "systemctl",
+        &["--user", "enable", "--now", &format!("{TIMER_NAME}.timer")],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_default_dir_is_sibling() {
+        assert_eq!(
+            default_dir(Path::new("/home/u/.kno")),
+            PathBuf::from("/home/u/.kno-backups")
+        );
+    }
+
+    #[test]
+    fn test_archive_name() {
+        let now = Local.with_ymd_and_hms(2026, 2, 15, 9, 5, 3).unwrap();
+        assert_eq!(archive_name(now), "kno-20260215-090503.tar.gz");
+    }
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        for name in [
+            "kno-20260103-000000.tar.gz",
+            "kno-20260101-000000.tar.gz",
+            "kno-20260102-000000.tar.gz",
+            "notes.txt",
+        ] {
+            fs::write(tmp.path().join(name), "").unwrap();
+        }
+
+        let removed = prune(tmp.path(), 2);
+
+        assert_eq!(removed, [tmp.path().join("kno-20260101-000000.tar.gz")]);
+        let left: Vec<_> = archives(tmp.path())
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            left,
+            ["kno-20260102-000000.tar.gz", "kno-20260103-000000.tar.gz"]
+        );
+        assert!(tmp.path().join("notes.txt").exists());
+    }
+
+    #[test]
+    fn test_create_archive() {
+        let notes = tempfile::TempDir::new().unwrap();
+        let out = tempfile::TempDir::new().unwrap();
+        fs::write(notes.path().join("a.md"), "# A\n").unwrap();
+
+        let archive = create(notes.path(), out.path()).unwrap();
+
+        assert!(archive.is_file());
+        assert_eq!(archives(out.path()), [archive]);
+    }
+
+    fn archive_of(files: &[(&str, &str)]) -> (tempfile::TempDir, PathBuf) {
+        let notes = tempfile::TempDir::new().unwrap();
+        for (rel, content) in files {
+            fs::write(notes.path().join(rel), content).unwrap();
+        }
+        let out = tempfile::TempDir::new().unwrap();
+        let archive = create(notes.path(), out.path()).unwrap();
+        (out, archive)
+    }
+
+    #[test]
+    fn test_restore_into_new_dir() {
+        let (_out, archive) = archive_of(&[("a.md", "# A\n")]);
+        let tmp = tempfile::TempDir::new().unwrap();
+        let into = tmp.path().join("notes");
+
+        let summary = restore(&archive, &into, false).unwrap();
+
+        assert_eq!(summary.added, [PathBuf::from("a.md")]);
+        assert_eq!(fs::read_to_string(into.join("a.md")).unwrap(), "# A\n");
+        assert!(!tmp.path().join(".notes-restore").exists());
+    }
+
+    #[test]
+    fn test_restore_refuses_existing_vault_without_force() {
+        let (_out, archive) = archive_of(&[("a.md", "# A\n"), ("b.md", "# B\n")]);
+        let tmp = tempfile::TempDir::new().unwrap();
+        let into = tmp.path().join("notes");
+        fs::create_dir_all(&into).unwrap();
+        fs::write(into.join("a.md"), "# A edited\n").unwrap();
+        fs::write(into.join("c.md"), "# C\n").unwrap();
+
+        assert!(restore(&archive, &into, false).is_err());
+        assert_eq!(
+            fs::read_to_string(into.join("a.md")).unwrap(),
+            "# A edited\n"
+        );
+
+        let summary = restore(&archive, &into, true).unwrap();
+        assert_eq!(summary.added, [PathBuf::from("b.md")]);
+        assert_eq!(summary.changed, [PathBuf::from("a.md")]);
+        assert_eq!(summary.removed, [PathBuf::from("c.md")]);
+        assert_eq!(fs::read_to_string(into.join("a.md")).unwrap(), "# A\n");
+        let previous = summary.previous.unwrap();
+        assert_eq!(fs::read_to_string(previous.join("c.md")).unwrap(), "# C\n");
+    }
+
+    #[test]
+    fn test_timer_units() {
+        let exe = Path::new("/usr/bin/kno");
+        let (service, timer) = systemd_units(exe, Schedule::Weekly);
+        assert!(service.contains("ExecStart=/usr/bin/kno --quiet backup\n"));
+        assert!(timer.contains("OnCalendar=weekly\n"));
+
+        let plist = launchd_plist(exe, Schedule::Hourly);
+        assert!(plist.contains("<string>/usr/bin/kno</string>"));
+        assert!(plist.contains("<integer>3600</integer>"));
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::commit::git;
+use crate::vault;
+
+/// git's empty tree, the base when the repo has no commits before the window.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Created,
+    Modified,
+}
+
+/// A note created or modified since some point, summarized.
+#[derive(Debug, PartialEq)]
+pub struct Changed {
+    pub path: PathBuf,
+    pub change: Change,
+    pub added: usize,
+    pub removed: usize,
+    /// The heading the first change falls under, if any.
+    pub heading: Option<String>,
+}
+
+/// The last commit made before `start`, or the empty tree if there is none.
+pub fn base_before(notes_dir: &Path, start: DateTime<Local>) -> String {
+    let before = format!("--before={}", start.to_rfc3339());
+    let commit = git(notes_dir, &["rev-list", "-1", &before, "HEAD"]).unwrap_or_default();
+    match commit.trim() {
+        "" => EMPTY_TREE.to_string(),
+        commit => commit.to_string(),
+    }
+}
+
+/// The text of the last heading at or above 1-based `line`, outside code
+/// fences.
+fn heading_above(content: &str, line: usize) -> Option<String> {
+    let mut in_fence = false;
+    let mut heading = None;
+    for text in content.lines().take(line.max(1)) {
+        if text.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        let level = text.chars().take_while(|&c| c == '#').count();
+        if !in_fence && (1..=6).contains(&level) && text[level..].starts_with(' ') {
+            heading = Some(text[level..].trim().to_string());
+        }
+    }
+    heading
+}
+
+/// Notes created or modified between `base` and the working tree, including
+/// uncommitted and untracked ones, in path order. Deleted notes are left out.
+pub fn changes(notes_dir: &Path, base: &str) -> Result<Vec<Changed>, String> {
+    let notes: HashSet<PathBuf> = vault::notes(notes_dir).into_iter().collect();
+    let diff = git(
+        notes_dir,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--no-renames",
+            "--no-color",
+            "-U0",
+            base,
+            "--",
+            "*.md",
+        ],
+    )?;
+
+    let mut found: BTreeMap<PathBuf, Changed> = BTreeMap::new();
+    let mut current: Option<Changed> = None;
+    let mut first_line = None;
+    let mut in_hunk = false;
+    let mut finish = |current: Option<Changed>, first_line: Option<usize>| {
+        if let Some(mut changed) = current.filter(|c| notes.contains(&c.path)) {
+            let content = fs::read_to_string(notes_dir.join(&changed.path)).unwrap_or_default();
+            changed.heading = heading_above(&content, first_line.unwrap_or(1));
+            found.insert(changed.path.clone(), changed);
+        }
+    };
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            finish(current.take(), first_line.take());
+            in_hunk = false;
+            continue;
+        }
+        if let Some(hunk) = line.strip_prefix("@@ ") {
+            in_hunk = true;
+            // `@@ -a,b +c,d @@`: the change starts at line c of the new file
+            let start = hunk
+                .split_whitespace()
+                .find_map(|part| part.strip_prefix('+'))
+                .and_then(|range| range.split(',').next()?.parse().ok());
+            first_line = first_line.or(start);
+            continue;
+        }
+        match (in_hunk, current.as_mut()) {
+            (false, _) if line.starts_with("new file mode") => {
+                current = Some(Changed {
+                    path: PathBuf::new(),
+                    change: Change::Created,
+                    added: 0,
+                    removed: 0,
+                    heading: None,
+                });
+            }
+            (false, _) if line.starts_with("+++ ") => {
+                let Some(path) = line.strip_prefix("+++ b/") else {
+                    // `+++ /dev/null`: deleted
+                    current = None;
+                    continue;
+                };
+                let change = current.as_ref().map_or(Change::Modified, |c| c.change);
+                current = Some(Changed {
+                    path: PathBuf::from(path),
+                    change,
+                    added: 0,
+                    removed: 0,
+                    heading: None,
+                });
+            }
+            (true, Some(changed)) if line.starts_with('+') => changed.added += 1,
+            (true, Some(changed)) if line.starts_with('-') => changed.removed += 1,
+            _ => {}
+        }
+    }
+    finish(current.take(), first_line.take());
+
+    let untracked = git(
+        notes_dir,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "ls-files",
+            "--others",
+            "--exclude-standard",
+            "--",
+            "*.md",
+        ],
+    )?;
+    for path in untracked.lines().map(PathBuf::from) {
+        if !notes.contains(&path) {
+            continue;
+        }
+        let content = fs::read_to_string(notes_dir.join(&path)).unwrap_or_default();
+        found.insert(
+            path.clone(),
+            Changed {
+                path,
+                change: Change::Created,
+                added: content.lines().count(),
+                removed: 0,
+                heading: heading_above(&content, 1),
+            },
+        );
+    }
+    Ok(found.into_values().collect())
+}
+
+/// One aligned line per note: `modified  sql/joins.md  +12 -3  Inner joins`.
+pub fn format(changes: &[Changed]) -> String {
+    let width = changes
+        .iter()
+        .map(|c| c.path.display().to_string().chars().count())
+        .max()
+        .unwrap_or(0);
+    let stats: Vec<String> = changes
+        .iter()
+        .map(|c| format!("+{} -{}", c.added, c.removed))
+        .collect();
+    let stats_width = stats.iter().map(String::len).max().unwrap_or(0);
+    changes
+        .iter()
+        .zip(&stats)
+        .map(|(c, stat)| {
+            let change = match c.change {
+                Change::Created => "created",
+                Change::Modified => "modified",
+            };
+            let path = c.path.display().to_string();
+            let line = format!(
+                "{change:<8}  {path:<width$}  {stat:<stats_width$}  {}",
+                c.heading.as_deref().unwrap_or_default()
+            );
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit;
+
+    #[test]
+    fn test_heading_above() {
+        let content = "# Joins\n\nintro\n\n## Inner\n\n```\n# not a heading\n```\ntext\n";
+        assert_eq!(heading_above(content, 3).as_deref(), Some("Joins"));
+        assert_eq!(heading_above(content, 10).as_deref(), Some("Inner"));
+        assert_eq!(heading_above("no headings\n", 1), None);
+    }
+
+    #[test]
+    fn test_changes_since_base() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path();
+        git(dir, &["init", "-q"]).unwrap();
+        git(dir, &["config", "user.name", "t"]).unwrap();
+        git(dir, &["config", "user.email", "t@t"]).unwrap();
+        fs::create_dir_all(dir.join("sql")).unwrap();
+        fs::write(
+            dir.join("sql/joins.md"),
+            "# Joins\n\nintro\n\n## Inner\n\nold\n",
+        )
+        .unwrap();
+        fs::write(dir.join("old.md"), "# Old\n").unwrap();
+        fs::write(dir.join("gone.md"), "# Gone\n").unwrap();
+        commit::commit_all(dir, "first").unwrap();
+        let base = git(dir, &["rev-parse", "HEAD"]).unwrap();
+
+        // A commit in the window plus uncommitted and untracked changes
+        fs::write(dir.join("committed.md"), "# Committed\n\ntext\n").unwrap();
+        fs::remove_file(dir.join("gone.md")).unwrap();
+        commit::commit_all(dir, "second").unwrap();
+        fs::write(
+            dir.join("sql/joins.md"),
+            "# Joins\n\nintro\n\n## Inner\n\nnew\nmore\n",
+        )
+        .unwrap();
+        fs::write(dir.join("fresh.md"), "# Fresh\n\nidea\n").unwrap();
+
+        let found = changes(dir, base.trim()).unwrap();
+        assert_eq!(
+            format(&found),
+            "created   committed.md  +3 -0  Committed\n\
+             created   fresh.md      +3 -0  Fresh\n\
+             modified  sql/joins.md  +2 -1  Inner\n"
+        );
+        assert_eq!(changes(dir, "HEAD").unwrap().len(), 2);
+    }
+}
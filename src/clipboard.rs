@@ -0,0 +1,122 @@
+use std::io::Write;
+use std::process;
+
+/// Commands that print the clipboard, in the order to try them on `os`.
+/// Wayland sessions try `wl-paste` before the X11 tools.
+fn commands(os: &str, wayland: bool) -> Vec<&'static [&'static str]> {
+    match os {
+        "macos" => vec![&["pbpaste"]],
+        "windows" => vec![&["powershell", "-NoProfile", "-Command", "Get-Clipboard"]],
+        _ => {
+            let mut cmds: Vec<&'static [&'static str]> = vec![
+                &["xclip", "-selection", "clipboard", "-out"],
+                &["xsel", "--clipboard", "--output"],
+            ];
+            if wayland {
+                cmds.insert(0, &["wl-paste", "--no-newline"]);
+            }
+            cmds
+        }
+    }
+}
+
+/// Commands that set the clipboard from their stdin, in the order to try them.
+fn copy_commands(os: &str, wayland: bool) -> Vec<&'static [&'static str]> {
+    match os {
+        "macos" => vec![&["pbcopy"]],
+        "windows" => vec![&["clip"]],
+        _ => {
+            let mut cmds: Vec<&'static [&'static str]> = vec![
+                &["xclip", "-selection", "clipboard", "-in"],
+                &["xsel", "--clipboard", "--input"],
+            ];
+            if wayland {
+                cmds.insert(0, &["wl-copy"]);
+            }
+            cmds
+        }
+    }
+}
+
+/// The clipboard's text, from the first clipboard tool that's installed.
+pub fn read() -> Result<String, String> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let cmds = commands(std::env::consts::OS, wayland);
+    for cmd in &cmds {
+        crate::log::debug!("running {}", cmd.join(" "));
+        let Ok(output) = process::Command::new(cmd[0]).args(&cmd[1..]).output() else {
+            continue;
+        };
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed: {}",
+                cmd[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let text = String::from_utf8_lossy(&output.stdout);
+        let text = text.trim_end_matches(['\n', '\r']);
+        return match text.trim().is_empty() {
+            true => Err("the clipboard is empty".to_string()),
+            false => Ok(text.to_string()),
+        };
+    }
+
+    let tools: Vec<_> = cmds.iter().map(|cmd| cmd[0]).collect();
+    Err(format!(
+        "no clipboard tool found (tried {})",
+        tools.join(", ")
+    ))
+}
+
+/// Put `text` on the clipboard with the first clipboard tool that's installed.
+pub fn write(text: &str) -> Result<(), String> {
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let cmds = copy_commands(std::env::consts::OS, wayland);
+    for cmd in &cmds {
+        crate::log::debug!("running {}", cmd.join(" "));
+        let Ok(mut child) = process::Command::new(cmd[0])
+            .args(&cmd[1..])
+            .stdin(process::Stdio::piped())
+            .stdout(process::Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| format!("{} failed: {e}", cmd[0]))?;
+        }
+        let status = child
+            .wait()
+            .map_err(|e| format!("{} failed: {e}", cmd[0]))?;
+        return match status.success() {
+            true => Ok(()),
+            false => Err(format!("{} exited with {status}", cmd[0])),
+        };
+    }
+
+    let tools: Vec<_> = cmds.iter().map(|cmd| cmd[0]).collect();
+    Err(format!(
+        "no clipboard tool found (tried {})",
+        tools.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipboard_commands() {
+        assert_eq!(commands("macos", false), [&["pbpaste"][..]]);
+        let linux: Vec<_> = commands("linux", false).iter().map(|c| c[0]).collect();
+        assert_eq!(linux, ["xclip", "xsel"]);
+        let wayland: Vec<_> = commands("linux", true).iter().map(|c| c[0]).collect();
+        assert_eq!(wayland, ["wl-paste", "xclip", "xsel"]);
+        let copy: Vec<_> = copy_commands("linux", true).iter().map(|c| c[0]).collect();
+        assert_eq!(copy, ["wl-copy", "xclip", "xsel"]);
+        assert_eq!(copy_commands("macos", false), [&["pbcopy"][..]]);
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::{DateTime, Local};
+
+use crate::config::Config;
+use crate::cursors::CURSORS_FILE_NAME;
+use crate::remote::STATE_FILE_NAME;
+use crate::urls::URL_CACHE_FILE_NAME;
+use crate::vault::HISTORY_FILE_NAME;
+use crate::{context, template};
+
+/// Changed files named in `{{files}}` before the rest are summarized.
+const FILES_SHOWN: usize = 3;
+
+/// Per-machine state kept in the vault, which is never committed: `.history`
+/// changes whenever a note is opened, so committing it would make every
+/// sync between two machines conflict.
+const LOCAL_FILES: &[&str] = &[
+    HISTORY_FILE_NAME,
+    CURSORS_FILE_NAME,
+    STATE_FILE_NAME,
+    URL_CACHE_FILE_NAME,
+];
+
+/// Output of `git <args>` run in `notes_dir`, or git's error.
+pub fn git(notes_dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = process::Command::new("git")
+        .arg("-C")
+        .arg(notes_dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("could not run git: {e}"))?;
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        false => Err(format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+/// Files with uncommitted changes, including new and deleted ones, sorted.
+pub fn changed(notes_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let args = [
+        "ls-files",
+        "--modified",
+        "--deleted",
+        "--others",
+        "--exclude-standard",
+    ];
+    let mut files: Vec<PathBuf> = git(notes_dir, &args)?.lines().map(PathBuf::from).collect();
+    let staged = git(notes_dir, &["diff", "--cached", "--name-only"]).unwrap_or_default();
+    files.extend(staged.lines().map(PathBuf::from));
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// `a.md, b.md, c.md and 2 more`
+fn summarize(files: &[PathBuf]) -> String {
+    let names: Vec<_> = files
+        .iter()
+        .take(FILES_SHOWN)
+        .map(|f| f.display().to_string())
+        .collect();
+    match files.len().saturating_sub(FILES_SHOWN) {
+        0 => names.join(", "),
+        more => format!("{} and {more} more", names.join(", ")),
+    }
+}
+
+/// A commit message from `template`, which may use `{{files}}`, `{{count}}`
+/// and `{{host}}` besides the date, time and env variables of note templates.
+pub fn message(template: &str, files: &[PathBuf], now: DateTime<Local>, config: &Config) -> String {
+    let files_summary = summarize(files);
+    let count = files.len().to_string();
+    let host = context::hostname().unwrap_or_default();
+    let extra = [
+        ("files", files_summary.as_str()),
+        ("count", count.as_str()),
+        ("host", host.as_str()),
+    ];
+    let vars = template::Vars {
+        title: "",
+        path: "",
+        now,
+        date_format: &config.dates.format,
+        locale: config.dates.locale,
+        extra: &extra,
+    };
+    template::render(template, &vars).content.trim().to_string()
+}
+
+/// Keep the per-machine state files out of git: list them in
+/// `.git/info/exclude` and untrack any committed by an older kno. Returns
+/// whether anything had to change.
+pub fn ignore_local_files(notes_dir: &Path) -> Result<bool, String> {
+    let exclude = notes_dir.join(".git").join("info").join("exclude");
+    let existing = fs::read_to_string(&exclude).unwrap_or_default();
+    let missing: Vec<String> = LOCAL_FILES
+        .iter()
+        .map(|name| format!("/{name}"))
+        .filter(|rule| !existing.lines().any(|l| l.trim() == rule))
+        .collect();
+    if !missing.is_empty() {
+        let separator = match existing.is_empty() || existing.ends_with('\n') {
+            true => "",
+            false => "\n",
+        };
+        fs::create_dir_all(exclude.parent().unwrap())
+            .and_then(|()| {
+                fs::write(
+                    &exclude,
+                    format!("{existing}{separator}{}\n", missing.join("\n")),
+                )
+            })
+            .map_err(|e| format!("failed to write {}: {e}", exclude.display()))?;
+    }
+
+    let mut args = vec!["ls-files", "--"];
+    args.extend(LOCAL_FILES);
+    let tracked = !git(notes_dir, &args)?.trim().is_empty();
+    if tracked {
+        let mut args = vec!["rm", "--cached", "--quiet", "--ignore-unmatch", "--"];
+        args.extend(LOCAL_FILES);
+        git(notes_dir, &args)?;
+    }
+    Ok(!missing.is_empty() || tracked)
+}
+
+/// Stage every change in the vault and commit it with `message`.
+pub fn commit_all(notes_dir: &Path, message: &str) -> Result<(), String> {
+    git(notes_dir, &["add", "--all"])?;
+    git(notes_dir, &["commit", "--quiet", "--message", message])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::fs;
+
+    #[test]
+    fn test_message() {
+        let now = Local.with_ymd_and_hms(2026, 10, 18, 9, 5, 0).unwrap();
+        let config = Config::default();
+        let files: Vec<_> = ["a.md", "b.md", "c.md", "d.md", "e.md"]
+            .map(PathBuf::from)
+            .into();
+
+        assert_eq!(
+            message(
+                "{{files}} ({{count}}) {{date}} {{time}}",
+                &files,
+                now,
+                &config
+            ),
+            "a.md, b.md, c.md and 2 more (5) 2026-10-18 09:05"
+        );
+        assert_eq!(
+            message("Update {{files}}", &files[..1], now, &config),
+            "Update a.md"
+        );
+    }
+
+    #[test]
+    fn test_changed_and_commit() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path();
+        git(dir, &["init", "-q"]).unwrap();
+        git(dir, &["config", "user.name", "t"]).unwrap();
+        git(dir, &["config", "user.email", "t@t"]).unwrap();
+        fs::create_dir_all(dir.join("sql")).unwrap();
+        fs::write(dir.join("sql/joins.md"), "# Joins\n").unwrap();
+        fs::write(dir.join("a.md"), "# A\n").unwrap();
+        assert_eq!(
+            changed(dir).unwrap(),
+            ["a.md", "sql/joins.md"].map(PathBuf::from)
+        );
+
+        commit_all(dir, "first").unwrap();
+        assert!(changed(dir).unwrap().is_empty());
+
+        fs::remove_file(dir.join("a.md")).unwrap();
+        fs::write(dir.join("sql/joins.md"), "# Joins\n\nmore\n").unwrap();
+        assert_eq!(
+            changed(dir).unwrap(),
+            ["a.md", "sql/joins.md"].map(PathBuf::from)
+        );
+        let log = git(dir, &["log", "--format=%s"]).unwrap();
+        assert_eq!(log.trim(), "first");
+    }
+}
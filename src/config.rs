@@ -0,0 +1,640 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::{Locale, Weekday};
+use serde::{Deserialize, Deserializer};
+
+use crate::archive::Rule;
+use crate::backup::Schedule;
+use crate::context::Field;
+use crate::daily::Layout;
+use crate::dry_run;
+use crate::export::Profile;
+use crate::week::Numbering;
+
+/// Config file inside the notes dir.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Settings read from `~/.kno/config.toml`. Every field is optional; a missing
+/// file behaves exactly like an empty one.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub daily: DailyConfig,
+    pub append: AppendConfig,
+    pub tasks: TasksConfig,
+    pub notes: NotesConfig,
+    pub share: ShareConfig,
+    pub mail: MailConfig,
+    pub backup: BackupConfig,
+    pub week: WeekConfig,
+    pub dates: DatesConfig,
+    pub capture: CaptureConfig,
+    pub archive: ArchiveConfig,
+    pub goal: GoalConfig,
+    pub git: GitConfig,
+    pub lint: LintConfig,
+    pub locks: LocksConfig,
+    pub remote: RemoteConfig,
+    pub export: ExportConfig,
+    /// Saved `kno search` arguments by name, for `kno search --saved <name>`.
+    pub searches: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DailyConfig {
+    /// Link a new daily note to the previous one, and the previous one back.
+    pub nav_links: bool,
+    /// Directory grouping for new daily notes; `kno migrate daily` moves old ones.
+    pub layout: Layout,
+    /// `## ` sections added to each new daily note (e.g. "Log", "Tasks", "Notes").
+    pub sections: Vec<String>,
+    /// Section that `kno -a` appends to in the daily note, when it has one.
+    pub log_section: String,
+    /// Section that `kno -t` adds tasks to in the daily note, when it has one.
+    pub task_section: String,
+    /// Section that URLs captured with `kno -a <url>` go to, when it has one.
+    pub link_section: String,
+    /// Section that `kno habit done` marks habits in, when it has one.
+    pub habit_section: String,
+}
+
+impl Default for DailyConfig {
+    fn default() -> DailyConfig {
+        DailyConfig {
+            nav_links: false,
+            layout: Layout::default(),
+            sections: Vec::new(),
+            log_section: "Log".to_string(),
+            task_section: "Tasks".to_string(),
+            link_section: "Links".to_string(),
+            habit_section: "Habits".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppendConfig {
+    /// Create missing notes with only the appended text, no title header.
+    pub no_header: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TasksConfig {
+    /// Note that `kno -t` adds tasks to (e.g. "tasks"); today's daily note if unset.
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotesConfig {
+    /// Stamp new notes with `title`, `created` and path-derived `tags` frontmatter.
+    pub frontmatter: bool,
+    /// Behave as if `--existing` was always passed: never create a note by opening it.
+    pub existing: bool,
+    /// Ask before opening a missing note creates it, offering similar notes.
+    pub confirm_create: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ShareConfig {
+    /// Token for `kno share` (needs the gist scope); falls back to `$GITHUB_TOKEN`.
+    pub github_token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MailConfig {
+    /// Sender address; required with `smtp_url`, otherwise left to sendmail.
+    pub from: Option<String>,
+    /// SMTP server (e.g. "smtps://smtp.example.com:465"); uses sendmail if unset.
+    pub smtp_url: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    /// sendmail-compatible program to use instead of `sendmail` (e.g. "msmtp").
+    pub sendmail: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Where archives are written; `~/.kno-backups` if unset.
+    pub dir: Option<PathBuf>,
+    /// Archives to keep; older ones are deleted after each backup.
+    pub keep: usize,
+    /// How often the timer installed by `kno backup --install-timer` runs.
+    pub schedule: Schedule,
+}
+
+impl Default for BackupConfig {
+    fn default() -> BackupConfig {
+        BackupConfig {
+            dir: None,
+            keep: 10,
+            schedule: Schedule::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeekConfig {
+    /// First day of the week (e.g. "monday", "sunday").
+    pub start: Weekday,
+    /// "iso" or "us" week numbers in weekly note names.
+    pub numbering: Numbering,
+}
+
+impl Default for WeekConfig {
+    fn default() -> WeekConfig {
+        WeekConfig {
+            start: Weekday::Mon,
+            numbering: Numbering::Iso,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatesConfig {
+    /// Locale for month and day names (e.g. "de_DE").
+    #[serde(deserialize_with = "locale")]
+    pub locale: Locale,
+    /// strftime format for daily note headers and `{{date}}`.
+    pub format: String,
+}
+
+impl Default for DatesConfig {
+    fn default() -> DatesConfig {
+        DatesConfig {
+            locale: Locale::POSIX,
+            format: "%Y-%m-%d".to_string(),
+        }
+    }
+}
+
+fn locale<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Locale, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    Locale::try_from(name.as_str())
+        .map_err(|_| serde::de::Error::custom(format!("unknown locale {name:?}")))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    /// Context recorded by `kno capture`, in order: time, cwd, repo, branch, host.
+    pub fields: Vec<Field>,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> CaptureConfig {
+        CaptureConfig {
+            fields: vec![
+                Field::Time,
+                Field::Cwd,
+                Field::Repo,
+                Field::Branch,
+                Field::Host,
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchiveConfig {
+    /// Directory archived notes move to, keeping their paths.
+    pub dir: String,
+    /// `[[archive.rules]]` applied by `kno archive --auto`.
+    pub rules: Vec<Rule>,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> ArchiveConfig {
+        ArchiveConfig {
+            dir: "archive".to_string(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GoalConfig {
+    /// Words to write each day, for `kno goal`.
+    pub words: usize,
+}
+
+impl Default for GoalConfig {
+    fn default() -> GoalConfig {
+        GoalConfig { words: 500 }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitConfig {
+    /// Message template for `kno commit`: `{{files}}`, `{{count}}`, `{{host}}`,
+    /// `{{date}}`, `{{time}}` and the other note template variables.
+    pub commit_message: String,
+    /// Times `kno sync` retries a failed pull or push before queueing the
+    /// commits locally.
+    pub push_retries: u32,
+    /// Seconds before the first retry; each later retry waits twice as long.
+    pub retry_secs: u64,
+}
+
+impl Default for GitConfig {
+    fn default() -> GitConfig {
+        GitConfig {
+            commit_message: "Update {{files}}".to_string(),
+            push_retries: 2,
+            retry_secs: 2,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// Bucket or WebDAV folder for `kno sync --backend`, e.g.
+    /// "https://s3.eu-west-1.amazonaws.com/my-notes" or
+    /// "https://cloud.example.com/remote.php/dav/files/me/notes".
+    pub url: Option<String>,
+    /// WebDAV user, or S3 access key id.
+    pub user: Option<String>,
+    /// WebDAV password, or S3 secret access key.
+    pub password: Option<String>,
+    /// S3 region requests are signed for.
+    pub region: String,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> RemoteConfig {
+        RemoteConfig {
+            url: None,
+            user: None,
+            password: None,
+            region: "us-east-1".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    /// Named pandoc pipelines for `kno export --profile <name>`.
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// Seconds to wait for each URL checked by `kno lint --urls`.
+    pub timeout_secs: u64,
+    /// URLs checked at once.
+    pub concurrency: usize,
+    /// Hours a URL that answered isn't checked again for.
+    pub cache_hours: u64,
+}
+
+impl Default for LintConfig {
+    fn default() -> LintConfig {
+        LintConfig {
+            timeout_secs: 10,
+            concurrency: 8,
+            cache_hours: 24,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LocksConfig {
+    /// Record which machine is editing a note in `.locks/`, pushed to the
+    /// vault's git remote as the editor opens and closes, and warn when
+    /// opening a note another machine is editing.
+    pub enabled: bool,
+    /// Minutes after which another machine's lock is ignored, for editors
+    /// left open or machines that went away without syncing.
+    pub expire_minutes: u64,
+}
+
+impl Default for LocksConfig {
+    fn default() -> LocksConfig {
+        LocksConfig {
+            enabled: false,
+            expire_minutes: 240,
+        }
+    }
+}
+
+/// `content` with `key = value` set in its `[table]`: an existing line for
+/// the key is replaced, a new one goes at the end of the table, and a missing
+/// table is appended. Everything else, comments included, is left alone.
+fn set_table_key(content: &str, table: &str, key: &str, value: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let entry = format!("{key} = {value}");
+    let header = format!("[{table}]");
+    let Some(start) = lines.iter().position(|l| l.trim() == header) else {
+        if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.extend([header, entry]);
+        return lines.join("\n") + "\n";
+    };
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| l.trim_start().starts_with('['))
+        .map_or(lines.len(), |i| start + 1 + i);
+    let existing = (start + 1..end).find(|&i| {
+        lines[i]
+            .split_once('=')
+            .is_some_and(|(k, _)| k.trim() == key)
+    });
+    match existing {
+        Some(i) => lines[i] = entry,
+        None => {
+            // After the table's last entry, before any blank lines
+            let last = (start..end)
+                .rev()
+                .find(|&i| !lines[i].trim().is_empty())
+                .unwrap_or(start);
+            lines.insert(last + 1, entry);
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Save `args` as the search `name` in the config file's `[searches]`,
+/// replacing any earlier search of that name.
+pub fn save_search(notes_dir: &Path, name: &str, args: &[String]) -> Result<(), String> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if name.is_empty() || !name.chars().all(valid) {
+        return Err(format!(
+            "invalid search name `{name}` (use letters, digits, - and _)"
+        ));
+    }
+    let path = notes_dir.join(CONFIG_FILE_NAME);
+    let content = fs::read_to_string(&path).unwrap_or_default();
+    let value = toml::Value::Array(args.iter().cloned().map(toml::Value::String).collect());
+    let updated = set_table_key(&content, "searches", name, &value.to_string());
+    toml::from_str::<Config>(&updated)
+        .map_err(|e| format!("can't save to {}: {e}", path.display()))?;
+
+    if dry_run::skip(format_args!("save search {name} in {}", path.display())) {
+        return Ok(());
+    }
+    fs::write(&path, updated).map_err(|e| format!("failed to write {}: {e}", path.display()))
+}
+
+impl Config {
+    pub fn load(notes_dir: &Path) -> Config {
+        let path = notes_dir.join(CONFIG_FILE_NAME);
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Config::default();
+        };
+
+        match toml::from_str(&content) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Invalid config {}: {e}", path.display());
+                process::exit(1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_config_is_default() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let config = Config::load(tmp.path());
+        assert!(!config.daily.nav_links);
+    }
+
+    #[test]
+    fn test_parses_daily_section() {
+        let config: Config = toml::from_str("[daily]\nnav_links = true\n").unwrap();
+        assert!(config.daily.nav_links);
+    }
+
+    #[test]
+    fn test_parses_append_section() {
+        let config: Config = toml::from_str("[append]\nno_header = true\n").unwrap();
+        assert!(config.append.no_header);
+        assert!(!config.daily.nav_links);
+    }
+
+    #[test]
+    fn test_parses_tasks_section() {
+        let config: Config = toml::from_str("[tasks]\nnote = \"inbox/tasks\"\n").unwrap();
+        assert_eq!(config.tasks.note.as_deref(), Some("inbox/tasks"));
+    }
+
+    #[test]
+    fn test_parses_notes_section() {
+        let config: Config = toml::from_str("[notes]\nfrontmatter = true\n").unwrap();
+        assert!(config.notes.frontmatter);
+    }
+
+    #[test]
+    fn test_parses_share_section() {
+        let config: Config = toml::from_str("[share]\ngithub_token = \"ghp_x\"\n").unwrap();
+        assert_eq!(config.share.github_token.as_deref(), Some("ghp_x"));
+    }
+
+    #[test]
+    fn test_parses_mail_section() {
+        let config: Config =
+            toml::from_str("[mail]\nfrom = \"me@example.com\"\nsmtp_url = \"smtps://x\"\n")
+                .unwrap();
+        assert_eq!(config.mail.from.as_deref(), Some("me@example.com"));
+        assert_eq!(config.mail.smtp_url.as_deref(), Some("smtps://x"));
+        assert_eq!(config.mail.sendmail, None);
+    }
+
+    #[test]
+    fn test_parses_backup_section() {
+        let config = Config::default();
+        assert_eq!(config.backup.keep, 10);
+        assert_eq!(config.backup.schedule, Schedule::Daily);
+
+        let config: Config = toml::from_str("[backup]\nkeep = 3\nschedule = \"weekly\"\n").unwrap();
+        assert_eq!(config.backup.keep, 3);
+        assert_eq!(config.backup.schedule, Schedule::Weekly);
+        assert!(toml::from_str::<Config>("[backup]\nschedule = \"monthly\"\n").is_err());
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys() {
+        assert!(toml::from_str::<Config>("[daily]\nnav_link = true\n").is_err());
+    }
+
+    #[test]
+    fn test_parses_week_section() {
+        let config = Config::default();
+        assert_eq!(config.week.start, Weekday::Mon);
+
+        let config: Config =
+            toml::from_str("[week]\nstart = \"sunday\"\nnumbering = \"us\"\n").unwrap();
+        assert_eq!(config.week.start, Weekday::Sun);
+        assert_eq!(config.week.numbering, Numbering::Us);
+    }
+
+    #[test]
+    fn test_parses_dates_section() {
+        let config: Config =
+            toml::from_str("[dates]\nlocale = \"de_DE\"\nformat = \"%A, %-d. %B %Y\"\n").unwrap();
+        assert_eq!(config.dates.locale, Locale::de_DE);
+        assert_eq!(config.dates.format, "%A, %-d. %B %Y");
+        assert_eq!(Config::default().dates.format, "%Y-%m-%d");
+        assert!(toml::from_str::<Config>("[dates]\nlocale = \"xx_YY\"\n").is_err());
+    }
+
+    #[test]
+    fn test_parses_daily_sections() {
+        let config: Config =
+            toml::from_str("[daily]\nsections = [\"Log\", \"Todo\"]\ntask_section = \"Todo\"\n")
+                .unwrap();
+        assert_eq!(config.daily.sections, ["Log", "Todo"]);
+        assert_eq!(config.daily.task_section, "Todo");
+        assert_eq!(config.daily.log_section, "Log");
+    }
+
+    #[test]
+    fn test_parses_capture_section() {
+        assert_eq!(Config::default().capture.fields.len(), 5);
+        let config: Config =
+            toml::from_str("[capture]\nfields = [\"repo\", \"branch\"]\n").unwrap();
+        assert_eq!(config.capture.fields, [Field::Repo, Field::Branch]);
+    }
+
+    #[test]
+    fn test_parses_archive_rules() {
+        assert_eq!(Config::default().archive.dir, "archive");
+        let config: Config = toml::from_str(
+            "[archive]\ndir = \"old\"\n\n[[archive.rules]]\npath = \"clippings\"\nafter_days = 180\n",
+        )
+        .unwrap();
+        assert_eq!(config.archive.dir, "old");
+        assert_eq!(
+            config.archive.rules,
+            [Rule {
+                path: "clippings".to_string(),
+                after_days: 180
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parses_goal_section() {
+        assert_eq!(Config::default().goal.words, 500);
+        let config: Config = toml::from_str("[goal]\nwords = 750\n").unwrap();
+        assert_eq!(config.goal.words, 750);
+    }
+
+    #[test]
+    fn test_parses_lint_section() {
+        assert_eq!(Config::default().lint.concurrency, 8);
+        let config: Config = toml::from_str("[lint]\ntimeout_secs = 5\ncache_hours = 0\n").unwrap();
+        assert_eq!(config.lint.timeout_secs, 5);
+        assert_eq!(config.lint.cache_hours, 0);
+        assert_eq!(config.lint.concurrency, 8);
+    }
+
+    #[test]
+    fn test_parses_locks_section() {
+        assert!(!Config::default().locks.enabled);
+        let config: Config = toml::from_str("[locks]\nenabled = true\n").unwrap();
+        assert!(config.locks.enabled);
+        assert_eq!(config.locks.expire_minutes, 240);
+    }
+
+    #[test]
+    fn test_parses_remote_section() {
+        assert_eq!(Config::default().remote.url, None);
+        let config: Config =
+            toml::from_str("[remote]\nurl = \"https://dav.example.com/notes\"\nuser = \"me\"\n")
+                .unwrap();
+        assert_eq!(
+            config.remote.url.as_deref(),
+            Some("https://dav.example.com/notes")
+        );
+        assert_eq!(config.remote.user.as_deref(), Some("me"));
+        assert_eq!(config.remote.region, "us-east-1");
+    }
+
+    #[test]
+    fn test_parses_export_section() {
+        assert!(Config::default().export.profiles.is_empty());
+        let config: Config = toml::from_str(
+            "[export.profiles.thesis]\nformat = \"latex\"\ntemplate = \"thesis.tex\"\nfilters = [\"pandoc-crossref\"]\nargs = [\"--toc\"]\n",
+        )
+        .unwrap();
+        let thesis = &config.export.profiles["thesis"];
+        assert_eq!(thesis.format, "latex");
+        assert_eq!(thesis.template.as_deref(), Some("thesis.tex"));
+        assert_eq!(thesis.filters, ["pandoc-crossref"]);
+        assert_eq!(thesis.args, ["--toc"]);
+        assert!(toml::from_str::<Config>("[export.profiles.x]\nformt = \"docx\"\n").is_err());
+    }
+
+    #[test]
+    fn test_save_search() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        fs::write(
+            tmp.path().join(CONFIG_FILE_NAME),
+            "# my config\n[goal]\nwords = 750\n",
+        )
+        .unwrap();
+
+        save_search(tmp.path(), "inbox-actions", &args(&["TODO", "inbox"])).unwrap();
+        save_search(tmp.path(), "quotes", &args(&["say \"hi\"", "-i"])).unwrap();
+        save_search(tmp.path(), "inbox-actions", &args(&["TODO", "inbox", "-w"])).unwrap();
+        assert!(save_search(tmp.path(), "bad name", &[]).is_err());
+
+        let content = fs::read_to_string(tmp.path().join(CONFIG_FILE_NAME)).unwrap();
+        assert!(content.starts_with("# my config\n[goal]\nwords = 750\n\n[searches]\n"));
+        let config = Config::load(tmp.path());
+        assert_eq!(config.goal.words, 750);
+        assert_eq!(config.searches["inbox-actions"], ["TODO", "inbox", "-w"]);
+        assert_eq!(config.searches["quotes"], ["say \"hi\"", "-i"]);
+    }
+
+    #[test]
+    fn test_set_table_key() {
+        let content = "[searches]\na = [\"x\"]\n\n[goal]\nwords = 1\n";
+        assert_eq!(
+            set_table_key(content, "searches", "b", "[\"y\"]"),
+            "[searches]\na = [\"x\"]\nb = [\"y\"]\n\n[goal]\nwords = 1\n"
+        );
+        assert_eq!(
+            set_table_key("", "searches", "a", "[]"),
+            "[searches]\na = []\n"
+        );
+    }
+
+    #[test]
+    fn test_parses_git_section() {
+        assert_eq!(Config::default().git.commit_message, "Update {{files}}");
+        let config: Config =
+            toml::from_str("[git]\ncommit_message = \"{{host}}: {{files}}\"\n").unwrap();
+        assert_eq!(config.git.commit_message, "{{host}}: {{files}}");
+        assert_eq!(config.git.push_retries, 2);
+        let config: Config = toml::from_str("[git]\npush_retries = 0\n").unwrap();
+        assert_eq!(config.git.push_retries, 0);
+        assert_eq!(config.git.retry_secs, 2);
+    }
+}
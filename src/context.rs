@@ -0,0 +1,153 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde::Deserialize;
+
+/// A piece of context `kno capture` can record alongside the text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    /// Time of the capture, as `HH:MM` before the text
+    Time,
+    /// Working directory, with the home directory shortened to `~`
+    Cwd,
+    /// Name of the git repository the working directory is in
+    Repo,
+    /// Current git branch
+    Branch,
+    /// Machine hostname
+    Host,
+}
+
+/// Where a command was run from.
+#[derive(Debug, Default, PartialEq)]
+pub struct Context {
+    pub cwd: Option<PathBuf>,
+    /// Top-level directory of the enclosing git repository
+    pub repo_root: Option<PathBuf>,
+    pub branch: Option<String>,
+    pub host: Option<String>,
+}
+
+fn output(cmd: &mut process::Command) -> Option<String> {
+    let output = cmd.stderr(process::Stdio::null()).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !text.is_empty()).then_some(text)
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    output(process::Command::new("git").arg("-C").arg(dir).args(args))
+}
+
+/// This machine's name.
+pub fn hostname() -> Option<String> {
+    output(&mut process::Command::new("hostname"))
+        .or_else(|| env::var("HOSTNAME").ok())
+        .filter(|h| !h.is_empty())
+}
+
+impl Context {
+    /// Detect the context of the current working directory.
+    pub fn detect() -> Context {
+        let cwd = env::current_dir().ok();
+        let repo_root = cwd
+            .as_deref()
+            .and_then(|dir| git(dir, &["rev-parse", "--show-toplevel"]))
+            .map(PathBuf::from);
+        let branch = repo_root
+            .as_deref()
+            .and_then(|dir| git(dir, &["branch", "--show-current"]));
+        Context {
+            cwd,
+            repo_root,
+            branch,
+            host: hostname(),
+        }
+    }
+
+    /// Name of the enclosing git repository (its directory name).
+    pub fn repo(&self) -> Option<String> {
+        let root = self.repo_root.as_deref()?;
+        Some(root.file_name()?.to_string_lossy().into_owned())
+    }
+
+    /// `cwd: ~/code/kno, repo: kno, branch: main` for the fields that are
+    /// known, in the order given. `Time` isn't part of the context.
+    pub fn describe(&self, fields: &[Field], home: Option<&Path>) -> String {
+        fields
+            .iter()
+            .filter_map(|field| {
+                let (name, value) = match field {
+                    Field::Time => return None,
+                    Field::Cwd => ("cwd", self.cwd.as_deref().map(|cwd| shorten(cwd, home))),
+                    Field::Repo => ("repo", self.repo()),
+                    Field::Branch => ("branch", self.branch.clone()),
+                    Field::Host => ("host", self.host.clone()),
+                };
+                Some(format!("{name}: {}", value?))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// `path` with a leading `home` replaced by `~`.
+fn shorten(path: &Path, home: Option<&Path>) -> String {
+    match home.and_then(|home| path.strip_prefix(home).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Some(rest) => format!("~/{}", rest.display()),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_known_fields() {
+        let context = Context {
+            cwd: Some(PathBuf::from("/home/me/code/kno")),
+            repo_root: Some(PathBuf::from("/home/me/code/kno")),
+            branch: Some("main".into()),
+            host: None,
+        };
+        let all = [
+            Field::Time,
+            Field::Cwd,
+            Field::Repo,
+            Field::Branch,
+            Field::Host,
+        ];
+        assert_eq!(
+            context.describe(&all, Some(Path::new("/home/me"))),
+            "cwd: ~/code/kno, repo: kno, branch: main"
+        );
+        assert_eq!(
+            context.describe(&[Field::Branch, Field::Cwd], None),
+            "branch: main, cwd: /home/me/code/kno"
+        );
+        assert_eq!(Context::default().describe(&all, None), "");
+    }
+
+    #[test]
+    fn test_detect_repo() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let repo = tmp.path().join("myrepo");
+        std::fs::create_dir_all(repo.join("src")).unwrap();
+        process::Command::new("git")
+            .args(["init", "-q", "-b", "trunk"])
+            .arg(&repo)
+            .status()
+            .unwrap();
+
+        let root = git(&repo.join("src"), &["rev-parse", "--show-toplevel"]);
+        assert_eq!(root.map(PathBuf::from), Some(repo.canonicalize().unwrap()));
+        assert_eq!(
+            git(&repo, &["branch", "--show-current"]).as_deref(),
+            Some("trunk")
+        );
+        assert_eq!(git(tmp.path(), &["rev-parse", "--show-toplevel"]), None);
+    }
+}
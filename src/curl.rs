@@ -0,0 +1,55 @@
+use std::io::Write;
+
+use tempfile::NamedTempFile;
+
+/// `value` as a double-quoted string in a curl config file.
+fn quote(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+/// A curl config file setting `options` (like `("user", "me:secret")`), for
+/// `--config`. Credentials on curl's command line can be read by other users
+/// from the process list; the file is readable only by us and is deleted when
+/// dropped, so keep it until curl exits.
+pub fn secret_config(options: &[(&str, &str)]) -> Result<NamedTempFile, String> {
+    let mut file = tempfile::Builder::new()
+        .prefix("kno-curl-")
+        .tempfile()
+        .map_err(|e| format!("failed to create curl config: {e}"))?;
+    let content: String = options
+        .iter()
+        .map(|(name, value)| format!("{name} = {}\n", quote(value)))
+        .collect();
+    file.write_all(content.as_bytes())
+        .map_err(|e| format!("failed to write curl config: {e}"))?;
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_secret_config() {
+        let file = secret_config(&[
+            ("user", "me:p\"w\\d"),
+            ("header", "Authorization: Bearer t"),
+        ])
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "user = \"me:p\\\"w\\\\d\"\nheader = \"Authorization: Bearer t\"\n"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(file.path()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
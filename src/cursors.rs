@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::dry_run;
+
+/// File recording the last cursor line in each note, one `<line>\t<path>`
+/// per line.
+pub const CURSORS_FILE_NAME: &str = ".cursors";
+
+/// Editors that understand the exit hook from `vim_report_args`.
+const VIM_EDITORS: &[&str] = &["vim", "nvim", "gvim", "mvim"];
+
+/// Last known cursor line of each note, by path relative to the notes dir.
+pub fn read(notes_dir: &Path) -> BTreeMap<PathBuf, usize> {
+    let content = fs::read_to_string(notes_dir.join(CURSORS_FILE_NAME)).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let (line, path) = line.split_once('\t')?;
+            Some((PathBuf::from(path), line.parse().ok()?))
+        })
+        .collect()
+}
+
+/// The line the cursor was on when `relative_path` was last closed.
+pub fn get(notes_dir: &Path, relative_path: &Path) -> Option<usize> {
+    read(notes_dir).remove(relative_path)
+}
+
+/// Store cursor lines for notes, dropping entries for notes that no longer
+/// exist.
+pub fn record(notes_dir: &Path, lines: &[(PathBuf, usize)]) {
+    if dry_run::is_enabled() || lines.is_empty() {
+        return;
+    }
+    let mut cursors = read(notes_dir);
+    cursors.extend(lines.iter().cloned());
+    cursors.retain(|rel, _| notes_dir.join(rel).is_file());
+    write(notes_dir, &cursors);
+}
+
+/// Point entries for moved notes at their new paths.
+pub fn rename(notes_dir: &Path, moves: &HashMap<PathBuf, PathBuf>) {
+    let cursors = read(notes_dir);
+    if !cursors.keys().any(|rel| moves.contains_key(rel)) {
+        return;
+    }
+    let cursors = cursors
+        .into_iter()
+        .map(|(rel, line)| (moves.get(&rel).cloned().unwrap_or(rel), line))
+        .collect();
+    write(notes_dir, &cursors);
+}
+
+fn write(notes_dir: &Path, cursors: &BTreeMap<PathBuf, usize>) {
+    let content: String = cursors
+        .iter()
+        .map(|(rel, line)| format!("{line}\t{}\n", rel.display()))
+        .collect();
+    // Best effort: losing a cursor position should never block editing
+    let _ = fs::write(notes_dir.join(CURSORS_FILE_NAME), content);
+}
+
+/// Arguments that make vim or Neovim (`editor`) write the cursor line of
+/// each open buffer to `report` as it exits, as `<line>\t<absolute path>`.
+/// `None` for other editors.
+pub fn vim_report_args(editor: &Path, report: &Path) -> Option<Vec<String>> {
+    let name = editor.file_name()?.to_string_lossy();
+    if !VIM_EDITORS.contains(&name.as_ref()) {
+        return None;
+    }
+    let report = report.to_string_lossy().replace('\'', "''");
+    // The current buffer's stored line is stale, so use the live cursor
+    let hook = format!(
+        r#"autocmd VimLeavePre * call writefile(map(getbufinfo({{'buflisted': 1}}), {{_, b -> (b.bufnr == bufnr('%') ? line('.') : b.lnum) . "\t" . b.name}}), '{report}')"#
+    );
+    Some(vec!["-c".to_string(), hook])
+}
+
+/// Cursor lines from a `vim_report_args` report, for notes under `notes_dir`.
+pub fn parse_report(notes_dir: &Path, report: &str) -> Vec<(PathBuf, usize)> {
+    report
+        .lines()
+        .filter_map(|line| {
+            let (line, path) = line.split_once('\t')?;
+            let rel = Path::new(path).strip_prefix(notes_dir).ok()?;
+            Some((rel.to_path_buf(), line.parse().ok()?))
+        })
+        .filter(|(_, line)| *line > 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_and_get() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join("a.md"), "").unwrap();
+        fs::write(tmp.path().join("b.md"), "").unwrap();
+
+        record(tmp.path(), &[(PathBuf::from("a.md"), 40)]);
+        record(
+            tmp.path(),
+            &[
+                (PathBuf::from("b.md"), 7),
+                (PathBuf::from("a.md"), 12),
+                (PathBuf::from("gone.md"), 3),
+            ],
+        );
+
+        assert_eq!(get(tmp.path(), Path::new("a.md")), Some(12));
+        assert_eq!(get(tmp.path(), Path::new("b.md")), Some(7));
+        assert_eq!(get(tmp.path(), Path::new("gone.md")), None);
+    }
+
+    #[test]
+    fn test_rename() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(tmp.path().join("a.md"), "").unwrap();
+        record(tmp.path(), &[(PathBuf::from("a.md"), 5)]);
+
+        let moves = HashMap::from([(PathBuf::from("a.md"), PathBuf::from("sql/a.md"))]);
+        rename(tmp.path(), &moves);
+
+        assert_eq!(get(tmp.path(), Path::new("sql/a.md")), Some(5));
+        assert_eq!(get(tmp.path(), Path::new("a.md")), None);
+    }
+
+    #[test]
+    fn test_vim_report_args() {
+        let report = Path::new("/tmp/kno-cursors-1");
+        let args = vim_report_args(Path::new("/usr/bin/nvim"), report).unwrap();
+        assert_eq!(args[0], "-c");
+        assert!(args[1].starts_with("autocmd VimLeavePre"));
+        assert!(args[1].ends_with("'/tmp/kno-cursors-1')"));
+        assert!(vim_report_args(Path::new("vim"), report).is_some());
+        assert_eq!(vim_report_args(Path::new("nano"), report), None);
+        assert_eq!(vim_report_args(Path::new("code"), report), None);
+    }
+
+    #[test]
+    fn test_parse_report() {
+        let report = "12\t/home/me/.kno/sql/joins.md\n1\t/etc/hosts\n0\t/home/me/.kno/new.md\n";
+        assert_eq!(
+            parse_report(Path::new("/home/me/.kno"), report),
+            [(PathBuf::from("sql/joins.md"), 12)]
+        );
+    }
+}
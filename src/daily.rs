@@ -0,0 +1,784 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::Deserialize;
+
+use crate::{dry_run, frontmatter, links, vault};
+
+/// Directory inside the notes dir holding daily notes.
+pub const DAILY_DIR_NAME: &str = "daily";
+
+fn collect(
+    notes_dir: &Path,
+    dir: &Path,
+    ancestors: &mut Vec<PathBuf>,
+    out: &mut Vec<(NaiveDate, PathBuf)>,
+) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(real) = vault::descend(&path, ancestors) {
+                ancestors.push(real);
+                collect(notes_dir, &path, ancestors, out);
+                ancestors.pop();
+            }
+            continue;
+        }
+
+        let date = path
+            .extension()
+            .filter(|ext| *ext == "md")
+            .and_then(|_| path.file_stem())
+            .and_then(|stem| parse_stem(&path, &stem.to_string_lossy()));
+        if let Some(date) = date {
+            let relative = path.strip_prefix(notes_dir).unwrap().to_path_buf();
+            out.push((date, relative));
+        }
+    }
+}
+
+/// The date of a daily note named `YYYY-MM-DD.md`, or `MM-DD.md` inside a
+/// `YYYY` directory.
+fn parse_stem(path: &Path, stem: &str) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(stem, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let year = path.parent()?.file_name()?.to_string_lossy();
+    match year.len() == 4 && stem.len() == 5 {
+        true => NaiveDate::parse_from_str(&format!("{year}-{stem}"), "%Y-%m-%d").ok(),
+        false => None,
+    }
+}
+
+/// How daily notes are grouped into directories under `daily/`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Layout {
+    /// `daily/2026-02-15.md`
+    Flat,
+    /// `daily/2026/2026-02-15.md`
+    #[default]
+    Year,
+    /// `daily/2026/02/2026-02-15.md`
+    YearMonth,
+}
+
+/// Where the daily note for `date` lives under `layout`, relative to the notes dir.
+pub fn note_path(date: NaiveDate, layout: Layout) -> PathBuf {
+    let file = format!("{}.md", date.format("%Y-%m-%d"));
+    let dir = PathBuf::from(DAILY_DIR_NAME);
+    match layout {
+        Layout::Flat => dir.join(file),
+        Layout::Year => dir.join(date.format("%Y").to_string()).join(file),
+        Layout::YearMonth => dir
+            .join(date.format("%Y").to_string())
+            .join(date.format("%m").to_string())
+            .join(file),
+    }
+}
+
+/// The moves (old path, new path) that put every daily note where `layout`
+/// expects it. Fails without moving anything if two notes share a date or a
+/// target is taken by another file.
+pub fn migration(notes_dir: &Path, layout: Layout) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let notes = daily_notes(notes_dir);
+    let mut moves = Vec::new();
+    for pair in notes.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            return Err(format!(
+                "{} and {} are both daily notes for {}",
+                pair[0].1.display(),
+                pair[1].1.display(),
+                pair[0].0
+            ));
+        }
+    }
+    for (date, path) in notes {
+        let target = note_path(date, layout);
+        if target == path {
+            continue;
+        }
+        if notes_dir.join(&target).exists() {
+            return Err(format!(
+                "can't move {} to {}: file exists",
+                path.display(),
+                target.display()
+            ));
+        }
+        moves.push((path, target));
+    }
+    Ok(moves)
+}
+
+/// Remove directories under `dir` left empty after daily notes moved out.
+/// Symlinked directories are never entered, even with `--follow-symlinks`,
+/// so nothing outside the vault is removed.
+pub fn remove_empty_dirs(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        // `file_type` describes a symlink itself rather than its target
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            remove_empty_dirs(&entry.path());
+            // Fails, as intended, unless the directory is now empty
+            let _ = fs::remove_dir(entry.path());
+        }
+    }
+}
+
+/// All daily notes (paths relative to the notes dir), oldest first.
+pub fn daily_notes(notes_dir: &Path) -> Vec<(NaiveDate, PathBuf)> {
+    let mut notes = Vec::new();
+    let daily_dir = notes_dir.join(DAILY_DIR_NAME);
+    collect(
+        notes_dir,
+        &daily_dir,
+        &mut vault::walk_root(&daily_dir),
+        &mut notes,
+    );
+    notes.sort();
+    notes
+}
+
+/// A day relative to `today`: `today`, `yesterday`, `tomorrow`, a weekday
+/// (`friday` is the most recent Friday, today included; `last friday` the one
+/// before today) or `YYYY-MM-DD`.
+pub fn parse_day(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let s = s.trim().to_lowercase();
+    match s.as_str() {
+        "today" => return Some(today),
+        "yesterday" => return today.pred_opt(),
+        "tomorrow" => return today.succ_opt(),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&s, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let (name, strictly_before) = match s.strip_prefix("last ") {
+        Some(name) => (name.trim(), true),
+        None => (s.as_str(), false),
+    };
+    let weekday: Weekday = name.parse().ok()?;
+    let back = (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+    let back = match (back, strictly_before) {
+        (0, true) => 7,
+        (back, _) => back,
+    };
+    today.checked_sub_days(Days::new(u64::from(back)))
+}
+
+/// The most recent daily note dated strictly before `date`.
+pub fn previous_before(notes_dir: &Path, date: NaiveDate) -> Option<(NaiveDate, PathBuf)> {
+    daily_notes(notes_dir)
+        .into_iter()
+        .rev()
+        .find(|(d, _)| *d < date)
+}
+
+/// Append a "Next" link to the previous daily note pointing at `relative_path`,
+/// and return the matching "Previous" line for the new note.
+pub fn link_neighbours(notes_dir: &Path, relative_path: &Path, date: NaiveDate) -> Option<String> {
+    let (prev_date, prev_path) = previous_before(notes_dir, date)?;
+
+    let next_line = format!(
+        "Next: [{date}]({})",
+        links::relative_link(&prev_path, relative_path)
+    );
+    let prev_file = notes_dir.join(&prev_path);
+    let mut prev_content = fs::read_to_string(&prev_file).unwrap_or_default();
+    if !prev_content.contains(&next_line) {
+        if !prev_content.is_empty() && !prev_content.ends_with('\n') {
+            prev_content.push('\n');
+        }
+        prev_content.push_str(&format!("\n{next_line}\n"));
+        fs::write(&prev_file, prev_content).expect("failed to update previous daily note");
+        crate::log::debug!(
+            "linked {} to {}",
+            prev_file.display(),
+            relative_path.display()
+        );
+    }
+
+    Some(format!(
+        "Previous: [{prev_date}]({})",
+        links::relative_link(relative_path, &prev_path)
+    ))
+}
+
+/// `## <section>` headings for a new daily note, skipping any that `content`
+/// (e.g. from a template) already has.
+pub fn section_headings(content: &str, sections: &[String]) -> String {
+    sections
+        .iter()
+        .map(|name| format!("## {name}"))
+        .filter(|heading| !content.lines().any(|l| l.trim() == heading))
+        .map(|heading| format!("{heading}\n\n"))
+        .collect()
+}
+
+/// Insert `text` at the end of the `## <section>` section of `content`,
+/// before the next heading. `None` if the note has no such section.
+pub fn insert_in_section(content: &str, section: &str, text: &str) -> Option<String> {
+    let mut lines: Vec<&str> = content.lines().collect();
+    let mut in_fence = false;
+    let mut heading = None;
+    let mut end = lines.len();
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            continue;
+        }
+        let is_section = line.starts_with("# ") || line.starts_with("## ");
+        match heading {
+            None if line.strip_prefix("## ").map(str::trim) == Some(section) => heading = Some(i),
+            Some(_) if is_section => {
+                end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let heading = heading?;
+    let last = (heading + 1..end)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty())
+        .unwrap_or(heading);
+    let insert: &[&str] = match last == heading {
+        true => &["", text],
+        false => &[text],
+    };
+    let at = last + 1;
+    lines.splice(at..at, insert.iter().copied());
+    Some(lines.join("\n") + "\n")
+}
+
+/// The non-blank lines of the `## <section>` section of `content`, up to the
+/// next heading.
+pub fn section_lines<'a>(content: &'a str, section: &str) -> Vec<&'a str> {
+    let mut lines = content.lines();
+    if !lines.any(|l| l.strip_prefix("## ").map(str::trim) == Some(section)) {
+        return Vec::new();
+    }
+    let mut in_fence = false;
+    let mut out = Vec::new();
+    for line in lines {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if !in_fence && (line.starts_with("# ") || line.starts_with("## ")) {
+            break;
+        }
+        if !line.trim().is_empty() {
+            out.push(line);
+        }
+    }
+    out
+}
+
+/// `content` with `text` under its `## <section>` heading, adding the heading
+/// at the end of the note if it has none.
+pub fn add_to_section(content: &str, section: &str, text: &str) -> String {
+    insert_in_section(content, section, text).unwrap_or_else(|| match content.trim_end() {
+        "" => format!("## {section}\n\n{text}\n"),
+        body => format!("{body}\n\n## {section}\n\n{text}\n"),
+    })
+}
+
+/// How `compact` groups old daily notes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Bundle {
+    Month,
+    Year,
+}
+
+/// A day's note as a bundle section: frontmatter and Previous/Next links are
+/// dropped, headings move down a level, and a `## <date>` heading is added if
+/// the note didn't start with one.
+fn bundle_section(date: NaiveDate, content: &str) -> String {
+    let mut in_fence = false;
+    let mut lines = Vec::new();
+    for line in frontmatter::body(content).lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let is_heading = !in_fence && (line.starts_with("# ") || line.starts_with("##"));
+        let is_nav = !in_fence && (line.starts_with("Previous: [") || line.starts_with("Next: ["));
+        match (is_heading, is_nav) {
+            (_, true) => {}
+            (true, false) => lines.push(format!("#{line}")),
+            (false, false) => lines.push(line.to_string()),
+        }
+    }
+
+    let body = lines.join("\n");
+    let body = body.trim();
+    match body.starts_with("## ") {
+        true => format!("{body}\n"),
+        false if body.is_empty() => format!("## {date}\n"),
+        false => format!("## {date}\n\n{body}\n"),
+    }
+}
+
+/// Merge daily notes dated before `before` into one note per month or year
+/// (`daily/2022/2022-03.md` or `daily/2022/2022.md`) and delete the originals.
+/// Existing bundles are appended to. Returns each bundle with its note count.
+pub fn compact(notes_dir: &Path, before: NaiveDate, by: Bundle) -> Vec<(PathBuf, usize)> {
+    let mut groups: BTreeMap<String, Vec<(NaiveDate, PathBuf)>> = BTreeMap::new();
+    for (date, path) in daily_notes(notes_dir) {
+        if date < before {
+            let key = match by {
+                Bundle::Month => date.format("%Y-%m").to_string(),
+                Bundle::Year => date.format("%Y").to_string(),
+            };
+            groups.entry(key).or_default().push((date, path));
+        }
+    }
+
+    let mut bundles = Vec::new();
+    for (key, days) in groups {
+        let year = &key[..4];
+        let bundle = PathBuf::from(DAILY_DIR_NAME)
+            .join(year)
+            .join(format!("{key}.md"));
+        bundles.push((bundle.clone(), days.len()));
+
+        if dry_run::skip(format_args!(
+            "compact {} daily notes into {}",
+            days.len(),
+            bundle.display()
+        )) {
+            continue;
+        }
+
+        let bundle_file = notes_dir.join(&bundle);
+        let mut content = fs::read_to_string(&bundle_file).unwrap_or_else(|_| format!("# {key}\n"));
+        for (date, path) in &days {
+            let day = fs::read_to_string(notes_dir.join(path)).unwrap_or_default();
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push('\n');
+            content.push_str(&bundle_section(*date, &day));
+        }
+
+        fs::create_dir_all(bundle_file.parent().unwrap())
+            .expect("failed to create bundle directory");
+        fs::write(&bundle_file, content).expect("failed to write bundle");
+        for (_, path) in &days {
+            fs::remove_file(notes_dir.join(path)).expect("failed to remove compacted daily note");
+        }
+        crate::log::debug!("compacted {} notes into {}", days.len(), bundle.display());
+    }
+
+    bundles
+}
+
+/// Whether the file stem `name` is a sync service's conflict copy of the note
+/// with stem `stem`: Dropbox and Nextcloud `stem (... conflicted copy ...)`,
+/// Syncthing `stem.sync-conflict-...` or ownCloud `stem_conflict-...`.
+pub fn is_conflict_copy(stem: &str, name: &str) -> bool {
+    let Some(rest) = name.strip_prefix(stem) else {
+        return false;
+    };
+    match rest.strip_prefix(" (") {
+        Some(rest) => rest.ends_with(')') && rest.to_lowercase().contains("conflict"),
+        None => rest.starts_with(".sync-conflict-") || rest.starts_with("_conflict-"),
+    }
+}
+
+/// Conflict copies (see `is_conflict_copy`) next to the note at
+/// `relative_path`, sorted by path.
+pub fn conflict_copies(notes_dir: &Path, relative_path: &Path) -> Vec<PathBuf> {
+    let dir = relative_path.parent().unwrap_or(Path::new(""));
+    let stem = relative_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let Ok(entries) = fs::read_dir(notes_dir.join(dir)) else {
+        return Vec::new();
+    };
+    let mut copies: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| PathBuf::from(e.file_name()))
+        .filter(|name| name.extension().is_some_and(|ext| ext == "md"))
+        .filter(|name| {
+            let name = name.file_stem().unwrap_or_default().to_string_lossy();
+            is_conflict_copy(&stem, &name)
+        })
+        .map(|name| dir.join(name))
+        .collect();
+    copies.sort();
+    copies
+}
+
+/// Merge the conflict copies of the daily note at `relative_path` into it,
+/// adding the lines it doesn't already have (see `merge::union`), then trash
+/// them. Returns the merged copies.
+pub fn merge_conflict_copies(notes_dir: &Path, relative_path: &Path) -> Vec<PathBuf> {
+    let copies = conflict_copies(notes_dir, relative_path);
+    if copies.is_empty() {
+        return copies;
+    }
+    let file_path = notes_dir.join(relative_path);
+    let mut content = fs::read_to_string(&file_path).unwrap_or_default();
+    for copy in &copies {
+        let theirs = fs::read_to_string(notes_dir.join(copy)).unwrap_or_default();
+        content = match content.is_empty() {
+            true => theirs,
+            false => crate::merge::union("", &content, &theirs),
+        };
+    }
+    if !dry_run::skip(format_args!(
+        "merge {} conflict copies into {}",
+        copies.len(),
+        relative_path.display()
+    )) {
+        fs::write(&file_path, content).expect("failed to write daily note");
+    }
+    for copy in &copies {
+        crate::dedupe::trash(notes_dir, copy);
+    }
+    copies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_daily_notes_sorted_and_filtered() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "daily/2026/2026-02-13.md", "");
+        write(tmp.path(), "daily/2025/2025-12-31.md", "");
+        write(tmp.path(), "daily/2026/notes.md", "");
+        write(tmp.path(), "daily/2026/2026-02-14.txt", "");
+
+        let notes = daily_notes(tmp.path());
+        let dates: Vec<_> = notes.iter().map(|(d, _)| d.to_string()).collect();
+        assert_eq!(dates, ["2025-12-31", "2026-02-13"]);
+        assert_eq!(notes[1].1, PathBuf::from("daily/2026/2026-02-13.md"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_daily_walks_stay_inside_the_vault() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vault = tmp.path().join("vault");
+        write(&vault, "daily/2026/2026-02-13.md", "");
+        write(tmp.path(), "shared/2026-02-14.md", "");
+        fs::create_dir_all(tmp.path().join("shared/empty")).unwrap();
+        fs::create_dir_all(vault.join("daily/2025")).unwrap();
+        std::os::unix::fs::symlink(tmp.path().join("shared"), vault.join("daily/shared")).unwrap();
+        // A link back up the tree
+        std::os::unix::fs::symlink(vault.join("daily"), tmp.path().join("shared/loop")).unwrap();
+
+        assert_eq!(daily_notes(&vault).len(), 1);
+        vault::set_follow_symlinks(true);
+        let followed = daily_notes(&vault);
+        vault::set_follow_symlinks(false);
+        let dates: Vec<_> = followed.iter().map(|(d, _)| d.to_string()).collect();
+        assert_eq!(dates, ["2026-02-13", "2026-02-14"]);
+
+        remove_empty_dirs(&vault.join(DAILY_DIR_NAME));
+        assert!(!vault.join("daily/2025").exists());
+        assert!(tmp.path().join("shared/empty").is_dir());
+    }
+
+    #[test]
+    fn test_parse_day() {
+        // 2026-02-15 is a Sunday
+        let today = date("2026-02-15");
+        assert_eq!(parse_day("today", today), Some(today));
+        assert_eq!(parse_day("Yesterday", today), Some(date("2026-02-14")));
+        assert_eq!(parse_day("tomorrow", today), Some(date("2026-02-16")));
+        assert_eq!(parse_day("2025-12-31", today), Some(date("2025-12-31")));
+        assert_eq!(parse_day("friday", today), Some(date("2026-02-13")));
+        assert_eq!(parse_day("mon", today), Some(date("2026-02-09")));
+        assert_eq!(parse_day("sunday", today), Some(today));
+        assert_eq!(parse_day("last sunday", today), Some(date("2026-02-08")));
+        assert_eq!(parse_day("someday", today), None);
+    }
+
+    #[test]
+    fn test_note_path_layouts() {
+        let day = date("2026-02-15");
+        assert_eq!(
+            note_path(day, Layout::Flat),
+            PathBuf::from("daily/2026-02-15.md")
+        );
+        assert_eq!(
+            note_path(day, Layout::Year),
+            PathBuf::from("daily/2026/2026-02-15.md")
+        );
+        assert_eq!(
+            note_path(day, Layout::YearMonth),
+            PathBuf::from("daily/2026/02/2026-02-15.md")
+        );
+    }
+
+    #[test]
+    fn test_migration_plan() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "daily/2025/02-15.md", "");
+        write(tmp.path(), "daily/2025-02-16.md", "");
+        write(tmp.path(), "daily/2025/2025-02-17.md", "");
+
+        let moves = migration(tmp.path(), Layout::Year).unwrap();
+        assert_eq!(
+            moves,
+            [
+                (
+                    PathBuf::from("daily/2025/02-15.md"),
+                    PathBuf::from("daily/2025/2025-02-15.md")
+                ),
+                (
+                    PathBuf::from("daily/2025-02-16.md"),
+                    PathBuf::from("daily/2025/2025-02-16.md")
+                ),
+            ]
+        );
+
+        write(tmp.path(), "daily/2025/2025-02-16.md", "");
+        assert!(migration(tmp.path(), Layout::Flat).is_err());
+    }
+
+    #[test]
+    fn test_section_headings() {
+        let sections = ["Log".to_string(), "Tasks".to_string()];
+        assert_eq!(
+            section_headings("# 2026-02-15\n\n", &sections),
+            "## Log\n\n## Tasks\n\n"
+        );
+        assert_eq!(
+            section_headings("# 2026-02-15\n\n## Tasks\n", &sections),
+            "## Log\n\n"
+        );
+    }
+
+    #[test]
+    fn test_add_to_section_creates_it() {
+        let note = "# 2026-02-15\n\n## Tasks\n\n- [ ] a\n";
+        let note = add_to_section(note, "Log", "- **09:10** standup");
+        let note = add_to_section(&note, "Log", "- **14:32** deploy");
+        assert_eq!(
+            note,
+            "# 2026-02-15\n\n## Tasks\n\n- [ ] a\n\n## Log\n\n- **09:10** standup\n- **14:32** deploy\n"
+        );
+        assert_eq!(add_to_section("", "Log", "x"), "## Log\n\nx\n");
+    }
+
+    #[test]
+    fn test_section_lines() {
+        let note =
+            "# D\n\n## Log\n\n- **09:10** standup\n\n- **14:32** deploy\n## Tasks\n\n- [ ] a\n";
+        assert_eq!(
+            section_lines(note, "Log"),
+            ["- **09:10** standup", "- **14:32** deploy"]
+        );
+        assert!(section_lines(note, "Links").is_empty());
+    }
+
+    #[test]
+    fn test_insert_in_section() {
+        let note = "# 2026-02-15\n\n## Log\n\n## Tasks\n\n## Notes\n";
+        let note = insert_in_section(note, "Log", "first").unwrap();
+        let note = insert_in_section(&note, "Log", "second").unwrap();
+        let note = insert_in_section(&note, "Notes", "- idea").unwrap();
+        assert_eq!(
+            note,
+            "# 2026-02-15\n\n## Log\n\nfirst\nsecond\n\n## Tasks\n\n## Notes\n\n- idea\n"
+        );
+        assert_eq!(insert_in_section(&note, "Links", "x"), None);
+    }
+
+    #[test]
+    fn test_bundle_section() {
+        let day = "---\ntags: [x]\n---\n# 2022-03-01\n\nwork\n## Later\n```\n# comment\n```\n\nNext: [2022-03-02](2022-03-02.md)\n";
+        assert_eq!(
+            bundle_section(date("2022-03-01"), day),
+            "## 2022-03-01\n\nwork\n### Later\n```\n# comment\n```\n"
+        );
+        assert_eq!(
+            bundle_section(date("2022-03-02"), "just text\n"),
+            "## 2022-03-02\n\njust text\n"
+        );
+    }
+
+    #[test]
+    fn test_compact_by_month() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(
+            tmp.path(),
+            "daily/2022/2022-03-01.md",
+            "# 2022-03-01\n\none\n",
+        );
+        write(
+            tmp.path(),
+            "daily/2022/2022-03-02.md",
+            "# 2022-03-02\n\ntwo\n",
+        );
+        write(tmp.path(), "daily/2022/2022-04-01.md", "# 2022-04-01\n");
+        write(tmp.path(), "daily/2023/2023-01-01.md", "# 2023-01-01\n");
+
+        let bundles = compact(tmp.path(), date("2023-01-01"), Bundle::Month);
+
+        assert_eq!(
+            bundles,
+            [
+                (PathBuf::from("daily/2022/2022-03.md"), 2),
+                (PathBuf::from("daily/2022/2022-04.md"), 1)
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("daily/2022/2022-03.md")).unwrap(),
+            "# 2022-03\n\n## 2022-03-01\n\none\n\n## 2022-03-02\n\ntwo\n"
+        );
+        let remaining: Vec<_> = daily_notes(tmp.path())
+            .into_iter()
+            .map(|(d, _)| d)
+            .collect();
+        assert_eq!(remaining, [date("2023-01-01")]);
+    }
+
+    #[test]
+    fn test_compact_appends_to_existing_bundle() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(
+            tmp.path(),
+            "daily/2022/2022.md",
+            "# 2022\n\n## 2022-01-01\n",
+        );
+        write(tmp.path(), "daily/2022/2022-05-01.md", "# 2022-05-01\n");
+
+        compact(tmp.path(), date("2023-01-01"), Bundle::Year);
+
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("daily/2022/2022.md")).unwrap(),
+            "# 2022\n\n## 2022-01-01\n\n## 2022-05-01\n"
+        );
+    }
+
+    #[test]
+    fn test_previous_before_skips_gaps() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "daily/2026/2026-02-10.md", "");
+        write(tmp.path(), "daily/2026/2026-02-13.md", "");
+        write(tmp.path(), "daily/2026/2026-02-16.md", "");
+
+        let (d, _) = previous_before(tmp.path(), date("2026-02-16")).unwrap();
+        assert_eq!(d, date("2026-02-13"));
+        assert!(previous_before(tmp.path(), date("2026-02-10")).is_none());
+    }
+
+    #[test]
+    fn test_link_neighbours() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(
+            tmp.path(),
+            "daily/2025/2025-12-31.md",
+            "# 2025-12-31\n\nwork",
+        );
+
+        let line = link_neighbours(
+            tmp.path(),
+            Path::new("daily/2026/2026-01-02.md"),
+            date("2026-01-02"),
+        )
+        .unwrap();
+
+        assert_eq!(line, "Previous: [2025-12-31](../2025/2025-12-31.md)");
+        let prev = fs::read_to_string(tmp.path().join("daily/2025/2025-12-31.md")).unwrap();
+        assert_eq!(
+            prev,
+            "# 2025-12-31\n\nwork\n\nNext: [2026-01-02](../2026/2026-01-02.md)\n"
+        );
+    }
+
+    #[test]
+    fn test_link_neighbours_without_previous() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let line = link_neighbours(
+            tmp.path(),
+            Path::new("daily/2026/2026-01-02.md"),
+            date("2026-01-02"),
+        );
+        assert!(line.is_none());
+    }
+
+    #[test]
+    fn test_is_conflict_copy() {
+        let stem = "2025-02-15";
+        assert!(is_conflict_copy(stem, "2025-02-15 (conflicted copy)"));
+        assert!(is_conflict_copy(
+            stem,
+            "2025-02-15 (Bo's conflicted copy 2025-02-16)"
+        ));
+        assert!(is_conflict_copy(
+            stem,
+            "2025-02-15.sync-conflict-20250215-101500-ABCDEFG"
+        ));
+        assert!(is_conflict_copy(
+            stem,
+            "2025-02-15_conflict-20250215-101500"
+        ));
+        assert!(!is_conflict_copy(stem, "2025-02-15"));
+        assert!(!is_conflict_copy(stem, "2025-02-15 (draft)"));
+        assert!(!is_conflict_copy(stem, "2025-02-16 (conflicted copy)"));
+    }
+
+    #[test]
+    fn test_merge_conflict_copies() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path().join("daily/2025");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("2025-02-15.md"),
+            "# 2025-02-15\n\n## Log\n\n- 09:00 standup\n- 12:00 lunch\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("2025-02-15 (conflicted copy).md"),
+            "# 2025-02-15\n\n## Log\n\n- 09:00 standup\n- 10:30 laptop entry\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("2025-02-15.sync-conflict-20250215-130000-XYZ.md"),
+            "# 2025-02-15\n\n## Log\n\n- 09:00 standup\n- 12:00 lunch\n- 13:00 phone entry\n",
+        )
+        .unwrap();
+        fs::write(dir.join("2025-02-16 (conflicted copy).md"), "other day\n").unwrap();
+
+        let rel = Path::new("daily/2025/2025-02-15.md");
+        let merged = merge_conflict_copies(tmp.path(), rel);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            fs::read_to_string(tmp.path().join(rel)).unwrap(),
+            "# 2025-02-15\n\n## Log\n\n- 09:00 standup\n- 10:30 laptop entry\n- 12:00 lunch\n- 13:00 phone entry\n"
+        );
+        assert!(!dir.join("2025-02-15 (conflicted copy).md").exists());
+        assert!(dir.join("2025-02-16 (conflicted copy).md").exists());
+        assert!(conflict_copies(tmp.path(), rel).is_empty());
+    }
+}
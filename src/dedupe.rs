@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::{dry_run, vault};
+
+/// Directory inside the notes dir that trashed notes are moved to.
+pub const TRASH_DIR_NAME: &str = ".trash";
+
+/// Two notes with identical (`similarity == 1.0`) or near-identical content.
+#[derive(Debug, PartialEq)]
+pub struct Duplicate {
+    pub first: PathBuf,
+    pub second: PathBuf,
+    pub similarity: f64,
+}
+
+fn content_lines(content: &str) -> HashSet<&str> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+/// Jaccard similarity of the notes' non-blank lines, ignoring indentation
+/// and line order.
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (content_lines(a), content_lines(b));
+    let union = a.union(&b).count();
+    match union {
+        0 => 1.0,
+        _ => a.intersection(&b).count() as f64 / union as f64,
+    }
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Pairs of non-empty notes whose content is identical or at least
+/// `threshold` similar, most similar first.
+pub fn find(notes_dir: &Path, threshold: f64) -> Vec<Duplicate> {
+    let notes: Vec<(PathBuf, String)> = vault::notes(notes_dir)
+        .into_iter()
+        .filter_map(|rel| {
+            let content = fs::read_to_string(notes_dir.join(&rel)).ok()?;
+            (!content.trim().is_empty()).then_some((rel, content))
+        })
+        .collect();
+
+    let hashes: Vec<u64> = notes.iter().map(|(_, content)| hash(content)).collect();
+
+    let mut duplicates = Vec::new();
+    for i in 0..notes.len() {
+        for j in i + 1..notes.len() {
+            let (a, b) = (&notes[i].1, &notes[j].1);
+            let similarity = match hashes[i] == hashes[j] && a == b {
+                true => 1.0,
+                false => similarity(a, b),
+            };
+            if similarity >= threshold {
+                duplicates.push(Duplicate {
+                    first: notes[i].0.clone(),
+                    second: notes[j].0.clone(),
+                    similarity,
+                });
+            }
+        }
+    }
+
+    duplicates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    duplicates
+}
+
+/// Move a note (or any file in the vault) into `.trash`, keeping its relative
+/// path. Returns where it went.
+pub fn trash(notes_dir: &Path, relative_path: &Path) -> PathBuf {
+    let mut target = notes_dir.join(TRASH_DIR_NAME).join(relative_path);
+    let mut n = 1;
+    while target.exists() {
+        n += 1;
+        let stem = relative_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        target.set_file_name(format!("{stem}-{n}"));
+        if let Some(ext) = relative_path.extension() {
+            target.set_extension(ext);
+        }
+    }
+
+    if dry_run::skip(format_args!(
+        "move {} to {}",
+        relative_path.display(),
+        target.display()
+    )) {
+        return target;
+    }
+
+    fs::create_dir_all(target.parent().unwrap()).expect("failed to create trash directory");
+    fs::rename(notes_dir.join(relative_path), &target).expect("failed to trash note");
+    target
+}
+
+/// Append the lines of `duplicate` that `keep` doesn't already have to
+/// `keep`, then trash `duplicate`.
+pub fn merge(notes_dir: &Path, keep: &Path, duplicate: &Path) {
+    let keep_file = notes_dir.join(keep);
+    let mut content = fs::read_to_string(&keep_file).unwrap_or_default();
+    let other = fs::read_to_string(notes_dir.join(duplicate)).unwrap_or_default();
+
+    let existing: HashSet<String> = content_lines(&content)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let missing: Vec<&str> = other
+        .lines()
+        .filter(|l| !l.trim().is_empty() && !existing.contains(l.trim()))
+        .collect();
+
+    if !missing.is_empty()
+        && !dry_run::skip(format_args!(
+            "add {} lines from {} to {}",
+            missing.len(),
+            duplicate.display(),
+            keep.display()
+        ))
+    {
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push('\n');
+        content.push_str(&missing.join("\n"));
+        content.push('\n');
+        fs::write(&keep_file, content).expect("failed to update note");
+    }
+
+    trash(notes_dir, duplicate);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("a\nb\n", "  b\n\na\n"), 1.0);
+        assert_eq!(similarity("a\nb\nc\n", "a\nb\nd\n"), 0.5);
+        assert_eq!(similarity("a\n", "b\n"), 0.0);
+    }
+
+    #[test]
+    fn test_find_identical_and_similar() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "a.md", "# Joins\n\ninner\nouter\nleft\nright\n");
+        write(
+            tmp.path(),
+            "import/a.md",
+            "# Joins\n\ninner\nouter\nleft\nright\n",
+        );
+        write(tmp.path(), "b.md", "# Joins\n\ninner\nouter\nleft\ncross\n");
+        write(tmp.path(), "c.md", "# Other\n");
+        write(tmp.path(), "empty1.md", "");
+        write(tmp.path(), "empty2.md", "");
+
+        let found = find(tmp.path(), 0.6);
+        let pairs: Vec<_> = found
+            .iter()
+            .map(|d| (d.first.to_str().unwrap(), d.second.to_str().unwrap()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("a.md", "import/a.md"),
+                ("a.md", "b.md"),
+                ("b.md", "import/a.md")
+            ]
+        );
+        assert_eq!(found[0].similarity, 1.0);
+    }
+
+    #[test]
+    fn test_trash_keeps_both_copies() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "a.md", "one");
+        write(tmp.path(), ".trash/a.md", "older");
+
+        let target = trash(tmp.path(), Path::new("a.md"));
+
+        assert_eq!(target, tmp.path().join(".trash/a-2.md"));
+        assert!(!tmp.path().join("a.md").exists());
+        assert_eq!(fs::read_to_string(target).unwrap(), "one");
+    }
+
+    #[test]
+    fn test_merge_appends_missing_lines() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "a.md", "# Joins\n\ninner\n");
+        write(tmp.path(), "b.md", "# Joins\n\ninner\n- outer\n");
+
+        merge(tmp.path(), Path::new("a.md"), Path::new("b.md"));
+
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("a.md")).unwrap(),
+            "# Joins\n\ninner\n\n- outer\n"
+        );
+        assert!(!tmp.path().join("b.md").exists());
+        assert!(tmp.path().join(".trash/b.md").exists());
+    }
+}
@@ -0,0 +1,44 @@
+use std::cell::Cell;
+use std::fmt::Display;
+
+// kno is single-threaded; a thread-local keeps parallel tests independent.
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn set(enabled: bool) {
+    ENABLED.with(|e| e.set(enabled));
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+/// In dry-run mode, report `action` instead of performing it and return true.
+/// Mutating code calls this right before touching the filesystem:
+///
+/// ```ignore
+/// if dry_run::skip(format_args!("create {}", path.display())) {
+///     return;
+/// }
+/// ```
+pub fn skip(action: impl Display) -> bool {
+    let enabled = is_enabled();
+    if enabled {
+        println!("Would {action}");
+    }
+    enabled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skip_follows_flag() {
+        assert!(!skip("do nothing"));
+        set(true);
+        assert!(skip("do nothing"));
+        set(false);
+    }
+}
@@ -0,0 +1,158 @@
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+
+use crate::log;
+
+/// Unix datagram socket in the notes dir that `kno events` listens on.
+pub const EVENTS_SOCKET_NAME: &str = ".events.sock";
+
+/// Something kno did to a note that other tools may want to react to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Created,
+    Edited,
+    Appended,
+}
+
+impl Event {
+    fn name(self) -> &'static str {
+        match self {
+            Event::Created => "created",
+            Event::Edited => "edited",
+            Event::Appended => "appended",
+        }
+    }
+}
+
+/// The JSON line announcing `event` on the note at `relative_path`.
+pub fn line(event: Event, relative_path: &Path, now: DateTime<Local>) -> String {
+    serde_json::json!({
+        "event": event.name(),
+        "path": relative_path.to_string_lossy(),
+        "time": now.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+    })
+    .to_string()
+}
+
+/// Announce `event` on `file_path` to a running `kno events`, if there is
+/// one. Best effort: a missing or busy listener never fails a command.
+pub fn emit(notes_dir: &Path, event: Event, file_path: &Path) {
+    let relative_path = file_path.strip_prefix(notes_dir).unwrap_or(file_path);
+    let line = line(event, relative_path, Local::now());
+    log::debug!("event {line}");
+    #[cfg(unix)]
+    {
+        use std::os::unix::net::UnixDatagram;
+        let socket_path = notes_dir.join(EVENTS_SOCKET_NAME);
+        if !socket_path.exists() {
+            return;
+        }
+        // A stopped or stalled listener fills its queue; drop the event
+        // rather than wait for it
+        let sent = UnixDatagram::unbound().and_then(|socket| {
+            socket.set_nonblocking(true)?;
+            socket.send_to(line.as_bytes(), &socket_path)
+        });
+        match sent {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                log::debug!("events listener is busy, dropping {line}");
+            }
+            Err(e) => log::debug!("no events listener at {}: {e}", socket_path.display()),
+        }
+    }
+}
+
+/// Listen on the events socket, writing each event as a JSON line to `out`
+/// until it closes. A socket left behind by a listener that died is replaced.
+#[cfg(unix)]
+pub fn listen(notes_dir: &Path, out: &mut impl std::io::Write) -> Result<(), String> {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket_path = notes_dir.join(EVENTS_SOCKET_NAME);
+    if socket_path.exists() {
+        let alive = UnixDatagram::unbound()
+            .and_then(|socket| socket.connect(&socket_path))
+            .is_ok();
+        if alive {
+            return Err(format!(
+                "another kno events is already listening on {}",
+                socket_path.display()
+            ));
+        }
+        std::fs::remove_file(&socket_path)
+            .map_err(|e| format!("failed to remove stale {}: {e}", socket_path.display()))?;
+    }
+    let socket = UnixDatagram::bind(&socket_path)
+        .map_err(|e| format!("failed to listen on {}: {e}", socket_path.display()))?;
+    log::debug!("listening on {}", socket_path.display());
+
+    let mut buf = vec![0; 64 * 1024];
+    let result = loop {
+        let len = match socket.recv(&mut buf) {
+            Ok(len) => len,
+            Err(e) => break Err(format!("failed to read event: {e}")),
+        };
+        // Stop quietly once whoever reads our output goes away
+        if writeln!(out, "{}", String::from_utf8_lossy(&buf[..len]))
+            .and_then(|()| out.flush())
+            .is_err()
+        {
+            break Ok(());
+        }
+    };
+    let _ = std::fs::remove_file(&socket_path);
+    result
+}
+
+#[cfg(not(unix))]
+pub fn listen(_notes_dir: &Path, _out: &mut impl std::io::Write) -> Result<(), String> {
+    Err("kno events needs unix sockets, which this platform lacks".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_line() {
+        let now = Local.with_ymd_and_hms(2025, 3, 1, 9, 30, 0).unwrap();
+        let line = line(Event::Appended, Path::new("daily/2025-03-01.md"), now);
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(value["event"], "appended");
+        assert_eq!(value["path"], "daily/2025-03-01.md");
+        assert!(
+            value["time"]
+                .as_str()
+                .unwrap()
+                .starts_with("2025-03-01T09:30:00")
+        );
+        assert!(!line.contains('\n'));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_emit_reaches_listener() {
+        use std::os::unix::net::UnixDatagram;
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        // Without a listener, emitting is a no-op
+        emit(tmp.path(), Event::Created, &tmp.path().join("a.md"));
+
+        let listener = UnixDatagram::bind(tmp.path().join(EVENTS_SOCKET_NAME)).unwrap();
+        emit(tmp.path(), Event::Edited, &tmp.path().join("sql/joins.md"));
+        let mut buf = [0; 1024];
+        let len = listener.recv(&mut buf).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&buf[..len]).unwrap();
+        assert_eq!(value["event"], "edited");
+        assert_eq!(value["path"], "sql/joins.md");
+
+        // A listener that stops reading never blocks the command emitting
+        for _ in 0..10_000 {
+            emit(tmp.path(), Event::Appended, &tmp.path().join("inbox.md"));
+        }
+        drop(listener);
+    }
+}
@@ -0,0 +1,584 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use pulldown_cmark::{CowStr, Event, LinkType, Options, Parser, Tag, html};
+use serde::Deserialize;
+
+use crate::assets::ASSETS_DIR_NAME;
+use crate::{dry_run, frontmatter, links, log, org, vault};
+
+/// `.obsidian/app.json` for an exported vault: wikilinks by full path, and
+/// attachments in `assets/`.
+const OBSIDIAN_APP_CONFIG: &str = "{\n  \"attachmentFolderPath\": \"assets\",\n  \"newLinkFormat\": \"absolute\",\n  \"useMarkdownLinks\": false\n}\n";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// One HTML page per note plus an index.html
+    Html,
+    /// One Org file per note, for Emacs
+    Org,
+    /// An Obsidian vault: [[wikilinks]], attachments in assets/ and an .obsidian/ config
+    Obsidian,
+}
+
+/// A named pandoc pipeline from `[export.profiles.<name>]`, run with
+/// `kno export --profile <name>`.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// pandoc output format (e.g. "docx", "latex"); "pdf" leaves it to pandoc's PDF engine.
+    pub format: String,
+    /// pandoc template, relative to the notes dir unless absolute.
+    pub template: Option<String>,
+    /// Filters run in order; `.lua` files are passed as Lua filters.
+    pub filters: Vec<String>,
+    /// Extra pandoc arguments (e.g. ["--toc", "--citeproc"]).
+    pub args: Vec<String>,
+    /// Extension of the output file; derived from `format` if unset.
+    pub extension: Option<String>,
+}
+
+impl Profile {
+    fn extension(&self) -> String {
+        if let Some(extension) = &self.extension {
+            return extension.clone();
+        }
+        // Drop pandoc extensions like `markdown+smart` or `gfm-raw_html`
+        let format = self.format.split(['+', '-']).next().unwrap_or_default();
+        match format {
+            "latex" | "beamer" => "tex",
+            "markdown" | "gfm" | "commonmark" | "commonmark_x" => "md",
+            "plain" => "txt",
+            "html4" | "html5" | "revealjs" | "slidy" => "html",
+            "asciidoc" => "adoc",
+            format => format,
+        }
+        .to_string()
+    }
+
+    /// Arguments for running pandoc from the notes dir on `notes`, writing
+    /// `out` (an absolute path).
+    fn pandoc_args(&self, notes: &[PathBuf], out: &Path) -> Vec<String> {
+        let mut args = vec!["--from".to_string(), "markdown".to_string()];
+        if self.format != "pdf" {
+            args.extend(["--to".to_string(), self.format.clone()]);
+        }
+        if let Some(template) = &self.template {
+            args.extend(["--template".to_string(), template.clone()]);
+        }
+        for filter in &self.filters {
+            let flag = match filter.ends_with(".lua") {
+                true => "--lua-filter",
+                false => "--filter",
+            };
+            args.extend([flag.to_string(), filter.clone()]);
+        }
+        // Let images linked relative to each note resolve
+        let mut dirs: Vec<String> = vec![".".to_string()];
+        for note in notes {
+            let dir = note.parent().unwrap_or(Path::new("")).to_string_lossy();
+            if !dir.is_empty() && !dirs.iter().any(|d| *d == dir) {
+                dirs.push(dir.into_owned());
+            }
+        }
+        args.push(format!("--resource-path={}", dirs.join(":")));
+        args.extend(self.args.iter().cloned());
+        args.extend(["--output".to_string(), out.to_string_lossy().into_owned()]);
+        args.extend(notes.iter().map(|n| n.to_string_lossy().into_owned()));
+        args
+    }
+}
+
+/// Run `profile` through pandoc on `notes`, in order, into a single file at
+/// `out` (given the profile's extension if it has none). Returns the path
+/// written.
+pub fn export_profile(
+    profile: &Profile,
+    notes_dir: &Path,
+    notes: &[PathBuf],
+    out: &Path,
+) -> Result<PathBuf, String> {
+    if profile.format.is_empty() {
+        return Err("the profile has no format".to_string());
+    }
+    let out = match out.extension() {
+        Some(_) => out.to_path_buf(),
+        None => out.with_extension(profile.extension()),
+    };
+    let out = std::env::current_dir()
+        .map(|dir| dir.join(&out))
+        .unwrap_or(out);
+    let args = profile.pandoc_args(notes, &out);
+    if dry_run::skip(format_args!("pandoc {}", args.join(" "))) {
+        return Ok(out);
+    }
+    if let Some(parent) = out.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    log::debug!("pandoc {}", args.join(" "));
+    let status = process::Command::new("pandoc")
+        .args(&args)
+        .current_dir(notes_dir)
+        .status()
+        .map_err(|e| format!("failed to run pandoc (is it installed?): {e}"))?;
+    match status.success() {
+        true => Ok(out),
+        false => Err(format!("pandoc exited with {status}")),
+    }
+}
+
+/// Whether a note may be exposed, from its `publish: true|false` or
+/// `visibility: public|private` frontmatter. Private wins if both are set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    Public,
+    Private,
+    Unset,
+}
+
+pub fn visibility(content: &str) -> Visibility {
+    let publish = frontmatter::get(content, "publish");
+    let visibility = frontmatter::get(content, "visibility");
+    let says = |publish_value: &str, visibility_value: &str| {
+        publish.as_deref() == Some(publish_value) || visibility.as_deref() == Some(visibility_value)
+    };
+    match (says("false", "private"), says("true", "public")) {
+        (true, _) => Visibility::Private,
+        (false, true) => Visibility::Public,
+        (false, false) => Visibility::Unset,
+    }
+}
+
+/// Notes from `notes` that may be exported: everything not marked private,
+/// or with `public_only`, just the notes explicitly marked public.
+pub fn exposed(notes_dir: &Path, notes: Vec<PathBuf>, public_only: bool) -> Vec<PathBuf> {
+    notes
+        .into_iter()
+        .filter(|rel| {
+            let content = fs::read_to_string(notes_dir.join(rel)).unwrap_or_default();
+            match visibility(&content) {
+                Visibility::Public => true,
+                Visibility::Unset => !public_only,
+                Visibility::Private => {
+                    log::debug!("skipping private note {}", rel.display());
+                    false
+                }
+            }
+        })
+        .collect()
+}
+
+/// Whether the note at `rel` may be sent out of the vault one at a time, by
+/// `kno share` or `kno mail`: not if it's marked private or hidden.
+pub fn check_sendable(rel: &Path, content: &str) -> Result<(), String> {
+    if visibility(content) == Visibility::Private {
+        return Err(format!("{} is marked private", rel.display()));
+    }
+    if vault::is_hidden(rel) {
+        return Err(format!("{} is hidden", rel.display()));
+    }
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Point links between notes at the exported pages (`joins.md#x` becomes
+/// `joins.html#x`). External links are left alone.
+fn rewrite_link(dest: CowStr) -> CowStr {
+    if dest.contains("://") {
+        return dest;
+    }
+    let (path, fragment) = dest.split_once('#').unwrap_or((&dest, ""));
+    match path.strip_suffix(".md") {
+        Some(stem) if fragment.is_empty() => format!("{stem}.html").into(),
+        Some(stem) => format!("{stem}.html#{fragment}").into(),
+        None => dest,
+    }
+}
+
+/// Render a note's markdown (frontmatter stripped) as a standalone HTML page.
+pub fn render_html(content: &str, title: &str) -> String {
+    let parser =
+        Parser::new_ext(frontmatter::body(content), Options::all()).map(|event| match event {
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: rewrite_link(dest_url),
+                title,
+                id,
+            }),
+            other => other,
+        });
+
+    let mut body = String::new();
+    html::push_html(&mut body, parser);
+    page(title, &body)
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{body}</body>\n</html>\n",
+        escape(title)
+    )
+}
+
+/// Frontmatter `title`, falling back to the file name.
+pub fn note_title(content: &str, relative_path: &Path) -> String {
+    frontmatter::get(content, "title").unwrap_or_else(|| {
+        relative_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    })
+}
+
+fn index_html(pages: &[(PathBuf, String)]) -> String {
+    let mut body = String::from("<ul>\n");
+    for (relative_path, title) in pages {
+        let href = relative_path.with_extension("html");
+        body.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            escape(&href.to_string_lossy()),
+            escape(title)
+        ));
+    }
+    body.push_str("</ul>\n");
+    page("Notes", &body)
+}
+
+/// Export `notes` (relative to `notes_dir`) as HTML pages under `out`,
+/// mirroring the vault layout, plus an `index.html` linking to each page.
+pub fn export_html(notes_dir: &Path, notes: &[PathBuf], out: &Path) {
+    if dry_run::skip(format_args!(
+        "export {} notes to {}",
+        notes.len(),
+        out.display()
+    )) {
+        return;
+    }
+
+    let mut pages = Vec::new();
+    for relative_path in notes {
+        let content = fs::read_to_string(notes_dir.join(relative_path)).unwrap_or_default();
+        let title = note_title(&content, relative_path);
+        let target = out.join(relative_path).with_extension("html");
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).expect("failed to create export directory");
+        }
+        fs::write(&target, render_html(&content, &title)).expect("failed to write export");
+        log::debug!("exported {}", target.display());
+        pages.push((relative_path.clone(), title));
+    }
+
+    fs::create_dir_all(out).expect("failed to create export directory");
+    fs::write(out.join("index.html"), index_html(&pages)).expect("failed to write export");
+}
+
+/// Export `notes` as Org files under `out`, mirroring the vault layout.
+pub fn export_org(notes_dir: &Path, notes: &[PathBuf], out: &Path) {
+    if dry_run::skip(format_args!(
+        "export {} notes to {} as Org",
+        notes.len(),
+        out.display()
+    )) {
+        return;
+    }
+
+    for relative_path in notes {
+        let content = fs::read_to_string(notes_dir.join(relative_path)).unwrap_or_default();
+        let title = note_title(&content, relative_path);
+        let target = out.join(relative_path).with_extension("org");
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).expect("failed to create export directory");
+        }
+        fs::write(&target, org::to_org(&content, &title)).expect("failed to write export");
+        log::debug!("exported {}", target.display());
+    }
+}
+
+/// Where the attachment at `rel` goes in an Obsidian export: under the
+/// attachment folder, `assets/`, if it isn't there already.
+fn obsidian_attachment(rel: &Path) -> PathBuf {
+    match rel.starts_with(ASSETS_DIR_NAME) {
+        true => rel.to_path_buf(),
+        false => Path::new(ASSETS_DIR_NAME).join(rel),
+    }
+}
+
+/// `content`, the note at `from`, with its relative markdown links written as
+/// Obsidian wikilinks: `[[sql/joins#btree|text]]` for notes and
+/// `![[assets/diagram.png]]` for embeds. URLs are left alone.
+pub fn to_wikilinks(content: &str, from: &Path) -> String {
+    let body = frontmatter::body(content);
+    let mut out = content[..content.len() - body.len()].to_string();
+    let mut last = 0;
+    for (event, range) in Parser::new_ext(body, Options::all()).into_offset_iter() {
+        let (embed, dest) = match event {
+            Event::Start(Tag::Link {
+                link_type: LinkType::Inline,
+                dest_url,
+                ..
+            }) => (false, dest_url),
+            Event::Start(Tag::Image {
+                link_type: LinkType::Inline,
+                dest_url,
+                ..
+            }) => (true, dest_url),
+            _ => continue,
+        };
+        // An image inside a link is handled with the link
+        if range.start < last {
+            continue;
+        }
+        let (link, fragment) = dest.split_once('#').unwrap_or((&dest, ""));
+        if link.is_empty() || link.contains(':') {
+            continue;
+        }
+        let Some(target) = links::resolve(from, link) else {
+            continue;
+        };
+        let source = &body[range.clone()];
+        let Some(text_end) = source.rfind("](") else {
+            continue;
+        };
+        let text = &source[if embed { 2 } else { 1 }..text_end];
+
+        let name = match target.extension().is_some_and(|ext| ext == "md") {
+            true => target.with_extension(""),
+            false => obsidian_attachment(&target),
+        };
+        let mut wikilink = name.to_string_lossy().into_owned();
+        if !fragment.is_empty() {
+            wikilink = format!("{wikilink}#{fragment}");
+        }
+        let stem = name.file_name().unwrap_or_default().to_string_lossy();
+        let replacement = match embed {
+            true => format!("![[{wikilink}]]"),
+            false if text.is_empty() || text == wikilink || text == stem => {
+                format!("[[{wikilink}]]")
+            }
+            false => format!("[[{wikilink}|{text}]]"),
+        };
+        out.push_str(&body[last..range.start]);
+        out.push_str(&replacement);
+        last = range.end;
+    }
+    out.push_str(&body[last..]);
+    out
+}
+
+/// Export `notes` as an Obsidian vault under `out`: notes keep their paths
+/// with links as wikilinks, the files they link to are copied into `assets/`,
+/// and `.obsidian/app.json` points Obsidian there.
+pub fn export_obsidian(notes_dir: &Path, notes: &[PathBuf], out: &Path) {
+    if dry_run::skip(format_args!(
+        "export {} notes to {} for Obsidian",
+        notes.len(),
+        out.display()
+    )) {
+        return;
+    }
+
+    let mut attachments = BTreeSet::new();
+    for relative_path in notes {
+        let content = fs::read_to_string(notes_dir.join(relative_path)).unwrap_or_default();
+        for target in links::targets(relative_path, &content) {
+            let is_note = target.extension().is_some_and(|ext| ext == "md");
+            if !is_note && notes_dir.join(&target).is_file() {
+                attachments.insert(target);
+            }
+        }
+        let target = out.join(relative_path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).expect("failed to create export directory");
+        }
+        fs::write(&target, to_wikilinks(&content, relative_path)).expect("failed to write export");
+        log::debug!("exported {}", target.display());
+    }
+    for relative_path in attachments {
+        let target = out.join(obsidian_attachment(&relative_path));
+        fs::create_dir_all(target.parent().unwrap()).expect("failed to create export directory");
+        fs::copy(notes_dir.join(&relative_path), &target).expect("failed to copy attachment");
+    }
+
+    let config = out.join(".obsidian");
+    fs::create_dir_all(&config).expect("failed to create export directory");
+    fs::write(config.join("app.json"), OBSIDIAN_APP_CONFIG).expect("failed to write export");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_html_rewrites_note_links() {
+        let html = render_html(
+            "---\ntags: [sql]\n---\n# Joins\n\nSee [idx](indexes.md#btree) and [web](https://x.io/a.md).\n",
+            "Joins",
+        );
+        assert!(html.contains("<title>Joins</title>"));
+        assert!(html.contains("<h1>Joins</h1>"));
+        assert!(html.contains("href=\"indexes.html#btree\""));
+        assert!(html.contains("href=\"https://x.io/a.md\""));
+        assert!(!html.contains("tags:"));
+    }
+
+    #[test]
+    fn test_visibility() {
+        let cases = [
+            ("# x\n", Visibility::Unset),
+            ("---\npublish: true\n---\n", Visibility::Public),
+            ("---\nvisibility: public\n---\n", Visibility::Public),
+            ("---\npublish: false\n---\n", Visibility::Private),
+            ("---\nvisibility: \"private\"\n---\n", Visibility::Private),
+            (
+                "---\npublish: true\nvisibility: private\n---\n",
+                Visibility::Private,
+            ),
+        ];
+        for (content, expected) in cases {
+            assert_eq!(visibility(content), expected, "{content}");
+        }
+    }
+
+    #[test]
+    fn test_exposed() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(tmp.path().join("a.md"), "---\npublish: true\n---\n").unwrap();
+        fs::write(tmp.path().join("b.md"), "# b\n").unwrap();
+        fs::write(tmp.path().join("c.md"), "---\nvisibility: private\n---\n").unwrap();
+        let notes = || ["a.md", "b.md", "c.md"].map(PathBuf::from).to_vec();
+
+        assert_eq!(
+            exposed(tmp.path(), notes(), false),
+            ["a.md", "b.md"].map(PathBuf::from)
+        );
+        assert_eq!(exposed(tmp.path(), notes(), true), [PathBuf::from("a.md")]);
+    }
+
+    #[test]
+    fn test_check_sendable() {
+        let note = Path::new("sql/joins.md");
+        assert!(check_sendable(note, "# Joins\n").is_ok());
+        assert!(check_sendable(note, "---\npublish: true\n---\n").is_ok());
+        assert_eq!(
+            check_sendable(note, "---\nvisibility: private\n---\n").unwrap_err(),
+            "sql/joins.md is marked private"
+        );
+        assert_eq!(
+            check_sendable(Path::new(".journal/today.md"), "# Today\n").unwrap_err(),
+            ".journal/today.md is hidden"
+        );
+    }
+
+    #[test]
+    fn test_export_html_writes_pages_and_index() {
+        let notes = tempfile::TempDir::new().unwrap();
+        let out = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(notes.path().join("sql")).unwrap();
+        fs::write(notes.path().join("sql/joins.md"), "# Joins\n").unwrap();
+
+        export_html(notes.path(), &[PathBuf::from("sql/joins.md")], out.path());
+
+        let page = fs::read_to_string(out.path().join("sql/joins.html")).unwrap();
+        assert!(page.contains("<h1>Joins</h1>"));
+        let index = fs::read_to_string(out.path().join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"sql/joins.html\">joins</a>"));
+    }
+
+    #[test]
+    fn test_profile_pandoc_args() {
+        let profile = Profile {
+            format: "latex".to_string(),
+            template: Some("templates/thesis.tex".to_string()),
+            filters: vec!["pandoc-crossref".to_string(), "wordcount.lua".to_string()],
+            args: vec!["--toc".to_string()],
+            extension: None,
+        };
+        let notes = [
+            PathBuf::from("thesis/intro.md"),
+            PathBuf::from("thesis/method.md"),
+        ];
+        assert_eq!(profile.extension(), "tex");
+        assert_eq!(
+            profile.pandoc_args(&notes, Path::new("/tmp/thesis.tex")),
+            [
+                "--from",
+                "markdown",
+                "--to",
+                "latex",
+                "--template",
+                "templates/thesis.tex",
+                "--filter",
+                "pandoc-crossref",
+                "--lua-filter",
+                "wordcount.lua",
+                "--resource-path=.:thesis",
+                "--toc",
+                "--output",
+                "/tmp/thesis.tex",
+                "thesis/intro.md",
+                "thesis/method.md",
+            ]
+        );
+
+        let pdf = Profile {
+            format: "pdf".to_string(),
+            ..Profile::default()
+        };
+        assert_eq!(pdf.extension(), "pdf");
+        assert!(
+            !pdf.pandoc_args(&notes, Path::new("/tmp/x.pdf"))
+                .contains(&"--to".to_string())
+        );
+    }
+
+    #[test]
+    fn test_to_wikilinks() {
+        let content = "---\ntags: [sql]\n---\n# Joins\n\nSee [indexes](indexes.md), [the B-tree part](../db/btree.md#pages), ![diagram](../assets/joins.png) and [spec](spec.pdf).\nAlso [web](https://x.io/a.md) and `[code](x.md)`.\n";
+        assert_eq!(
+            to_wikilinks(content, Path::new("sql/joins.md")),
+            "---\ntags: [sql]\n---\n# Joins\n\nSee [[sql/indexes]], [[db/btree#pages|the B-tree part]], ![[assets/joins.png]] and [[assets/sql/spec.pdf|spec]].\nAlso [web](https://x.io/a.md) and `[code](x.md)`.\n"
+        );
+    }
+
+    #[test]
+    fn test_export_obsidian_copies_attachments() {
+        let notes = tempfile::TempDir::new().unwrap();
+        let out = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(notes.path().join("sql")).unwrap();
+        fs::create_dir_all(notes.path().join("assets")).unwrap();
+        fs::write(
+            notes.path().join("sql/joins.md"),
+            "# Joins\n\n![d](../assets/d.png) [s](spec.pdf)\n",
+        )
+        .unwrap();
+        fs::write(notes.path().join("sql/spec.pdf"), "pdf").unwrap();
+        fs::write(notes.path().join("assets/d.png"), "png").unwrap();
+        fs::write(notes.path().join("assets/unused.png"), "png").unwrap();
+
+        export_obsidian(notes.path(), &[PathBuf::from("sql/joins.md")], out.path());
+
+        let read = |rel: &str| fs::read_to_string(out.path().join(rel)).unwrap();
+        assert_eq!(
+            read("sql/joins.md"),
+            "# Joins\n\n![[assets/d.png]] [[assets/sql/spec.pdf|s]]\n"
+        );
+        assert_eq!(read("assets/d.png"), "png");
+        assert_eq!(read("assets/sql/spec.pdf"), "pdf");
+        assert!(!out.path().join("assets/unused.png").exists());
+        assert!(read(".obsidian/app.json").contains("\"attachmentFolderPath\": \"assets\""));
+    }
+}
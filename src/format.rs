@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+
+use crate::{tags, vault};
+
+/// Fields a `--format` template can use for notes (`kno list`, `kno recent`).
+pub const NOTE_FIELDS: &[&str] = &["path", "title", "modified", "tags"];
+
+/// Fields a `--format` template can use for `kno search` matches.
+pub const MATCH_FIELDS: &[&str] = &["path", "line", "text"];
+
+/// Fields a `--format` template can use for `kno todo` tasks.
+pub const TASK_FIELDS: &[&str] = &["path", "line", "text", "priority", "number"];
+
+/// A run of literal text or a `{field}` in a template.
+#[derive(Debug, PartialEq)]
+enum Piece<'a> {
+    Text(String),
+    Field(&'a str),
+}
+
+/// Split a template into text and fields. `\t`, `\n` and `\\` are escapes
+/// (shells don't expand them in single quotes) and `{{`/`}}` are literal braces.
+fn pieces(template: &str) -> Result<Vec<Piece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            '\\' => match rest.chars().next() {
+                Some('t') => text.push('\t'),
+                Some('n') => text.push('\n'),
+                Some('\\') => text.push('\\'),
+                _ => {
+                    text.push('\\');
+                    continue;
+                }
+            },
+            '{' if rest.starts_with('{') => text.push('{'),
+            '}' if rest.starts_with('}') => text.push('}'),
+            '{' => {
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| format!("unclosed {{ in format: {template}"))?;
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(Piece::Field(rest[..end].trim()));
+                rest = &rest[end + 1..];
+                continue;
+            }
+            '}' => return Err(format!("unmatched }} in format: {template}")),
+            _ => {
+                text.push(c);
+                continue;
+            }
+        }
+        // Skip the second character of an escape or doubled brace
+        rest = &rest[1..];
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+/// Check that `template` parses and only uses `fields`, returning it for use
+/// as a clap value parser.
+pub fn check(template: &str, fields: &[&str]) -> Result<String, String> {
+    for piece in pieces(template)? {
+        if let Piece::Field(name) = piece
+            && !fields.contains(&name)
+        {
+            return Err(format!(
+                "unknown field {{{name}}}; expected one of {}",
+                fields
+                    .iter()
+                    .map(|f| format!("{{{f}}}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+    }
+    Ok(template.to_string())
+}
+
+/// Fill in a template already checked by `check` with field values.
+pub fn render(template: &str, values: &[(&str, String)]) -> String {
+    let pieces = pieces(template).expect("format is checked when parsing arguments");
+    let mut out = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => out.push_str(&text),
+            Piece::Field(name) => {
+                if let Some((_, value)) = values.iter().find(|(n, _)| *n == name) {
+                    out.push_str(value);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Values of `NOTE_FIELDS` for a note.
+pub fn note_values(notes_dir: &Path, relative_path: &Path) -> Vec<(&'static str, String)> {
+    let file_path = notes_dir.join(relative_path);
+    let content = fs::read_to_string(&file_path).unwrap_or_default();
+    let modified = vault::modified(&file_path)
+        .map(|m| {
+            DateTime::<Local>::from(m)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default();
+    vec![
+        ("path", relative_path.display().to_string()),
+        ("title", vault::title(&content, relative_path)),
+        ("modified", modified),
+        ("tags", tags::note_tags(&content).join(",")),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render_fields_and_escapes() {
+        let values = [("path", "a.md".to_string()), ("line", "3".to_string())];
+        assert_eq!(render(r"{path}\t{line}\n", &values), "a.md\t3\n");
+        assert_eq!(render("{{{path}}} \\x", &values), "{a.md} \\x");
+    }
+
+    #[test]
+    fn test_check_rejects_unknown_and_unbalanced() {
+        assert!(check("{path}:{line}", MATCH_FIELDS).is_ok());
+        let err = check("{path} {size}", NOTE_FIELDS).unwrap_err();
+        assert!(err.contains("{size}"), "{err}");
+        assert!(check("{path", NOTE_FIELDS).is_err());
+        assert!(check("path}", NOTE_FIELDS).is_err());
+    }
+
+    #[test]
+    fn test_note_values() {
+        let tmp = TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("a.md"),
+            "---\ntags: [rust, cli]\n---\n# Alpha\n",
+        )
+        .unwrap();
+        let values = note_values(tmp.path(), &PathBuf::from("a.md"));
+        assert_eq!(
+            render("{path}|{title}|{tags}", &values),
+            "a.md|Alpha|rust,cli"
+        );
+        assert_eq!(
+            render("{modified}", &values).len(),
+            "2025-01-01 12:00".len()
+        );
+    }
+}
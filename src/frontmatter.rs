@@ -0,0 +1,210 @@
+// Minimal YAML frontmatter support: enough to read and rewrite simple
+// `key: value` and list fields in place, leaving everything else untouched.
+
+/// Split `content` into frontmatter lines and the body that follows.
+/// Returns `None` when the note has no `---` delimited block at the top.
+fn split(content: &str) -> Option<(Vec<&str>, &str)> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+
+    let mut offset = 0;
+    let mut lines = Vec::new();
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return Some((lines, &rest[offset..]));
+        }
+        lines.push(trimmed);
+    }
+    None
+}
+
+/// The body of a note without its frontmatter block.
+pub fn body(content: &str) -> &str {
+    split(content).map(|(_, body)| body).unwrap_or(content)
+}
+
+fn key_of(line: &str) -> Option<&str> {
+    if line.starts_with([' ', '\t', '-', '#']) {
+        return None;
+    }
+    line.split_once(':').map(|(k, _)| k.trim())
+}
+
+/// Index range of the lines making up `key` (the key line plus any indented
+/// block list items that follow it).
+fn key_range(lines: &[&str], key: &str) -> Option<std::ops::Range<usize>> {
+    let start = lines.iter().position(|l| key_of(l) == Some(key))?;
+    let len = lines[start + 1..]
+        .iter()
+        .take_while(|l| l.starts_with([' ', '\t']) || l.starts_with("- "))
+        .count();
+    Some(start..start + 1 + len)
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .or_else(|| s.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')))
+        .unwrap_or(s)
+}
+
+/// A scalar field, unquoted. `None` if the note has no such key.
+pub fn get(content: &str, key: &str) -> Option<String> {
+    let (lines, _) = split(content)?;
+    let line = lines.iter().find(|l| key_of(l) == Some(key))?;
+    let (_, value) = line.split_once(':')?;
+    Some(unquote(value).to_string())
+}
+
+/// A list field in flow (`tags: [a, b]`), block (`tags:` + `- a` lines) or
+/// comma-separated scalar (`tags: a, b`) form.
+pub fn get_list(content: &str, key: &str) -> Vec<String> {
+    let Some((lines, _)) = split(content) else {
+        return Vec::new();
+    };
+    let Some(range) = key_range(&lines, key) else {
+        return Vec::new();
+    };
+
+    let (_, inline) = lines[range.start].split_once(':').unwrap_or_default();
+    let inline = inline.trim();
+    let items: Vec<&str> = match inline {
+        "" => lines[range.start + 1..range.end]
+            .iter()
+            .filter_map(|l| l.trim_start().strip_prefix('-'))
+            .collect(),
+        _ => inline
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(',')
+            .collect(),
+    };
+
+    items
+        .into_iter()
+        .map(unquote)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn quote_if_needed(value: &str) -> String {
+    let special = value.is_empty()
+        || value.contains([':', '#', ',', '[', ']', '{', '}', '"', '\''])
+        || value.starts_with(['-', '!', '&', '*', ' ']);
+    match special {
+        true => format!("\"{}\"", value.replace('"', "\\\"")),
+        false => value.to_string(),
+    }
+}
+
+/// Replace (or add) `key` with the given raw YAML line, or remove it when
+/// `line` is `None`. Adds a frontmatter block if the note has none.
+fn set_line(content: &str, key: &str, line: Option<String>) -> String {
+    let (mut lines, body): (Vec<String>, &str) = match split(content) {
+        Some((lines, body)) => (lines.into_iter().map(str::to_string).collect(), body),
+        None if line.is_none() => return content.to_string(),
+        None => (Vec::new(), content),
+    };
+
+    let borrowed: Vec<&str> = lines.iter().map(String::as_str).collect();
+    match (key_range(&borrowed, key), line) {
+        (Some(range), Some(line)) => {
+            lines.splice(range, [line]);
+        }
+        (Some(range), None) => {
+            lines.drain(range);
+        }
+        (None, Some(line)) => lines.push(line),
+        (None, None) => {}
+    }
+
+    let mut out = String::from("---\n");
+    for line in &lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str("---\n");
+    out.push_str(body);
+    out
+}
+
+/// Set a scalar field, quoting the value when YAML would misread it.
+pub fn set(content: &str, key: &str, value: &str) -> String {
+    set_line(
+        content,
+        key,
+        Some(format!("{key}: {}", quote_if_needed(value))),
+    )
+}
+
+/// Set a list field in flow style, removing the key when `values` is empty.
+pub fn set_list(content: &str, key: &str, values: &[String]) -> String {
+    let line = (!values.is_empty()).then(|| {
+        let items: Vec<_> = values.iter().map(|v| quote_if_needed(v)).collect();
+        format!("{key}: [{}]", items.join(", "))
+    });
+    set_line(content, key, line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_frontmatter() {
+        let content = "# Joins\n---\n";
+        assert!(get_list(content, "tags").is_empty());
+        assert_eq!(body(content), content);
+    }
+
+    #[test]
+    fn test_body_skips_frontmatter() {
+        assert_eq!(body("---\ntags: [a]\n---\n# Joins\n"), "# Joins\n");
+    }
+
+    #[test]
+    fn test_get_list_forms() {
+        let flow = "---\ntags: [sql, 'db']\n---\n";
+        let block = "---\ntags:\n  - sql\n  - db\ntitle: x\n---\n";
+        let scalar = "---\ntags: sql, db\n---\n";
+        for content in [flow, block, scalar] {
+            assert_eq!(get_list(content, "tags"), ["sql", "db"], "{content}");
+        }
+    }
+
+    #[test]
+    fn test_get_and_set_scalar() {
+        let content = set("# Joins\n", "title", "SQL: joins");
+        assert_eq!(content, "---\ntitle: \"SQL: joins\"\n---\n# Joins\n");
+        assert_eq!(get(&content, "title").as_deref(), Some("SQL: joins"));
+        assert_eq!(get(&content, "created"), None);
+    }
+
+    #[test]
+    fn test_set_list_replaces_block_list() {
+        let content = "---\ntitle: x\ntags:\n  - sql\n  - db\nother: y\n---\nbody\n";
+        let out = set_list(content, "tags", &["sql".into(), "rust".into()]);
+        assert_eq!(
+            out,
+            "---\ntitle: x\ntags: [sql, rust]\nother: y\n---\nbody\n"
+        );
+    }
+
+    #[test]
+    fn test_set_list_adds_frontmatter() {
+        let out = set_list("# Joins\n", "tags", &["sql".into()]);
+        assert_eq!(out, "---\ntags: [sql]\n---\n# Joins\n");
+    }
+
+    #[test]
+    fn test_set_list_empty_removes_key() {
+        let out = set_list("---\ntags: [sql]\ntitle: x\n---\nbody", "tags", &[]);
+        assert_eq!(out, "---\ntitle: x\n---\nbody");
+        assert_eq!(set_list("body", "tags", &[]), "body");
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{Local, NaiveDate};
+
+use crate::commit::git;
+use crate::stats;
+
+/// Git's empty tree, the base for days before the first commit.
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Words added in `git diff --word-diff=porcelain` output.
+pub fn added_words(word_diff: &str) -> usize {
+    let mut in_hunk = false;
+    let mut words = 0;
+    for line in word_diff.lines() {
+        if line.starts_with("diff --git") {
+            in_hunk = false;
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+        } else if in_hunk && let Some(added) = line.strip_prefix('+') {
+            words += added.split_whitespace().count();
+        }
+    }
+    words
+}
+
+/// The last commit made before `date` began, or the empty tree.
+fn commit_before(notes_dir: &Path, date: NaiveDate) -> Result<String, String> {
+    let before = format!("--before={date} 00:00");
+    let rev = git(notes_dir, &["rev-list", "-1", &before, "HEAD"]).unwrap_or_default();
+    match rev.trim() {
+        "" => Ok(EMPTY_TREE.to_string()),
+        rev => Ok(rev.to_string()),
+    }
+}
+
+/// Words written in notes on `date`: those added between the last commit
+/// before that day and the last one before the next. For today the working
+/// tree, including notes git doesn't track yet, is compared instead.
+pub fn words_on(notes_dir: &Path, date: NaiveDate) -> Result<usize, String> {
+    let base = commit_before(notes_dir, date)?;
+    let today = Local::now().date_naive();
+    let mut args = vec!["diff", "--word-diff=porcelain", &base];
+    let next;
+    if date < today {
+        next = commit_before(notes_dir, date.succ_opt().unwrap())?;
+        args.push(&next);
+    }
+    args.extend(["--", "*.md"]);
+    let mut words = added_words(&git(notes_dir, &args)?);
+
+    if date >= today {
+        let untracked = git(
+            notes_dir,
+            &["ls-files", "--others", "--exclude-standard", "--", "*.md"],
+        )?;
+        for rel in untracked.lines() {
+            let content = fs::read_to_string(notes_dir.join(rel)).unwrap_or_default();
+            words += stats::word_count(&content);
+        }
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_added_words() {
+        let diff = "\
+diff --git a/sql/joins.md b/sql/joins.md
+index 1111111..2222222 100644
+--- a/sql/joins.md
++++ b/sql/joins.md
+@@ -1,2 +1,2 @@
+ # Joins
+~
+-old words
++three new words
+ kept
+~
+diff --git a/new.md b/new.md
+new file mode 100644
+--- /dev/null
++++ b/new.md
+@@ -0,0 +1 @@
++# New
+~
+";
+        assert_eq!(added_words(diff), 5);
+        assert_eq!(added_words(""), 0);
+    }
+
+    #[test]
+    fn test_words_on_today() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path();
+        git(dir, &["init", "-q"]).unwrap();
+        fs::write(dir.join("a.md"), "one two\n").unwrap();
+        fs::write(dir.join("b.md"), "three four five\n").unwrap();
+
+        // Nothing committed yet: everything counts as written today
+        let today = Local::now().date_naive();
+        assert_eq!(words_on(dir, today).unwrap(), 5);
+
+        git(dir, &["add", "a.md"]).unwrap();
+        let commit = [
+            "-c",
+            "user.name=t",
+            "-c",
+            "user.email=t@t",
+            "commit",
+            "-qm",
+            "a",
+        ];
+        git(dir, &commit).unwrap();
+        fs::write(dir.join("a.md"), "one two six\n").unwrap();
+        assert_eq!(words_on(dir, today).unwrap(), 6);
+    }
+}
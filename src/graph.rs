@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{links, vault};
+
+/// Links between the notes in the vault. Links to missing notes are dropped.
+#[derive(Debug, Default)]
+pub struct Graph {
+    /// Every note, with the notes it links to.
+    pub links: BTreeMap<PathBuf, BTreeSet<PathBuf>>,
+}
+
+impl Graph {
+    pub fn build(notes_dir: &Path) -> Graph {
+        let notes = vault::notes(notes_dir);
+        let known: BTreeSet<&PathBuf> = notes.iter().collect();
+        let mut links = BTreeMap::new();
+        for rel in &notes {
+            let content = fs::read_to_string(notes_dir.join(rel)).unwrap_or_default();
+            let targets = links::targets(rel, &content)
+                .into_iter()
+                .filter(|target| target != rel && known.contains(target))
+                .collect();
+            links.insert(rel.clone(), targets);
+        }
+        Graph { links }
+    }
+
+    /// How many notes link to each note.
+    fn backlink_counts(&self) -> BTreeMap<&PathBuf, usize> {
+        let mut counts: BTreeMap<&PathBuf, usize> = self.links.keys().map(|n| (n, 0)).collect();
+        for target in self.links.values().flatten() {
+            *counts.entry(target).or_default() += 1;
+        }
+        counts
+    }
+
+    /// The `n` most linked-to notes with their backlink counts, most first.
+    /// Notes nothing links to aren't hubs.
+    pub fn hubs(&self, n: usize) -> Vec<(&PathBuf, usize)> {
+        let mut hubs: Vec<_> = self
+            .backlink_counts()
+            .into_iter()
+            .filter(|&(_, count)| count > 0)
+            .collect();
+        hubs.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        hubs.truncate(n);
+        hubs
+    }
+
+    /// Notes that other notes link to but that link nowhere themselves.
+    pub fn dead_ends(&self) -> Vec<&PathBuf> {
+        self.backlink_counts()
+            .into_iter()
+            .filter(|&(note, count)| count > 0 && self.links[note].is_empty())
+            .map(|(note, _)| note)
+            .collect()
+    }
+
+    /// Groups of notes connected by links in either direction, largest first.
+    pub fn clusters(&self) -> Vec<Vec<&PathBuf>> {
+        let mut neighbours: BTreeMap<&PathBuf, Vec<&PathBuf>> = BTreeMap::new();
+        for (from, targets) in &self.links {
+            neighbours.entry(from).or_default();
+            for to in targets {
+                neighbours.entry(from).or_default().push(to);
+                neighbours.entry(to).or_default().push(from);
+            }
+        }
+
+        let mut seen = BTreeSet::new();
+        let mut clusters = Vec::new();
+        for &start in neighbours.keys() {
+            if !seen.insert(start) {
+                continue;
+            }
+            let mut cluster = vec![start];
+            let mut stack = vec![start];
+            while let Some(note) = stack.pop() {
+                for &next in &neighbours[note] {
+                    if seen.insert(next) {
+                        cluster.push(next);
+                        stack.push(next);
+                    }
+                }
+            }
+            cluster.sort();
+            clusters.push(cluster);
+        }
+        clusters.sort_by_key(|c| std::cmp::Reverse(c.len()));
+        clusters
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_metrics() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(
+            tmp.path(),
+            "index.md",
+            "[j](sql/joins.md) [i](sql/indexes.md)",
+        );
+        write(
+            tmp.path(),
+            "sql/joins.md",
+            "[i](indexes.md) [gone](missing.md)",
+        );
+        write(tmp.path(), "sql/indexes.md", "[self](indexes.md)");
+        write(tmp.path(), "trip/plan.md", "[p](packing.md)");
+        write(tmp.path(), "trip/packing.md", "");
+        write(tmp.path(), "lonely.md", "[web](https://example.com)");
+
+        let graph = Graph::build(tmp.path());
+        let p = |s: &str| PathBuf::from(s);
+        assert_eq!(
+            graph.hubs(2),
+            [(&p("sql/indexes.md"), 2), (&p("sql/joins.md"), 1)]
+        );
+        assert_eq!(
+            graph.dead_ends(),
+            [&p("sql/indexes.md"), &p("trip/packing.md")]
+        );
+
+        let clusters = graph.clusters();
+        assert_eq!(
+            clusters,
+            [
+                vec![&p("index.md"), &p("sql/indexes.md"), &p("sql/joins.md")],
+                vec![&p("trip/packing.md"), &p("trip/plan.md")],
+                vec![&p("lonely.md")],
+            ]
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::{daily, dry_run, tasks};
+
+/// File listing tracked habits, one name per line.
+pub const HABITS_FILE_NAME: &str = ".habits";
+
+/// Checklist text marking a habit done in a daily note: `- [x] habit: <name>`.
+const MARK_PREFIX: &str = "habit: ";
+
+/// Tracked habits, in the order they were added.
+pub fn read(notes_dir: &Path) -> Vec<String> {
+    let content = fs::read_to_string(notes_dir.join(HABITS_FILE_NAME)).unwrap_or_default();
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Start tracking `name`.
+pub fn add(notes_dir: &Path, name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("habit name can't be empty".to_string());
+    }
+    let mut habits = read(notes_dir);
+    if habits.iter().any(|h| h == name) {
+        return Err(format!("already tracking {name}"));
+    }
+    if dry_run::skip(format_args!("add habit {name}")) {
+        return Ok(());
+    }
+    habits.push(name.to_string());
+    let content: String = habits.iter().map(|h| format!("{h}\n")).collect();
+    fs::write(notes_dir.join(HABITS_FILE_NAME), content).expect("failed to write habits file");
+    Ok(())
+}
+
+/// The checklist line recording `name` as done.
+pub fn mark(name: &str) -> String {
+    format!("- [x] {MARK_PREFIX}{name}")
+}
+
+/// Habits marked done in a daily note's content.
+pub fn marked(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(tasks::parse_line)
+        .filter_map(|(done, text)| done.then(|| text.strip_prefix(MARK_PREFIX))?)
+        .map(|name| name.trim().to_string())
+        .collect()
+}
+
+/// Days from `start` to `end` (inclusive) on which each habit was marked done.
+pub fn history(
+    notes_dir: &Path,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> HashMap<String, BTreeSet<NaiveDate>> {
+    let mut history: HashMap<String, BTreeSet<NaiveDate>> = HashMap::new();
+    for (date, rel) in daily::daily_notes(notes_dir) {
+        if date < start || date > end {
+            continue;
+        }
+        let content = fs::read_to_string(notes_dir.join(rel)).unwrap_or_default();
+        for name in marked(&content) {
+            history.entry(name).or_default().insert(date);
+        }
+    }
+    history
+}
+
+/// Consecutive days done up to `today`. A streak still counts if today
+/// isn't done yet but yesterday was.
+pub fn streak(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> usize {
+    let mut day = match days.contains(&today) {
+        true => today,
+        false => today.pred_opt().unwrap(),
+    };
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        day = day.pred_opt().unwrap();
+    }
+    streak
+}
+
+/// One character per day from `start` to `end`: `#` done, `.` missed.
+pub fn chart(days: &BTreeSet<NaiveDate>, start: NaiveDate, end: NaiveDate) -> String {
+    start
+        .iter_days()
+        .take_while(|d| *d <= end)
+        .map(|d| match days.contains(&d) {
+            true => '#',
+            false => '.',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_add_and_read() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        add(tmp.path(), "meditate").unwrap();
+        add(tmp.path(), " read ").unwrap();
+        assert_eq!(read(tmp.path()), ["meditate", "read"]);
+        assert!(add(tmp.path(), "read").is_err());
+        assert!(add(tmp.path(), "  ").is_err());
+    }
+
+    #[test]
+    fn test_marked() {
+        let content = format!(
+            "# Day\n\n## Habits\n\n{}\n- [ ] habit: run\n- [x] read a book\n",
+            mark("meditate")
+        );
+        assert_eq!(marked(&content), ["meditate"]);
+    }
+
+    #[test]
+    fn test_history_streak_and_chart() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let daily = tmp.path().join("daily/2026");
+        fs::create_dir_all(&daily).unwrap();
+        for day in ["2026-10-13", "2026-10-15", "2026-10-16", "2026-10-17"] {
+            fs::write(daily.join(format!("{day}.md")), mark("run")).unwrap();
+        }
+        fs::write(daily.join("2026-09-01.md"), mark("run")).unwrap();
+
+        let history = history(tmp.path(), date("2026-10-12"), date("2026-10-18"));
+        let run = &history["run"];
+        assert_eq!(run.len(), 4);
+        assert_eq!(
+            chart(run, date("2026-10-12"), date("2026-10-18")),
+            ".#.###."
+        );
+        assert_eq!(streak(run, date("2026-10-18")), 3);
+        assert_eq!(streak(run, date("2026-10-17")), 3);
+        assert_eq!(streak(run, date("2026-10-20")), 0);
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{dry_run, frontmatter, links, log};
+
+/// Extensions of markdown files, which are imported as `.md` notes.
+const MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown", "mdown", "mkd", "mkdn", "mdwn"];
+
+/// What importing a tree will do: files to copy (source path to path in the
+/// vault) and files skipped because something is already at their path.
+#[derive(Debug, Default, PartialEq)]
+pub struct Plan {
+    pub copies: Vec<(PathBuf, PathBuf)>,
+    pub conflicts: Vec<(PathBuf, String)>,
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| {
+        MARKDOWN_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str())
+    })
+}
+
+/// A file or directory name in kno's style: lowercase, words joined by `-`,
+/// and nothing that needs quoting in a shell or link.
+pub fn normalize_name(name: &str) -> String {
+    let mut out = String::new();
+    for c in name.trim().chars().flat_map(char::to_lowercase) {
+        match c {
+            c if c.is_alphanumeric() || c == '.' => out.push(c),
+            _ if out.ends_with('-') || out.is_empty() => {}
+            _ => out.push('-'),
+        }
+    }
+    let out = out.trim_matches(['-', '.']);
+    match out.is_empty() {
+        true => "untitled".to_string(),
+        false => out.to_string(),
+    }
+}
+
+/// Where the file at `rel` in the imported tree goes, relative to `into`.
+fn target(rel: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    let dirs = rel.parent().unwrap_or(Path::new(""));
+    for dir in dirs {
+        out.push(normalize_name(&dir.to_string_lossy()));
+    }
+    let stem = rel.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = rel.extension().unwrap_or_default().to_string_lossy();
+    match is_markdown(rel) {
+        true => out.push(format!("{}.md", normalize_name(&stem))),
+        false if ext.is_empty() => out.push(normalize_name(&stem)),
+        false => out.push(format!("{}.{}", normalize_name(&stem), ext.to_lowercase())),
+    }
+    out
+}
+
+fn collect(root: &Path, dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("failed to read {}: {e}", dir.display()))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        match path.is_dir() {
+            true => collect(root, &path, out)?,
+            false => out.push(path.strip_prefix(root).unwrap().to_path_buf()),
+        }
+    }
+    Ok(())
+}
+
+/// `path`, or `path` numbered `-2`, `-3`, ... until it's neither in the
+/// vault nor already taken by this import.
+pub fn unique_path(notes_dir: &Path, path: PathBuf, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let mut candidate = path.clone();
+    let mut n = 1;
+    while taken.contains(&candidate) || notes_dir.join(&candidate).exists() {
+        n += 1;
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        candidate = path.with_file_name(format!("{stem}-{n}"));
+        if let Some(ext) = path.extension() {
+            candidate.set_extension(ext);
+        }
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+/// Plan importing the tree at `src` into `into` (relative to the notes dir),
+/// skipping dot-files and anything that would overwrite a file.
+pub fn plan_dir(src: &Path, notes_dir: &Path, into: &Path) -> Result<Plan, String> {
+    if !src.is_dir() {
+        return Err(format!("{} is not a directory", src.display()));
+    }
+    let (real_src, real_notes) = (fs::canonicalize(src), fs::canonicalize(notes_dir));
+    if let (Ok(real_src), Ok(real_notes)) = (real_src, real_notes)
+        && (real_src.starts_with(&real_notes) || real_notes.starts_with(&real_src))
+    {
+        return Err(format!("{} overlaps the notes directory", src.display()));
+    }
+
+    let mut files = Vec::new();
+    collect(src, src, &mut files)?;
+    files.sort();
+
+    let mut plan = Plan::default();
+    let mut claimed: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for rel in files {
+        let to = into.join(target(&rel));
+        if let Some(other) = claimed.get(&to) {
+            let reason = format!("{} is also imported from {}", to.display(), other.display());
+            plan.conflicts.push((rel, reason));
+        } else if notes_dir.join(&to).exists() {
+            plan.conflicts
+                .push((rel, format!("{} already exists", to.display())));
+        } else {
+            claimed.insert(to.clone(), rel.clone());
+            plan.copies.push((rel, to));
+        }
+    }
+    Ok(plan)
+}
+
+/// `content` with a `# title` header after any frontmatter, unless its body
+/// already starts with one.
+pub fn with_title(content: &str, title: &str) -> String {
+    let body = frontmatter::body(content);
+    let has_title = body
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .is_some_and(|l| l.starts_with("# "));
+    if has_title {
+        return content.to_string();
+    }
+    let front = &content[..content.len() - body.len()];
+    let body = body.trim_start_matches('\n');
+    match body.is_empty() {
+        true => format!("{front}# {title}\n"),
+        false => format!("{front}# {title}\n\n{body}"),
+    }
+}
+
+/// Copy the files in `plan` from `src` into the notes dir. Notes get a title
+/// from their original file name if they lack one, and links between the
+/// imported files follow them to their new names.
+pub fn import(src: &Path, notes_dir: &Path, into: &Path, plan: &Plan) -> Result<(), String> {
+    // Links in the imported notes are relative to where they were in `src`,
+    // which corresponds to the same place under `into`
+    let moves: HashMap<PathBuf, PathBuf> = plan
+        .copies
+        .iter()
+        .map(|(rel, to)| (into.join(rel), to.clone()))
+        .collect();
+
+    for (rel, to) in &plan.copies {
+        if dry_run::skip(format_args!("import {} as {}", rel.display(), to.display())) {
+            continue;
+        }
+        let from = src.join(rel);
+        let path = notes_dir.join(to);
+        fs::create_dir_all(path.parent().unwrap())
+            .map_err(|e| format!("failed to create {}: {e}", path.display()))?;
+        let copied = match is_markdown(rel) {
+            true => fs::read(&from).and_then(|bytes| {
+                let content = String::from_utf8_lossy(&bytes);
+                let title = rel.file_stem().unwrap_or_default().to_string_lossy();
+                let content = with_title(&content, title.trim());
+                let content = links::rewrite_moved(&content, &into.join(rel), to, &moves);
+                fs::write(&path, content)
+            }),
+            false => fs::copy(&from, &path).map(|_| ()),
+        };
+        copied.map_err(|e| format!("failed to import {}: {e}", rel.display()))?;
+        log::debug!("imported {} as {}", rel.display(), to.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Meeting Notes"), "meeting-notes");
+        assert_eq!(normalize_name("  Q3 Plan (draft) "), "q3-plan-draft");
+        assert_eq!(normalize_name("Café_menu"), "café-menu");
+        assert_eq!(normalize_name("???"), "untitled");
+    }
+
+    #[test]
+    fn test_with_title() {
+        assert_eq!(with_title("Some text\n", "Ideas"), "# Ideas\n\nSome text\n");
+        assert_eq!(with_title("\n# Has one\n", "Ideas"), "\n# Has one\n");
+        assert_eq!(
+            with_title("---\ntags: [a]\n---\nText\n", "Ideas"),
+            "---\ntags: [a]\n---\n# Ideas\n\nText\n"
+        );
+        assert_eq!(with_title("", "Empty"), "# Empty\n");
+    }
+
+    #[test]
+    fn test_import_dir() {
+        let tmp = TempDir::new().unwrap();
+        let (src, notes) = (tmp.path().join("src"), tmp.path().join("notes"));
+        write(
+            &src,
+            "Work Stuff/Q3 Plan.markdown",
+            "See [ideas](../Ideas.md)\n",
+        );
+        write(&src, "Ideas.md", "# Ideas\n\n![d](img/Diagram.PNG)\n");
+        write(&src, "ideas.mkd", "duplicate name\n");
+        write(&src, "img/Diagram.PNG", "png");
+        write(&src, "taken.md", "new\n");
+        write(&src, ".obsidian/app.json", "{}");
+        write(&notes, "imported/taken.md", "old\n");
+
+        let into = Path::new("imported");
+        let plan = plan_dir(&src, &notes, into).unwrap();
+        assert_eq!(
+            plan.conflicts,
+            [
+                (
+                    PathBuf::from("ideas.mkd"),
+                    "imported/ideas.md is also imported from Ideas.md".to_string()
+                ),
+                (
+                    PathBuf::from("taken.md"),
+                    "imported/taken.md already exists".to_string()
+                ),
+            ]
+        );
+        import(&src, &notes, into, &plan).unwrap();
+
+        let read = |rel: &str| fs::read_to_string(notes.join(rel)).unwrap();
+        assert_eq!(
+            read("imported/work-stuff/q3-plan.md"),
+            "# Q3 Plan\n\nSee [ideas](../ideas.md)\n"
+        );
+        assert_eq!(
+            read("imported/ideas.md"),
+            "# Ideas\n\n![d](img/diagram.png)\n"
+        );
+        assert_eq!(read("imported/img/diagram.png"), "png");
+        assert_eq!(read("imported/taken.md"), "old\n");
+        assert!(!notes.join("imported/.obsidian").exists());
+    }
+
+    #[test]
+    fn test_plan_dir_rejects_the_vault_itself() {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("notes/old")).unwrap();
+        let notes = tmp.path().join("notes");
+        assert!(plan_dir(&notes.join("old"), &notes, Path::new("")).is_err());
+    }
+}
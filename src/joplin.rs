@@ -0,0 +1,391 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+use std::time::SystemTime;
+
+use chrono::DateTime;
+use regex::Regex;
+
+use crate::{assets, dry_run, frontmatter, import, links, log, tags};
+
+// Joplin's item types (`type_` in an item's metadata)
+const NOTE: &str = "1";
+const FOLDER: &str = "2";
+const RESOURCE: &str = "4";
+const TAG: &str = "5";
+const NOTE_TAG: &str = "6";
+
+/// One item of a JEX export: a note, notebook, resource, tag or note-tag
+/// link, stored as `<id>.md` with its title, body and a trailing block of
+/// `key: value` metadata.
+#[derive(Debug, Default, PartialEq)]
+struct Item {
+    title: String,
+    body: String,
+    meta: HashMap<String, String>,
+}
+
+impl Item {
+    fn get(&self, key: &str) -> &str {
+        self.meta.get(key).map_or("", String::as_str)
+    }
+
+    /// The user-facing time `key` (`created_time`, `updated_time`), which the
+    /// `user_` variant overrides.
+    fn time(&self, key: &str) -> Option<DateTime<chrono::FixedOffset>> {
+        let value = match self.get(&format!("user_{key}")) {
+            "" => self.get(key),
+            value => value,
+        };
+        DateTime::parse_from_rfc3339(value).ok()
+    }
+}
+
+fn is_meta_line(line: &str) -> bool {
+    line.split_once(':').is_some_and(|(key, value)| {
+        !key.is_empty()
+            && key.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+            && (value.is_empty() || value.starts_with(' '))
+    })
+}
+
+fn parse_item(content: &str) -> Option<Item> {
+    let lines: Vec<&str> = content.trim_end().lines().collect();
+    let meta_start = lines
+        .iter()
+        .rposition(|l| !is_meta_line(l))
+        .map_or(0, |i| i + 1);
+    if meta_start == lines.len() {
+        return None;
+    }
+    let meta = lines[meta_start..]
+        .iter()
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.to_string(), v.trim().to_string()))
+        .collect();
+    let head = &lines[..meta_start];
+    let title = head.first().unwrap_or(&"").trim().to_string();
+    let body = head.get(2..).unwrap_or_default().join("\n");
+    Some(Item {
+        title,
+        body: body.trim_end().to_string(),
+        meta,
+    })
+}
+
+/// What an import did.
+#[derive(Debug, Default, PartialEq)]
+pub struct Summary {
+    pub notes: Vec<PathBuf>,
+    pub resources: Vec<PathBuf>,
+    /// Items not imported (by title), and why.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Import an unpacked JEX export in `dir`: notebooks become directories under
+/// `into`, notes become markdown files with their tags and creation date in
+/// frontmatter, and resources go to `assets/`. Links between notes and to
+/// resources are rewritten to relative links.
+pub fn import_extracted(dir: &Path, notes_dir: &Path, into: &Path) -> Result<Summary, String> {
+    let mut items = BTreeMap::new();
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("failed to read {}: {e}", dir.display()))?;
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+        let content = fs::read_to_string(&path).unwrap_or_default();
+        match parse_item(&content) {
+            Some(item) => {
+                items.insert(item.get("id").to_string(), item);
+            }
+            None => log::debug!("not a Joplin item: {}", path.display()),
+        }
+    }
+    let of_type = |kind: &'static str| items.values().filter(move |i| i.get("type_") == kind);
+
+    // Notebooks nest by parent_id; the depth limit guards against cycles
+    let folder_path = |id: &str| {
+        let mut parts = Vec::new();
+        let mut id = id;
+        while let Some(folder) = items.get(id).filter(|i| i.get("type_") == FOLDER) {
+            if parts.len() > 32 {
+                break;
+            }
+            parts.push(import::normalize_name(&folder.title));
+            id = folder.get("parent_id");
+        }
+        parts.iter().rev().collect::<PathBuf>()
+    };
+
+    let mut note_tags: HashMap<&str, Vec<String>> = HashMap::new();
+    for link in of_type(NOTE_TAG) {
+        let tag = items
+            .get(link.get("tag_id"))
+            .filter(|t| t.get("type_") == TAG);
+        if let Some(tag) = tag {
+            note_tags
+                .entry(link.get("note_id"))
+                .or_default()
+                .push(tag.title.clone());
+        }
+    }
+
+    let mut taken = HashSet::new();
+    let mut targets: HashMap<&str, PathBuf> = HashMap::new();
+    let mut summary = Summary::default();
+    for resource in of_type(RESOURCE) {
+        let id = resource.get("id");
+        let ext = resource.get("file_extension");
+        let file = match ext {
+            "" => dir.join("resources").join(id),
+            ext => dir.join("resources").join(format!("{id}.{ext}")),
+        };
+        if !file.is_file() {
+            summary.skipped.push((
+                resource.title.clone(),
+                "resource file missing from the export".to_string(),
+            ));
+            continue;
+        }
+        let stem = Path::new(&resource.title)
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let name = match ext {
+            "" => import::normalize_name(&stem),
+            ext => format!("{}.{}", import::normalize_name(&stem), ext.to_lowercase()),
+        };
+        let rel = import::unique_path(
+            notes_dir,
+            Path::new(assets::ASSETS_DIR_NAME).join(name),
+            &mut taken,
+        );
+        targets.insert(id, rel.clone());
+        if !dry_run::skip(format_args!(
+            "import resource {} as {}",
+            resource.title,
+            rel.display()
+        )) {
+            let path = notes_dir.join(&rel);
+            fs::create_dir_all(path.parent().unwrap())
+                .map_err(|e| format!("failed to create {}: {e}", path.display()))?;
+            fs::copy(&file, &path)
+                .map_err(|e| format!("failed to import {}: {e}", resource.title))?;
+        }
+        summary.resources.push(rel);
+    }
+    for note in of_type(NOTE) {
+        let folder = into.join(folder_path(note.get("parent_id")));
+        let name = format!("{}.md", import::normalize_name(&note.title));
+        targets.insert(
+            note.get("id"),
+            import::unique_path(notes_dir, folder.join(name), &mut taken),
+        );
+    }
+
+    let link = Regex::new(r"\]\(:/([0-9a-fA-F]{32})([#)\s])").expect("valid regex");
+    for note in of_type(NOTE) {
+        let rel = &targets[note.get("id")];
+        let body = link.replace_all(&note.body, |caps: &regex::Captures| {
+            match targets.get(&caps[1]) {
+                Some(target) => format!("]({}{}", links::relative_link(rel, target), &caps[2]),
+                None => caps[0].to_string(),
+            }
+        });
+        let title = match note.title.is_empty() {
+            true => "Untitled",
+            false => &note.title,
+        };
+        let mut content = format!("# {title}\n\n{body}\n");
+        if let Some(created) = note.time("created_time") {
+            content =
+                frontmatter::set(&content, "created", &created.format("%Y-%m-%d").to_string());
+        }
+        if let Some(names) = note_tags.get(note.get("id")) {
+            let mut tags: Vec<String> = names
+                .iter()
+                .map(|t| tags::normalize(t).replace(' ', "-"))
+                .collect();
+            tags.sort();
+            tags.dedup();
+            content = frontmatter::set_list(&content, "tags", &tags);
+        }
+
+        if dry_run::skip(format_args!(
+            "import note {} as {}",
+            note.title,
+            rel.display()
+        )) {
+            summary.notes.push(rel.clone());
+            continue;
+        }
+        let path = notes_dir.join(rel);
+        fs::create_dir_all(path.parent().unwrap())
+            .map_err(|e| format!("failed to create {}: {e}", path.display()))?;
+        fs::write(&path, content).map_err(|e| format!("failed to write {}: {e}", rel.display()))?;
+        // Keep the note's own edit time rather than the time of the import
+        if let Some(updated) = note.time("updated_time") {
+            let time = SystemTime::from(updated);
+            let _ = fs::File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|f| f.set_modified(time));
+        }
+        summary.notes.push(rel.clone());
+    }
+    summary.notes.sort();
+    summary.resources.sort();
+    Ok(summary)
+}
+
+/// Unpack the JEX export (a tar archive) at `jex` and import it; see
+/// `import_extracted`.
+pub fn import(jex: &Path, notes_dir: &Path, into: &Path) -> Result<Summary, String> {
+    let listing = process::Command::new("tar")
+        .arg("-tf")
+        .arg(jex)
+        .output()
+        .map_err(|e| format!("failed to run tar: {e}"))?;
+    if !listing.status.success() {
+        return Err(format!("{} is not a readable JEX export", jex.display()));
+    }
+    let unsafe_entry = String::from_utf8_lossy(&listing.stdout)
+        .lines()
+        .map(Path::new)
+        .any(|entry| {
+            entry
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        });
+    if unsafe_entry {
+        return Err(format!("{} has entries outside the archive", jex.display()));
+    }
+
+    // Private to this user, and removed however the import ends
+    let staging = tempfile::Builder::new()
+        .prefix("kno-joplin-")
+        .tempdir()
+        .map_err(|e| format!("failed to create a staging directory: {e}"))?;
+    log::debug!("tar -xf {} -C {}", jex.display(), staging.path().display());
+    let status = process::Command::new("tar")
+        .arg("-xf")
+        .arg(jex)
+        .arg("-C")
+        .arg(staging.path())
+        .status()
+        .map_err(|e| format!("failed to run tar: {e}"))?;
+    match status.success() {
+        true => import_extracted(staging.path(), notes_dir, into),
+        false => Err(format!("tar exited with {status}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const NOTE_ID: &str = "0123456789abcdef0123456789abcdef";
+    const OTHER_ID: &str = "11111111111111111111111111111111";
+    const FOLDER_ID: &str = "22222222222222222222222222222222";
+    const CHILD_ID: &str = "33333333333333333333333333333333";
+    const RESOURCE_ID: &str = "44444444444444444444444444444444";
+    const TAG_ID: &str = "55555555555555555555555555555555";
+
+    fn write(dir: &Path, rel: &str, content: &str) {
+        let path = dir.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_parse_item() {
+        let item =
+            parse_item("Joins\n\nInner join: rows in both.\n\nid: abc\nparent_id: def\ntype_: 1\n")
+                .unwrap();
+        assert_eq!(item.title, "Joins");
+        assert_eq!(item.body, "Inner join: rows in both.");
+        assert_eq!(item.get("parent_id"), "def");
+
+        let link = parse_item("id: x\nnote_id: n\ntag_id: t\ntype_: 6").unwrap();
+        assert_eq!(link.title, "");
+        assert_eq!(link.get("tag_id"), "t");
+        assert_eq!(parse_item("Just text\n\nno metadata here."), None);
+    }
+
+    #[test]
+    fn test_import_extracted() {
+        let tmp = TempDir::new().unwrap();
+        let (export, notes) = (tmp.path().join("export"), tmp.path().join("notes"));
+        write(
+            &export,
+            &format!("{FOLDER_ID}.md"),
+            &format!("Work Stuff\n\nid: {FOLDER_ID}\nparent_id: \ntype_: 2\n"),
+        );
+        write(
+            &export,
+            &format!("{CHILD_ID}.md"),
+            &format!("SQL\n\nid: {CHILD_ID}\nparent_id: {FOLDER_ID}\ntype_: 2\n"),
+        );
+        write(
+            &export,
+            &format!("{NOTE_ID}.md"),
+            &format!(
+                "Joins\n\nSee [other](:/{OTHER_ID}) and ![d](:/{RESOURCE_ID}).\n\nid: {NOTE_ID}\nparent_id: {CHILD_ID}\ncreated_time: 2021-05-04T10:00:00.000Z\nuser_created_time: 2020-01-02T09:30:00.000Z\nupdated_time: 2021-06-01T08:00:00.000Z\ntype_: 1\n"
+            ),
+        );
+        write(
+            &export,
+            &format!("{OTHER_ID}.md"),
+            &format!("Inbox\n\nhello\n\nid: {OTHER_ID}\nparent_id: \ntype_: 1\n"),
+        );
+        write(
+            &export,
+            &format!("{RESOURCE_ID}.md"),
+            &format!("Diagram 1.PNG\n\nid: {RESOURCE_ID}\nfile_extension: png\ntype_: 4\n"),
+        );
+        write(&export, &format!("resources/{RESOURCE_ID}.png"), "png");
+        write(
+            &export,
+            &format!("{TAG_ID}.md"),
+            &format!("databases\n\nid: {TAG_ID}\ntype_: 5\n"),
+        );
+        write(
+            &export,
+            "66666666666666666666666666666666.md",
+            &format!("id: 6\nnote_id: {NOTE_ID}\ntag_id: {TAG_ID}\ntype_: 6\n"),
+        );
+        write(&notes, "joplin/inbox.md", "already here\n");
+
+        let summary = import_extracted(&export, &notes, Path::new("joplin")).unwrap();
+        assert_eq!(
+            summary.notes,
+            [
+                PathBuf::from("joplin/inbox-2.md"),
+                PathBuf::from("joplin/work-stuff/sql/joins.md")
+            ]
+        );
+        assert_eq!(summary.resources, [PathBuf::from("assets/diagram-1.png")]);
+
+        let joins = notes.join("joplin/work-stuff/sql/joins.md");
+        assert_eq!(
+            fs::read_to_string(&joins).unwrap(),
+            "---\ncreated: 2020-01-02\ntags: [databases]\n---\n# Joins\n\nSee [other](../../inbox-2.md) and ![d](../../../assets/diagram-1.png).\n"
+        );
+        let modified = fs::metadata(&joins).unwrap().modified().unwrap();
+        let expected = DateTime::parse_from_rfc3339("2021-06-01T08:00:00Z").unwrap();
+        assert_eq!(modified, SystemTime::from(expected));
+        assert_eq!(
+            fs::read_to_string(notes.join("joplin/inbox.md")).unwrap(),
+            "already here\n"
+        );
+        assert_eq!(
+            fs::read_to_string(notes.join("assets/diagram-1.png")).unwrap(),
+            "png"
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+use crate::frontmatter;
+
+/// Build a markdown-friendly relative link from the note at `from` to the note
+/// at `to`. Both paths are relative to the notes dir.
+pub fn relative_link(from: &Path, to: &Path) -> String {
+    let from_dir: Vec<_> = from
+        .parent()
+        .map(|p| p.components().collect())
+        .unwrap_or_default();
+    let to_parts: Vec<_> = to.components().collect();
+
+    let common = from_dir
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let ups = std::iter::repeat_n("..".to_string(), from_dir.len() - common);
+    let downs = to_parts[common..].iter().map(|c| match c {
+        Component::Normal(s) => s.to_string_lossy().into_owned(),
+        other => other.as_os_str().to_string_lossy().into_owned(),
+    });
+
+    ups.chain(downs).collect::<Vec<_>>().join("/")
+}
+
+/// `link` with its `%XX` escapes decoded, as editors write spaces and other
+/// characters in link destinations (`my%20note.md`). Anything that isn't a
+/// valid escape is kept as it is.
+pub fn percent_decode(link: &str) -> String {
+    let bytes = link.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).unwrap_or_else(|_| link.to_string())
+}
+
+/// The note a relative link in the note at `from` points to, relative to the
+/// notes dir, with escapes like `%20` decoded. `None` for links that leave
+/// the vault.
+pub fn resolve(from: &Path, link: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    let dir = from.parent().unwrap_or(Path::new(""));
+    for component in dir.join(percent_decode(link)).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !path.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(path)
+}
+
+/// Notes and files the markdown links and images in `content`, the note at
+/// `from`, point to, relative to the notes dir, in order and without repeats.
+/// URLs, in-page anchors and links leaving the vault are skipped.
+pub fn targets(from: &Path, content: &str) -> Vec<PathBuf> {
+    let mut targets = Vec::new();
+    for event in Parser::new_ext(frontmatter::body(content), Options::all()) {
+        let (Event::Start(Tag::Link { dest_url, .. }) | Event::Start(Tag::Image { dest_url, .. })) =
+            event
+        else {
+            continue;
+        };
+        let link = dest_url.split('#').next().unwrap_or_default();
+        if link.is_empty() || link.contains(':') {
+            continue;
+        }
+        if let Some(target) = resolve(from, link)
+            && !targets.contains(&target)
+        {
+            targets.push(target);
+        }
+    }
+    targets
+}
+
+/// The byte ranges of `content` inside code spans and code blocks.
+fn code_ranges(content: &str) -> Vec<Range<usize>> {
+    Parser::new_ext(content, Options::all())
+        .into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::Code(_) | Event::Start(Tag::CodeBlock(_))))
+        .map(|(_, range)| range)
+        .collect()
+}
+
+/// Rewrite the relative markdown links in `content`, a note moving from `from`
+/// to `to`, so they still point at the same notes after every `(old, new)`
+/// move in `moves`. URLs, in-page anchors and anything in code are left alone.
+pub fn rewrite_moved(
+    content: &str,
+    from: &Path,
+    to: &Path,
+    moves: &HashMap<PathBuf, PathBuf>,
+) -> String {
+    let code = code_ranges(content);
+    let mut out = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("](") {
+        let at = content.len() - rest.len() + start;
+        let (before, after) = rest.split_at(start + 2);
+        out.push_str(before);
+        if code.iter().any(|range| range.contains(&at)) {
+            rest = after;
+            continue;
+        }
+        let Some(end) = after.find([')', ' ']) else {
+            rest = after;
+            break;
+        };
+        let (link, fragment) = match after[..end].split_once('#') {
+            Some((link, fragment)) => (link, Some(fragment)),
+            None => (&after[..end], None),
+        };
+        let rewritten = (!link.is_empty() && !link.contains(':'))
+            .then(|| resolve(from, link))
+            .flatten()
+            .filter(|target| from != to || moves.contains_key(target))
+            .map(|target| {
+                let target = moves.get(&target).unwrap_or(&target);
+                // A bare space would end the link
+                let mut link = relative_link(to, target).replace(' ', "%20");
+                if let Some(fragment) = fragment {
+                    link = format!("{link}#{fragment}");
+                }
+                link
+            });
+        match rewritten {
+            Some(link) => out.push_str(&link),
+            None => out.push_str(&after[..end]),
+        }
+        rest = &after[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_link_sibling() {
+        let link = relative_link(Path::new("sql/joins.md"), Path::new("sql/indexes.md"));
+        assert_eq!(link, "indexes.md");
+    }
+
+    #[test]
+    fn test_relative_link_from_daily() {
+        let link = relative_link(
+            Path::new("daily/2026/2026-02-15.md"),
+            Path::new("meetings/2026-02-15-sync.md"),
+        );
+        assert_eq!(link, "../../meetings/2026-02-15-sync.md");
+    }
+
+    #[test]
+    fn test_relative_link_from_root() {
+        let link = relative_link(Path::new("inbox.md"), Path::new("sql/joins.md"));
+        assert_eq!(link, "sql/joins.md");
+    }
+
+    #[test]
+    fn test_rewrite_moved_links() {
+        let moves = HashMap::from([(
+            PathBuf::from("daily/2025/2025-02-15.md"),
+            PathBuf::from("daily/2025-02-15.md"),
+        )]);
+
+        // A note linking to a moved note
+        let content = "See [day](../daily/2025/2025-02-15.md#todo) and [web](https://x.io/a.md).";
+        assert_eq!(
+            rewrite_moved(
+                content,
+                Path::new("sql/joins.md"),
+                Path::new("sql/joins.md"),
+                &moves
+            ),
+            "See [day](../daily/2025-02-15.md#todo) and [web](https://x.io/a.md)."
+        );
+
+        // A moved note linking out
+        let content = "- [Sync](../../meetings/sync.md \"title\")\n[top](#top)\n";
+        assert_eq!(
+            rewrite_moved(
+                content,
+                Path::new("daily/2025/2025-02-15.md"),
+                Path::new("daily/2025-02-15.md"),
+                &moves
+            ),
+            "- [Sync](../meetings/sync.md \"title\")\n[top](#top)\n"
+        );
+
+        // Escaped links resolve, and stay escaped
+        let moves = HashMap::from([(PathBuf::from("my note.md"), PathBuf::from("old/my note.md"))]);
+        assert_eq!(
+            rewrite_moved(
+                "[m](my%20note.md)",
+                Path::new("a.md"),
+                Path::new("a.md"),
+                &moves
+            ),
+            "[m](old/my%20note.md)"
+        );
+
+        // Unrelated links are untouched
+        let content = "[a](./b.md) [out](../../../etc.md)";
+        assert_eq!(
+            rewrite_moved(content, Path::new("a.md"), Path::new("a.md"), &moves),
+            content
+        );
+
+        // Links in code are examples, not links
+        let content = "[m](my%20note.md) `[m](my%20note.md)`\n\n```\n[m](my%20note.md)\n```\n";
+        assert_eq!(
+            rewrite_moved(content, Path::new("a.md"), Path::new("a.md"), &moves),
+            "[m](old/my%20note.md) `[m](my%20note.md)`\n\n```\n[m](my%20note.md)\n```\n"
+        );
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("my%20note.md"), "my note.md");
+        assert_eq!(percent_decode("caf%C3%A9.md"), "café.md");
+        assert_eq!(percent_decode("100%.md"), "100%.md");
+        assert_eq!(percent_decode("a%+1.md"), "a%+1.md");
+    }
+
+    #[test]
+    fn test_targets_decode_escapes() {
+        let content = "[b](my%20note.md) ![scan](assets/my%20scan.png)\n";
+        assert_eq!(
+            targets(Path::new("index.md"), content),
+            [
+                PathBuf::from("my note.md"),
+                PathBuf::from("assets/my scan.png")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_targets() {
+        let content = "---\ntitle: x\n---\n[a](../sql/joins.md#on) [b](indexes.md) \
+                       [again](../sql/joins.md) [web](https://x.io/a.md) [top](#top) \
+                       [out](../../x.md) ![er](../assets/er.png)\n\n`[code](not.md)`\n";
+        assert_eq!(
+            targets(Path::new("notes/db.md"), content),
+            [
+                PathBuf::from("sql/joins.md"),
+                PathBuf::from("notes/indexes.md"),
+                PathBuf::from("assets/er.png")
+            ]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::commit::git;
+use crate::{context, daily, sync};
+
+/// Directory in the notes dir holding a lock for each note being edited.
+/// It is committed and pushed as locks are taken and released (see
+/// `publish`), so other machines see the locks.
+pub const LOCKS_DIR_NAME: &str = ".locks";
+
+/// Which machine is editing a note, and since when.
+#[derive(Debug, PartialEq)]
+pub struct Lock {
+    pub host: String,
+    pub since: SystemTime,
+}
+
+/// This machine's name in locks.
+pub fn host() -> String {
+    context::hostname().unwrap_or_else(|| "unknown".to_string())
+}
+
+/// `.locks/<note path>.lock`, holding `<host>\t<unix secs>`.
+fn lock_path(notes_dir: &Path, relative_path: &Path) -> PathBuf {
+    let mut name = relative_path.as_os_str().to_owned();
+    name.push(".lock");
+    notes_dir.join(LOCKS_DIR_NAME).join(name)
+}
+
+/// The lock on the note at `relative_path`, if any.
+pub fn read(notes_dir: &Path, relative_path: &Path) -> Option<Lock> {
+    let content = fs::read_to_string(lock_path(notes_dir, relative_path)).ok()?;
+    let (host, secs) = content.trim().split_once('\t')?;
+    Some(Lock {
+        host: host.to_string(),
+        since: UNIX_EPOCH + Duration::from_secs(secs.parse().ok()?),
+    })
+}
+
+/// Another machine's lock on the note, unless it's older than `expire`.
+pub fn held_elsewhere(
+    notes_dir: &Path,
+    relative_path: &Path,
+    host: &str,
+    expire: Duration,
+    now: SystemTime,
+) -> Option<Lock> {
+    read(notes_dir, relative_path).filter(|lock| {
+        let age = now.duration_since(lock.since).unwrap_or_default();
+        lock.host != host && age < expire
+    })
+}
+
+/// Record that `host` is editing the note as of `now`. Best effort: a lock
+/// that can't be written never blocks editing.
+pub fn acquire(notes_dir: &Path, relative_path: &Path, host: &str, now: SystemTime) {
+    let path = lock_path(notes_dir, relative_path);
+    let secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let _ = fs::write(&path, format!("{host}\t{secs}\n"));
+}
+
+/// Drop `host`'s lock on the note; another machine's lock is left alone.
+pub fn release(notes_dir: &Path, relative_path: &Path, host: &str) {
+    if read(notes_dir, relative_path).is_some_and(|lock| lock.host == host) {
+        let _ = fs::remove_file(lock_path(notes_dir, relative_path));
+        daily::remove_empty_dirs(&notes_dir.join(LOCKS_DIR_NAME));
+    }
+}
+
+/// Whether the vault has a remote branch to share locks through.
+fn shared(notes_dir: &Path) -> bool {
+    git(notes_dir, &["rev-parse", "--abbrev-ref", "@{u}"]).is_ok()
+}
+
+/// Pull the other machines' locks, if the vault has a remote. Uncommitted
+/// changes are stashed around the pull, and a pull that stops on conflicts
+/// is undone so the vault is left as it was for the next `kno sync`.
+pub fn pull(notes_dir: &Path) -> Result<(), String> {
+    if !shared(notes_dir) {
+        return Ok(());
+    }
+    let pulled = git(notes_dir, &["pull", "--rebase", "--autostash", "--quiet"]);
+    if pulled.is_err() && sync::rebase_in_progress(notes_dir) {
+        let _ = git(notes_dir, &["rebase", "--abort"]);
+    }
+    pulled.map(|_| ())
+}
+
+/// Share this machine's lock changes: commit what changed under `.locks/`
+/// (and nothing else, so the pre-commit hook is skipped) with `message`,
+/// pull and push. Without a remote this
+/// does nothing, and the locks go out with the next `kno sync`; a failed
+/// pull or push leaves the commit for that sync too.
+pub fn publish(notes_dir: &Path, message: &str) -> Result<(), String> {
+    if !shared(notes_dir) {
+        return Ok(());
+    }
+    git(notes_dir, &["add", "--all", "--", LOCKS_DIR_NAME])?;
+    let staged = git(
+        notes_dir,
+        &["diff", "--cached", "--quiet", "--", LOCKS_DIR_NAME],
+    )
+    .is_err();
+    if staged {
+        git(
+            notes_dir,
+            &[
+                "commit",
+                "--quiet",
+                "--no-verify",
+                "--message",
+                message,
+                "--",
+                LOCKS_DIR_NAME,
+            ],
+        )?;
+    }
+    pull(notes_dir)?;
+    git(notes_dir, &["push", "--quiet"]).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn test_acquire_and_release() {
+        let tmp = TempDir::new().unwrap();
+        let rel = Path::new("sql/joins.md");
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        acquire(tmp.path(), rel, "laptop", now);
+        assert!(tmp.path().join(".locks/sql/joins.md.lock").is_file());
+        assert_eq!(
+            read(tmp.path(), rel),
+            Some(Lock {
+                host: "laptop".to_string(),
+                since: now
+            })
+        );
+
+        // Only the machine holding the lock releases it
+        release(tmp.path(), rel, "desktop");
+        assert!(read(tmp.path(), rel).is_some());
+        release(tmp.path(), rel, "laptop");
+        assert_eq!(read(tmp.path(), rel), None);
+    }
+
+    #[test]
+    fn test_held_elsewhere() {
+        let tmp = TempDir::new().unwrap();
+        let rel = Path::new("inbox.md");
+        let then = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        acquire(tmp.path(), rel, "laptop", then);
+
+        let soon = then + Duration::from_secs(600);
+        assert!(held_elsewhere(tmp.path(), rel, "desktop", HOUR, soon).is_some());
+        assert_eq!(held_elsewhere(tmp.path(), rel, "laptop", HOUR, soon), None);
+        let later = then + 2 * HOUR;
+        assert_eq!(
+            held_elsewhere(tmp.path(), rel, "desktop", HOUR, later),
+            None
+        );
+    }
+
+    #[test]
+    fn test_locks_shared_between_clones() {
+        let tmp = TempDir::new().unwrap();
+        let remote = tmp.path().join("remote");
+        fs::create_dir_all(&remote).unwrap();
+        git(&remote, &["init", "-q", "--bare"]).unwrap();
+        let url = remote.to_str().unwrap();
+        let clone = |name: &str| {
+            git(tmp.path(), &["clone", "-q", url, name]).unwrap();
+            let dir = tmp.path().join(name);
+            git(&dir, &["config", "user.name", "t"]).unwrap();
+            git(&dir, &["config", "user.email", "t@t"]).unwrap();
+            dir
+        };
+        let laptop = clone("laptop");
+        fs::write(laptop.join("inbox.md"), "# Inbox\n").unwrap();
+        crate::commit::commit_all(&laptop, "first").unwrap();
+        git(&laptop, &["push", "-q", "-u", "origin", "HEAD"]).unwrap();
+        let desktop = clone("desktop");
+
+        let rel = Path::new("inbox.md");
+        let now = SystemTime::now();
+        acquire(&laptop, rel, "laptop", now);
+        // Unrelated uncommitted edits stay out of the lock commit
+        fs::write(laptop.join("inbox.md"), "# Inbox\n\n- draft\n").unwrap();
+        publish(&laptop, "Lock inbox.md").unwrap();
+
+        fs::write(desktop.join("scratch.md"), "wip\n").unwrap();
+        pull(&desktop).unwrap();
+        let lock = held_elsewhere(&desktop, rel, "desktop", HOUR, now).unwrap();
+        assert_eq!(lock.host, "laptop");
+        assert!(desktop.join("scratch.md").is_file());
+
+        release(&laptop, rel, "laptop");
+        publish(&laptop, "Unlock inbox.md").unwrap();
+        pull(&desktop).unwrap();
+        assert_eq!(held_elsewhere(&desktop, rel, "desktop", HOUR, now), None);
+        assert_eq!(
+            fs::read_to_string(desktop.join("inbox.md")).unwrap(),
+            "# Inbox\n"
+        );
+        let changed = git(&laptop, &["status", "--porcelain"]).unwrap();
+        assert_eq!(changed, " M inbox.md\n");
+    }
+}
@@ -0,0 +1,64 @@
+use std::cell::Cell;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+// Set once in main. A thread-local keeps parallel tests independent; code that
+// spawns threads (the URL checks in `kno lint --urls`) passes the level on
+// with `set_level` in each one.
+thread_local! {
+    static LEVEL: Cell<Level> = const { Cell::new(Level::Normal) };
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.with(|l| l.set(level));
+}
+
+pub fn level() -> Level {
+    LEVEL.with(|l| l.get())
+}
+
+/// Informational output on stdout, silenced by `--quiet`.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::log::level() >= $crate::log::Level::Normal {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Diagnostics on stderr (resolved paths, files written, commands run),
+/// shown only with `--verbose`.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::log::level() >= $crate::log::Level::Verbose {
+            eprintln!("kno: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+pub(crate) use debug;
+pub(crate) use info;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_ordering() {
+        assert!(Level::Quiet < Level::Normal);
+        assert!(Level::Normal < Level::Verbose);
+    }
+
+    #[test]
+    fn test_set_level() {
+        assert_eq!(level(), Level::Normal);
+        set_level(Level::Verbose);
+        assert_eq!(level(), Level::Verbose);
+        set_level(Level::Normal);
+    }
+}
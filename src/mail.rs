@@ -0,0 +1,250 @@
+use std::io::Write;
+use std::process::{self, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tempfile::NamedTempFile;
+
+use crate::config::MailConfig;
+use crate::{curl, dry_run, log};
+
+/// A note rendered as an email, ready to hand to sendmail or an SMTP server.
+pub struct Message<'a> {
+    pub from: Option<&'a str>,
+    pub to: &'a [String],
+    pub subject: &'a str,
+    pub text: &'a str,
+    pub html: &'a str,
+}
+
+fn crlf(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\n', "\r\n")
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+/// `text` as a header value: as it is if it's printable ASCII, otherwise as
+/// RFC 2047 `=?UTF-8?B?...?=` encoded words, folded so each stays within the
+/// 75 characters the RFC allows.
+fn encode_header(text: &str) -> String {
+    if text.bytes().all(|b| (b' '..=b'~').contains(&b)) {
+        return text.to_string();
+    }
+    // 45 bytes encode to 60 characters, plus 12 for `=?UTF-8?B?` and `?=`
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if word.len() + c.len_utf8() > 45 {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c);
+    }
+    words.push(word);
+    words
+        .iter()
+        .map(|word| format!("=?UTF-8?B?{}?=", base64(word.as_bytes())))
+        .collect::<Vec<_>>()
+        .join("\r\n ")
+}
+
+/// Build a `multipart/alternative` message with the note's markdown as the
+/// plain-text part and its rendered HTML as the rich part.
+pub fn format_message(message: &Message, boundary: &str) -> String {
+    let mut out = String::new();
+    if let Some(from) = message.from {
+        out.push_str(&format!("From: {from}\r\n"));
+    }
+    out.push_str(&format!("To: {}\r\n", message.to.join(", ")));
+    out.push_str(&format!("Subject: {}\r\n", encode_header(message.subject)));
+    out.push_str("MIME-Version: 1.0\r\n");
+    out.push_str(&format!(
+        "Content-Type: multipart/alternative; boundary=\"{boundary}\"\r\n\r\n"
+    ));
+    for (kind, body) in [("plain", message.text), ("html", message.html)] {
+        out.push_str(&format!("--{boundary}\r\n"));
+        out.push_str(&format!("Content-Type: text/{kind}; charset=utf-8\r\n"));
+        out.push_str("Content-Transfer-Encoding: 8bit\r\n\r\n");
+        out.push_str(&crlf(body));
+        if !body.ends_with('\n') {
+            out.push_str("\r\n");
+        }
+    }
+    out.push_str(&format!("--{boundary}--\r\n"));
+    out
+}
+
+/// The command that delivers the message: `curl` when an SMTP server is
+/// configured, otherwise the system `sendmail`. SMTP credentials go in a
+/// private curl config file, returned alongside to keep until curl exits.
+fn delivery_cmd(
+    config: &MailConfig,
+    to: &[String],
+) -> Result<(process::Command, Option<NamedTempFile>), String> {
+    let Some(url) = &config.smtp_url else {
+        let mut cmd = process::Command::new(config.sendmail.as_deref().unwrap_or("sendmail"));
+        cmd.arg("-i").arg("--").args(to);
+        return Ok((cmd, None));
+    };
+
+    let from = config
+        .from
+        .as_deref()
+        .ok_or("mail.from is required when mail.smtp_url is set")?;
+    let mut cmd = process::Command::new("curl");
+    cmd.args(["--silent", "--show-error", "--ssl-reqd", "--url", url]);
+    cmd.args(["--mail-from", from]);
+    for rcpt in to {
+        cmd.args(["--mail-rcpt", rcpt]);
+    }
+    let credentials = match &config.user {
+        Some(user) => {
+            let password = config.password.as_deref().unwrap_or_default();
+            let file = curl::secret_config(&[("user", &format!("{user}:{password}"))])?;
+            cmd.arg("--config").arg(file.path());
+            Some(file)
+        }
+        None => None,
+    };
+    cmd.args(["--upload-file", "-"]);
+    Ok((cmd, credentials))
+}
+
+pub fn send(config: &MailConfig, message: &Message) -> Result<(), String> {
+    if dry_run::skip(format_args!(
+        "mail \"{}\" to {}",
+        message.subject,
+        message.to.join(", ")
+    )) {
+        return Ok(());
+    }
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let raw = format_message(message, &format!("kno-{nanos:x}"));
+
+    let (mut cmd, _credentials) = delivery_cmd(config, message.to)?;
+    log::debug!("{cmd:?}");
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run {:?}: {e}", cmd.get_program()))?;
+    child
+        .stdin
+        .take()
+        .expect("mailer stdin is piped")
+        .write_all(raw.as_bytes())
+        .map_err(|e| format!("failed to send message: {e}"))?;
+
+    match child.wait() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("mailer exited with {status}")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_message() {
+        let to = ["ana@example.com".to_string(), "bo@example.com".to_string()];
+        let raw = format_message(
+            &Message {
+                from: Some("me@example.com"),
+                to: &to,
+                subject: "Joins",
+                text: "# Joins\n\nbody\n",
+                html: "<h1>Joins</h1>",
+            },
+            "b",
+        );
+        assert!(raw.starts_with(
+            "From: me@example.com\r\nTo: ana@example.com, bo@example.com\r\nSubject: Joins\r\n"
+        ));
+        assert!(raw.contains("text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n# Joins\r\n\r\nbody\r\n--b\r\n"));
+        assert!(raw.ends_with("<h1>Joins</h1>\r\n--b--\r\n"));
+    }
+
+    #[test]
+    fn test_encode_header() {
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(encode_header("Sync notes"), "Sync notes");
+        assert_eq!(encode_header("Café"), "=?UTF-8?B?Q2Fmw6k=?=");
+        assert_eq!(
+            encode_header("a\r\nBcc: x@example.com"),
+            "=?UTF-8?B?YQ0KQmNjOiB4QGV4YW1wbGUuY29t?="
+        );
+
+        let long = encode_header(&"é".repeat(40));
+        let words: Vec<_> = long.split("\r\n ").collect();
+        assert_eq!(words.len(), 2);
+        assert!(words.iter().all(|w| w.len() <= 75));
+    }
+
+    #[test]
+    fn test_delivery_cmd_defaults_to_sendmail() {
+        let (cmd, _) = delivery_cmd(&MailConfig::default(), &["ana@example.com".into()]).unwrap();
+        assert_eq!(cmd.get_program(), "sendmail");
+        assert_eq!(
+            cmd.get_args().collect::<Vec<_>>(),
+            ["-i", "--", "ana@example.com"]
+        );
+    }
+
+    #[test]
+    fn test_delivery_cmd_smtp_needs_from() {
+        let config = MailConfig {
+            smtp_url: Some("smtps://smtp.example.com".into()),
+            ..MailConfig::default()
+        };
+        assert!(delivery_cmd(&config, &[]).is_err());
+
+        let config = MailConfig {
+            from: Some("me@example.com".into()),
+            ..config
+        };
+        let (cmd, credentials) = delivery_cmd(&config, &["ana@example.com".into()]).unwrap();
+        assert_eq!(cmd.get_program(), "curl");
+        assert!(credentials.is_none());
+    }
+
+    #[test]
+    fn test_delivery_cmd_keeps_password_off_command_line() {
+        let config = MailConfig {
+            smtp_url: Some("smtps://smtp.example.com".into()),
+            from: Some("me@example.com".into()),
+            user: Some("me".into()),
+            password: Some("hunter2".into()),
+            ..MailConfig::default()
+        };
+        let (cmd, credentials) = delivery_cmd(&config, &["ana@example.com".into()]).unwrap();
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy()).collect();
+        assert!(!args.iter().any(|a| a.contains("hunter2")));
+        let credentials = credentials.unwrap();
+        assert!(args.contains(&credentials.path().to_string_lossy()));
+        assert_eq!(
+            std::fs::read_to_string(credentials.path()).unwrap(),
+            "user = \"me:hunter2\"\n"
+        );
+    }
+}
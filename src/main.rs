@@ -1,19 +1,89 @@
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
 use std::process;
 
-use chrono::Local;
-use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
-use clap_complete::engine::{ArgValueCompleter, PathCompleter};
+use chrono::{Local, NaiveDate};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use clap_complete::engine::{
+    ArgValueCompleter, CompletionCandidate, PathCompleter, ValueCompleter,
+};
+use config::Config;
+use regex::Regex;
+
+mod apple_notes;
+mod archive;
+mod assets;
+mod backup;
+mod changed;
+mod clipboard;
+mod commit;
+mod config;
+mod context;
+mod curl;
+mod cursors;
+mod daily;
+mod dedupe;
+mod dry_run;
+mod events;
+mod export;
+mod format;
+mod frontmatter;
+mod goal;
+mod graph;
+mod habits;
+mod import;
+mod joplin;
+mod links;
+mod locks;
+mod log;
+mod mail;
+mod merge;
+mod migrate;
+mod nvim;
+mod org;
+mod pick;
+mod query;
+mod remote;
+mod scratch;
+mod search;
+mod secrets;
+mod share;
+mod standup;
+mod stats;
+mod status;
+mod sync;
+mod tags;
+mod tasks;
+mod template;
+mod update;
+mod urls;
+mod vault;
+mod verify;
+mod week;
 
 const NOTES_DIR_NAME: &str = ".kno";
 
+const MEETING_TEMPLATE: &str = "\
+# {{title}}
+
+Date: {{date}}
+
+## Attendees
+
+{{attendees}}
+
+## Agenda
+
+## Action Items
+";
+
 #[derive(Parser)]
-#[command(about = "A simple notes CLI", args_conflicts_with_subcommands = true)]
+#[command(about = "A simple notes CLI")]
 struct Cli {
-    /// Note path (e.g. sql/joins). Opens daily note if omitted.
-    path: Option<String>,
+    /// Note paths (e.g. sql/joins). Opens daily note if omitted.
+    paths: Vec<String>,
 
     /// Print the resolved file path instead of opening the editor.
     /// Convenience for vim integration, e.g. nnoremap <leader>kn :execute 'e' trim(system('kno -p'))<CR>
@@ -23,13 +93,111 @@ struct Cli {
     print: bool,
 
     /// Text to append to the note. Appends and exits without opening editor.
-    #[arg(short, long, allow_hyphen_values = true)]
+    /// Without text, pass --clip or --edit
+    #[arg(short, long, allow_hyphen_values = true, num_args = 0..=1, default_missing_value = "", group = "text")]
     append: Option<String>,
 
+    /// Like --append, but insert the text at the top of the note, right after
+    /// its frontmatter and title (for newest-first notes)
+    #[arg(long, allow_hyphen_values = true, num_args = 0..=1, default_missing_value = "", group = "text")]
+    prepend: Option<String>,
+
+    /// With --append, insert the text after the first line matching this regex
+    /// (e.g. "## Links"), instead of at the end. The note must exist
+    #[arg(long, value_name = "REGEX", value_parser = parse_regex_arg, requires = "append", conflicts_with_all = ["before", "prepend"])]
+    after: Option<Regex>,
+
+    /// With --append, insert the text before the first line matching this regex
+    #[arg(long, value_name = "REGEX", value_parser = parse_regex_arg, requires = "append", conflicts_with = "prepend")]
+    before: Option<Regex>,
+
+    /// With --append or --prepend, add the clipboard contents instead of text
+    #[arg(long, requires = "text")]
+    clip: bool,
+
+    /// With --append or --prepend, write the text in $EDITOR (a scratch buffer, not the note)
+    #[arg(long, requires = "text", conflicts_with = "clip")]
+    edit: bool,
+
+    /// Add an open task (`- [ ] text`) to today's daily note, or the configured tasks note
+    #[arg(short = 't', long = "todo", value_name = "TASK", allow_hyphen_values = true, conflicts_with_all = ["text", "print"])]
+    todo: Option<String>,
+
+    /// With --append or --prepend, format the text as a list item (`- text`)
+    #[arg(long, requires = "text", conflicts_with = "task")]
+    bullet: bool,
+
+    /// With --append or --prepend, format the text as an open task (`- [ ] text`)
+    #[arg(long, requires = "text")]
+    task: bool,
+
+    /// With --append or --prepend, prefix the text with the current time (HH:MM)
+    #[arg(long, requires = "text")]
+    timestamp: bool,
+
+    /// With --append or --prepend, create a missing note with only the added text (no title header)
+    #[arg(long, requires = "text")]
+    no_header: bool,
+
+    /// Use the daily note for another day: yesterday, tomorrow, monday, last friday
+    /// or YYYY-MM-DD. Also applies to trailing-slash notes
+    #[arg(long, value_parser = parse_day_arg)]
+    date: Option<NaiveDate>,
+
+    /// Only open notes that already exist; error instead of creating a missing one
+    #[arg(long, global = true)]
+    existing: bool,
+
+    /// Show what would be created or changed without touching any files
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Show resolved paths, created files and commands run (on stderr)
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Suppress informational output
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Descend into symlinked directories when listing and searching notes
+    #[arg(long, global = true)]
+    follow_symlinks: bool,
+
+    /// Include hidden (dot-prefixed) notes and directories when listing,
+    /// searching and picking notes
+    #[arg(long, global = true)]
+    hidden: bool,
+
+    /// Open notes as buffers in the Neovim listening on this socket (e.g. $NVIM)
+    /// instead of starting an editor
+    #[arg(long, global = true, value_name = "SOCKET")]
+    nvim_server: Option<PathBuf>,
+
+    /// Extra arguments passed to the editor, after `--` (e.g. -- +/pattern)
+    #[arg(last = true, value_name = "EDITOR_ARGS")]
+    editor_args: Vec<String>,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
 
+impl Cli {
+    /// `-a` and `--prepend` take text starting with `-` (for list items), so
+    /// clap reads `kno -a --clip` as appending the text "--clip". Treat it as
+    /// the flag.
+    fn normalize(&mut self) {
+        for text in [&mut self.append, &mut self.prepend] {
+            match text.as_deref() {
+                Some("--clip") => self.clip = true,
+                Some("--edit") => self.edit = true,
+                _ => continue,
+            }
+            *text = Some(String::new());
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Command {
     /// List notes and directories
@@ -40,10 +208,627 @@ enum Command {
         /// Max depth to display (default: 1, 0 for unlimited)
         #[arg(short = 'L', long)]
         level: Option<usize>,
+
+        /// Only notes with this frontmatter tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Only notes modified on or after this date (YYYY-MM-DD, YYYY-MM or YYYY)
+        #[arg(long, value_parser = query::parse_date)]
+        since: Option<NaiveDate>,
+
+        /// Order of notes (and directories) in the output
+        #[arg(long, value_enum, default_value_t = SortKey::Name)]
+        sort: SortKey,
+
+        /// Print one note path per line instead of a tree
+        #[arg(long)]
+        flat: bool,
+
+        /// Only notes matching a query, e.g. 'tag:rust AND path:projects/* AND modified>2025-01-01'
+        #[arg(long, value_parser = parse_query_arg)]
+        query: Option<String>,
+
+        /// Print each note with a template instead, e.g. '{path}\t{title}\t{modified}'
+        /// (fields: path, title, modified, tags)
+        #[arg(long, value_parser = |s: &str| format::check(s, format::NOTE_FIELDS))]
+        format: Option<String>,
+
+        /// Also list attachments and other non-note files, with their kind
+        #[arg(
+            short,
+            long,
+            conflicts_with_all = ["tag", "since", "sort", "flat", "query", "format"]
+        )]
+        all: bool,
+    },
+
+    /// Search notes for text, printing matching lines with the matches highlighted
+    #[command(alias = "grep")]
+    Search {
+        /// Text to look for
+        #[arg(required_unless_present = "saved")]
+        query: Option<String>,
+
+        /// Only search notes under this directory
+        path: Option<String>,
+
+        /// Treat the query as a regular expression
+        #[arg(long)]
+        regex: bool,
+
+        /// Match regardless of case
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Only match whole words
+        #[arg(short, long)]
+        word: bool,
+
+        /// Only notes dated (daily notes) or modified on or after this date
+        /// (YYYY-MM-DD, YYYY-MM or YYYY)
+        #[arg(long, value_parser = query::parse_date)]
+        since: Option<NaiveDate>,
+
+        /// Only notes dated or modified on or before this date; YYYY-MM and
+        /// YYYY include the whole month or year
+        #[arg(long, value_parser = parse_until_arg)]
+        until: Option<NaiveDate>,
+
+        /// Only search notes matching a query (see `kno list --query`)
+        #[arg(long = "query", value_name = "QUERY", value_parser = parse_query_arg)]
+        filter: Option<String>,
+
+        /// Print how many lines match in each note instead
+        #[arg(short, long, conflicts_with = "files_with_matches")]
+        count: bool,
+
+        /// Print only the paths of notes with matches
+        #[arg(short = 'l', long)]
+        files_with_matches: bool,
+
+        /// Print each match with a template instead, e.g. '{path}:{line}\t{text}'
+        /// (fields: path, line, text)
+        #[arg(
+            long,
+            value_parser = |s: &str| format::check(s, format::MATCH_FIELDS),
+            conflicts_with_all = ["count", "files_with_matches"]
+        )]
+        format: Option<String>,
+
+        /// Save the query, path and matching options in config as NAME
+        #[arg(long, value_name = "NAME", conflicts_with = "saved")]
+        save: Option<String>,
+
+        /// Run the search saved as NAME (--count and -l still apply)
+        #[arg(
+            long,
+            value_name = "NAME",
+            conflicts_with_all = ["query", "path", "regex", "ignore_case", "word", "since", "until", "filter"]
+        )]
+        saved: Option<String>,
+    },
+
+    /// Track habits, marked done in daily notes
+    Habit {
+        #[command(subcommand)]
+        action: HabitAction,
+    },
+
+    /// Words written today (per git) against the daily goal in [goal], and this week so far
+    Goal,
+
+    /// Report note and word counts, newest and oldest notes, and task completion
+    Stats {
+        /// Directory to report on (the whole vault if omitted)
+        path: Option<String>,
+
+        /// List the N notes with the most words instead (candidates for splitting)
+        #[arg(long, value_name = "N")]
+        largest: Option<usize>,
+
+        /// List the N notes untouched the longest instead (candidates for archiving)
+        #[arg(long, value_name = "N")]
+        stalest: Option<usize>,
+    },
+
+    /// Print the links between notes, one `from -> to` per line
+    Graph {
+        /// Report hubs, dead ends and disconnected clusters instead
+        #[arg(long)]
+        metrics: bool,
+
+        /// Max number of hubs to show with --metrics
+        #[arg(short = 'n', long, default_value_t = 10, requires = "metrics")]
+        limit: usize,
+    },
+
+    /// Check the vault for empty, corrupt or conflicted notes, broken links and stale state files
+    #[command(alias = "lint")]
+    Verify {
+        /// Also check that external http(s) links still answer (uses the network)
+        #[arg(long)]
+        urls: bool,
+
+        /// Also flag file names that break on other platforms or sync services
+        #[arg(long)]
+        portable: bool,
+
+        /// Rename the files --portable flags, rewriting links to them
+        #[arg(long, requires = "portable")]
+        fix: bool,
+
+        /// Only check the notes staged for commit, for broken links and
+        /// conflict markers (what the pre-commit hook runs)
+        #[arg(long, conflicts_with_all = ["urls", "portable"])]
+        staged: bool,
+    },
+
+    /// Create a note, failing if it already exists (unlike `kno <path>`)
+    New {
+        /// Note path (e.g. sql/joins)
+        path: String,
+
+        /// Template from ~/.kno/.templates to use instead of note.md
+        #[arg(long)]
+        template: Option<String>,
+
+        /// Open the new note in $EDITOR instead of printing its path
+        #[arg(long)]
+        edit: bool,
+    },
+
+    /// Print the path of the vault or a directory in it (the shell wrapper cd's there)
+    Cd {
+        /// Directory inside the vault (the vault itself if omitted)
+        path: Option<String>,
+
+        /// Print the shell function that makes `kno cd` change directory
+        #[arg(long, conflicts_with = "path")]
+        wrapper: bool,
     },
 
     /// Initialize kno: create notes dir, git repo, and shell completions
-    Init,
+    Init {
+        /// Also install a pre-commit hook that runs `kno verify` (broken links,
+        /// conflict markers, ...) before every commit to the notes repo
+        #[arg(long)]
+        hooks: bool,
+    },
+
+    /// Git merge driver for daily notes, set up by `kno init`: merges OURS
+    /// and THEIRS into OURS, keeping the entries both sides added
+    #[command(hide = true)]
+    MergeDriver {
+        /// Common ancestor version (%O)
+        base: PathBuf,
+        /// Our version, overwritten with the result (%A)
+        ours: PathBuf,
+        /// Their version (%B)
+        theirs: PathBuf,
+    },
+
+    /// Merge sync conflict copies of a daily note (Dropbox, Nextcloud, Syncthing)
+    /// into it, skipping lines it already has, and move them to .trash
+    MergeDaily {
+        /// Day of the note: today, yesterday, monday, last friday or YYYY-MM-DD
+        #[arg(value_parser = parse_day_arg)]
+        date: NaiveDate,
+    },
+
+    /// Create a dated meeting note and link it from today's daily note
+    Meeting {
+        /// Meeting name (e.g. standup)
+        name: String,
+
+        /// Comma-separated list of attendees
+        #[arg(long, value_delimiter = ',')]
+        attendees: Vec<String>,
+    },
+
+    /// Open (or create) projects/<repo>.md for the git repository you're in
+    Here {
+        /// Also link it from today's daily note, with the current branch
+        #[arg(long)]
+        link: bool,
+
+        /// Print the note path instead of opening the editor
+        #[arg(short, long)]
+        print: bool,
+    },
+
+    /// Open this week's note (weekly/YYYY-Www.md), per the [week] config
+    Week {
+        /// Print the note path instead of opening the editor
+        #[arg(short, long)]
+        print: bool,
+    },
+
+    /// Open a throwaway note in .scratch, kept out of listings and searches
+    Scratch {
+        #[command(subcommand)]
+        action: Option<ScratchAction>,
+
+        /// Print the note path instead of opening the editor
+        #[arg(short, long)]
+        print: bool,
+    },
+
+    /// Open the most recent daily note before today
+    Prev {
+        /// Print the note path instead of opening the editor
+        #[arg(short, long)]
+        print: bool,
+    },
+
+    /// Print notes to stdout; `@today` is today's daily note
+    Cat {
+        /// Notes to print
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+
+    /// Append a thought to today's daily note, noting where it happened
+    /// (time, directory, git repo and branch, host; see [capture])
+    Capture {
+        /// Text to capture
+        #[arg(allow_hyphen_values = true)]
+        text: String,
+    },
+
+    /// List notes created or modified recently, with lines added and removed
+    /// and the heading of the first change, from git and uncommitted edits
+    Changed {
+        /// Start of the window: today, yesterday, a weekday or YYYY-MM-DD
+        #[arg(long, value_parser = parse_day_arg, default_value = "today")]
+        since: NaiveDate,
+    },
+
+    /// Print a standup update: the last working day's finished tasks and log
+    /// entries, and today's open tasks
+    Standup {
+        /// Also copy it to the clipboard
+        #[arg(long)]
+        copy: bool,
+    },
+
+    /// Add a timestamped entry (`- **14:32** text`) under the log section of
+    /// today's daily note, creating the section if needed
+    Log {
+        /// Entry text
+        #[arg(allow_hyphen_values = true)]
+        text: String,
+    },
+
+    /// Reopen the most recently edited or opened note
+    Last {
+        /// Print the note path instead of opening the editor
+        #[arg(short, long)]
+        print: bool,
+    },
+
+    /// List recently edited notes, most recent first
+    #[command(alias = "recent")]
+    History {
+        /// List notes opened through kno instead of recently edited ones
+        #[arg(long)]
+        opened: bool,
+
+        /// Max number of notes to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        /// Print each note with a template instead (see `kno list --format`)
+        #[arg(long, value_parser = |s: &str| format::check(s, format::NOTE_FIELDS))]
+        format: Option<String>,
+    },
+
+    /// Print note events (created, edited, appended) from other kno commands
+    /// as JSON lines, for status bars and scripts
+    Events,
+
+    /// Open several notes in one editor session
+    Open {
+        /// Note paths (e.g. sql/joins)
+        paths: Vec<String>,
+
+        /// Open every existing note matching a glob (e.g. 'projects/foo/*')
+        #[arg(long, value_name = "PATTERN")]
+        all: Vec<String>,
+
+        /// Open the note whose title (frontmatter or first heading) contains this,
+        /// choosing from a list when several do
+        #[arg(long, value_name = "QUERY", conflicts_with_all = ["paths", "all"])]
+        title: Option<String>,
+
+        /// Extra arguments passed to the editor, after `--`
+        #[arg(last = true, value_name = "EDITOR_ARGS")]
+        editor_args: Vec<String>,
+    },
+
+    /// Choose a note with fzf (path and title, with a preview) and open it.
+    /// Exits 130 if the picker is cancelled or nothing matches, 1 on errors
+    Pick {
+        /// Open the picker in a tmux popup and the note in a new tmux window
+        #[arg(long)]
+        tmux: bool,
+
+        /// With --tmux, type the note's path into the current pane instead of opening it
+        #[arg(long, requires = "tmux")]
+        send: bool,
+
+        /// Allow choosing several notes (tab to mark them)
+        #[arg(long, conflicts_with = "send")]
+        multi: bool,
+
+        /// Print the absolute path of each chosen note instead of opening it,
+        /// and nothing else (for editor plugins)
+        #[arg(short, long, conflicts_with = "send")]
+        print: bool,
+
+        /// Like --print, but end each path with a NUL byte instead of a newline
+        #[arg(long, conflicts_with_all = ["send", "print"])]
+        print0: bool,
+    },
+
+    /// Open a note or attachment with the OS default handler or another app
+    OpenWith {
+        /// Note or file path (e.g. sql/joins or assets/diagram.pdf)
+        path: String,
+
+        /// Use the platform opener (open / xdg-open / start); the default
+        #[arg(long, conflicts_with = "app")]
+        system: bool,
+
+        /// Open with a specific application instead
+        #[arg(long)]
+        app: Option<String>,
+    },
+
+    /// List open tasks across all notes, highest priority first
+    Todo {
+        /// Only show tasks at this priority or above
+        #[arg(long)]
+        priority: Option<tasks::Priority>,
+
+        /// Only tasks in notes matching a query (see `kno list --query`)
+        #[arg(long, value_parser = parse_query_arg)]
+        query: Option<String>,
+
+        /// Print each task with a template instead, e.g. '{priority}\t{text}'
+        /// (fields: path, line, text, priority, number)
+        #[arg(long, value_parser = |s: &str| format::check(s, format::TASK_FIELDS))]
+        format: Option<String>,
+
+        /// Show checklist completion per note and directory instead of open tasks
+        #[arg(long, conflicts_with_all = ["priority", "format"])]
+        progress: bool,
+
+        /// With --progress, only notes under this directory
+        #[arg(requires = "progress")]
+        path: Option<String>,
+    },
+
+    /// Check off open tasks by number (prompts when no numbers are given)
+    Done {
+        /// Task numbers or ranges as shown in the list (e.g. 1 3-4)
+        selection: Vec<String>,
+
+        /// Append the completion date to each task
+        #[arg(long)]
+        date: bool,
+
+        /// Move completed tasks under a "## Done" heading in their note
+        #[arg(long = "move")]
+        move_to_done: bool,
+    },
+
+    /// Archive the notes dir, keeping the newest `backup.keep` archives
+    Backup {
+        #[command(subcommand)]
+        action: Option<BackupAction>,
+
+        /// Install a systemd user timer / launchd agent that runs `kno backup` on `backup.schedule`
+        #[arg(long)]
+        install_timer: bool,
+    },
+
+    /// Update kno to the latest GitHub release for this platform
+    SelfUpdate {
+        /// Only report whether a newer release exists
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Bundle old notes into fewer files
+    Compact {
+        #[command(subcommand)]
+        target: CompactTarget,
+    },
+
+    /// Move existing notes to a new layout
+    #[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
+    Migrate {
+        #[command(subcommand)]
+        target: Option<MigrateTarget>,
+
+        /// TOML file mapping old path prefixes to new ones ("work/acme" = "clients/acme");
+        /// notes are moved with git mv and links to them rewritten
+        #[arg(long, value_name = "FILE", required = true)]
+        map: Option<PathBuf>,
+    },
+
+    /// Find notes with identical or near-identical content and merge or trash them
+    Dedupe {
+        /// Minimum share of lines two notes must have in common (0.0-1.0)
+        #[arg(long, default_value_t = 0.8)]
+        threshold: f64,
+
+        /// Only report duplicates, don't prompt
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Bring notes in from elsewhere
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+
+    /// Move or rename a note or a directory of notes, rewriting links to them
+    Mv {
+        /// Note or directory to move (e.g. projects/oldname)
+        from: String,
+
+        /// Where it goes (e.g. projects/newname)
+        to: String,
+
+        /// Leave a note at each old path linking to where it moved
+        #[arg(long)]
+        redirect: bool,
+    },
+
+    /// Move notes into archive/ (per [archive]), keeping their paths and links
+    Archive {
+        /// Notes to archive (e.g. clippings/some-article)
+        #[arg(required_unless_present = "auto", conflicts_with = "auto")]
+        paths: Vec<String>,
+
+        /// Archive notes untouched for longer than the [[archive.rules]] allow
+        #[arg(long)]
+        auto: bool,
+    },
+
+    /// Move notes whose `expires: YYYY-MM-DD` frontmatter date has come to .trash
+    Gc {
+        /// Only list expired notes, don't trash them
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Manage attachments in assets/
+    Assets {
+        #[command(subcommand)]
+        action: AssetsAction,
+    },
+
+    /// Export notes, optionally only those under a directory or with a tag
+    Export {
+        /// Output format; left out with --profile
+        #[arg(
+            value_enum,
+            required_unless_present = "profile",
+            conflicts_with = "profile"
+        )]
+        format: Option<export::Format>,
+
+        /// Only notes under this directory
+        path: Option<String>,
+
+        /// Run a pandoc pipeline from [export.profiles] instead of a built-in
+        /// format, writing one file (--out gets the profile's extension)
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// With --profile, only notes under this directory
+        #[arg(long = "in", value_name = "DIR", conflicts_with = "format")]
+        profile_path: Option<String>,
+
+        /// Only notes with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Directory to write the export to
+        #[arg(short, long, default_value = "kno-export")]
+        out: PathBuf,
+    },
+
+    /// Export only notes marked `publish: true` / `visibility: public` as HTML
+    Publish {
+        /// List the notes that would be published without generating anything
+        #[arg(long)]
+        check: bool,
+
+        /// Directory to write the site to
+        #[arg(short, long, default_value = "kno-publish")]
+        out: PathBuf,
+    },
+
+    /// Share a note as a secret GitHub gist and print its URL
+    Share {
+        /// Note to share (e.g. sql/joins)
+        path: String,
+
+        /// Push changes to the gist the note was shared to before
+        #[arg(long)]
+        update: bool,
+
+        /// Share the note even if it's marked private or hidden
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Keep API tokens and passwords in the system keychain instead of config.toml
+    Secret {
+        #[command(subcommand)]
+        action: SecretAction,
+    },
+
+    /// Email a note, rendered as HTML with the markdown as plain text
+    Mail {
+        /// Note to send (e.g. sql/joins)
+        path: String,
+
+        /// Recipient address (repeat or comma-separate for several)
+        #[arg(long, required = true, value_delimiter = ',')]
+        to: Vec<String>,
+
+        /// Subject line (defaults to the note's title)
+        #[arg(long)]
+        subject: Option<String>,
+
+        /// Send the note even if it's marked private or hidden
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// List all tags in the vault
+    Tags {
+        /// Show how many notes use each tag and when one last changed
+        #[arg(long)]
+        counts: bool,
+
+        /// Order of tags in the output
+        #[arg(long, value_enum, default_value_t = TagSort::Name)]
+        sort: TagSort,
+    },
+
+    /// Manage frontmatter tags
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+
+    /// Commit every change in the notes dir, with a message from git.commit_message
+    Commit {
+        /// Use this message instead of the template
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+
+    /// Show uncommitted changes, commits ahead/behind the remote, last sync and conflicts
+    Status,
+
+    /// Commit every change, pull and push; offline, the commits are queued for the next sync
+    Sync {
+        /// Use this message instead of the template
+        #[arg(short, long, conflicts_with = "backend")]
+        message: Option<String>,
+
+        /// Sync with object storage at remote.url instead of the git remote
+        #[arg(long, value_enum)]
+        backend: Option<remote::Backend>,
+    },
 
     /// Run git commands in the notes directory
     #[command(trailing_var_arg = true)]
@@ -53,91 +838,774 @@ enum Command {
     },
 }
 
-fn titlecase(s: &str) -> String {
-    s.split(|c| c == '-' || c == '_')
-        .map(|word| {
-            let mut chars = word.chars();
-            match chars.next() {
-                None => String::new(),
-                Some(c) => {
-                    let upper: String = c.to_uppercase().collect();
-                    upper + chars.as_str()
-                }
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" ")
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Unpack a backup archive, refusing to replace an existing vault unless --force
+    Restore {
+        /// Archive to restore (a path, or a file name in the backup dir)
+        archive: PathBuf,
+
+        /// Directory to restore into (default: the notes dir)
+        #[arg(long)]
+        into: Option<PathBuf>,
+
+        /// Replace an existing vault; it's moved aside, not deleted
+        #[arg(long)]
+        force: bool,
+    },
 }
 
-fn resolve_note(path: Option<&str>) -> (PathBuf, String) {
-    let today = Local::now().format("%Y-%m-%d").to_string();
+#[derive(Subcommand)]
+enum SecretAction {
+    /// Store a secret, typed at a prompt or piped on stdin
+    Set {
+        /// Secret name: github_token (for kno share) or smtp_password (for kno mail)
+        name: String,
+    },
 
-    match path {
-        None => {
-            // Default: daily directory with year grouping
-            let year = &today[..4];
-            let path = PathBuf::from("daily").join(year).join(format!("{today}.md"));
-            let header = format!("# {today}");
-            (path, header)
-        }
-        Some(note_path) if note_path.ends_with('/') => {
-            // Trailing slash: treat as directory, use today's date as filename
-            let path = PathBuf::from(note_path).join(format!("{today}.md"));
-            let header = format!("# {today}");
-            (path, header)
-        }
-        Some(note_path) => {
-            // Explicit note name
-            let path = PathBuf::from(format!("{note_path}.md"));
-            let stem = path.file_stem().unwrap().to_string_lossy();
-            let header = format!("# {}", titlecase(&stem));
-            (path, header)
-        }
-    }
+    /// Print a stored secret
+    Get {
+        /// Secret name
+        name: String,
+    },
+
+    /// Delete a stored secret
+    Rm {
+        /// Secret name
+        name: String,
+    },
 }
 
-fn open_note(notes_dir: &std::path::Path, path: Option<&str>) -> PathBuf {
-    let (relative_path, header) = resolve_note(path);
-    let file_path = notes_dir.join(&relative_path);
+#[derive(Subcommand)]
+enum HabitAction {
+    /// Start tracking a habit
+    Add {
+        /// Habit name (e.g. meditate)
+        name: String,
+    },
 
-    if let Some(parent) = file_path.parent() {
-        fs::create_dir_all(parent).expect("failed to create note directory");
-    }
+    /// Mark a habit done in today's daily note, under daily.habit_section
+    Done {
+        /// Habit name
+        name: String,
 
-    let needs_header = fs::read_to_string(&file_path)
-        .map(|c| c.trim().is_empty())
-        .unwrap_or(true);
+        /// Day to mark instead of today (yesterday, friday, YYYY-MM-DD, ...)
+        #[arg(long, value_parser = parse_day_arg)]
+        date: Option<NaiveDate>,
+    },
 
-    if needs_header {
-        fs::write(&file_path, format!("{header}\n\n")).expect("failed to write note file");
-    }
+    /// Show each habit's current streak, and a chart of the days it was done
+    Status {
+        /// Number of days to chart, ending today
+        #[arg(long, default_value_t = 28, value_parser = clap::value_parser!(u64).range(1..))]
+        days: u64,
+    },
+}
 
-    file_path
+#[derive(Subcommand)]
+enum AssetsAction {
+    /// List attachments no note links to or mentions
+    Gc {
+        /// Move them to .trash instead of only listing them
+        #[arg(long)]
+        delete: bool,
+    },
 }
 
-fn append_to_note(file_path: &std::path::Path, text: &str) {
+#[derive(Subcommand)]
+enum ScratchAction {
+    /// Keep a scratch note by moving it into the vault
+    Promote {
+        /// Note path to move it to (e.g. ideas/cache)
+        name: String,
+
+        /// Scratch note to promote, as listed by `kno scratch list` (default: the newest)
+        #[arg(long)]
+        from: Option<String>,
+    },
+
+    /// List scratch notes, oldest first
+    List,
+}
+
+#[derive(Subcommand)]
+enum ImportSource {
+    /// Copy a directory of markdown files (and their attachments) into the vault,
+    /// normalizing names and adding missing titles; existing files are never overwritten
+    Dir {
+        /// Directory to import
+        path: PathBuf,
+
+        /// Vault subdirectory to import into (e.g. imported/obsidian)
+        #[arg(long)]
+        into: Option<String>,
+    },
+
+    /// Import a Joplin export: notebooks become directories, tags and creation
+    /// dates go to frontmatter, and attachments go to assets/
+    Joplin {
+        /// JEX export file (File > Export all > JEX in Joplin)
+        path: PathBuf,
+
+        /// Vault subdirectory to import into
+        #[arg(long, default_value = "imported/joplin")]
+        into: String,
+    },
+
+    /// Import Apple Notes, keeping their folders as directories; reads Notes.app
+    /// directly (macOS) unless given a directory of exported HTML
+    AppleNotes {
+        /// Directory of exported .html notes instead of reading Notes.app
+        #[arg(long)]
+        html: Option<PathBuf>,
+
+        /// Vault subdirectory to import into
+        #[arg(long, default_value = "imported/apple")]
+        into: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum MigrateTarget {
+    /// Move daily notes into another directory layout, updating links to them
+    Daily {
+        /// Layout to move to
+        #[arg(long, value_enum)]
+        to: daily::Layout,
+    },
+}
+
+#[derive(Subcommand)]
+enum CompactTarget {
+    /// Merge old daily notes into one note per month or year, removing the originals
+    Daily {
+        /// Compact daily notes dated before this (YYYY, YYYY-MM or YYYY-MM-DD)
+        #[arg(long, value_parser = query::parse_date)]
+        before: NaiveDate,
+
+        /// Bundle size
+        #[arg(long, value_enum, default_value_t = daily::Bundle::Month)]
+        by: daily::Bundle,
+    },
+}
+
+#[derive(Subcommand)]
+enum TagAction {
+    /// Add tags to a note
+    Add {
+        /// Note path (e.g. sql/joins)
+        path: String,
+
+        /// Tags to add
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+
+    /// Remove tags from a note
+    Rm {
+        /// Note path (e.g. sql/joins)
+        path: String,
+
+        /// Tags to remove
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+
+    /// List notes carrying a tag
+    Show {
+        /// Tag to look for
+        tag: String,
+    },
+
+    /// Rename a tag in every note that has it
+    Rename {
+        /// Current tag name
+        old: String,
+
+        /// New tag name
+        new: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum SortKey {
+    /// Alphabetical by path
+    Name,
+    /// Most recently modified first
+    Modified,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+enum TagSort {
+    /// Alphabetical, so near-duplicates like rust / rust-lang sit together
+    Name,
+    /// Most used first
+    Count,
+    /// Most recently used first
+    Recent,
+}
+
+/// Parse an end date: `YYYY-MM-DD`, or the last day of a `YYYY-MM` month or
+/// `YYYY` year.
+fn parse_until_arg(s: &str) -> Result<NaiveDate, String> {
+    let start = query::parse_date(s)?;
+    let next = match s.len() {
+        4 => start.checked_add_months(chrono::Months::new(12)),
+        7 => start.checked_add_months(chrono::Months::new(1)),
+        _ => return Ok(start),
+    };
+    Ok(next.and_then(|d| d.pred_opt()).unwrap_or(NaiveDate::MAX))
+}
+
+/// Check a `--query` expression while parsing arguments, so mistakes are
+/// reported like any other bad argument.
+fn parse_query_arg(s: &str) -> Result<String, String> {
+    query::Query::parse(s).map(|_| s.to_string())
+}
+
+/// A `--query` expression already checked by `parse_query_arg`.
+fn query_arg(s: &str) -> query::Query {
+    query::Query::parse(s).expect("query is checked when parsing arguments")
+}
+
+fn titlecase(s: &str) -> String {
+    s.split(['-', '_'])
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                None => String::new(),
+                Some(c) => {
+                    let upper: String = c.to_uppercase().collect();
+                    upper + chars.as_str()
+                }
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse `--date`: `today`, `yesterday`, `tomorrow`, a weekday (`monday`,
+/// `last friday`) or `YYYY-MM-DD`.
+fn parse_day_arg(s: &str) -> Result<NaiveDate, String> {
+    daily::parse_day(s, Local::now().date_naive()).ok_or_else(|| {
+        format!("invalid date `{s}` (expected today, yesterday, a weekday or YYYY-MM-DD)")
+    })
+}
+
+/// The current time of day on `date`, for notes dated other than today.
+fn now_on(date: NaiveDate) -> chrono::DateTime<Local> {
+    let now = Local::now();
+    match now.date_naive() == date {
+        true => now,
+        false => date
+            .and_time(now.time())
+            .and_local_timezone(Local)
+            .earliest()
+            .unwrap_or(now),
+    }
+}
+
+fn resolve_note(path: Option<&str>, config: &Config) -> (PathBuf, String) {
+    resolve_note_on(path, Local::now().date_naive(), config)
+}
+
+/// Like `resolve_note`, but daily and trailing-slash notes are for `date`.
+fn resolve_note_on(path: Option<&str>, date: NaiveDate, config: &Config) -> (PathBuf, String) {
+    let now = now_on(date);
+    let today = now.format("%Y-%m-%d").to_string();
+    // Dated notes are titled in the configured date format and locale
+    let dated_header = || {
+        let title = template::format_date(&now, &config.dates.format, config.dates.locale);
+        format!("# {}", title.unwrap_or_else(|| today.clone()))
+    };
+
+    match path {
+        None => {
+            // Default: daily directory, grouped by the configured layout
+            let path = daily::note_path(date, config.daily.layout);
+            (path, dated_header())
+        }
+        Some(note_path) if note_path.ends_with('/') => {
+            // Trailing slash: treat as directory, use today's date as filename
+            let path = PathBuf::from(note_path).join(format!("{today}.md"));
+            (path, dated_header())
+        }
+        Some(note_path) => {
+            // Explicit note name
+            let path = PathBuf::from(format!("{note_path}.md"));
+            let stem = path.file_stem().unwrap().to_string_lossy();
+            let header = format!("# {}", titlecase(&stem));
+            (path, header)
+        }
+    }
+}
+
+fn open_note(notes_dir: &std::path::Path, path: Option<&str>) -> PathBuf {
+    open_note_with_cursor(notes_dir, path).0
+}
+
+/// Like `open_note`, but also returns the line the editor should start on when
+/// the note was just created from a template containing `{{cursor}}`.
+fn open_note_with_cursor(
+    notes_dir: &std::path::Path,
+    path: Option<&str>,
+) -> (PathBuf, Option<usize>) {
+    open_note_on(notes_dir, path, Local::now().date_naive())
+}
+
+/// Like `open_note_with_cursor`, but daily and trailing-slash notes are for `date`.
+fn open_note_on(
+    notes_dir: &std::path::Path,
+    path: Option<&str>,
+    date: NaiveDate,
+) -> (PathBuf, Option<usize>) {
+    let (relative_path, header) = resolve_note_on(path, date, &Config::load(notes_dir));
+    let file_path = notes_dir.join(&relative_path);
+    log::debug!(
+        "resolved {} to {}",
+        path.unwrap_or("daily note"),
+        file_path.display()
+    );
+
+    let needs_header = fs::read_to_string(&file_path)
+        .map(|c| c.trim().is_empty())
+        .unwrap_or(true);
+
+    if !needs_header {
+        return (file_path, None);
+    }
+
+    let template_name = match path {
+        None => "daily",
+        Some(p) if p.ends_with('/') => "daily",
+        Some(_) => "note",
+    };
+    let cursor = create_note(
+        notes_dir,
+        &relative_path,
+        &header,
+        template_name,
+        date,
+        path.is_none(),
+    );
+    (file_path, cursor)
+}
+
+/// Write a new note from `template_name`, returning the template's cursor
+/// line. Template dates use `date`. `link_daily` marks a daily note, which
+/// gets Previous/Next links when `daily.nav_links` is set and the
+/// `daily.sections` headings.
+fn create_note(
+    notes_dir: &std::path::Path,
+    relative_path: &std::path::Path,
+    header: &str,
+    template_name: &str,
+    date: NaiveDate,
+    link_daily: bool,
+) -> Option<usize> {
+    let file_path = notes_dir.join(relative_path);
+    if dry_run::skip(format_args!("create {}", file_path.display())) {
+        return None;
+    }
+
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).expect("failed to create note directory");
+    }
+
+    let title = header.trim_start_matches("# ");
+    let config = Config::load(notes_dir);
+    let rendered = template::render(
+        &template::load(notes_dir, template_name, &format!("{header}\n\n")),
+        &template::Vars {
+            title,
+            path: &relative_path.to_string_lossy(),
+            now: now_on(date),
+            date_format: &config.dates.format,
+            locale: config.dates.locale,
+            extra: &[],
+        },
+    );
+    let mut content = rendered.content;
+    let mut cursor = rendered.cursor;
+    if config.notes.frontmatter {
+        let stamped = stamp_frontmatter(&content, title, relative_path, Local::now());
+        let added = stamped.matches('\n').count() - content.matches('\n').count();
+        cursor = cursor.map(|line| line + added);
+        content = stamped;
+    }
+    if link_daily
+        && config.daily.nav_links
+        && let Some(line) = daily::link_neighbours(notes_dir, relative_path, date)
+    {
+        content.push_str(&format!("{line}\n\n"));
+    }
+    if link_daily {
+        let sections = daily::section_headings(&content, &config.daily.sections);
+        content.push_str(&sections);
+    }
+    fs::write(&file_path, content).expect("failed to write note file");
+    log::debug!(
+        "created {} from template {template_name}",
+        file_path.display()
+    );
+    events::emit(notes_dir, events::Event::Created, &file_path);
+
+    cursor
+}
+
+/// Create `path` from `template` (default: the note template), refusing to
+/// touch a note that already exists.
+fn new_note(
+    notes_dir: &std::path::Path,
+    path: &str,
+    template: Option<&str>,
+) -> Result<(PathBuf, Option<usize>), String> {
+    let (relative_path, header) = resolve_note(Some(path), &Config::load(notes_dir));
+    let file_path = notes_dir.join(&relative_path);
+    if file_path.exists() {
+        return Err(format!("{} already exists", relative_path.display()));
+    }
+
+    let template_name = template.unwrap_or("note");
+    let has_default = matches!(template_name, "note" | "daily");
+    let template_file = notes_dir
+        .join(template::TEMPLATES_DIR_NAME)
+        .join(format!("{template_name}.md"));
+    if !has_default && !template_file.is_file() {
+        return Err(format!("template {} not found", template_file.display()));
+    }
+
+    let cursor = create_note(
+        notes_dir,
+        &relative_path,
+        &header,
+        template_name,
+        Local::now().date_naive(),
+        false,
+    );
+    Ok((file_path, cursor))
+}
+
+/// Add `title`, `created` and `tags` frontmatter to a new note, keeping any
+/// of those keys a template already set. Tags come from the note's
+/// directories, so `sql/joins.md` is tagged `sql`; date folders are skipped.
+fn stamp_frontmatter(
+    content: &str,
+    title: &str,
+    relative_path: &std::path::Path,
+    now: chrono::DateTime<Local>,
+) -> String {
+    let mut content = content.to_string();
+    if frontmatter::get(&content, "title").is_none() {
+        content = frontmatter::set(&content, "title", title);
+    }
+    if frontmatter::get(&content, "created").is_none() {
+        content = frontmatter::set(&content, "created", &now.format("%Y-%m-%d").to_string());
+    }
+    if frontmatter::get(&content, "tags").is_none() {
+        let tags: Vec<String> = relative_path
+            .parent()
+            .into_iter()
+            .flat_map(|p| p.iter())
+            .map(|c| tags::normalize(&c.to_string_lossy()))
+            .filter(|c| !c.is_empty() && !c.chars().all(|ch| ch.is_ascii_digit()))
+            .collect();
+        content = frontmatter::set_list(&content, "tags", &tags);
+    }
+    content
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EntryStyle {
+    Plain,
+    Bullet,
+    Task,
+}
+
+/// Format appended text as a plain line, list item, or open task,
+/// optionally prefixed with a time.
+fn format_entry(text: &str, style: EntryStyle, time: Option<&str>) -> String {
+    let text = match time {
+        Some(time) => format!("{time} {text}"),
+        None => text.to_string(),
+    };
+    match style {
+        EntryStyle::Plain => text,
+        EntryStyle::Bullet => format!("- {text}"),
+        EntryStyle::Task => format!("- [ ] {text}"),
+    }
+}
+
+/// A `kno capture` list item: `- 14:32 text (cwd: ~/code/kno, repo: kno, ...)`.
+fn capture_entry(
+    text: &str,
+    context: &context::Context,
+    fields: &[context::Field],
+    time: &str,
+    home: Option<&std::path::Path>,
+) -> String {
+    let time = fields.contains(&context::Field::Time).then_some(time);
+    let mut entry = format_entry(text, EntryStyle::Bullet, time);
+    let description = context.describe(fields, home);
+    if !description.is_empty() {
+        entry.push_str(&format!(" ({description})"));
+    }
+    entry
+}
+
+/// Whether captured text is a bare link, which goes to the daily note's link section.
+fn is_url(text: &str) -> bool {
+    let text = text.trim();
+    (text.starts_with("https://") || text.starts_with("http://"))
+        && !text.contains(char::is_whitespace)
+}
+
+fn append_to_note(file_path: &std::path::Path, text: &str) {
     use std::io::Write;
+    if dry_run::skip(format_args!("append to {}: {text}", file_path.display())) {
+        return;
+    }
+    log::debug!("appending to {}", file_path.display());
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent).expect("failed to create note directory");
+    }
     let mut file = fs::OpenOptions::new()
+        .create(true)
         .append(true)
         .open(file_path)
         .expect("failed to open note for appending");
     writeln!(file, "{text}").expect("failed to append to note");
 }
 
+/// Where `append_note` puts new text.
+#[derive(Clone, Copy, Debug)]
+enum Placement<'a> {
+    /// At the end of the note; in a daily note with a `## <section>`
+    /// heading, at the end of that section instead
+    Section(&'a str),
+    /// Under `## <section>`, adding the heading at the end of the note if
+    /// it's missing
+    NewSection(&'a str),
+    /// Right after the frontmatter and `# Title` line (--prepend)
+    Top,
+    /// After the first line matching (--after)
+    After(&'a Regex),
+    /// Before the first line matching (--before)
+    Before(&'a Regex),
+}
+
+/// Parse a `--after`/`--before` pattern.
+fn parse_regex_arg(s: &str) -> Result<Regex, String> {
+    Regex::new(s).map_err(|e| e.to_string())
+}
+
+/// `content` with `text` inserted before or after its first line matching
+/// `pattern`. After a heading, the blank line below it stays put. `None` if
+/// no line matches.
+fn insert_at_match(content: &str, pattern: &Regex, text: &str, after: bool) -> Option<String> {
+    let mut lines: Vec<&str> = content.lines().collect();
+    let found = lines.iter().position(|l| pattern.is_match(l))?;
+    let at = match after {
+        false => found,
+        true if lines[found].starts_with('#')
+            && lines.get(found + 1).is_some_and(|l| l.trim().is_empty()) =>
+        {
+            found + 2
+        }
+        true => found + 1,
+    };
+    lines.insert(at, text);
+    Some(lines.join("\n") + "\n")
+}
+
+/// `content` with `text` inserted after its frontmatter and `# Title` line,
+/// above everything else.
+fn prepend_text(content: &str, text: &str) -> String {
+    let body = frontmatter::body(content);
+    let mut out = content[..content.len() - body.len()].to_string();
+    let rest = body.trim_start_matches('\n');
+    let rest = match rest.starts_with("# ") {
+        true => {
+            let (title, rest) = rest.split_once('\n').unwrap_or((rest, ""));
+            out.push_str(&format!("{title}\n\n"));
+            rest.trim_start_matches('\n')
+        }
+        false => rest,
+    };
+    out.push_str(text);
+    out.push('\n');
+    out.push_str(rest);
+    out
+}
+
+/// Add `text` to the note at `path` (dated `date` for daily notes), creating
+/// it first if needed. Without `header`, a missing note is created holding
+/// only the added text.
+fn append_note(
+    notes_dir: &std::path::Path,
+    path: Option<&str>,
+    date: NaiveDate,
+    text: &str,
+    header: bool,
+    placement: Placement,
+) -> PathBuf {
+    let file_path = match header {
+        true => open_note_on(notes_dir, path, date).0,
+        false => notes_dir.join(resolve_note_on(path, date, &Config::load(notes_dir)).0),
+    };
+    let content = fs::read_to_string(&file_path).unwrap_or_default();
+    let (updated, location) = match placement {
+        Placement::Top => (Some(prepend_text(&content, text)), "at the top".to_string()),
+        Placement::After(pattern) | Placement::Before(pattern) => {
+            let after = matches!(placement, Placement::After(_));
+            let Some(updated) = insert_at_match(&content, pattern, text, after) else {
+                eprintln!("No line in {} matches `{pattern}`", file_path.display());
+                process::exit(1);
+            };
+            let side = if after { "after" } else { "before" };
+            (Some(updated), format!("{side} `{pattern}`"))
+        }
+        Placement::Section(section) => (
+            path.is_none()
+                .then(|| daily::insert_in_section(&content, section, text))
+                .flatten(),
+            format!("under ## {section}"),
+        ),
+        Placement::NewSection(section) => (
+            Some(daily::add_to_section(&content, section, text)),
+            format!("under ## {section}"),
+        ),
+    };
+    match updated {
+        Some(updated) => {
+            if !dry_run::skip(format_args!(
+                "add to {} {location}: {text}",
+                file_path.display()
+            )) {
+                if let Some(parent) = file_path.parent() {
+                    fs::create_dir_all(parent).expect("failed to create note directory");
+                }
+                fs::write(&file_path, updated).expect("failed to update note");
+            }
+        }
+        None => append_to_note(&file_path, text),
+    }
+    if !dry_run::is_enabled() {
+        events::emit(notes_dir, events::Event::Appended, &file_path);
+    }
+    file_path
+}
+
+fn create_meeting_note(
+    notes_dir: &std::path::Path,
+    name: &str,
+    attendees: &[String],
+) -> (PathBuf, Option<usize>) {
+    let now = Local::now();
+    let today = now.format("%Y-%m-%d").to_string();
+    let relative_path = PathBuf::from("meetings").join(format!("{today}-{name}.md"));
+    let file_path = notes_dir.join(&relative_path);
+    let title = titlecase(name);
+    let mut cursor = None;
+
+    let create =
+        !file_path.exists() && !dry_run::skip(format_args!("create {}", file_path.display()));
+    if create {
+        let attendee_list = attendees
+            .iter()
+            .map(|a| format!("- {}", a.trim()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let config = Config::load(notes_dir);
+        let rendered = template::render(
+            &template::load(notes_dir, "meeting", MEETING_TEMPLATE),
+            &template::Vars {
+                title: &title,
+                path: &relative_path.to_string_lossy(),
+                now,
+                date_format: &config.dates.format,
+                locale: config.dates.locale,
+                extra: &[("attendees", &attendee_list)],
+            },
+        );
+        fs::create_dir_all(file_path.parent().unwrap())
+            .expect("failed to create meetings directory");
+        fs::write(&file_path, rendered.content).expect("failed to write meeting note");
+        events::emit(notes_dir, events::Event::Created, &file_path);
+        log::debug!("created {}", file_path.display());
+        cursor = rendered.cursor;
+    }
+
+    link_from_daily(notes_dir, &relative_path, &title, "");
+    (file_path, cursor)
+}
+
+/// Add `- [title](link) suffix` pointing at `relative_path` to today's daily
+/// note, unless it's already there.
+fn link_from_daily(
+    notes_dir: &std::path::Path,
+    relative_path: &std::path::Path,
+    title: &str,
+    suffix: &str,
+) {
+    let daily_path = open_note(notes_dir, None);
+    let (daily_relative, _) = resolve_note(None, &Config::load(notes_dir));
+    let link = format!(
+        "- [{title}]({}){suffix}",
+        links::relative_link(&daily_relative, relative_path)
+    );
+    let already_linked = fs::read_to_string(&daily_path)
+        .map(|c| c.lines().any(|l| l == link))
+        .unwrap_or(false);
+    if !already_linked {
+        append_to_note(&daily_path, &link);
+    }
+}
+
+/// Open (creating if needed) `projects/<repo>.md` for the git repository the
+/// working directory is in. With `link`, today's daily note links to it along
+/// with the current branch.
+fn open_project_note(
+    notes_dir: &std::path::Path,
+    context: &context::Context,
+    link: bool,
+) -> Result<(PathBuf, Option<usize>), String> {
+    let repo = context
+        .repo()
+        .ok_or("not inside a git repository".to_string())?;
+    let path = format!("projects/{repo}");
+    let (file_path, cursor) = open_note_with_cursor(notes_dir, Some(&path));
+
+    if link {
+        let relative_path = PathBuf::from(format!("{path}.md"));
+        let suffix = match &context.branch {
+            Some(branch) => format!(" (branch: {branch})"),
+            None => String::new(),
+        };
+        link_from_daily(notes_dir, &relative_path, &repo, &suffix);
+    }
+    Ok((file_path, cursor))
+}
+
+/// Write the tree under `dir` to `out` a line at a time, stopping at the
+/// first failed write (e.g. a closed pipe). With `all`, files other than
+/// notes are listed too, tagged with their kind.
 fn list_tree(
     dir: &std::path::Path,
     prefix: &str,
     max_depth: Option<usize>,
     depth: usize,
-    output: &mut String,
-) {
+    all: bool,
+    ancestors: &mut Vec<PathBuf>,
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
     if max_depth.is_some_and(|m| depth >= m) {
-        return;
+        return Ok(());
     }
 
     let mut entries: Vec<_> = match fs::read_dir(dir) {
         Ok(rd) => rd.filter_map(|e| e.ok()).collect(),
-        Err(_) => return,
+        Err(_) => return Ok(()),
     };
     entries.sort_by_key(|e| e.file_name());
 
@@ -145,353 +1613,3859 @@ fn list_tree(
         .into_iter()
         .filter(|e| {
             let name_str = e.file_name();
-            let name = name_str.to_string_lossy();
-            match e.file_type().expect("failed to read file type").is_dir() {
-                true => !name.starts_with('.'),
-                false => e.path().extension().is_some_and(|ext| ext == "md"),
+            let is_dir = e.path().is_dir();
+            if vault::is_skipped(&name_str.to_string_lossy(), is_dir) {
+                return false;
             }
+            is_dir || all || e.path().extension().is_some_and(|ext| ext == "md")
+        })
+        .filter_map(|e| {
+            // Directories carry the entry to push onto `ancestors`
+            let real = match e.path().is_dir() {
+                true => Some(vault::descend(&e.path(), ancestors)?),
+                false => None,
+            };
+            Some((e.file_name(), real))
         })
         .collect();
 
     let last = entries.len().saturating_sub(1);
-    for (i, entry) in entries.iter().enumerate() {
+    for (i, (name, real)) in entries.into_iter().enumerate() {
         let (connector, extension) = match i == last {
             true => ("└── ", "    "),
             false => ("├── ", "│   "),
         };
-        let name = entry.file_name();
-        let is_dir = entry
-            .file_type()
-            .expect("failed to read file type")
-            .is_dir();
 
-        match is_dir {
-            true => {
-                output.push_str(&format!("{prefix}{connector}{}/\n", name.to_string_lossy()));
+        match real {
+            Some(real) => {
+                writeln!(out, "{prefix}{connector}{}/", name.to_string_lossy())?;
                 let child_prefix = format!("{prefix}{extension}");
-                list_tree(&entry.path(), &child_prefix, max_depth, depth + 1, output);
-            }
-            false => {
-                output.push_str(&format!("{prefix}{connector}{}\n", name.to_string_lossy()));
+                ancestors.push(real);
+                let path = dir.join(&name);
+                list_tree(
+                    &path,
+                    &child_prefix,
+                    max_depth,
+                    depth + 1,
+                    all,
+                    ancestors,
+                    out,
+                )?;
+                ancestors.pop();
             }
+            None => match vault::file_kind(std::path::Path::new(&name)) {
+                Some(kind) => writeln!(
+                    out,
+                    "{prefix}{connector}{} [{kind}]",
+                    name.to_string_lossy()
+                )?,
+                None => writeln!(out, "{prefix}{connector}{}", name.to_string_lossy())?,
+            },
         }
     }
+    Ok(())
 }
 
-fn list_notes(notes_dir: &std::path::Path, path: Option<&str>, max_depth: Option<usize>) -> String {
+fn list_notes(
+    notes_dir: &std::path::Path,
+    path: Option<&str>,
+    max_depth: Option<usize>,
+    all: bool,
+    out: &mut impl std::io::Write,
+) -> std::io::Result<()> {
     let root = match path {
         Some(p) => notes_dir.join(p),
         None => notes_dir.to_path_buf(),
     };
 
-    if !root.is_dir() {
-        return format!("{} is not a directory\n", root.display());
+    if !root.is_dir() {
+        return writeln!(out, "{} is not a directory", root.display());
+    }
+
+    writeln!(out, "{}", path.unwrap_or("."))?;
+    let mut ancestors = vault::walk_root(&root);
+    list_tree(&root, "", max_depth, 0, all, &mut ancestors, out)
+}
+
+/// Notes under `path` (the whole vault if `None`) that pass the tag and
+/// modification-date filters, in `sort` order.
+fn filtered_notes(
+    notes_dir: &std::path::Path,
+    path: Option<&str>,
+    tag: Option<&str>,
+    since: Option<NaiveDate>,
+    sort: SortKey,
+) -> Vec<PathBuf> {
+    let tag = tag.map(tags::normalize);
+    let since = since.and_then(|d| d.and_hms_opt(0, 0, 0)?.and_local_timezone(Local).earliest());
+
+    let mut notes: Vec<_> = vault::visible_notes(notes_dir)
+        .into_iter()
+        .filter(|rel| path.is_none_or(|p| rel.starts_with(p)))
+        .filter_map(|rel| {
+            let file_path = notes_dir.join(&rel);
+            let modified = fs::metadata(&file_path).and_then(|m| m.modified()).ok()?;
+            Some((rel, file_path, chrono::DateTime::<Local>::from(modified)))
+        })
+        .filter(|(_, _, modified)| since.is_none_or(|since| *modified >= since))
+        .filter(|(_, file_path, _)| {
+            tag.as_ref().is_none_or(|tag| {
+                let content = fs::read_to_string(file_path).unwrap_or_default();
+                tags::all_tags(&content).contains(tag)
+            })
+        })
+        .collect();
+
+    if sort == SortKey::Modified {
+        notes.sort_by_key(|(_, _, modified)| std::cmp::Reverse(*modified));
+    }
+    notes.into_iter().map(|(rel, _, _)| rel).collect()
+}
+
+/// Render a tree from a list of note paths, keeping the order in which
+/// entries first appear in `paths`.
+fn render_tree(label: &str, paths: &[PathBuf], max_depth: Option<usize>) -> String {
+    #[derive(Default)]
+    struct Node {
+        children: Vec<(String, Node)>,
+        is_dir: bool,
+    }
+
+    fn insert(node: &mut Node, parts: &[String]) {
+        let Some((first, rest)) = parts.split_first() else {
+            return;
+        };
+        let idx = match node.children.iter().position(|(name, _)| name == first) {
+            Some(idx) => idx,
+            None => {
+                node.children.push((first.clone(), Node::default()));
+                node.children.len() - 1
+            }
+        };
+        let child = &mut node.children[idx].1;
+        child.is_dir |= !rest.is_empty();
+        insert(child, rest);
+    }
+
+    fn render(
+        node: &Node,
+        prefix: &str,
+        max_depth: Option<usize>,
+        depth: usize,
+        output: &mut String,
+    ) {
+        if max_depth.is_some_and(|m| depth >= m) {
+            return;
+        }
+        let last = node.children.len().saturating_sub(1);
+        for (i, (name, child)) in node.children.iter().enumerate() {
+            let (connector, extension) = match i == last {
+                true => ("└── ", "    "),
+                false => ("├── ", "│   "),
+            };
+            let slash = if child.is_dir { "/" } else { "" };
+            output.push_str(&format!("{prefix}{connector}{name}{slash}\n"));
+            render(
+                child,
+                &format!("{prefix}{extension}"),
+                max_depth,
+                depth + 1,
+                output,
+            );
+        }
+    }
+
+    let mut root = Node::default();
+    for path in paths {
+        let parts: Vec<String> = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        insert(&mut root, &parts);
+    }
+
+    let mut output = format!("{label}\n");
+    render(&root, "", max_depth, 0, &mut output);
+    output
+}
+
+fn create_notes_dir(notes_dir: &std::path::Path) {
+    match notes_dir.exists() {
+        true => log::info!("{} already exists", notes_dir.display()),
+        false if dry_run::skip(format_args!("create {}", notes_dir.display())) => {}
+        false => {
+            fs::create_dir_all(notes_dir).expect("failed to create notes directory");
+            log::info!("Created {}", notes_dir.display());
+        }
+    }
+}
+
+/// Move notes (old path to new, relative to the notes dir) with `git mv`,
+/// or plain renames for files git doesn't track, rewriting links to and from
+/// the moved notes first.
+fn move_notes(notes_dir: &std::path::Path, moves: &HashMap<PathBuf, PathBuf>) {
+    for rel in vault::notes(notes_dir) {
+        let file = notes_dir.join(&rel);
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let new_rel = moves.get(&rel).unwrap_or(&rel);
+        let updated = links::rewrite_moved(&content, &rel, new_rel, moves);
+        if updated != content && !dry_run::skip(format_args!("update links in {}", rel.display())) {
+            fs::write(&file, updated).expect("failed to update links");
+        }
+    }
+
+    let use_git = notes_dir.join(".git").exists();
+    let mut sorted: Vec<_> = moves.iter().collect();
+    sorted.sort();
+    for (old, new) in sorted {
+        if dry_run::skip(format_args!("move {} to {}", old.display(), new.display())) {
+            continue;
+        }
+        fs::create_dir_all(notes_dir.join(new).parent().unwrap())
+            .expect("failed to create note directory");
+        let moved_by_git = use_git
+            && git_cmd(notes_dir)
+                .args(["mv", "--"])
+                .arg(old)
+                .arg(new)
+                .stderr(process::Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success());
+        if !moved_by_git {
+            fs::rename(notes_dir.join(old), notes_dir.join(new)).expect("failed to move note");
+        }
+        log::debug!("moved {} to {}", old.display(), new.display());
+    }
+
+    if !dry_run::is_enabled() {
+        vault::rename_in_history(notes_dir, moves);
+        cursors::rename(notes_dir, moves);
+    }
+}
+
+/// Remove the directories `moves` left empty, innermost first.
+fn remove_emptied_dirs(notes_dir: &std::path::Path, moves: &HashMap<PathBuf, PathBuf>) {
+    let mut dirs: Vec<&std::path::Path> = moves
+        .keys()
+        .flat_map(|old| old.ancestors().skip(1))
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect();
+    dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+    dirs.dedup();
+    for dir in dirs {
+        // Fails, as it should, for directories that still hold something
+        let _ = fs::remove_dir(notes_dir.join(dir));
+    }
+}
+
+/// Move daily notes into layout `to`, fixing links to and from them.
+fn run_migrate_daily(notes_dir: &std::path::Path, to: daily::Layout) {
+    let name = to.to_possible_value().unwrap().get_name().to_string();
+    let moves = match daily::migration(notes_dir, to) {
+        Ok(moves) => moves,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    };
+    if moves.is_empty() {
+        log::info!("Daily notes already use the {name} layout");
+    }
+    let moves: HashMap<PathBuf, PathBuf> = moves.into_iter().collect();
+    move_notes(notes_dir, &moves);
+
+    if dry_run::is_enabled() || moves.is_empty() {
+        return;
+    }
+    daily::remove_empty_dirs(&notes_dir.join(daily::DAILY_DIR_NAME));
+    log::info!("Moved {} daily notes to the {name} layout", moves.len());
+    if Config::load(notes_dir).daily.layout != to {
+        log::info!(
+            "Set `layout = \"{name}\"` under [daily] in config.toml to keep new notes there"
+        );
+    }
+}
+
+/// Move notes as the mapping file at `map` says, fixing links to and from them.
+fn run_migrate_map(notes_dir: &std::path::Path, map: &std::path::Path) {
+    let moves = match migrate::read_map(map).and_then(|map| migrate::moves(notes_dir, &map)) {
+        Ok(moves) => moves,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    };
+    if moves.is_empty() {
+        log::info!("No notes match the mapping");
+        return;
+    }
+    let moves: HashMap<PathBuf, PathBuf> = moves.into_iter().collect();
+    move_notes(notes_dir, &moves);
+    if !dry_run::is_enabled() {
+        remove_emptied_dirs(notes_dir, &moves);
+        log::info!("Moved {} files", moves.len());
+    }
+}
+
+fn run_export(
+    notes_dir: &std::path::Path,
+    format: Option<export::Format>,
+    path: Option<&str>,
+    profile: Option<&str>,
+    tag: Option<&str>,
+    out: &std::path::Path,
+) {
+    let path = path.map(|p| p.trim_end_matches('/'));
+    let notes = filtered_notes(notes_dir, path, tag, None, SortKey::Name);
+    let notes = export::exposed(notes_dir, notes, false);
+    if notes.is_empty() {
+        eprintln!("No notes to export");
+        process::exit(1);
+    }
+    let Some(format) = format else {
+        let name = profile.unwrap_or_default();
+        let profiles = Config::load(notes_dir).export.profiles;
+        let Some(profile) = profiles.get(name) else {
+            eprintln!("Error: no export profile {name:?} in [export.profiles]");
+            process::exit(1);
+        };
+        match export::export_profile(profile, notes_dir, &notes, out) {
+            Ok(file) if !dry_run::is_enabled() => {
+                log::info!("Exported {} notes to {}", notes.len(), file.display())
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Error: export profile {name}: {e}");
+                process::exit(1);
+            }
+        }
+        return;
+    };
+    match format {
+        export::Format::Html => export::export_html(notes_dir, &notes, out),
+        export::Format::Org => export::export_org(notes_dir, &notes, out),
+        export::Format::Obsidian => export::export_obsidian(notes_dir, &notes, out),
+    }
+    if !dry_run::is_enabled() {
+        log::info!("Exported {} notes to {}", notes.len(), out.display());
+    }
+}
+
+fn run_import_dir(notes_dir: &std::path::Path, src: &std::path::Path, into: Option<&str>) {
+    let into = PathBuf::from(into.unwrap_or_default().trim_matches('/'));
+    let plan = match import::plan_dir(src, notes_dir, &into) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    };
+    if let Err(e) = import::import(src, notes_dir, &into, &plan) {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+    for (rel, reason) in &plan.conflicts {
+        eprintln!("Warning: skipped {}: {reason}", rel.display());
+    }
+    if !dry_run::is_enabled() {
+        log::info!(
+            "Imported {} files into {}",
+            plan.copies.len(),
+            notes_dir.join(&into).display()
+        );
+    }
+}
+
+fn run_import_joplin(notes_dir: &std::path::Path, jex: &std::path::Path, into: &str) {
+    let into = PathBuf::from(into.trim_matches('/'));
+    let summary = match joplin::import(jex, notes_dir, &into) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    };
+    for (title, reason) in &summary.skipped {
+        eprintln!("Warning: skipped {title}: {reason}");
+    }
+    if !dry_run::is_enabled() {
+        log::info!(
+            "Imported {} notes and {} attachments into {}",
+            summary.notes.len(),
+            summary.resources.len(),
+            notes_dir.join(&into).display()
+        );
+    }
+}
+
+fn run_import_apple_notes(notes_dir: &std::path::Path, html: Option<&std::path::Path>, into: &str) {
+    let into = PathBuf::from(into.trim_matches('/'));
+    let notes = match html {
+        Some(dir) => apple_notes::from_html_dir(dir),
+        None => apple_notes::from_notes_app(),
+    };
+    let written = notes.and_then(|notes| apple_notes::import(&notes, notes_dir, &into));
+    match written {
+        Ok(written) if !dry_run::is_enabled() => log::info!(
+            "Imported {} notes into {}",
+            written.len(),
+            notes_dir.join(&into).display()
+        ),
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Move the note or directory `from` to `to`, fixing links to and from the
+/// moved notes, and with `redirect` leaving stubs at the old paths.
+fn run_mv(notes_dir: &std::path::Path, from: &str, to: &str, redirect: bool) {
+    let clean = |p: &str| PathBuf::from(p.trim_end_matches('/').trim_end_matches(".md"));
+    let map = [(clean(from), clean(to))];
+    let moves = match migrate::moves(notes_dir, &map) {
+        Ok(moves) if moves.is_empty() => {
+            eprintln!("Error: {from} not found in {}", notes_dir.display());
+            process::exit(1);
+        }
+        Ok(moves) => moves,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    };
+    let titles: Vec<String> = moves
+        .iter()
+        .map(|(old, _)| {
+            let content = fs::read_to_string(notes_dir.join(old)).unwrap_or_default();
+            vault::title(&content, old)
+        })
+        .collect();
+
+    let moves_map: HashMap<PathBuf, PathBuf> = moves.iter().cloned().collect();
+    move_notes(notes_dir, &moves_map);
+    if !redirect && !dry_run::is_enabled() {
+        remove_emptied_dirs(notes_dir, &moves_map);
+    }
+    if redirect {
+        for ((old, new), title) in moves.iter().zip(&titles) {
+            if dry_run::skip(format_args!("leave a redirect at {}", old.display())) {
+                continue;
+            }
+            let stub = migrate::redirect_stub(old, new, title);
+            fs::create_dir_all(notes_dir.join(old).parent().unwrap())
+                .expect("failed to create note directory");
+            fs::write(notes_dir.join(old), stub).expect("failed to write redirect note");
+        }
+    }
+    if !dry_run::is_enabled() {
+        match moves.len() {
+            1 => log::info!("Moved {} to {}", moves[0].0.display(), moves[0].1.display()),
+            n => log::info!("Moved {n} notes to {}", map[0].1.display()),
+        }
+    }
+}
+
+/// Move `paths`, or with `auto` the notes due under `[[archive.rules]]`, into
+/// the archive dir, fixing links to and from them.
+fn run_archive(notes_dir: &std::path::Path, paths: &[String], auto: bool) {
+    let config = Config::load(notes_dir);
+    let dir = &config.archive.dir;
+    let moves = match auto {
+        true if config.archive.rules.is_empty() => {
+            eprintln!("No [[archive.rules]] in {}", config::CONFIG_FILE_NAME);
+            process::exit(1);
+        }
+        true => archive::due(
+            notes_dir,
+            dir,
+            &config.archive.rules,
+            std::time::SystemTime::now(),
+        ),
+        false => paths
+            .iter()
+            .map(|path| {
+                let (rel, _) = resolve_note(Some(path), &config);
+                let to = archive::target(dir, &rel);
+                match (notes_dir.join(&rel).is_file(), notes_dir.join(&to).exists()) {
+                    (false, _) => Err(format!("{path} not found in {}", notes_dir.display())),
+                    (_, true) => Err(format!("{} already exists", to.display())),
+                    _ if rel.starts_with(dir) => Err(format!("{path} is already archived")),
+                    _ => Ok((rel, to)),
+                }
+            })
+            .collect(),
+    };
+    let moves: HashMap<PathBuf, PathBuf> = match moves {
+        Ok(moves) => moves.into_iter().collect(),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    };
+    if moves.is_empty() {
+        log::info!("No notes due for archiving");
+        return;
+    }
+
+    move_notes(notes_dir, &moves);
+    if !dry_run::is_enabled() {
+        log::info!("Archived {} notes to {dir}/", moves.len());
+    }
+}
+
+fn git_cmd(notes_dir: &std::path::Path) -> process::Command {
+    let mut cmd = process::Command::new("git");
+    cmd.arg("-C").arg(notes_dir);
+    log::debug!("running git in {}", notes_dir.display());
+    cmd
+}
+
+fn init_git_repo(notes_dir: &std::path::Path) {
+    if notes_dir.join(".git").exists() {
+        log::info!("Git repo already initialized");
+        return;
+    }
+
+    if dry_run::skip(format_args!("run git init in {}", notes_dir.display())) {
+        return;
+    }
+
+    let mut cmd = git_cmd(notes_dir);
+    cmd.arg("init");
+    if log::level() == log::Level::Quiet {
+        cmd.arg("--quiet");
+    }
+
+    match cmd.status() {
+        Ok(s) if s.success() => log::info!("Initialized git repo"),
+        Ok(_) => eprintln!("Warning: git init failed"),
+        Err(e) => eprintln!("Warning: could not run git: {e}"),
+    }
+}
+
+/// Append `line` to ~/.zshrc unless it's already there. `what` names the
+/// feature in messages (e.g. "shell completions").
+fn add_to_zshrc(line: &str, what: &str) {
+    let home = env::var("HOME").expect("HOME not set");
+    let zshrc = PathBuf::from(&home).join(".zshrc");
+
+    let already_present = zshrc
+        .exists()
+        .then(|| fs::read_to_string(&zshrc).unwrap_or_default())
+        .is_some_and(|content| content.contains(line));
+
+    if already_present {
+        log::info!("{} already configured", titlecase(what));
+        return;
+    }
+
+    if dry_run::skip(format_args!("append `{line}` to {}", zshrc.display())) {
+        return;
+    }
+
+    let result = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&zshrc)
+        .and_then(|mut file| {
+            use std::io::Write;
+            writeln!(file, "\n{line}")
+        });
+
+    match result {
+        Ok(()) => {
+            log::info!("Added {what} to ~/.zshrc (restart your shell or `source ~/.zshrc`)")
+        }
+        Err(e) => eprintln!("Warning: could not update .zshrc: {e}"),
+    }
+}
+
+fn setup_shell_completions() {
+    add_to_zshrc("source <(COMPLETE=zsh kno)", "shell completions");
+}
+
+/// Shell function that makes `kno cd` change the calling shell's directory;
+/// every other invocation passes straight through to the binary.
+const CD_WRAPPER: &str = r#"kno() {
+  if [ "$1" = cd ] && [ "$2" != --wrapper ]; then
+    shift
+    local dir
+    dir="$(command kno cd "$@")" && builtin cd "$dir"
+  else
+    command kno "$@"
+  fi
+}
+"#;
+
+fn setup_cd_wrapper() {
+    add_to_zshrc(r#"eval "$(kno cd --wrapper)""#, "the kno cd wrapper");
+}
+
+/// Absolute path of a directory inside the vault, or the vault itself.
+fn vault_dir(notes_dir: &std::path::Path, path: Option<&str>) -> Result<PathBuf, String> {
+    let dir = notes_dir.join(path.unwrap_or_default());
+    let resolved = dir
+        .canonicalize()
+        .ok()
+        .filter(|d| d.is_dir())
+        .ok_or_else(|| format!("{} is not a directory in the vault", dir.display()))?;
+    let root = notes_dir.canonicalize().unwrap_or(notes_dir.to_path_buf());
+    match resolved.starts_with(&root) {
+        true => Ok(resolved),
+        false => Err(format!("{} is outside the vault", dir.display())),
+    }
+}
+
+const PRE_COMMIT_HOOK: &str = "#!/bin/sh
+# Installed by `kno init --hooks`: stop commits of notes with broken links or
+# conflict markers. Bypass with `git commit --no-verify`.
+exec kno -q verify --staged
+";
+
+/// How hooks installed by `kno init --hooks` start, so older versions of
+/// them are replaced rather than left alone.
+const PRE_COMMIT_HOOK_HEADER: &str = "#!/bin/sh\n# Installed by `kno init --hooks`";
+
+fn install_pre_commit_hook(notes_dir: &std::path::Path) {
+    let hook = notes_dir.join(".git").join("hooks").join("pre-commit");
+    match fs::read_to_string(&hook) {
+        Ok(existing) if existing == PRE_COMMIT_HOOK => {
+            log::info!("Pre-commit hook already installed");
+            return;
+        }
+        Ok(existing) if !existing.starts_with(PRE_COMMIT_HOOK_HEADER) => {
+            eprintln!(
+                "Warning: {} already exists; add `kno -q verify --staged` to it to check notes before commits",
+                hook.display()
+            );
+            return;
+        }
+        _ => {}
+    }
+    if dry_run::skip(format_args!("install pre-commit hook {}", hook.display())) {
+        return;
+    }
+    if !notes_dir.join(".git").exists() {
+        eprintln!(
+            "Warning: no git repo in {}, hook not installed",
+            notes_dir.display()
+        );
+        return;
+    }
+
+    fs::create_dir_all(hook.parent().unwrap()).expect("failed to create hooks directory");
+    fs::write(&hook, PRE_COMMIT_HOOK).expect("failed to write pre-commit hook");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))
+            .expect("failed to make pre-commit hook executable");
+    }
+    log::info!("Installed pre-commit hook running `kno verify --staged`");
+}
+
+/// Merge driver `kno init` registers for daily notes, so appends made on two
+/// machines merge instead of conflicting.
+const MERGE_DRIVER: &str = "kno merge-driver %O %A %B";
+
+/// Route daily notes through `kno merge-driver`: the `.gitattributes` line is
+/// committed with the notes, but the driver itself lives in each clone's
+/// `.git/config`, so `kno init` must be run on every machine.
+/// Keep per-machine state like `.history` out of the vault's commits.
+fn ignore_local_files(notes_dir: &std::path::Path) {
+    if !notes_dir.join(".git").exists()
+        || dry_run::skip(format_args!("exclude kno's local state files from git"))
+    {
+        return;
+    }
+    match commit::ignore_local_files(notes_dir) {
+        Ok(true) => log::info!("Excluded kno's local state files from git"),
+        Ok(false) => {}
+        Err(e) => eprintln!("Warning: {e}"),
+    }
+}
+
+fn install_merge_driver(notes_dir: &std::path::Path) {
+    if !notes_dir.join(".git").exists() {
+        return;
+    }
+    let attributes = notes_dir.join(".gitattributes");
+    let rule = format!("{}/** merge=kno", daily::DAILY_DIR_NAME);
+    let existing = fs::read_to_string(&attributes).unwrap_or_default();
+    if !existing.lines().any(|l| l.trim() == rule)
+        && !dry_run::skip(format_args!("add `{rule}` to {}", attributes.display()))
+    {
+        let separator = match existing.is_empty() || existing.ends_with('\n') {
+            true => "",
+            false => "\n",
+        };
+        fs::write(&attributes, format!("{existing}{separator}{rule}\n"))
+            .expect("failed to write .gitattributes");
+    }
+
+    let configured = git_cmd(notes_dir)
+        .args(["config", "--get", "merge.kno.driver"])
+        .output()
+        .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).trim() == MERGE_DRIVER);
+    if configured {
+        log::info!("Merge driver for daily notes already installed");
+        return;
+    }
+    if dry_run::skip(format_args!(
+        "set git config merge.kno.driver to {MERGE_DRIVER}"
+    )) {
+        return;
+    }
+    for (key, value) in [
+        ("merge.kno.name", "kno: union of daily note entries"),
+        ("merge.kno.driver", MERGE_DRIVER),
+    ] {
+        let status = git_cmd(notes_dir)
+            .args(["config", key, value])
+            .status()
+            .expect("failed to run git");
+        if !status.success() {
+            eprintln!("Warning: failed to set git config {key}");
+            return;
+        }
+    }
+    log::info!("Installed merge driver for daily notes");
+}
+
+/// Write the union of `ours` and `theirs` (see `merge::union`) over `ours`,
+/// as git expects of a merge driver. Exits 1, leaving a conflict, if a
+/// version can't be read.
+fn run_merge_driver(base: &std::path::Path, ours: &std::path::Path, theirs: &std::path::Path) {
+    let read = |path: &std::path::Path| {
+        fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Error: failed to read {}: {e}", path.display());
+            process::exit(1);
+        })
+    };
+    let merged = merge::union(&read(base), &read(ours), &read(theirs));
+    if let Err(e) = fs::write(ours, merged) {
+        eprintln!("Error: failed to write {}: {e}", ours.display());
+        process::exit(1);
+    }
+}
+
+fn run_init(notes_dir: &std::path::Path) {
+    create_notes_dir(notes_dir);
+    init_git_repo(notes_dir);
+    install_merge_driver(notes_dir);
+    ignore_local_files(notes_dir);
+    setup_shell_completions();
+    setup_cd_wrapper();
+}
+
+fn require_git_repo(notes_dir: &std::path::Path) {
+    if !notes_dir.join(".git").exists() {
+        eprintln!("Notes directory is not a git repo. Run `kno git init` to initialize.");
+        process::exit(1);
+    }
+}
+
+/// Commit every change in the notes dir, if there are any.
+fn commit_changes(notes_dir: &std::path::Path, message: Option<&str>) -> Result<(), String> {
+    if !dry_run::is_enabled() {
+        commit::ignore_local_files(notes_dir)?;
+    }
+    let files = commit::changed(notes_dir)?;
+    if files.is_empty() {
+        log::info!("Nothing to commit");
+        return Ok(());
+    }
+    let config = Config::load(notes_dir);
+    let message = match message {
+        Some(message) => message.to_string(),
+        None => commit::message(&config.git.commit_message, &files, Local::now(), &config),
+    };
+    if dry_run::skip(format_args!("commit {} files: {message}", files.len())) {
+        return Ok(());
+    }
+    commit::commit_all(notes_dir, &message)?;
+    log::info!("Committed {} files: {message}", files.len());
+    Ok(())
+}
+
+fn run_commit(notes_dir: &std::path::Path, message: Option<&str>) {
+    require_git_repo(notes_dir);
+    if let Err(e) = commit_changes(notes_dir, message) {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+fn run_sync(notes_dir: &std::path::Path, message: Option<&str>) {
+    require_git_repo(notes_dir);
+    let config = Config::load(notes_dir);
+    let result = commit_changes(notes_dir, message).and_then(|()| {
+        if dry_run::skip(format_args!("pull and push")) {
+            return Ok(None);
+        }
+        let delay = std::time::Duration::from_secs(config.git.retry_secs);
+        sync::sync(notes_dir, config.git.push_retries, delay, Local::now()).map(Some)
+    });
+    match result {
+        Ok(Some(sync::Synced::Pushed)) => log::info!("Synced"),
+        Ok(Some(sync::Synced::Queued(pending))) => eprintln!(
+            "Warning: could not reach the remote ({}); commits are queued locally, run `kno sync` again when online",
+            pending.error
+        ),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn run_remote_sync(notes_dir: &std::path::Path, backend: remote::Backend) {
+    let mut config = Config::load(notes_dir).remote;
+    if config.user.is_some() && config.password.is_none() {
+        config.password = secrets::lookup("remote_password");
+    }
+    let result =
+        remote::Curl::new(backend, &config).and_then(|store| remote::sync(notes_dir, &store));
+    match result {
+        Ok(_) if dry_run::is_enabled() => {}
+        Ok(actions) if actions.is_empty() => log::info!("Already in sync"),
+        Ok(actions) => {
+            for (rel, action) in &actions {
+                log::info!("{action:?}: {}", rel.display());
+            }
+            match actions.len() {
+                1 => log::info!("Synced 1 note"),
+                n => log::info!("Synced {n} notes"),
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn run_status(notes_dir: &std::path::Path) {
+    require_git_repo(notes_dir);
+    match status::read(notes_dir) {
+        Ok(status) => print!("{}", status::format(&status, Local::now())),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn run_git(notes_dir: &std::path::Path, args: &[String]) {
+    let is_init = args.first().is_some_and(|a| a == "init");
+
+    if !is_init && !notes_dir.join(".git").exists() {
+        eprintln!("Notes directory is not a git repo. Run `kno git init` to initialize.");
+        process::exit(1);
+    }
+
+    if dry_run::skip(format_args!("run git {}", args.join(" "))) {
+        process::exit(0);
+    }
+
+    log::debug!("git {}", args.join(" "));
+    let status = git_cmd(notes_dir)
+        .args(args)
+        .status()
+        .expect("failed to run git");
+
+    process::exit(status.code().unwrap_or(1));
+}
+
+/// Print each note filled into a `--format` template, stopping if stdout closes.
+fn write_formatted(
+    out: &mut impl std::io::Write,
+    notes_dir: &std::path::Path,
+    notes: &[PathBuf],
+    template: &str,
+) -> std::io::Result<()> {
+    for note in notes {
+        let values = format::note_values(notes_dir, note);
+        writeln!(out, "{}", format::render(template, &values))?;
+    }
+    out.flush()
+}
+
+/// What `kno search` prints.
+#[derive(Clone, PartialEq)]
+enum SearchOutput {
+    /// `path:line:text` for each matching line
+    Lines,
+    /// `path:count` for each note with matches
+    Count,
+    /// The path of each note with matches
+    Files,
+    /// Each matching line filled into a `--format` template
+    Format(String),
+}
+
+/// Print search matches in `output` form, stopping if stdout closes.
+fn write_matches(
+    out: &mut impl std::io::Write,
+    matches: &[search::Match],
+    output: &SearchOutput,
+    color: bool,
+) -> std::io::Result<()> {
+    for note in matches.chunk_by(|a, b| a.path == b.path) {
+        let path = note[0].path.display();
+        match output {
+            SearchOutput::Count => writeln!(out, "{path}:{}", note.len())?,
+            SearchOutput::Files => writeln!(out, "{path}")?,
+            SearchOutput::Lines => {
+                for m in note {
+                    writeln!(out, "{path}:{}:{}", m.line, search::highlight(m, color))?;
+                }
+            }
+            SearchOutput::Format(template) => {
+                for m in note {
+                    let values = [
+                        ("path", path.to_string()),
+                        ("line", m.line.to_string()),
+                        ("text", m.text.clone()),
+                    ];
+                    writeln!(out, "{}", format::render(template, &values))?;
+                }
+            }
+        }
+    }
+    out.flush()
+}
+
+/// What a `kno search` looks for and where, as `--save` stores it.
+struct SearchQuery {
+    query: String,
+    path: Option<String>,
+    syntax: search::Syntax,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    /// A `--query` expression the notes must match.
+    filter: Option<String>,
+}
+
+impl SearchQuery {
+    /// `kno search` arguments that repeat this search.
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let flags = [
+            (self.syntax.regex, "--regex"),
+            (self.syntax.ignore_case, "--ignore-case"),
+            (self.syntax.word, "--word"),
+        ];
+        for (_, flag) in flags.iter().filter(|(set, _)| *set) {
+            args.push(flag.to_string());
+        }
+        for (flag, date) in [("--since", self.since), ("--until", self.until)] {
+            if let Some(date) = date {
+                args.extend([flag.to_string(), date.format("%Y-%m-%d").to_string()]);
+            }
+        }
+        if let Some(filter) = &self.filter {
+            args.extend(["--query".to_string(), filter.clone()]);
+        }
+        // The query may start with `-`
+        args.extend(["--".to_string(), self.query.clone()]);
+        args.extend(self.path.clone());
+        args
+    }
+
+    /// The search saved in config as `name`.
+    fn saved(notes_dir: &std::path::Path, name: &str) -> Result<SearchQuery, String> {
+        let config = Config::load(notes_dir);
+        let args = config
+            .searches
+            .get(name)
+            .ok_or_else(|| format!("no saved search named {name}"))?;
+        let argv = ["kno", "search"].map(String::from).into_iter();
+        let cli = Cli::try_parse_from(argv.chain(args.iter().cloned()))
+            .map_err(|e| format!("saved search {name} is invalid: {}", e.kind()))?;
+        match cli.command {
+            Some(Command::Search {
+                query: Some(query),
+                path,
+                regex,
+                ignore_case,
+                word,
+                since,
+                until,
+                filter,
+                saved: None,
+                ..
+            }) => Ok(SearchQuery {
+                query,
+                path,
+                syntax: search::Syntax {
+                    regex,
+                    ignore_case,
+                    word,
+                },
+                since,
+                until,
+                filter,
+            }),
+            _ => Err(format!("saved search {name} is invalid")),
+        }
+    }
+}
+
+/// Run `search`. Exits 1 when nothing matches and 2 on an invalid regex,
+/// like grep.
+fn run_search(notes_dir: &std::path::Path, search: &SearchQuery, output: SearchOutput) {
+    let pattern = search::compile(&search.query, &search.syntax).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        process::exit(2);
+    });
+    let path = search.path.as_deref().map(|p| p.trim_end_matches('/'));
+    let notes: Vec<_> = vault::visible_notes(notes_dir)
+        .into_iter()
+        .filter(|rel| path.is_none_or(|p| rel.starts_with(p)))
+        .collect();
+    let mut notes = search::dated_within(notes_dir, notes, search.since, search.until);
+    if let Some(filter) = &search.filter {
+        notes = query_arg(filter).filter(notes_dir, notes);
+    }
+    let matches = search::search(notes_dir, &notes, &pattern);
+    if matches.is_empty() {
+        process::exit(1);
+    }
+    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+    // A closed pipe (e.g. `kno search x | head`) isn't an error
+    let _ = write_matches(&mut out, &matches, &output, search::use_color());
+}
+
+/// What `kno pick` does with the chosen notes.
+#[derive(Clone, Copy, PartialEq)]
+enum PickOutput {
+    /// Open them in the editor
+    Editor,
+    /// Open each in a new tmux window
+    TmuxWindow,
+    /// Type the path into the current tmux pane
+    TmuxSend,
+    /// Print each absolute path followed by this terminator
+    Print(&'static str),
+}
+
+/// Choose notes with fzf (in a tmux popup with `tmux`) and handle them per
+/// `output`. Exits 130 when the picker is cancelled, so scripts can tell it
+/// apart from an error.
+fn run_pick(notes_dir: &std::path::Path, tmux: bool, multi: bool, output: PickOutput) {
+    if tmux && env::var_os("TMUX").is_none() {
+        eprintln!("Error: --tmux only works inside a tmux session");
+        process::exit(1);
+    }
+    let picked = pick::pick(notes_dir, tmux, multi).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    });
+    if picked.is_empty() {
+        process::exit(pick::CANCELLED_EXIT_CODE);
+    }
+    let file_paths: Vec<_> = picked.iter().map(|rel| notes_dir.join(rel)).collect();
+    let result = match output {
+        PickOutput::Editor => launch_editor(notes_dir, &file_paths, None, &[]),
+        PickOutput::Print(end) => {
+            use std::io::Write;
+            let mut out = std::io::stdout().lock();
+            for file_path in &file_paths {
+                let _ = write!(out, "{}{end}", file_path.display());
+            }
+            let _ = out.flush();
+            Ok(())
+        }
+        PickOutput::TmuxSend => {
+            match dry_run::skip(format_args!(
+                "type {} into the pane",
+                file_paths[0].display()
+            )) {
+                true => Ok(()),
+                false => pick::send_to_tmux_pane(&file_paths[0]),
+            }
+        }
+        PickOutput::TmuxWindow => picked.iter().try_for_each(|rel| {
+            match dry_run::skip(format_args!("open {} in a new tmux window", rel.display())) {
+                true => Ok(()),
+                false => pick::open_in_tmux_window(notes_dir, rel),
+            }
+        }),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+/// Stream events from other kno commands to stdout until interrupted.
+fn run_events(notes_dir: &std::path::Path) {
+    let mut out = std::io::stdout().lock();
+    if let Err(e) = events::listen(notes_dir, &mut out) {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+/// Notes to open for `kno open --title`: the only match, or those picked
+/// from a numbered list when several titles match.
+fn pick_by_title(notes_dir: &std::path::Path, query: &str) -> Vec<PathBuf> {
+    use std::io::{IsTerminal, Write};
+
+    let matches = vault::find_by_title(notes_dir, query);
+    if let [(rel, _)] = matches.as_slice() {
+        return vec![notes_dir.join(rel)];
+    }
+    if matches.is_empty() {
+        eprintln!("No note titled like \"{query}\"");
+        process::exit(1);
+    }
+
+    for (i, (rel, title)) in matches.iter().enumerate() {
+        eprintln!("{:>3}. {title}  ({})", i + 1, rel.display());
+    }
+    if !std::io::stdin().is_terminal() {
+        eprintln!("{} notes match \"{query}\"; narrow it down", matches.len());
+        process::exit(1);
+    }
+    eprint!("Open (e.g. 1 3-4): ");
+    std::io::stderr().flush().ok();
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .expect("failed to read selection");
+    match tasks::parse_selection(&line, matches.len()) {
+        Ok(picked) if picked.is_empty() => process::exit(0),
+        Ok(picked) => picked
+            .into_iter()
+            .map(|i| notes_dir.join(&matches[i].0))
+            .collect(),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn run_done(notes_dir: &std::path::Path, selection: &[String], date: bool, move_to_done: bool) {
+    let open = tasks::open_tasks(notes_dir);
+    if open.is_empty() {
+        println!("No open tasks");
+        return;
+    }
+
+    let input = match selection.is_empty() {
+        false => selection.join(" "),
+        true => {
+            for (i, task) in open.iter().enumerate() {
+                println!("{:>3}. {}  ({})", i + 1, task.text, task.path.display());
+            }
+            print!("Mark done (e.g. 1 3-4): ");
+            use std::io::Write;
+            std::io::stdout().flush().ok();
+            let mut line = String::new();
+            std::io::stdin()
+                .read_line(&mut line)
+                .expect("failed to read selection");
+            line
+        }
+    };
+
+    let picked = match tasks::parse_selection(&input, open.len()) {
+        Ok(picked) => picked,
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    };
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let chosen: Vec<_> = picked.iter().map(|&i| &open[i]).collect();
+    tasks::complete(
+        notes_dir,
+        &chosen,
+        date.then_some(today.as_str()),
+        move_to_done,
+    );
+    for task in chosen {
+        log::info!("Done: {}", task.text);
+    }
+}
+
+/// The file `kno cat` prints for `path`: `@today` is today's daily note,
+/// anything else a note or attachment in the vault.
+fn cat_target(notes_dir: &std::path::Path, path: &str) -> Option<PathBuf> {
+    match path {
+        "@today" => {
+            let (relative_path, _) = resolve_note(None, &Config::load(notes_dir));
+            Some(notes_dir.join(relative_path)).filter(|p| p.is_file())
+        }
+        _ => resolve_existing(notes_dir, path),
+    }
+}
+
+fn existing_note(notes_dir: &std::path::Path, path: &str) -> PathBuf {
+    match resolve_existing(notes_dir, path) {
+        Some(file_path) => file_path,
+        None => {
+            eprintln!("{path} not found in {}", notes_dir.display());
+            process::exit(1);
+        }
+    }
+}
+
+/// Exit unless the note may leave the vault (see `export::check_sendable`)
+/// or `--force` says to send it anyway.
+fn check_sendable(relative_path: &std::path::Path, content: &str, force: bool) {
+    if let Err(e) = export::check_sendable(relative_path, content)
+        && !force
+    {
+        eprintln!("Error: {e}; pass --force to send it anyway");
+        process::exit(1);
+    }
+}
+
+fn run_share(notes_dir: &std::path::Path, path: &str, update: bool, force: bool) {
+    let file_path = existing_note(notes_dir, path);
+    let relative_path = file_path.strip_prefix(notes_dir).unwrap_or(&file_path);
+    let content = fs::read_to_string(&file_path).expect("failed to read note");
+    check_sendable(relative_path, &content, force);
+
+    let Some(token) = Config::load(notes_dir)
+        .share
+        .github_token
+        .or_else(|| secrets::lookup("github_token"))
+        .or_else(|| env::var("GITHUB_TOKEN").ok())
+    else {
+        eprintln!(
+            "No GitHub token: run `kno secret set github_token`, or set share.github_token in config.toml or GITHUB_TOKEN"
+        );
+        process::exit(1);
+    };
+
+    let existing = share::find(notes_dir, relative_path);
+    if update && existing.is_none() {
+        eprintln!("{path} has not been shared yet; run `kno share {path}` first");
+        process::exit(1);
+    }
+
+    let existing = existing.filter(|_| update);
+    match share::share(notes_dir, relative_path, &token, existing.as_ref()) {
+        Ok(Some(gist)) => println!("{}", gist.url),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Failed to share {path}: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn run_backup(notes_dir: &std::path::Path, install_timer: bool) {
+    let config = Config::load(notes_dir).backup;
+
+    if install_timer {
+        let home = env::var("HOME").expect("HOME not set");
+        if let Err(e) = backup::install_timer(std::path::Path::new(&home), config.schedule) {
+            eprintln!("Failed to install backup timer: {e}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    let dir = config.dir.unwrap_or_else(|| backup::default_dir(notes_dir));
+    match backup::create(notes_dir, &dir) {
+        Ok(archive) if !dry_run::is_enabled() => log::info!("Backed up to {}", archive.display()),
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("Backup failed: {e}");
+            process::exit(1);
+        }
+    }
+    for archive in backup::prune(&dir, config.keep.max(1)) {
+        log::debug!("pruned {}", archive.display());
+    }
+}
+
+fn run_self_update(check: bool) {
+    let current = env!("CARGO_PKG_VERSION");
+    let release = match update::latest() {
+        Ok(release) => release,
+        Err(e) => {
+            eprintln!("Failed to check for updates: {e}");
+            process::exit(1);
+        }
+    };
+
+    if !update::is_newer(current, &release.version) {
+        log::info!("kno {current} is up to date");
+        return;
+    }
+    if check {
+        log::info!(
+            "kno {} is available (installed: {current})",
+            release.version
+        );
+        return;
+    }
+
+    match update::install(&release) {
+        Ok(_) if dry_run::is_enabled() => {}
+        Ok(exe) => log::info!("Updated {} to {}", exe.display(), release.version),
+        Err(e) => {
+            eprintln!("Update failed: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn run_restore(
+    notes_dir: &std::path::Path,
+    archive: &std::path::Path,
+    into: Option<&std::path::Path>,
+    force: bool,
+) {
+    let config = Config::load(notes_dir).backup;
+    let backup_dir = config.dir.unwrap_or_else(|| backup::default_dir(notes_dir));
+    let archive = match archive.exists() {
+        true => archive.to_path_buf(),
+        false => backup_dir.join(archive),
+    };
+    let into = into.unwrap_or(notes_dir);
+
+    let summary = match backup::restore(&archive, into, force) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("Restore failed: {e}");
+            process::exit(1);
+        }
+    };
+
+    for (mark, paths) in [
+        ('+', &summary.added),
+        ('-', &summary.removed),
+        ('~', &summary.changed),
+    ] {
+        for path in paths {
+            log::info!("{mark} {}", path.display());
+        }
+    }
+    let counts = format!(
+        "{} added, {} removed, {} changed",
+        summary.added.len(),
+        summary.removed.len(),
+        summary.changed.len()
+    );
+    match dry_run::is_enabled() {
+        true => log::info!("{counts}"),
+        false => log::info!(
+            "Restored {} into {}: {counts}",
+            archive.display(),
+            into.display()
+        ),
+    }
+    if let Some(previous) = summary.previous {
+        log::info!("Previous notes moved to {}", previous.display());
+    }
+}
+
+fn run_dedupe(notes_dir: &std::path::Path, threshold: f64, list: bool) {
+    use std::io::{IsTerminal, Write};
+
+    let duplicates = dedupe::find(notes_dir, threshold);
+    if duplicates.is_empty() {
+        log::info!("No duplicate notes");
+        return;
+    }
+
+    let interactive = !list && std::io::stdin().is_terminal();
+    let mut gone: Vec<PathBuf> = Vec::new();
+    for dup in duplicates {
+        if gone.contains(&dup.first) || gone.contains(&dup.second) {
+            continue;
+        }
+        let (first, second) = (dup.first.display(), dup.second.display());
+        println!("{:>3.0}%  {first}  {second}", dup.similarity * 100.0);
+        if !interactive {
+            continue;
+        }
+
+        print!("  [k]eep both, [t]rash {second}, [m]erge it into {first}? [k] ");
+        std::io::stdout().flush().ok();
+        let mut line = String::new();
+        std::io::stdin()
+            .read_line(&mut line)
+            .expect("failed to read answer");
+        match line.trim() {
+            "t" => {
+                let target = dedupe::trash(notes_dir, &dup.second);
+                log::info!("Moved {second} to {}", target.display());
+            }
+            "m" => {
+                dedupe::merge(notes_dir, &dup.first, &dup.second);
+                log::info!("Merged {second} into {first}");
+            }
+            _ => continue,
+        }
+        gone.push(dup.second);
+    }
+}
+
+fn run_mail(
+    notes_dir: &std::path::Path,
+    path: &str,
+    to: &[String],
+    subject: Option<&str>,
+    force: bool,
+) {
+    let file_path = existing_note(notes_dir, path);
+    let relative_path = file_path.strip_prefix(notes_dir).unwrap_or(&file_path);
+    let content = fs::read_to_string(&file_path).expect("failed to read note");
+    check_sendable(relative_path, &content, force);
+
+    let title = export::note_title(&content, relative_path);
+    let mut config = Config::load(notes_dir).mail;
+    if config.user.is_some() && config.password.is_none() {
+        config.password = secrets::lookup("smtp_password");
+    }
+    let message = mail::Message {
+        from: config.from.as_deref(),
+        to,
+        subject: subject.unwrap_or(&title),
+        text: frontmatter::body(&content),
+        html: &export::render_html(&content, &title),
+    };
+
+    match mail::send(&config, &message) {
+        Ok(()) if dry_run::is_enabled() => {}
+        Ok(()) => log::info!("Sent {path} to {}", to.join(", ")),
+        Err(e) => {
+            eprintln!("Failed to mail {path}: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// A secret typed at a prompt, without echo, or read from piped stdin.
+fn read_secret(name: &str) -> Result<String, String> {
+    use std::io::{IsTerminal, Read};
+
+    let mut value = String::new();
+    let stdin = std::io::stdin();
+    match stdin.is_terminal() {
+        true => {
+            eprint!("Value for {name}: ");
+            let stty = |arg| process::Command::new("stty").arg(arg).status();
+            let _ = stty("-echo");
+            let read = stdin.read_line(&mut value);
+            let _ = stty("echo");
+            eprintln!();
+            read.map_err(|e| e.to_string())?;
+        }
+        false => {
+            stdin
+                .lock()
+                .read_to_string(&mut value)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    let value = value.trim_end_matches(['\n', '\r']);
+    match value.is_empty() {
+        true => Err("no value given".to_string()),
+        false => Ok(value.to_string()),
+    }
+}
+
+fn run_secret(action: &SecretAction) {
+    let result = match action {
+        SecretAction::Set { name } => read_secret(name).and_then(|value| {
+            secrets::set(name, &value)?;
+            if !dry_run::is_enabled() {
+                log::info!("Stored {name} in the keychain");
+            }
+            Ok(())
+        }),
+        SecretAction::Get { name } => secrets::get(name).and_then(|value| match value {
+            Some(value) => {
+                println!("{value}");
+                Ok(())
+            }
+            None => Err(format!("no secret named {name}")),
+        }),
+        SecretAction::Rm { name } => secrets::remove(name).map(|()| {
+            if !dry_run::is_enabled() {
+                log::info!("Removed {name} from the keychain");
+            }
+        }),
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        process::exit(1);
+    }
+}
+
+fn run_habit(notes_dir: &std::path::Path, action: &HabitAction) {
+    let today = Local::now().date_naive();
+    match action {
+        HabitAction::Add { name } => match habits::add(notes_dir, name) {
+            Ok(()) if !dry_run::is_enabled() => log::info!("Tracking {}", name.trim()),
+            Ok(()) => {}
+            Err(e) => {
+                eprintln!("Error: {e}");
+                process::exit(1);
+            }
+        },
+        HabitAction::Done { name, date } => {
+            if !habits::read(notes_dir).contains(name) {
+                eprintln!("Not tracking {name}. Add it with `kno habit add {name}`.");
+                process::exit(1);
+            }
+            let date = date.unwrap_or(today);
+            let done = habits::history(notes_dir, date, date);
+            if done.get(name).is_some_and(|days| days.contains(&date)) {
+                log::info!("{name} is already done on {date}");
+                return;
+            }
+            let config = Config::load(notes_dir);
+            append_note(
+                notes_dir,
+                None,
+                date,
+                &habits::mark(name),
+                !config.append.no_header,
+                Placement::Section(&config.daily.habit_section),
+            );
+        }
+        HabitAction::Status { days } => {
+            let tracked = habits::read(notes_dir);
+            if tracked.is_empty() {
+                log::info!("No habits yet. Add one with `kno habit add <name>`.");
+                return;
+            }
+            let Some(start) = today.checked_sub_days(chrono::Days::new(days - 1)) else {
+                eprintln!("Error: --days {days} reaches back before the earliest supported date");
+                process::exit(1);
+            };
+            let history = habits::history(notes_dir, start, today);
+            let width = tracked.iter().map(|h| h.len()).max().unwrap_or(0);
+            let none = std::collections::BTreeSet::new();
+            for habit in &tracked {
+                let done = history.get(habit).unwrap_or(&none);
+                println!(
+                    "{habit:<width$}  {}  streak {:>3}  {:>3}%",
+                    habits::chart(done, start, today),
+                    habits::streak(done, today),
+                    done.len() as u64 * 100 / days
+                );
+            }
+        }
+    }
+}
+
+/// Print today's words against the goal, then a line per day of this week.
+fn run_goal(notes_dir: &std::path::Path) {
+    if !notes_dir.join(".git").exists() {
+        eprintln!("kno goal counts words with git. Run `kno git init` to initialize.");
+        process::exit(1);
+    }
+    let config = Config::load(notes_dir);
+    let goal = config.goal.words;
+    let today = Local::now().date_naive();
+    let week = week::Week::of(today, config.week.start, config.week.numbering);
+
+    let mut days = Vec::new();
+    for date in week.start.iter_days().take_while(|d| *d <= today) {
+        match goal::words_on(notes_dir, date) {
+            Ok(words) => days.push((date, words)),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                process::exit(1);
+            }
+        }
+    }
+
+    let written = days.last().map_or(0, |&(_, words)| words);
+    let percent = written * 100 / goal.max(1);
+    println!("Today: {written} / {goal} words ({percent}%)");
+    println!("\nWeek {}:", week.label());
+    for &(date, words) in &days {
+        let met = match words >= goal {
+            true => "  met",
+            false => "",
+        };
+        println!("  {}  {words:>6}{met}", date.format("%a %Y-%m-%d"));
+    }
+    let total: usize = days.iter().map(|&(_, words)| words).sum();
+    let met = days.iter().filter(|&&(_, words)| words >= goal).count();
+    println!(
+        "  total {total} words, goal met {met} of {} days",
+        days.len()
+    );
+}
+
+/// Print the `kno stats` summary for `path`, or with `largest`/`stalest`
+/// only those lists of notes.
+fn run_stats(
+    notes_dir: &std::path::Path,
+    path: Option<&str>,
+    largest: Option<usize>,
+    stalest: Option<usize>,
+) {
+    if let Err(e) = vault_dir(notes_dir, path) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+    let dir = path.map(|p| std::path::Path::new(p.trim_end_matches('/')));
+    let report = stats::collect(notes_dir, dir);
+    if largest.is_none() && stalest.is_none() {
+        print!("{}", report.render());
+        return;
+    }
+
+    if let Some(n) = largest {
+        println!("Largest notes (words):");
+        for note in report.largest(n) {
+            println!("{:>7}  {}", note.words, note.path.display());
+        }
+    }
+    if let Some(n) = stalest {
+        if largest.is_some() {
+            println!();
+        }
+        println!("Stalest notes (last modified):");
+        for note in report.stalest(n) {
+            println!("{}  {}", note.modified.date_naive(), note.path.display());
+        }
+    }
+}
+
+/// The `kno graph --metrics` report: the `limit` most linked-to notes, notes
+/// that link nowhere, and the groups of notes cut off from the largest one.
+fn format_graph_metrics(graph: &graph::Graph, limit: usize) -> String {
+    let mut out = String::from("Hubs (most linked to):\n");
+    for (note, count) in graph.hubs(limit) {
+        out.push_str(&format!("{count:>7}  {}\n", note.display()));
+    }
+
+    out.push_str("\nDead ends (linked to, but link nowhere):\n");
+    for note in graph.dead_ends() {
+        out.push_str(&format!("  {}\n", note.display()));
+    }
+
+    let clusters = graph.clusters();
+    let (isolated, connected): (Vec<_>, Vec<_>) = clusters.iter().partition(|c| c.len() == 1);
+    let largest = connected.first().map_or(0, |c| c.len());
+    out.push_str(&format!(
+        "\nClusters: {} (largest: {largest} notes)\n",
+        connected.len()
+    ));
+    for cluster in connected.iter().skip(1) {
+        let names: Vec<_> = cluster.iter().map(|n| n.display().to_string()).collect();
+        out.push_str(&format!(
+            "  {} notes: {}\n",
+            cluster.len(),
+            names.join(", ")
+        ));
+    }
+
+    out.push_str(&format!("\nUnconnected notes: {}\n", isolated.len()));
+    for cluster in isolated {
+        out.push_str(&format!("  {}\n", cluster[0].display()));
+    }
+    out
+}
+
+fn format_tags(notes_dir: &std::path::Path, counts: bool, sort: TagSort) -> String {
+    let mut stats = tags::stats(notes_dir);
+    match sort {
+        TagSort::Name => {}
+        TagSort::Count => stats.sort_by_key(|s| std::cmp::Reverse(s.count)),
+        TagSort::Recent => stats.sort_by_key(|s| std::cmp::Reverse(s.last_used)),
+    }
+
+    let width = stats.iter().map(|s| s.tag.len()).max().unwrap_or(0);
+    stats
+        .iter()
+        .map(|s| match counts {
+            true => {
+                let last_used = s.last_used.map(|d| d.to_string()).unwrap_or_default();
+                format!("{:<width$}  {:>4}  {last_used}\n", s.tag, s.count)
+            }
+            false => format!("{}\n", s.tag),
+        })
+        .collect()
+}
+
+fn run_tag(notes_dir: &std::path::Path, action: &TagAction) {
+    match action {
+        TagAction::Add { path, tags } => {
+            let added = tags::add(&existing_note(notes_dir, path), tags);
+            log::info!("Added [{}] to {path}", added.join(", "));
+        }
+        TagAction::Rm { path, tags } => {
+            let removed = tags::remove(&existing_note(notes_dir, path), tags);
+            log::info!("Removed [{}] from {path}", removed.join(", "));
+        }
+        TagAction::Show { tag } => {
+            for note in filtered_notes(notes_dir, None, Some(tag), None, SortKey::Name) {
+                println!("{}", note.display());
+            }
+        }
+        TagAction::Rename { old, new } => {
+            let changed = tags::rename(notes_dir, old, new);
+            for note in &changed {
+                log::info!("{}", note.display());
+            }
+            log::info!("Renamed {old} to {new} in {} note(s)", changed.len());
+        }
+    }
+}
+
+fn editor_cmd() -> process::Command {
+    let editor = env::var("EDITOR").unwrap_or_else(|_| "nvim".to_string());
+    process::Command::new(editor)
+}
+
+/// Open an empty scratch buffer in `$EDITOR` and return what was written,
+/// for captures too long to pass on the command line.
+fn edit_buffer() -> Result<String, String> {
+    // Private to this user, unlike a predictable name in the shared temp dir
+    let buffer = tempfile::Builder::new()
+        .prefix("kno-append-")
+        .suffix(".md")
+        .tempfile()
+        .map_err(|e| format!("failed to create a buffer to edit: {e}"))?;
+    let mut cmd = editor_cmd();
+    cmd.arg(buffer.path());
+    log::debug!("running {cmd:?}");
+    let status = cmd.status();
+    let text = fs::read_to_string(buffer.path()).unwrap_or_default();
+    let _ = buffer.close();
+
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(_) => return Err("the editor exited with an error; nothing appended".to_string()),
+        Err(e) => return Err(format!("failed to launch editor: {e}")),
+    }
+    match text.trim().is_empty() {
+        true => Err("empty buffer; nothing appended".to_string()),
+        false => Ok(text.trim_end().to_string()),
+    }
+}
+
+fn launch_editor(
+    notes_dir: &std::path::Path,
+    file_paths: &[PathBuf],
+    cursor: Option<usize>,
+    editor_args: &[String],
+) -> ! {
+    if dry_run::is_enabled() {
+        for file_path in file_paths {
+            println!("Would open {}", file_path.display());
+        }
+        process::exit(0);
+    }
+
+    for file_path in file_paths {
+        vault::record_opened(notes_dir, file_path);
+    }
+    let server = nvim::server();
+    let mut cmd = match &server {
+        Some(server) => nvim::remote_cmd(server),
+        None => editor_cmd(),
+    };
+    // Without a cursor from the template, resume where a single note was left
+    let cursor = cursor.or_else(|| match file_paths {
+        [file_path] => cursors::get(notes_dir, file_path.strip_prefix(notes_dir).ok()?),
+        _ => None,
+    });
+    // A private file for the editor to report cursors in, rather than a
+    // predictable name in the shared temp dir
+    let report = match server {
+        Some(_) => None,
+        None => tempfile::Builder::new()
+            .prefix("kno-cursors-")
+            .tempfile()
+            .ok(),
+    };
+    let hook = report.as_ref().and_then(|report| {
+        cursors::vim_report_args(std::path::Path::new(cmd.get_program()), report.path())
+    });
+    if let Some(hook) = &hook {
+        cmd.args(hook);
+    }
+    // `+N` is understood by vi, vim, nvim, emacs, nano and most terminal editors
+    if let Some(line) = cursor {
+        cmd.arg(format!("+{line}"));
+    }
+    cmd.args(editor_args)
+        .args(file_paths)
+        .current_dir(notes_dir);
+    log::debug!("running {cmd:?}");
+    let before: Vec<_> = file_paths.iter().map(|p| vault::modified(p)).collect();
+    // A remote Neovim returns at once, so there's no editing session to lock
+    let host = locks::host();
+    let locked = match server {
+        Some(_) => Vec::new(),
+        None => lock_notes(notes_dir, file_paths, &host),
+    };
+    let status = cmd.status().expect("failed to launch editor");
+    for relative_path in &locked {
+        locks::release(notes_dir, relative_path, &host);
+    }
+    if !locked.is_empty() {
+        publish_locks(notes_dir, "Unlock", &locked, &host);
+    }
+    // Removed here since `process::exit` below skips destructors
+    if let Some(report) = report {
+        let content = fs::read_to_string(report.path()).unwrap_or_default();
+        let _ = report.close();
+        if hook.is_some() {
+            cursors::record(notes_dir, &cursors::parse_report(notes_dir, &content));
+        }
+    }
+    for (file_path, before) in file_paths.iter().zip(before) {
+        let after = vault::modified(file_path);
+        if after.is_some() && after != before {
+            events::emit(notes_dir, events::Event::Edited, file_path);
+        }
+    }
+    process::exit(status.code().unwrap_or(1));
+}
+
+/// Commit and push lock changes for `notes` (see `locks::publish`). Best
+/// effort: when offline, the commit goes out with the next `kno sync`.
+fn publish_locks(notes_dir: &std::path::Path, action: &str, notes: &[PathBuf], host: &str) {
+    let names: Vec<_> = notes.iter().map(|n| n.display().to_string()).collect();
+    let message = format!("{action} {} on {host}", names.join(", "));
+    if let Err(e) = locks::publish(notes_dir, &message) {
+        log::debug!("could not share locks: {e}");
+    }
+}
+
+/// With `locks.enabled`, pull the other machines' locks and warn about notes
+/// one of them is editing, then lock `file_paths` for `host` and push the
+/// locks. Returns the notes locked.
+fn lock_notes(notes_dir: &std::path::Path, file_paths: &[PathBuf], host: &str) -> Vec<PathBuf> {
+    let config = Config::load(notes_dir).locks;
+    if !config.enabled {
+        return Vec::new();
+    }
+    if let Err(e) = locks::pull(notes_dir) {
+        log::debug!("could not pull locks: {e}");
+    }
+    let now = std::time::SystemTime::now();
+    let expire = std::time::Duration::from_secs(config.expire_minutes * 60);
+    let mut locked = Vec::new();
+    for file_path in file_paths {
+        let Ok(relative_path) = file_path.strip_prefix(notes_dir) else {
+            continue;
+        };
+        if let Some(lock) = locks::held_elsewhere(notes_dir, relative_path, host, expire, now) {
+            let minutes = now.duration_since(lock.since).unwrap_or_default().as_secs() / 60;
+            eprintln!(
+                "Warning: {} is being edited on {} (opened {minutes} min ago); sync before editing to avoid a conflict",
+                relative_path.display(),
+                lock.host
+            );
+        }
+        locks::acquire(notes_dir, relative_path, host, now);
+        locked.push(relative_path.to_path_buf());
+    }
+    if !locked.is_empty() {
+        publish_locks(notes_dir, "Lock", &locked, host);
+    }
+    locked
+}
+
+/// Named notes in `paths` that don't exist yet. Daily notes (no path, or a
+/// directory with a trailing `/`) are always created, so they never count.
+fn missing_notes(notes_dir: &std::path::Path, paths: &[String]) -> Vec<PathBuf> {
+    paths
+        .iter()
+        .filter(|p| !p.ends_with('/'))
+        .map(|p| resolve_note(Some(p), &Config::load(notes_dir)).0)
+        .filter(|rel| !notes_dir.join(rel).exists())
+        .collect()
+}
+
+/// Note names from the command line, with frontmatter aliases replaced by
+/// the notes they stand for (`k8s` by `tech/kubernetes`).
+fn resolve_aliases(notes_dir: &std::path::Path, paths: &[String]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| match vault::resolve_alias(notes_dir, path) {
+            Some(rel) => {
+                log::debug!("{path} is an alias for {}", rel.display());
+                rel.with_extension("").to_string_lossy().into_owned()
+            }
+            None => path.clone(),
+        })
+        .collect()
+}
+
+/// Exit with an error if `--existing` (or `notes.existing`) is in effect and
+/// any named note is missing.
+fn require_existing(notes_dir: &std::path::Path, paths: &[String], existing: bool) {
+    if !(existing || Config::load(notes_dir).notes.existing) {
+        return;
+    }
+    let missing = missing_notes(notes_dir, paths);
+    for rel in &missing {
+        let name = rel.with_extension("");
+        eprintln!(
+            "{} does not exist (use `kno new {}` to create it)",
+            rel.display(),
+            name.display()
+        );
+    }
+    if !missing.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// With `notes.confirm_create`, ask before creating each missing note and
+/// offer similarly named notes instead. Returns the paths to open. Only
+/// prompts when stdin is a terminal.
+fn confirm_create(notes_dir: &std::path::Path, paths: &[String]) -> Vec<String> {
+    use std::io::{IsTerminal, Write};
+
+    if !Config::load(notes_dir).notes.confirm_create || !std::io::stdin().is_terminal() {
+        return paths.to_vec();
+    }
+
+    let mut confirmed = Vec::new();
+    for path in paths {
+        let relative_path = resolve_note(Some(path), &Config::load(notes_dir)).0;
+        if path.ends_with('/') || notes_dir.join(&relative_path).exists() {
+            confirmed.push(path.clone());
+            continue;
+        }
+
+        let similar = vault::similar(notes_dir, &relative_path, 5);
+        for (i, rel) in similar.iter().enumerate() {
+            eprintln!("{:>3}. {}", i + 1, rel.display());
+        }
+        let choices = match similar.len() {
+            0 => "y/N".to_string(),
+            1 => "y/N/1".to_string(),
+            n => format!("y/N/1-{n}"),
+        };
+        eprint!("Create {}? [{choices}] ", relative_path.display());
+        std::io::stderr().flush().ok();
+        let mut line = String::new();
+        std::io::stdin()
+            .read_line(&mut line)
+            .expect("failed to read answer");
+
+        let answer = line.trim();
+        match answer.parse::<usize>() {
+            Ok(n) if (1..=similar.len()).contains(&n) => {
+                let rel = similar[n - 1].with_extension("");
+                confirmed.push(rel.to_string_lossy().into_owned());
+            }
+            _ if answer.eq_ignore_ascii_case("y") => confirmed.push(path.clone()),
+            _ => {}
+        }
+    }
+
+    if confirmed.is_empty() && !paths.is_empty() {
+        process::exit(1);
+    }
+    confirmed
+}
+
+/// Open or create each note in `paths` (the daily note when empty).
+/// The cursor is only kept for a single note, since `+N` applies to the first file.
+fn open_notes(
+    notes_dir: &std::path::Path,
+    paths: &[String],
+    date: NaiveDate,
+) -> (Vec<PathBuf>, Option<usize>) {
+    let opened: Vec<_> = match paths.is_empty() {
+        true => vec![open_note_on(notes_dir, None, date)],
+        false => paths
+            .iter()
+            .map(|p| open_note_on(notes_dir, Some(p), date))
+            .collect(),
+    };
+    let cursor = match opened.as_slice() {
+        [(_, cursor)] => *cursor,
+        _ => None,
+    };
+    (opened.into_iter().map(|(path, _)| path).collect(), cursor)
+}
+
+/// Resolve `path` to an existing file in the vault: either the exact file
+/// (attachments) or the note `path.md`.
+fn resolve_existing(notes_dir: &std::path::Path, path: &str) -> Option<PathBuf> {
+    [notes_dir.join(path), notes_dir.join(format!("{path}.md"))]
+        .into_iter()
+        .find(|p| p.is_file())
+}
+
+fn opener_cmd(app: Option<&str>, file_path: &std::path::Path) -> process::Command {
+    let mut cmd = match (app, env::consts::OS) {
+        (Some(app), "macos") => {
+            let mut cmd = process::Command::new("open");
+            cmd.arg("-a").arg(app);
+            cmd
+        }
+        (Some(app), _) => process::Command::new(app),
+        (None, "macos") => process::Command::new("open"),
+        (None, "windows") => {
+            let mut cmd = process::Command::new("cmd");
+            cmd.args(["/C", "start", ""]);
+            cmd
+        }
+        (None, _) => process::Command::new("xdg-open"),
+    };
+    cmd.arg(file_path);
+    cmd
+}
+
+/// Attach dynamic completers: note and directory paths relative to the notes
+/// dir (plus note aliases), and tag names from the vault.
+fn with_completers(cmd: clap::Command, notes_dir: &std::path::Path) -> clap::Command {
+    let note = {
+        let notes_dir = notes_dir.to_path_buf();
+        let paths = PathCompleter::any().current_dir(&notes_dir);
+        ArgValueCompleter::new(move |current: &std::ffi::OsStr| {
+            let mut candidates = paths.complete(current);
+            let current = current.to_string_lossy();
+            candidates.extend(
+                vault::aliases(&notes_dir)
+                    .into_iter()
+                    .filter(|(alias, _)| alias.starts_with(&*current))
+                    .map(|(alias, rel)| {
+                        let help = format!("alias for {}", rel.with_extension("").display());
+                        CompletionCandidate::new(alias).help(Some(help.into()))
+                    }),
+            );
+            candidates
+        })
+    };
+    let dir = ArgValueCompleter::new(PathCompleter::dir().current_dir(notes_dir));
+    let path = ArgValueCompleter::new(PathCompleter::any().current_dir(notes_dir));
+    let tag = {
+        let notes_dir = notes_dir.to_path_buf();
+        ArgValueCompleter::new(move |current: &std::ffi::OsStr| {
+            let current = current.to_string_lossy();
+            tags::names(&notes_dir)
+                .into_iter()
+                .filter(|t| t.starts_with(&*current))
+                .map(CompletionCandidate::new)
+                .collect::<Vec<_>>()
+        })
+    };
+    let secret = ArgValueCompleter::new(|current: &std::ffi::OsStr| {
+        let current = current.to_string_lossy();
+        secrets::KNOWN
+            .iter()
+            .filter(|(name, _)| name.starts_with(&*current))
+            .map(|(name, key)| {
+                CompletionCandidate::new(name).help(Some(format!("replaces {key}").into()))
+            })
+            .collect::<Vec<_>>()
+    });
+    let saved_search = {
+        let notes_dir = notes_dir.to_path_buf();
+        ArgValueCompleter::new(move |current: &std::ffi::OsStr| {
+            let current = current.to_string_lossy();
+            Config::load(&notes_dir)
+                .searches
+                .into_iter()
+                .filter(|(name, _)| name.starts_with(&*current))
+                .map(|(name, args)| {
+                    CompletionCandidate::new(name).help(Some(args.join(" ").into()))
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+    let with = |c: ArgValueCompleter| move |a: clap::Arg| a.add(c.clone());
+
+    // mut_arg moves the arg to the end, so later positionals are re-added
+    // after it to keep their order (editor_args must stay last)
+    cmd.mut_arg("paths", with(note.clone()))
+        .mut_arg("editor_args", |a| a)
+        .mut_subcommand("list", |c| {
+            c.mut_arg("path", with(dir.clone()))
+                .mut_arg("tag", with(tag.clone()))
+        })
+        .mut_subcommand("cd", |c| c.mut_arg("path", with(dir.clone())))
+        .mut_subcommand("stats", |c| c.mut_arg("path", with(dir.clone())))
+        .mut_subcommand("search", |c| {
+            c.mut_arg("path", with(dir.clone()))
+                .mut_arg("saved", with(saved_search.clone()))
+        })
+        .mut_subcommand("new", |c| c.mut_arg("path", with(dir.clone())))
+        .mut_subcommand("open", |c| {
+            c.mut_arg("paths", with(note.clone()))
+                .mut_arg("editor_args", |a| a)
+        })
+        .mut_subcommand("open-with", |c| c.mut_arg("path", with(note.clone())))
+        .mut_subcommand("export", |c| {
+            c.mut_arg("path", with(dir.clone()))
+                .mut_arg("tag", with(tag.clone()))
+        })
+        .mut_subcommand("share", |c| c.mut_arg("path", with(note.clone())))
+        .mut_subcommand("cat", |c| c.mut_arg("paths", with(note.clone())))
+        .mut_subcommand("archive", |c| c.mut_arg("paths", with(note.clone())))
+        .mut_subcommand("mv", |c| {
+            c.mut_arg("from", with(note.clone()))
+                .mut_arg("to", with(path.clone()))
+        })
+        .mut_subcommand("mail", |c| c.mut_arg("path", with(note.clone())))
+        .mut_subcommand("secret", |c| {
+            c.mut_subcommand("set", |c| c.mut_arg("name", with(secret.clone())))
+                .mut_subcommand("get", |c| c.mut_arg("name", with(secret.clone())))
+                .mut_subcommand("rm", |c| c.mut_arg("name", with(secret.clone())))
+        })
+        .mut_subcommand("tag", |c| {
+            c.mut_subcommand("add", |c| {
+                c.mut_arg("path", with(note.clone())).mut_arg("tags", |a| a)
+            })
+            .mut_subcommand("rm", |c| {
+                c.mut_arg("path", with(note.clone()))
+                    .mut_arg("tags", with(tag.clone()))
+            })
+            .mut_subcommand("show", |c| c.mut_arg("tag", with(tag.clone())))
+            .mut_subcommand("rename", |c| {
+                c.mut_arg("old", with(tag.clone())).mut_arg("new", |a| a)
+            })
+        })
+}
+
+/// What the top-level arguments given with a subcommand wrongly include, if
+/// anything. Global flags like --dry-run may precede a subcommand, so clap's
+/// args_conflicts_with_subcommands can't be used to reject these, and a note
+/// path before one (`kno foo --dry-run list`) would otherwise be dropped.
+fn subcommand_conflict(cli: &Cli) -> Option<String> {
+    cli.command.as_ref()?;
+    if let Some(path) = cli.paths.first() {
+        return Some(format!(
+            "note path '{path}' can't be combined with a subcommand"
+        ));
+    }
+    (cli.print
+        || cli.append.is_some()
+        || cli.prepend.is_some()
+        || cli.todo.is_some()
+        || cli.date.is_some())
+    .then(|| {
+        "--print, --append, --prepend, --todo and --date can't be combined with a subcommand"
+            .to_string()
+    })
+}
+
+fn main() {
+    let home = env::var("HOME").expect("HOME not set");
+    let notes_dir = PathBuf::from(&home).join(NOTES_DIR_NAME);
+
+    let cmd = with_completers(Cli::command(), &notes_dir);
+    clap_complete::CompleteEnv::with_factory(|| cmd.clone()).complete();
+
+    let mut cli = Cli::from_arg_matches_mut(&mut cmd.get_matches_from(env::args_os())).unwrap();
+    cli.normalize();
+    dry_run::set(cli.dry_run);
+    vault::set_follow_symlinks(cli.follow_symlinks);
+    vault::set_show_hidden(cli.hidden);
+    nvim::set_server(cli.nvim_server.clone());
+    log::set_level(match (cli.verbose, cli.quiet) {
+        (true, _) => log::Level::Verbose,
+        (_, true) => log::Level::Quiet,
+        _ => log::Level::Normal,
+    });
+
+    if let Some(conflict) = subcommand_conflict(&cli) {
+        Cli::command()
+            .error(clap::error::ErrorKind::ArgumentConflict, conflict)
+            .exit();
+    }
+
+    match cli.command {
+        Some(Command::MergeDriver {
+            ref base,
+            ref ours,
+            ref theirs,
+        }) => {
+            run_merge_driver(base, ours, theirs);
+            return;
+        }
+        Some(Command::MergeDaily { date }) => {
+            let layout = Config::load(&notes_dir).daily.layout;
+            let relative_path = daily::note_path(date, layout);
+            let merged = daily::merge_conflict_copies(&notes_dir, &relative_path);
+            match merged.is_empty() {
+                true => log::info!("No conflict copies of {}", relative_path.display()),
+                false if !dry_run::is_enabled() => log::info!(
+                    "Merged {} conflict copies into {}",
+                    merged.len(),
+                    relative_path.display()
+                ),
+                false => {}
+            }
+            return;
+        }
+        Some(Command::Init { hooks }) => {
+            run_init(&notes_dir);
+            if hooks {
+                install_pre_commit_hook(&notes_dir);
+            }
+            return;
+        }
+        Some(Command::New {
+            ref path,
+            ref template,
+            edit,
+        }) => match new_note(&notes_dir, path, template.as_deref()) {
+            Ok((file_path, cursor)) if edit => launch_editor(&notes_dir, &[file_path], cursor, &[]),
+            Ok((file_path, _)) => {
+                println!("{}", file_path.display());
+                return;
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(1);
+            }
+        },
+        Some(Command::Cd { ref path, wrapper }) => {
+            if wrapper {
+                print!("{CD_WRAPPER}");
+                return;
+            }
+            match vault_dir(&notes_dir, path.as_deref()) {
+                Ok(dir) => println!("{}", dir.display()),
+                Err(e) => {
+                    eprintln!("{e}");
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Commit { ref message }) => {
+            run_commit(&notes_dir, message.as_deref());
+            return;
+        }
+        Some(Command::Status) => {
+            run_status(&notes_dir);
+            return;
+        }
+        Some(Command::Sync {
+            ref message,
+            backend: None,
+        }) => {
+            run_sync(&notes_dir, message.as_deref());
+            return;
+        }
+        Some(Command::Sync {
+            backend: Some(backend),
+            ..
+        }) => {
+            run_remote_sync(&notes_dir, backend);
+            return;
+        }
+        Some(Command::Git { ref args }) => {
+            run_git(&notes_dir, args);
+        }
+        Some(Command::Meeting {
+            ref name,
+            ref attendees,
+        }) => {
+            let (file_path, cursor) = create_meeting_note(&notes_dir, name, attendees);
+            launch_editor(&notes_dir, &[file_path], cursor, &[]);
+        }
+        Some(Command::List {
+            ref path,
+            level,
+            ref tag,
+            since,
+            sort,
+            flat,
+            ref query,
+            ref format,
+            all,
+        }) => {
+            let filtered = tag.is_some()
+                || since.is_some()
+                || query.is_some()
+                || format.is_some()
+                || sort != SortKey::Name
+                || flat;
+            use std::io::Write;
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            let written = match filtered {
+                false => {
+                    let depth = Some(level.unwrap_or(1)).filter(|&l| l > 0);
+                    list_notes(&notes_dir, path.as_deref(), depth, all, &mut out)
+                }
+                true => {
+                    let path = path.as_deref().map(|p| p.trim_end_matches('/'));
+                    let mut notes = filtered_notes(&notes_dir, path, tag.as_deref(), since, sort);
+                    if let Some(query) = query {
+                        notes = query_arg(query).filter(&notes_dir, notes);
+                    }
+                    match (format, flat) {
+                        (Some(template), _) => {
+                            write_formatted(&mut out, &notes_dir, &notes, template)
+                        }
+                        (None, true) => notes
+                            .iter()
+                            .try_for_each(|n| writeln!(out, "{}", n.display())),
+                        (None, false) => {
+                            // Filtered views show matching notes at any depth unless -L is given
+                            let depth = level.filter(|&l| l > 0);
+                            let tree = render_tree(path.unwrap_or("."), &notes, depth);
+                            write!(out, "{tree}")
+                        }
+                    }
+                }
+            };
+            // A closed pipe (e.g. `kno list --flat | head`) isn't an error
+            let _ = written.and_then(|()| out.flush());
+            return;
+        }
+        Some(Command::Prev { print }) => {
+            let today = Local::now().date_naive();
+            let Some((_, relative_path)) = daily::previous_before(&notes_dir, today) else {
+                eprintln!("No daily note before {today}");
+                process::exit(1);
+            };
+            let file_path = notes_dir.join(relative_path);
+            match print {
+                true => println!("{}", file_path.display()),
+                false => launch_editor(&notes_dir, &[file_path], None, &[]),
+            }
+            return;
+        }
+        Some(Command::Week { print }) => {
+            let config = Config::load(&notes_dir).week;
+            let today = Local::now().date_naive();
+            let week = week::Week::of(today, config.start, config.numbering);
+            let relative_path = week.note_path();
+            let file_path = notes_dir.join(&relative_path);
+            if print {
+                println!("{}", file_path.display());
+                return;
+            }
+            let mut cursor = None;
+            if !file_path.exists() {
+                let header = format!("# {} ({} to {})", week.label(), week.start, week.end());
+                cursor = create_note(
+                    &notes_dir,
+                    &relative_path,
+                    &header,
+                    "weekly",
+                    week.start,
+                    false,
+                );
+            }
+            launch_editor(&notes_dir, &[file_path], cursor, &[]);
+        }
+        Some(Command::Scratch {
+            action: Some(ScratchAction::Promote { ref name, ref from }),
+            ..
+        }) => {
+            let (relative_path, _) = resolve_note(Some(name), &Config::load(&notes_dir));
+            match scratch::promote(&notes_dir, from.as_deref(), &relative_path) {
+                Ok(source) if !dry_run::is_enabled() => log::info!(
+                    "Promoted {} to {}",
+                    source.display(),
+                    relative_path.display()
+                ),
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Scratch {
+            action: Some(ScratchAction::List),
+            ..
+        }) => {
+            for note in scratch::notes(&notes_dir) {
+                println!("{}", note.file_stem().unwrap().to_string_lossy());
+            }
+            return;
+        }
+        Some(Command::Scratch {
+            action: None,
+            print,
+        }) => {
+            let relative_path = scratch::new_path(&notes_dir, Local::now().naive_local());
+            let file_path = notes_dir.join(&relative_path);
+            if !dry_run::skip(format_args!("create {}", file_path.display())) {
+                fs::create_dir_all(file_path.parent().unwrap())
+                    .expect("failed to create scratch directory");
+                fs::write(&file_path, "").expect("failed to write note file");
+            }
+            match print {
+                true => println!("{}", file_path.display()),
+                false => launch_editor(&notes_dir, &[file_path], None, &[]),
+            }
+            return;
+        }
+        Some(Command::Cat { ref paths }) => {
+            use std::io::Write;
+            let mut stdout = std::io::stdout().lock();
+            for path in paths {
+                let Some(file_path) = cat_target(&notes_dir, path) else {
+                    eprintln!("{path} not found in {}", notes_dir.display());
+                    process::exit(1);
+                };
+                let bytes = fs::read(&file_path).expect("failed to read note");
+                // A closed pipe (e.g. `kno cat @today | head`) isn't an error
+                if stdout.write_all(&bytes).is_err() {
+                    return;
+                }
+            }
+            return;
+        }
+        Some(Command::Here { link, print }) => {
+            match open_project_note(&notes_dir, &context::Context::detect(), link) {
+                Ok((file_path, _)) if print => println!("{}", file_path.display()),
+                Ok((file_path, cursor)) => launch_editor(&notes_dir, &[file_path], cursor, &[]),
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Capture { ref text }) => {
+            let config = Config::load(&notes_dir);
+            let home = env::var_os("HOME").map(PathBuf::from);
+            let entry = capture_entry(
+                text,
+                &context::Context::detect(),
+                &config.capture.fields,
+                &Local::now().format("%H:%M").to_string(),
+                home.as_deref(),
+            );
+            append_note(
+                &notes_dir,
+                None,
+                Local::now().date_naive(),
+                &entry,
+                !config.append.no_header,
+                Placement::Section(&config.daily.log_section),
+            );
+            return;
+        }
+        Some(Command::Changed { since }) => {
+            require_git_repo(&notes_dir);
+            let start = since
+                .and_hms_opt(0, 0, 0)
+                .and_then(|t| t.and_local_timezone(Local).earliest())
+                .unwrap_or_else(Local::now);
+            let base = changed::base_before(&notes_dir, start);
+            match changed::changes(&notes_dir, &base) {
+                Ok(changes) if changes.is_empty() => log::info!("No notes changed since {since}"),
+                Ok(changes) => print!("{}", changed::format(&changes)),
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+        Some(Command::Standup { copy }) => {
+            let today = Local::now().date_naive();
+            let standup = standup::gather(&notes_dir, today, &Config::load(&notes_dir));
+            let text = standup::format(&standup, today);
+            print!("{text}");
+            if copy {
+                match clipboard::write(&text) {
+                    Ok(()) => log::info!("Copied to the clipboard"),
+                    Err(e) => {
+                        eprintln!("Error: {e}");
+                        process::exit(1);
+                    }
+                }
+            }
+            return;
+        }
+        Some(Command::Log { ref text }) => {
+            let config = Config::load(&notes_dir);
+            let now = Local::now();
+            let entry = format!("- **{}** {}", now.format("%H:%M"), text.trim());
+            append_note(
+                &notes_dir,
+                None,
+                now.date_naive(),
+                &entry,
+                !config.append.no_header,
+                Placement::NewSection(&config.daily.log_section),
+            );
+            return;
+        }
+        Some(Command::Last { print }) => {
+            let Some(file_path) = vault::last_note(&notes_dir) else {
+                eprintln!("No notes yet");
+                process::exit(1);
+            };
+            match print {
+                true => println!("{}", file_path.display()),
+                false => launch_editor(&notes_dir, &[file_path], None, &[]),
+            }
+            return;
+        }
+        Some(Command::History {
+            opened,
+            limit,
+            ref format,
+        }) => {
+            let notes = match opened {
+                true => vault::opened_history(&notes_dir),
+                false => vault::recently_modified(&notes_dir),
+            };
+            let notes = &notes[..limit.min(notes.len())];
+            match format {
+                Some(template) => {
+                    let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+                    let _ = write_formatted(&mut out, &notes_dir, notes, template);
+                }
+                None => notes.iter().for_each(|n| println!("{}", n.display())),
+            }
+            return;
+        }
+        Some(Command::Pick {
+            tmux,
+            send,
+            multi,
+            print,
+            print0,
+        }) => {
+            let output = match (print, print0, tmux, send) {
+                (true, _, _, _) => PickOutput::Print("\n"),
+                (_, true, _, _) => PickOutput::Print("\0"),
+                (_, _, true, true) => PickOutput::TmuxSend,
+                (_, _, true, false) => PickOutput::TmuxWindow,
+                _ => PickOutput::Editor,
+            };
+            run_pick(&notes_dir, tmux, multi, output);
+            return;
+        }
+        Some(Command::Events) => {
+            run_events(&notes_dir);
+            return;
+        }
+        Some(Command::Open {
+            ref title,
+            ref editor_args,
+            ..
+        }) if title.is_some() => {
+            let file_paths = pick_by_title(&notes_dir, title.as_deref().unwrap());
+            launch_editor(&notes_dir, &file_paths, None, editor_args);
+        }
+        Some(Command::Open {
+            ref paths,
+            ref all,
+            ref editor_args,
+            ..
+        }) => {
+            let paths = resolve_aliases(&notes_dir, paths);
+            require_existing(&notes_dir, &paths, cli.existing);
+            let paths = confirm_create(&notes_dir, &paths);
+            let (mut file_paths, cursor) = match paths.is_empty() && !all.is_empty() {
+                true => (Vec::new(), None),
+                false => open_notes(&notes_dir, &paths, Local::now().date_naive()),
+            };
+            for pattern in all {
+                file_paths.extend(
+                    vault::glob(&notes_dir, pattern)
+                        .into_iter()
+                        .map(|rel| notes_dir.join(rel)),
+                );
+            }
+            if file_paths.is_empty() {
+                eprintln!("No notes match {}", all.join(", "));
+                process::exit(1);
+            }
+            launch_editor(&notes_dir, &file_paths, cursor, editor_args);
+        }
+        Some(Command::Todo {
+            priority,
+            ref query,
+            ref format,
+            progress,
+            ref path,
+        }) => {
+            let notes = query
+                .as_deref()
+                .map(|q| query_arg(q).filter(&notes_dir, vault::notes(&notes_dir)));
+            if progress {
+                let prefix = path.as_deref().map(|p| p.trim_end_matches('/'));
+                let tasks: Vec<tasks::Task> = tasks::scan(&notes_dir)
+                    .into_iter()
+                    .filter(|t| prefix.is_none_or(|p| t.path.starts_with(p)))
+                    .filter(|t| notes.as_ref().is_none_or(|notes| notes.contains(&t.path)))
+                    .collect();
+                print!("{}", tasks::progress(&tasks));
+                return;
+            }
+            print!(
+                "{}",
+                tasks::list_open(&notes_dir, priority, notes.as_deref(), format.as_deref())
+            );
+            return;
+        }
+        Some(Command::Done {
+            ref selection,
+            date,
+            move_to_done,
+        }) => {
+            run_done(&notes_dir, selection, date, move_to_done);
+            return;
+        }
+        Some(Command::SelfUpdate { check }) => {
+            run_self_update(check);
+            return;
+        }
+        Some(Command::Backup {
+            action:
+                Some(BackupAction::Restore {
+                    ref archive,
+                    ref into,
+                    force,
+                }),
+            ..
+        }) => {
+            run_restore(&notes_dir, archive, into.as_deref(), force);
+            return;
+        }
+        Some(Command::Backup { install_timer, .. }) => {
+            run_backup(&notes_dir, install_timer);
+            return;
+        }
+        Some(Command::Compact {
+            target: CompactTarget::Daily { before, by },
+        }) => {
+            let bundles = daily::compact(&notes_dir, before, by);
+            if bundles.is_empty() {
+                log::info!("No daily notes before {before}");
+            }
+            if !dry_run::is_enabled() {
+                for (bundle, count) in bundles {
+                    log::info!("Compacted {count} notes into {}", bundle.display());
+                }
+            }
+            return;
+        }
+        Some(Command::Migrate {
+            target: Some(MigrateTarget::Daily { to }),
+            ..
+        }) => {
+            run_migrate_daily(&notes_dir, to);
+            return;
+        }
+        Some(Command::Migrate {
+            target: None,
+            ref map,
+        }) => {
+            run_migrate_map(&notes_dir, map.as_deref().expect("clap requires --map"));
+            return;
+        }
+        Some(Command::Dedupe { threshold, list }) => {
+            run_dedupe(&notes_dir, threshold, list);
+            return;
+        }
+        Some(Command::Import {
+            source: ImportSource::Dir { ref path, ref into },
+        }) => {
+            run_import_dir(&notes_dir, path, into.as_deref());
+            return;
+        }
+        Some(Command::Import {
+            source: ImportSource::Joplin { ref path, ref into },
+        }) => {
+            run_import_joplin(&notes_dir, path, into);
+            return;
+        }
+        Some(Command::Import {
+            source: ImportSource::AppleNotes { ref html, ref into },
+        }) => {
+            run_import_apple_notes(&notes_dir, html.as_deref(), into);
+            return;
+        }
+        Some(Command::Mv {
+            ref from,
+            ref to,
+            redirect,
+        }) => {
+            run_mv(&notes_dir, from, to, redirect);
+            return;
+        }
+        Some(Command::Archive { ref paths, auto }) => {
+            run_archive(&notes_dir, paths, auto);
+            return;
+        }
+        Some(Command::Gc { list }) => {
+            let expired = vault::expired(&notes_dir, Local::now().date_naive());
+            if expired.is_empty() {
+                log::info!("No expired notes");
+            }
+            for (rel, date) in expired {
+                match list {
+                    true => println!("{date}  {}", rel.display()),
+                    false => {
+                        let target = dedupe::trash(&notes_dir, &rel);
+                        if !dry_run::is_enabled() {
+                            log::info!(
+                                "Moved {} to {} (expired {date})",
+                                rel.display(),
+                                target.strip_prefix(&notes_dir).unwrap().display()
+                            );
+                        }
+                    }
+                }
+            }
+            return;
+        }
+        Some(Command::Assets {
+            action: AssetsAction::Gc { delete },
+        }) => {
+            let unused = assets::unused(&notes_dir);
+            if unused.is_empty() {
+                log::info!("No unused attachments");
+            }
+            for rel in unused {
+                match delete {
+                    true => {
+                        let target = dedupe::trash(&notes_dir, &rel);
+                        if !dry_run::is_enabled() {
+                            log::info!(
+                                "Moved {} to {}",
+                                rel.display(),
+                                target.strip_prefix(&notes_dir).unwrap().display()
+                            );
+                        }
+                    }
+                    false => println!("{}", rel.display()),
+                }
+            }
+            return;
+        }
+        Some(Command::Search {
+            ref query,
+            ref path,
+            regex,
+            ignore_case,
+            word,
+            since,
+            until,
+            ref filter,
+            count,
+            files_with_matches,
+            ref format,
+            ref save,
+            ref saved,
+        }) => {
+            let output = match (count, files_with_matches, format) {
+                (true, _, _) => SearchOutput::Count,
+                (_, true, _) => SearchOutput::Files,
+                (_, _, Some(template)) => SearchOutput::Format(template.clone()),
+                _ => SearchOutput::Lines,
+            };
+            let query = match saved {
+                Some(name) => SearchQuery::saved(&notes_dir, name).unwrap_or_else(|e| {
+                    eprintln!("Error: {e}");
+                    process::exit(2);
+                }),
+                None => SearchQuery {
+                    query: query.clone().expect("query is required without --saved"),
+                    path: path.clone(),
+                    syntax: search::Syntax {
+                        regex,
+                        ignore_case,
+                        word,
+                    },
+                    since,
+                    until,
+                    filter: filter.clone(),
+                },
+            };
+            if let Some(name) = save {
+                if let Err(e) = config::save_search(&notes_dir, name, &query.to_args()) {
+                    eprintln!("Error: {e}");
+                    process::exit(2);
+                }
+                if !dry_run::is_enabled() {
+                    log::info!("Saved search {name}");
+                }
+            }
+            run_search(&notes_dir, &query, output);
+            return;
+        }
+        Some(Command::Habit { ref action }) => {
+            run_habit(&notes_dir, action);
+            return;
+        }
+        Some(Command::Goal) => {
+            run_goal(&notes_dir);
+            return;
+        }
+        Some(Command::Stats {
+            ref path,
+            largest,
+            stalest,
+        }) => {
+            run_stats(&notes_dir, path.as_deref(), largest, stalest);
+            return;
+        }
+        Some(Command::Graph { metrics, limit }) => {
+            let graph = graph::Graph::build(&notes_dir);
+            match metrics {
+                true => print!("{}", format_graph_metrics(&graph, limit)),
+                false => {
+                    for (from, targets) in &graph.links {
+                        for to in targets {
+                            println!("{} -> {}", from.display(), to.display());
+                        }
+                    }
+                }
+            }
+            return;
+        }
+        Some(Command::Verify {
+            urls,
+            portable,
+            fix,
+            staged,
+        }) => {
+            let mut issues = match staged {
+                true => verify::check_staged(&notes_dir).unwrap_or_else(|e| {
+                    eprintln!("Error: {e}");
+                    process::exit(1);
+                }),
+                false => verify::check(&notes_dir),
+            };
+            let stale = verify::stale_history(&notes_dir);
+            if !staged && stale > 0 {
+                log::info!(
+                    "{stale} history entries for missing notes (harmless; delete {} to reset)",
+                    vault::HISTORY_FILE_NAME
+                );
+            }
+            if urls {
+                issues.extend(urls::check(&notes_dir, &Config::load(&notes_dir).lint));
+            }
+            if portable {
+                issues.extend(verify::portable(&notes_dir));
+            }
+            if fix {
+                let moves: HashMap<PathBuf, PathBuf> = issues
+                    .iter()
+                    .filter_map(|i| Some((i.path.clone(), i.rename.clone()?)))
+                    .collect();
+                if !moves.is_empty() {
+                    move_notes(&notes_dir, &moves);
+                    if !dry_run::is_enabled() {
+                        remove_emptied_dirs(&notes_dir, &moves);
+                        match moves.len() {
+                            1 => log::info!("Renamed 1 file"),
+                            n => log::info!("Renamed {n} files"),
+                        }
+                    }
+                }
+                issues.retain(|i| i.rename.is_none());
+            }
+            if issues.is_empty() {
+                log::info!("No problems found");
+                return;
+            }
+            for issue in &issues {
+                println!("{}: {}", issue.path.display(), issue.problem);
+                println!("  fix: {}", issue.fix);
+            }
+            process::exit(1);
+        }
+        Some(Command::Export {
+            format,
+            ref path,
+            ref profile,
+            ref profile_path,
+            ref tag,
+            ref out,
+        }) => {
+            run_export(
+                &notes_dir,
+                format,
+                path.as_deref().or(profile_path.as_deref()),
+                profile.as_deref(),
+                tag.as_deref(),
+                out,
+            );
+            return;
+        }
+        Some(Command::Publish { check, ref out }) => {
+            let notes = export::exposed(&notes_dir, vault::notes(&notes_dir), true);
+            if check {
+                notes.iter().for_each(|n| println!("{}", n.display()));
+                return;
+            }
+            if notes.is_empty() {
+                eprintln!("No notes are marked public (publish: true or visibility: public)");
+                process::exit(1);
+            }
+            export::export_html(&notes_dir, &notes, out);
+            if !dry_run::is_enabled() {
+                log::info!("Published {} notes to {}", notes.len(), out.display());
+            }
+            return;
+        }
+        Some(Command::Share {
+            ref path,
+            update,
+            force,
+        }) => {
+            run_share(&notes_dir, path, update, force);
+            return;
+        }
+        Some(Command::Secret { ref action }) => {
+            run_secret(action);
+            return;
+        }
+        Some(Command::Mail {
+            ref path,
+            ref to,
+            ref subject,
+            force,
+        }) => {
+            run_mail(&notes_dir, path, to, subject.as_deref(), force);
+            return;
+        }
+        Some(Command::Tags { counts, sort }) => {
+            print!("{}", format_tags(&notes_dir, counts, sort));
+            return;
+        }
+        Some(Command::Tag { ref action }) => {
+            run_tag(&notes_dir, action);
+            return;
+        }
+        Some(Command::OpenWith {
+            ref path, ref app, ..
+        }) => {
+            let file_path = existing_note(&notes_dir, path);
+            let mut cmd = opener_cmd(app.as_deref(), &file_path);
+            log::debug!("running {cmd:?}");
+            let status = cmd.status().expect("failed to launch opener");
+            process::exit(status.code().unwrap_or(1));
+        }
+        None => {}
+    }
+
+    cli.paths = resolve_aliases(&notes_dir, &cli.paths);
+    let single_path = || match cli.paths.as_slice() {
+        [] => None,
+        [path] => Some(path.as_str()),
+        _ => {
+            eprintln!("--append, --prepend and --todo take a single note");
+            process::exit(1);
+        }
+    };
+
+    if cli.date.is_some() && cli.paths.iter().any(|p| !p.ends_with('/')) {
+        eprintln!("--date only applies to daily notes and trailing-slash directories");
+        process::exit(1);
+    }
+    let date = cli.date.unwrap_or_else(|| Local::now().date_naive());
+
+    if let Some(text) = &cli.todo {
+        let config = Config::load(&notes_dir);
+        // An explicit --date means that day's note, not the tasks note
+        let tasks_note = config.tasks.note.as_deref().filter(|_| cli.date.is_none());
+        let path = single_path().or(tasks_note);
+        let entry = format_entry(text, EntryStyle::Task, None);
+        append_note(
+            &notes_dir,
+            path,
+            date,
+            &entry,
+            !config.append.no_header,
+            Placement::Section(&config.daily.task_section),
+        );
+        return;
+    }
+
+    if let (false, Some(text)) = (cli.print, cli.append.as_ref().or(cli.prepend.as_ref())) {
+        let text = match (cli.clip, cli.edit, text.is_empty()) {
+            (true, _, _) => clipboard::read(),
+            (_, true, _) => edit_buffer(),
+            (false, false, true) => {
+                Err("nothing to append: pass the text, --clip or --edit".into())
+            }
+            (false, false, false) => Ok(text.clone()),
+        };
+        let text = text.unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            process::exit(1);
+        });
+        let text = &text;
+        let path = single_path();
+        let config = Config::load(&notes_dir);
+        let header = !(cli.no_header || config.append.no_header);
+        let style = match (cli.bullet, cli.task) {
+            (_, true) => EntryStyle::Task,
+            (true, _) => EntryStyle::Bullet,
+            _ => EntryStyle::Plain,
+        };
+        let time = cli
+            .timestamp
+            .then(|| Local::now().format("%H:%M").to_string());
+        // A multi-line --edit capture gets the time as a heading of its own
+        let entry = match (cli.edit, time) {
+            (true, Some(time)) => format!("### {time}\n\n{}", format_entry(text, style, None)),
+            (_, time) => format_entry(text, style, time.as_deref()),
+        };
+        let section = match is_url(text) {
+            true => &config.daily.link_section,
+            false if cli.task => &config.daily.task_section,
+            false => &config.daily.log_section,
+        };
+        let placement = match (&cli.after, &cli.before) {
+            _ if cli.prepend.is_some() => Placement::Top,
+            (Some(pattern), _) => Placement::After(pattern),
+            (_, Some(pattern)) => Placement::Before(pattern),
+            _ => Placement::Section(section),
+        };
+        if (cli.after.is_some() || cli.before.is_some())
+            && let Some(path) = path
+            && resolve_existing(&notes_dir, path).is_none()
+        {
+            eprintln!("{path} not found in {}", notes_dir.display());
+            process::exit(1);
+        }
+        append_note(&notes_dir, path, date, &entry, header, placement);
+        return;
+    }
+
+    require_existing(&notes_dir, &cli.paths, cli.existing);
+    let paths = confirm_create(&notes_dir, &cli.paths);
+    let (file_paths, cursor) = open_notes(&notes_dir, &paths, date);
+
+    match cli.print {
+        true => {
+            for file_path in &file_paths {
+                // -p is how editor integrations open notes, so it counts as opening
+                vault::record_opened(&notes_dir, file_path);
+                println!("{}", file_path.display());
+            }
+        }
+        false => launch_editor(&notes_dir, &file_paths, cursor, &cli.editor_args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list_to_string(
+        notes_dir: &std::path::Path,
+        path: Option<&str>,
+        max_depth: Option<usize>,
+    ) -> String {
+        let mut out = Vec::new();
+        list_notes(notes_dir, path, max_depth, false, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_vault_dir() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("projects/foo")).unwrap();
+        fs::write(tmp.path().join("inbox.md"), "").unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+
+        assert_eq!(vault_dir(tmp.path(), None).unwrap(), root);
+        assert_eq!(
+            vault_dir(tmp.path(), Some("projects/foo/")).unwrap(),
+            root.join("projects/foo")
+        );
+        assert!(vault_dir(tmp.path(), Some("inbox.md")).is_err());
+        assert!(vault_dir(tmp.path(), Some("missing")).is_err());
+        assert!(vault_dir(tmp.path(), Some("..")).is_err());
+    }
+
+    #[test]
+    fn test_titlecase_simple() {
+        assert_eq!(titlecase("joins"), "Joins");
+    }
+
+    #[test]
+    fn test_titlecase_hyphenated() {
+        assert_eq!(titlecase("design-decisions"), "Design Decisions");
+    }
+
+    #[test]
+    fn test_titlecase_underscored() {
+        assert_eq!(titlecase("my_project"), "My Project");
+    }
+
+    #[test]
+    fn test_titlecase_allcaps() {
+        assert_eq!(titlecase("ALLCAPS"), "ALLCAPS");
+    }
+
+    #[test]
+    fn test_resolve_daily_note() {
+        let (path, header) = resolve_note(None, &Config::default());
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let year = &today[..4];
+        let expected_path = PathBuf::from("daily")
+            .join(year)
+            .join(format!("{today}.md"));
+        assert_eq!(path, expected_path);
+        assert_eq!(header, format!("# {today}"));
+    }
+
+    #[test]
+    fn test_resolve_simple_note() {
+        let (path, header) = resolve_note(Some("foo"), &Config::default());
+        assert_eq!(path, PathBuf::from("foo.md"));
+        assert_eq!(header, "# Foo");
+    }
+
+    #[test]
+    fn test_resolve_nested_note() {
+        let (path, header) = resolve_note(Some("sql/joins"), &Config::default());
+        assert_eq!(path, PathBuf::from("sql/joins.md"));
+        assert_eq!(header, "# Joins");
+    }
+
+    #[test]
+    fn test_resolve_hyphenated_name() {
+        let (path, header) = resolve_note(Some("my-project/design-decisions"), &Config::default());
+        assert_eq!(path, PathBuf::from("my-project/design-decisions.md"));
+        assert_eq!(header, "# Design Decisions");
+    }
+
+    #[test]
+    fn test_resolve_trailing_slash_uses_date() {
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let (path, header) = resolve_note(Some("my-dir/"), &Config::default());
+        assert_eq!(path, PathBuf::from(format!("my-dir/{today}.md")));
+        assert_eq!(header, format!("# {today}"));
+    }
+
+    #[test]
+    fn test_resolve_nested_trailing_slash() {
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let (path, header) = resolve_note(Some("projects/myproject/"), &Config::default());
+        assert_eq!(
+            path,
+            PathBuf::from(format!("projects/myproject/{today}.md"))
+        );
+        assert_eq!(header, format!("# {today}"));
+    }
+
+    #[test]
+    fn test_daily_note_creates_dirs_and_file() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = open_note(tmp.path(), None);
+
+        assert!(path.exists());
+        assert!(path.starts_with(tmp.path().join("daily")));
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# 2"));
+        assert!(content.ends_with("\n\n"));
+    }
+
+    #[test]
+    fn test_nested_note_creates_dirs() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = open_note(tmp.path(), Some("sql/joins"));
+
+        assert_eq!(path, tmp.path().join("sql/joins.md"));
+        assert!(tmp.path().join("sql").is_dir());
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "# Joins\n\n");
+    }
+
+    #[test]
+    fn test_existing_file_not_overwritten() {
+        let tmp = tempfile::TempDir::new().unwrap();
+
+        open_note(tmp.path(), Some("foo"));
+        fs::write(tmp.path().join("foo.md"), "# Foo\n\nMy notes here\n").unwrap();
+
+        open_note(tmp.path(), Some("foo"));
+        let content = fs::read_to_string(tmp.path().join("foo.md")).unwrap();
+        assert_eq!(content, "# Foo\n\nMy notes here\n");
+    }
+
+    #[test]
+    fn test_trailing_slash_creates_dir_and_dated_note() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let path = open_note(tmp.path(), Some("my-dir/"));
+
+        assert_eq!(path, tmp.path().join(format!("my-dir/{today}.md")));
+        assert!(tmp.path().join("my-dir").is_dir());
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, format!("# {today}\n\n"));
+    }
+
+    #[test]
+    fn test_empty_file_gets_header() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let file = tmp.path().join("foo.md");
+        fs::write(&file, "").unwrap();
+
+        open_note(tmp.path(), Some("foo"));
+
+        let content = fs::read_to_string(&file).unwrap();
+        assert_eq!(content, "# Foo\n\n");
+    }
+
+    #[test]
+    fn test_note_template_applied_on_creation() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join(".templates")).unwrap();
+        fs::write(
+            tmp.path().join(".templates/note.md"),
+            "# {{title}}\n\npath: {{path}}\n\n{{cursor}}\n",
+        )
+        .unwrap();
+
+        let (path, cursor) = open_note_with_cursor(tmp.path(), Some("sql/joins"));
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "# Joins\n\npath: sql/joins.md\n\n\n");
+        assert_eq!(cursor, Some(5));
+    }
+
+    #[test]
+    fn test_daily_template_applied_on_creation() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join(".templates")).unwrap();
+        fs::write(tmp.path().join(".templates/daily.md"), "# {{date:%Y}}\n").unwrap();
+
+        let path = open_note(tmp.path(), None);
+
+        let year = Local::now().format("%Y").to_string();
+        assert_eq!(fs::read_to_string(&path).unwrap(), format!("# {year}\n"));
+    }
+
+    #[test]
+    fn test_daily_nav_links_when_enabled() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("config.toml"),
+            "[daily]\nnav_links = true\n",
+        )
+        .unwrap();
+        fs::create_dir_all(tmp.path().join("daily/2020")).unwrap();
+        fs::write(
+            tmp.path().join("daily/2020/2020-01-01.md"),
+            "# 2020-01-01\n\n",
+        )
+        .unwrap();
+
+        let path = open_note(tmp.path(), None);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.ends_with("Previous: [2020-01-01](../2020/2020-01-01.md)\n\n"));
+        let prev = fs::read_to_string(tmp.path().join("daily/2020/2020-01-01.md")).unwrap();
+        assert!(prev.contains("Next: ["));
+    }
+
+    #[test]
+    fn test_frontmatter_stamped_when_enabled() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("config.toml"),
+            "[notes]\nfrontmatter = true\n",
+        )
+        .unwrap();
+        fs::create_dir_all(tmp.path().join(".templates")).unwrap();
+        fs::write(
+            tmp.path().join(".templates/note.md"),
+            "# {{title}}\n\n{{cursor}}\n",
+        )
+        .unwrap();
+
+        let (path, cursor) = open_note_with_cursor(tmp.path(), Some("sql/joins"));
+
+        let today = Local::now().format("%Y-%m-%d");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("---\ntitle: Joins\ncreated: {today}\ntags: [sql]\n---\n# Joins\n\n\n")
+        );
+        assert_eq!(cursor, Some(8));
+    }
+
+    #[test]
+    fn test_stamp_frontmatter_keeps_template_keys() {
+        let now = Local::now();
+        let out = stamp_frontmatter(
+            "---\ntags: [custom]\n---\n# Day\n",
+            "Day",
+            std::path::Path::new("daily/2026/2026-02-15.md"),
+            now,
+        );
+        assert_eq!(frontmatter::get_list(&out, "tags"), ["custom"]);
+        assert_eq!(frontmatter::get(&out, "title").as_deref(), Some("Day"));
+
+        let out = stamp_frontmatter("", "Day", std::path::Path::new("daily/2026/x.md"), now);
+        assert_eq!(frontmatter::get_list(&out, "tags"), ["daily"]);
+    }
+
+    #[test]
+    fn test_daily_nav_links_off_by_default() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("daily/2020")).unwrap();
+        fs::write(
+            tmp.path().join("daily/2020/2020-01-01.md"),
+            "# 2020-01-01\n\n",
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(open_note(tmp.path(), None)).unwrap();
+        assert!(!content.contains("Previous:"));
     }
 
-    let label = path.unwrap_or(".");
-    let mut output = format!("{label}\n");
-    list_tree(&root, "", max_depth, 0, &mut output);
-    output
-}
+    #[test]
+    fn test_missing_notes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("sql")).unwrap();
+        fs::write(tmp.path().join("sql/joins.md"), "").unwrap();
 
-fn create_notes_dir(notes_dir: &std::path::Path) {
-    match notes_dir.exists() {
-        true => println!("{} already exists", notes_dir.display()),
-        false => {
-            fs::create_dir_all(notes_dir).expect("failed to create notes directory");
-            println!("Created {}", notes_dir.display());
-        }
+        let paths = ["sql/joins", "sql/jions", "meetings/"].map(String::from);
+        assert_eq!(
+            missing_notes(tmp.path(), &paths),
+            [PathBuf::from("sql/jions.md")]
+        );
     }
-}
 
-fn git_cmd(notes_dir: &std::path::Path) -> process::Command {
-    let mut cmd = process::Command::new("git");
-    cmd.arg("-C").arg(notes_dir);
-    cmd
-}
+    #[test]
+    fn test_new_note_refuses_existing() {
+        let tmp = tempfile::TempDir::new().unwrap();
 
-fn init_git_repo(notes_dir: &std::path::Path) {
-    if notes_dir.join(".git").exists() {
-        println!("Git repo already initialized");
-        return;
-    }
+        let (path, _) = new_note(tmp.path(), "sql/joins", None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Joins\n\n");
 
-    match git_cmd(notes_dir).arg("init").status() {
-        Ok(s) if s.success() => println!("Initialized git repo"),
-        Ok(_) => eprintln!("Warning: git init failed"),
-        Err(e) => eprintln!("Warning: could not run git: {e}"),
+        fs::write(&path, "# Joins\n\nkeep me\n").unwrap();
+        assert_eq!(
+            new_note(tmp.path(), "sql/joins", None).unwrap_err(),
+            "sql/joins.md already exists"
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Joins\n\nkeep me\n");
     }
-}
 
-fn setup_shell_completions() {
-    let home = env::var("HOME").expect("HOME not set");
-    let zshrc = PathBuf::from(&home).join(".zshrc");
-    let completion_line = "source <(COMPLETE=zsh kno)";
+    #[test]
+    fn test_new_note_with_template() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join(".templates")).unwrap();
+        fs::write(
+            tmp.path().join(".templates/recipe.md"),
+            "# {{title}}\n\n## Ingredients\n{{cursor}}\n",
+        )
+        .unwrap();
 
-    let already_present = zshrc
-        .exists()
-        .then(|| fs::read_to_string(&zshrc).unwrap_or_default())
-        .is_some_and(|content| content.contains(completion_line));
+        let (path, cursor) = new_note(tmp.path(), "food/pasta", Some("recipe")).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Pasta\n\n## Ingredients\n\n"
+        );
+        assert_eq!(cursor, Some(4));
+        assert!(new_note(tmp.path(), "food/soup", Some("missing")).is_err());
+        assert!(!tmp.path().join("food/soup.md").exists());
+    }
 
-    if already_present {
-        println!("Shell completions already configured");
-        return;
+    #[test]
+    fn test_existing_note_has_no_cursor() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join(".templates")).unwrap();
+        fs::write(tmp.path().join(".templates/note.md"), "{{cursor}}\n").unwrap();
+        fs::write(tmp.path().join("foo.md"), "# Foo\n\nbody\n").unwrap();
+
+        let (_, cursor) = open_note_with_cursor(tmp.path(), Some("foo"));
+        assert_eq!(cursor, None);
     }
 
-    let result = fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&zshrc)
-        .and_then(|mut file| {
-            use std::io::Write;
-            writeln!(file, "\n{completion_line}")
-        });
+    #[test]
+    fn test_dry_run_open_creates_nothing() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        dry_run::set(true);
+        let path = open_note(tmp.path(), Some("sql/joins"));
+        dry_run::set(false);
 
-    match result {
-        Ok(()) => println!(
-            "Added shell completions to ~/.zshrc (restart your shell or `source ~/.zshrc`)"
-        ),
-        Err(e) => eprintln!("Warning: could not update .zshrc: {e}"),
+        assert_eq!(path, tmp.path().join("sql/joins.md"));
+        assert!(!tmp.path().join("sql").exists());
     }
-}
 
-fn run_init(notes_dir: &std::path::Path) {
-    create_notes_dir(notes_dir);
-    init_git_repo(notes_dir);
-    setup_shell_completions();
-}
+    #[test]
+    fn test_dry_run_append_leaves_note_untouched() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = open_note(tmp.path(), Some("foo"));
 
-fn run_git(notes_dir: &std::path::Path, args: &[String]) {
-    let is_init = args.first().is_some_and(|a| a == "init");
+        dry_run::set(true);
+        append_to_note(&path, "text");
+        dry_run::set(false);
 
-    if !is_init && !notes_dir.join(".git").exists() {
-        eprintln!("Notes directory is not a git repo. Run `kno git init` to initialize.");
-        process::exit(1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Foo\n\n");
     }
 
-    let status = git_cmd(notes_dir)
-        .args(args)
-        .status()
-        .expect("failed to run git");
+    #[test]
+    fn test_dry_run_meeting_creates_nothing() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        dry_run::set(true);
+        create_meeting_note(tmp.path(), "standup", &[]);
+        dry_run::set(false);
 
-    process::exit(status.code().unwrap_or(1));
-}
+        assert!(fs::read_dir(tmp.path()).unwrap().next().is_none());
+    }
 
-fn main() {
-    let home = env::var("HOME").expect("HOME not set");
-    let notes_dir = PathBuf::from(&home).join(NOTES_DIR_NAME);
+    #[test]
+    fn test_cli_parses_global_dry_run() {
+        let cli = Cli::parse_from(["kno", "meeting", "sync", "--dry-run"]);
+        assert!(cli.dry_run);
+        let cli = Cli::parse_from(["kno", "--dry-run", "init"]);
+        assert!(cli.dry_run);
+        assert!(matches!(cli.command, Some(Command::Init { hooks: false })));
+        let cli = Cli::parse_from(["kno", "--dry-run", "-a", "x"]);
+        assert!(cli.dry_run);
+    }
 
-    let mut cmd = Cli::command();
-    cmd = cmd.mut_arg("path", |a| {
-        a.add(ArgValueCompleter::new(
-            PathCompleter::any().current_dir(&notes_dir),
-        ))
-    });
-    clap_complete::CompleteEnv::with_factory(|| cmd.clone()).complete();
+    #[test]
+    fn test_cli_parses_verbose_and_quiet() {
+        let cli = Cli::parse_from(["kno", "-v", "list"]);
+        assert!(cli.verbose);
+        let cli = Cli::parse_from(["kno", "init", "--quiet"]);
+        assert!(cli.quiet);
+        assert!(Cli::try_parse_from(["kno", "-v", "-q"]).is_err());
+    }
 
-    let cli = Cli::from_arg_matches_mut(&mut cmd.get_matches_from(env::args_os())).unwrap();
+    #[test]
+    fn test_append_to_note() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = open_note(tmp.path(), Some("foo"));
 
-    match cli.command {
-        Some(Command::Init) => {
-            run_init(&notes_dir);
-            return;
-        }
-        Some(Command::Git { ref args }) => {
-            run_git(&notes_dir, args);
-        }
-        Some(Command::List { ref path, level }) => {
-            let depth = Some(level.unwrap_or(1)).filter(|&l| l > 0);
-            let output = list_notes(&notes_dir, path.as_deref(), depth);
-            print!("{output}");
-            return;
-        }
-        None => {}
+        append_to_note(&path, "first line");
+        append_to_note(&path, "second line");
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "# Foo\n\nfirst line\nsecond line\n");
     }
 
-    let file_path = open_note(&notes_dir, cli.path.as_deref());
+    #[test]
+    fn test_append_to_daily_note() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = open_note(tmp.path(), None);
 
-    match (cli.print, cli.append) {
-        (true, _) => println!("{}", file_path.display()),
-        (_, Some(text)) => append_to_note(&file_path, &text),
-        _ => {
-            let editor = env::var("EDITOR").unwrap_or_else(|_| "nvim".to_string());
-            let status = process::Command::new(&editor)
-                .arg(&file_path)
-                .current_dir(&notes_dir)
-                .status()
-                .expect("failed to launch editor");
-            process::exit(status.code().unwrap_or(1));
-        }
-    }
-}
+        append_to_note(&path, "quick thought");
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.ends_with("quick thought\n"));
+    }
 
     #[test]
-    fn test_titlecase_simple() {
-        assert_eq!(titlecase("joins"), "Joins");
+    fn test_append_creates_note_if_new() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = open_note(tmp.path(), Some("new-note"));
+
+        append_to_note(&path, "first entry");
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, "# New Note\n\nfirst entry\n");
     }
 
     #[test]
-    fn test_titlecase_hyphenated() {
-        assert_eq!(titlecase("design-decisions"), "Design Decisions");
+    fn test_append_without_header_creates_bare_note() {
+        let tmp = tempfile::TempDir::new().unwrap();
+
+        let path = append_note(
+            tmp.path(),
+            Some("logs/build"),
+            Local::now().date_naive(),
+            "first",
+            false,
+            Placement::Section("Log"),
+        );
+        append_note(
+            tmp.path(),
+            Some("logs/build"),
+            Local::now().date_naive(),
+            "second",
+            false,
+            Placement::Section("Log"),
+        );
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
     }
 
     #[test]
-    fn test_titlecase_underscored() {
-        assert_eq!(titlecase("my_project"), "My Project");
+    fn test_append_with_header_creates_titled_note() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = append_note(
+            tmp.path(),
+            Some("foo"),
+            Local::now().date_naive(),
+            "first",
+            true,
+            Placement::Section("Log"),
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Foo\n\nfirst\n");
     }
 
     #[test]
-    fn test_titlecase_allcaps() {
-        assert_eq!(titlecase("ALLCAPS"), "ALLCAPS");
+    fn test_cli_no_header_requires_append() {
+        assert!(Cli::try_parse_from(["kno", "foo", "--no-header"]).is_err());
+        let cli = Cli::parse_from(["kno", "foo", "-a", "x", "--no-header"]);
+        assert!(cli.no_header);
     }
 
     #[test]
-    fn test_resolve_daily_note() {
-        let (path, header) = resolve_note(None);
-        let today = Local::now().format("%Y-%m-%d").to_string();
-        let year = &today[..4];
-        let expected_path = PathBuf::from("daily")
-            .join(year)
-            .join(format!("{today}.md"));
-        assert_eq!(path, expected_path);
-        assert_eq!(header, format!("# {today}"));
+    fn test_format_entry_styles() {
+        assert_eq!(format_entry("idea", EntryStyle::Plain, None), "idea");
+        assert_eq!(format_entry("idea", EntryStyle::Bullet, None), "- idea");
+        assert_eq!(
+            format_entry("call bo", EntryStyle::Task, None),
+            "- [ ] call bo"
+        );
+        assert_eq!(
+            format_entry("call bo", EntryStyle::Task, Some("14:32")),
+            "- [ ] 14:32 call bo"
+        );
+        assert_eq!(
+            format_entry("idea", EntryStyle::Plain, Some("09:05")),
+            "09:05 idea"
+        );
     }
 
     #[test]
-    fn test_resolve_simple_note() {
-        let (path, header) = resolve_note(Some("foo"));
-        assert_eq!(path, PathBuf::from("foo.md"));
-        assert_eq!(header, "# Foo");
+    fn test_cli_bullet_and_task_conflict() {
+        assert!(Cli::try_parse_from(["kno", "-a", "x", "--bullet", "--task"]).is_err());
+        assert!(Cli::try_parse_from(["kno", "--task"]).is_err());
+        let cli = Cli::parse_from(["kno", "-a", "x", "--task", "--timestamp"]);
+        assert!(cli.task && cli.timestamp);
     }
 
     #[test]
-    fn test_resolve_nested_note() {
-        let (path, header) = resolve_note(Some("sql/joins"));
-        assert_eq!(path, PathBuf::from("sql/joins.md"));
-        assert_eq!(header, "# Joins");
+    fn test_cli_parses_todo_shorthand() {
+        let cli = Cli::parse_from(["kno", "-t", "follow up with Dana"]);
+        assert_eq!(cli.todo.as_deref(), Some("follow up with Dana"));
+        assert!(Cli::try_parse_from(["kno", "-t", "x", "-a", "y"]).is_err());
+        assert!(matches!(
+            Cli::parse_from(["kno", "todo"]).command,
+            Some(Command::Todo { priority: None, .. })
+        ));
+        assert!(matches!(
+            Cli::parse_from(["kno", "todo", "--priority", "high"]).command,
+            Some(Command::Todo {
+                priority: Some(tasks::Priority::High),
+                ..
+            })
+        ));
     }
 
     #[test]
-    fn test_resolve_hyphenated_name() {
-        let (path, header) = resolve_note(Some("my-project/design-decisions"));
-        assert_eq!(path, PathBuf::from("my-project/design-decisions.md"));
-        assert_eq!(header, "# Design Decisions");
+    fn test_cli_parses_done() {
+        let cli = Cli::parse_from(["kno", "done", "1", "3-4", "--date", "--move"]);
+        match &cli.command {
+            Some(Command::Done {
+                selection,
+                date,
+                move_to_done,
+            }) => {
+                assert_eq!(selection, &["1", "3-4"]);
+                assert!(*date && *move_to_done);
+            }
+            _ => panic!("expected Done command"),
+        }
     }
 
     #[test]
-    fn test_resolve_trailing_slash_uses_date() {
-        let today = Local::now().format("%Y-%m-%d").to_string();
-        let (path, header) = resolve_note(Some("my-dir/"));
-        assert_eq!(path, PathBuf::from(format!("my-dir/{today}.md")));
-        assert_eq!(header, format!("# {today}"));
+    fn test_cli_parses_tag_actions() {
+        let cli = Cli::parse_from(["kno", "tag", "add", "sql/joins", "sql", "db"]);
+        match &cli.command {
+            Some(Command::Tag {
+                action: TagAction::Add { path, tags },
+            }) => {
+                assert_eq!(path, "sql/joins");
+                assert_eq!(tags, &["sql", "db"]);
+            }
+            _ => panic!("expected tag add"),
+        }
+        assert!(Cli::try_parse_from(["kno", "tag", "rm", "sql/joins"]).is_err());
+        assert!(matches!(
+            Cli::parse_from(["kno", "tag", "rename", "rust-lang", "rust"]).command,
+            Some(Command::Tag {
+                action: TagAction::Rename { .. }
+            })
+        ));
     }
 
     #[test]
-    fn test_resolve_nested_trailing_slash() {
-        let today = Local::now().format("%Y-%m-%d").to_string();
-        let (path, header) = resolve_note(Some("projects/myproject/"));
+    fn test_format_tags_counts_sorted() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(tmp.path().join("a.md"), "---\ntags: [rust, cli]\n---\n").unwrap();
+        fs::write(tmp.path().join("b.md"), "---\ntags: [rust]\n---\n").unwrap();
+        fs::write(tmp.path().join("c.md"), "# C\n\nmore #rust\n").unwrap();
+        let today = Local::now().date_naive();
+
+        assert_eq!(format_tags(tmp.path(), false, TagSort::Name), "cli\nrust\n");
         assert_eq!(
-            path,
-            PathBuf::from(format!("projects/myproject/{today}.md"))
+            format_tags(tmp.path(), true, TagSort::Count),
+            format!("rust     3  {today}\ncli      1  {today}\n")
         );
-        assert_eq!(header, format!("# {today}"));
     }
 
     #[test]
-    fn test_daily_note_creates_dirs_and_file() {
+    fn test_todo_capture_visible_to_scanner() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let path = open_note(tmp.path(), None);
+        let entry = format_entry("follow up with Dana", EntryStyle::Task, None);
+        append_note(
+            tmp.path(),
+            None,
+            Local::now().date_naive(),
+            &entry,
+            true,
+            Placement::Section("Tasks"),
+        );
 
-        assert!(path.exists());
-        assert!(path.starts_with(tmp.path().join("daily")));
-        let content = fs::read_to_string(&path).unwrap();
-        assert!(content.starts_with("# 2"));
-        assert!(content.ends_with("\n\n"));
+        let open = tasks::list_open(tmp.path(), None, None, None);
+        assert!(open.starts_with("  1. daily/"));
+        assert!(open.ends_with(":3: follow up with Dana\n"));
     }
 
     #[test]
-    fn test_nested_note_creates_dirs() {
+    fn test_capture_into_daily_sections() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let path = open_note(tmp.path(), Some("sql/joins"));
+        fs::write(
+            tmp.path().join("config.toml"),
+            "[daily]\nsections = [\"Log\", \"Tasks\", \"Links\"]\n",
+        )
+        .unwrap();
 
-        assert_eq!(path, tmp.path().join("sql/joins.md"));
-        assert!(tmp.path().join("sql").is_dir());
-        let content = fs::read_to_string(&path).unwrap();
-        assert_eq!(content, "# Joins\n\n");
+        append_note(
+            tmp.path(),
+            None,
+            Local::now().date_naive(),
+            "- [ ] call the bank",
+            true,
+            Placement::Section("Tasks"),
+        );
+        let path = append_note(
+            tmp.path(),
+            None,
+            Local::now().date_naive(),
+            "shipped v1.2",
+            true,
+            Placement::Section("Log"),
+        );
+        append_note(
+            tmp.path(),
+            None,
+            Local::now().date_naive(),
+            "https://x.io",
+            true,
+            Placement::Section("Links"),
+        );
+        append_note(
+            tmp.path(),
+            None,
+            Local::now().date_naive(),
+            "no such section",
+            true,
+            Placement::Section("Notes"),
+        );
+
+        let content = fs::read_to_string(path).unwrap();
+        let body = content.split_once("\n\n").unwrap().1;
+        assert_eq!(
+            body,
+            "## Log\n\nshipped v1.2\n\n## Tasks\n\n- [ ] call the bank\n\n## Links\n\nhttps://x.io\n\nno such section\n"
+        );
+        assert!(is_url(" https://x.io/a?b "));
+        assert!(!is_url("see https://x.io"));
     }
 
     #[test]
-    fn test_existing_file_not_overwritten() {
+    fn test_cat_target() {
         let tmp = tempfile::TempDir::new().unwrap();
+        assert_eq!(cat_target(tmp.path(), "@today"), None);
 
-        open_note(tmp.path(), Some("foo"));
-        fs::write(tmp.path().join("foo.md"), "# Foo\n\nMy notes here\n").unwrap();
-
-        open_note(tmp.path(), Some("foo"));
-        let content = fs::read_to_string(tmp.path().join("foo.md")).unwrap();
-        assert_eq!(content, "# Foo\n\nMy notes here\n");
+        let daily = open_note(tmp.path(), None);
+        fs::write(tmp.path().join("inbox.md"), "x").unwrap();
+        assert_eq!(cat_target(tmp.path(), "@today"), Some(daily));
+        assert_eq!(
+            cat_target(tmp.path(), "inbox"),
+            Some(tmp.path().join("inbox.md"))
+        );
+        assert_eq!(cat_target(tmp.path(), "missing"), None);
     }
 
     #[test]
-    fn test_trailing_slash_creates_dir_and_dated_note() {
+    fn test_append_to_past_daily_note() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let today = Local::now().format("%Y-%m-%d").to_string();
-        let path = open_note(tmp.path(), Some("my-dir/"));
+        let cli = Cli::parse_from(["kno", "-a", "forgot this", "--date", "2026-02-14"]);
+        let date = cli.date.unwrap();
 
-        assert_eq!(path, tmp.path().join(format!("my-dir/{today}.md")));
-        assert!(tmp.path().join("my-dir").is_dir());
-        let content = fs::read_to_string(&path).unwrap();
-        assert_eq!(content, format!("# {today}\n\n"));
+        let path = append_note(
+            tmp.path(),
+            None,
+            date,
+            "forgot this",
+            true,
+            Placement::Section("Log"),
+        );
+
+        assert_eq!(path, tmp.path().join("daily/2026/2026-02-14.md"));
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "# 2026-02-14\n\nforgot this\n"
+        );
+        assert!(Cli::try_parse_from(["kno", "--date", "someday"]).is_err());
+    }
+
+    #[test]
+    fn test_capture_entry() {
+        let context = context::Context {
+            cwd: Some(PathBuf::from("/home/me/code/kno")),
+            repo_root: Some(PathBuf::from("/home/me/code/kno")),
+            branch: Some("main".into()),
+            host: Some("laptop".into()),
+        };
+        let home = Some(std::path::Path::new("/home/me"));
+        let all = Config::default().capture.fields;
+        assert_eq!(
+            capture_entry("fix auth", &context, &all, "14:32", home),
+            "- 14:32 fix auth (cwd: ~/code/kno, repo: kno, branch: main, host: laptop)"
+        );
+        assert_eq!(
+            capture_entry(
+                "fix auth",
+                &context::Context::default(),
+                &all,
+                "14:32",
+                home
+            ),
+            "- 14:32 fix auth"
+        );
+        assert_eq!(
+            capture_entry(
+                "fix auth",
+                &context,
+                &[context::Field::Branch],
+                "14:32",
+                home
+            ),
+            "- fix auth (branch: main)"
+        );
     }
 
     #[test]
-    fn test_empty_file_gets_header() {
+    fn test_project_note_linked_from_daily() {
         let tmp = tempfile::TempDir::new().unwrap();
-        let file = tmp.path().join("foo.md");
-        fs::write(&file, "").unwrap();
+        let context = context::Context {
+            repo_root: Some(PathBuf::from("/home/me/code/kno")),
+            branch: Some("feature-x".into()),
+            ..Default::default()
+        };
 
-        open_note(tmp.path(), Some("foo"));
+        let (path, _) = open_project_note(tmp.path(), &context, true).unwrap();
+        open_project_note(tmp.path(), &context, true).unwrap();
 
-        let content = fs::read_to_string(&file).unwrap();
-        assert_eq!(content, "# Foo\n\n");
+        assert_eq!(path, tmp.path().join("projects/kno.md"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Kno\n\n");
+        let daily = fs::read_to_string(open_note(tmp.path(), None)).unwrap();
+        assert_eq!(
+            daily
+                .matches("- [kno](../../projects/kno.md) (branch: feature-x)")
+                .count(),
+            1
+        );
+        assert!(open_project_note(tmp.path(), &context::Context::default(), false).is_err());
     }
 
     #[test]
-    fn test_append_to_note() {
-        let tmp = tempfile::TempDir::new().unwrap();
-        let path = open_note(tmp.path(), Some("foo"));
-
-        append_to_note(&path, "first line");
-        append_to_note(&path, "second line");
+    fn test_cli_append_clip() {
+        for args in [
+            ["kno", "-a", "--clip", "sql/joins"],
+            ["kno", "sql/joins", "-a", "--clip"],
+            ["kno", "sql/joins", "--clip", "-a"],
+        ] {
+            let mut cli = Cli::parse_from(args);
+            cli.normalize();
+            assert_eq!(cli.append.as_deref(), Some(""));
+            assert!(cli.clip);
+            assert_eq!(cli.paths, ["sql/joins"]);
+        }
+        assert!(Cli::try_parse_from(["kno", "--clip"]).is_err());
 
-        let content = fs::read_to_string(&path).unwrap();
-        assert_eq!(content, "# Foo\n\nfirst line\nsecond line\n");
+        let mut cli = Cli::parse_from(["kno", "-a", "--edit", "logs/deploys"]);
+        cli.normalize();
+        assert!(cli.edit && !cli.clip);
+        assert_eq!(cli.append.as_deref(), Some(""));
     }
 
     #[test]
-    fn test_append_to_daily_note() {
-        let tmp = tempfile::TempDir::new().unwrap();
-        let path = open_note(tmp.path(), None);
+    fn test_prepend_text() {
+        assert_eq!(
+            prepend_text("# Log\n\n- older\n", "- newer"),
+            "# Log\n\n- newer\n- older\n"
+        );
+        assert_eq!(
+            prepend_text("---\ntags: [x]\n---\n# Log\n- older\n", "- newer"),
+            "---\ntags: [x]\n---\n# Log\n\n- newer\n- older\n"
+        );
+        assert_eq!(prepend_text("no title\n", "new"), "new\nno title\n");
+        assert_eq!(prepend_text("# Log\n", "new"), "# Log\n\nnew\n");
+        assert_eq!(prepend_text("", "new"), "new\n");
+    }
 
-        append_to_note(&path, "quick thought");
+    #[test]
+    fn test_cli_prepend() {
+        let mut cli = Cli::parse_from(["kno", "changelog", "--prepend", "- v1.2", "--timestamp"]);
+        cli.normalize();
+        assert_eq!(cli.prepend.as_deref(), Some("- v1.2"));
+        assert!(cli.timestamp);
 
-        let content = fs::read_to_string(&path).unwrap();
-        assert!(content.ends_with("quick thought\n"));
+        let mut cli = Cli::parse_from(["kno", "changelog", "--prepend", "--clip"]);
+        cli.normalize();
+        assert!(cli.clip);
+        assert!(Cli::try_parse_from(["kno", "-a", "x", "--prepend", "y"]).is_err());
     }
 
     #[test]
-    fn test_append_creates_note_if_new() {
-        let tmp = tempfile::TempDir::new().unwrap();
-        let path = open_note(tmp.path(), Some("new-note"));
-
-        append_to_note(&path, "first entry");
+    fn test_insert_at_match() {
+        let note = "# Reading\n\n## Links\n\n- old\n\n## Books\n- Dune\n";
+        let links = Regex::new("^## Links").unwrap();
+        assert_eq!(
+            insert_at_match(note, &links, "- new", true).unwrap(),
+            "# Reading\n\n## Links\n\n- new\n- old\n\n## Books\n- Dune\n"
+        );
+        let books = Regex::new("## Books").unwrap();
+        assert_eq!(
+            insert_at_match(note, &books, "- Emma", true).unwrap(),
+            "# Reading\n\n## Links\n\n- old\n\n## Books\n- Emma\n- Dune\n"
+        );
+        assert_eq!(
+            insert_at_match(note, &books, "---", false).unwrap(),
+            "# Reading\n\n## Links\n\n- old\n\n---\n## Books\n- Dune\n"
+        );
+        let missing = Regex::new("## Films").unwrap();
+        assert_eq!(insert_at_match(note, &missing, "x", true), None);
+    }
 
-        let content = fs::read_to_string(&path).unwrap();
-        assert_eq!(content, "# New Note\n\nfirst entry\n");
+    #[test]
+    fn test_cli_after_and_before() {
+        let cli = Cli::parse_from(["kno", "reading", "-a", "- x", "--after", "## Links"]);
+        assert_eq!(cli.after.unwrap().as_str(), "## Links");
+        assert!(Cli::try_parse_from(["kno", "r", "-a", "x", "--before", "("]).is_err());
+        assert!(Cli::try_parse_from(["kno", "r", "--prepend", "x", "--after", "a"]).is_err());
+        assert!(Cli::try_parse_from(["kno", "r", "--prepend", "x", "--before", "a"]).is_err());
+        assert!(
+            Cli::try_parse_from(["kno", "r", "-a", "x", "--after", "a", "--before", "b"]).is_err()
+        );
     }
 
     #[test]
@@ -509,13 +5483,13 @@ mod tests {
     fn test_cli_parses_hyphen_append() {
         let cli = Cli::parse_from(["kno", "-a", "- my note"]);
         assert_eq!(cli.append.as_deref(), Some("- my note"));
-        assert!(cli.path.is_none());
+        assert!(cli.paths.is_empty());
     }
 
     #[test]
     fn test_cli_parses_hyphen_append_with_path() {
         let cli = Cli::parse_from(["kno", "sql/joins", "-a", "- todo item"]);
-        assert_eq!(cli.path.as_deref(), Some("sql/joins"));
+        assert_eq!(cli.paths, ["sql/joins"]);
         assert_eq!(cli.append.as_deref(), Some("- todo item"));
     }
 
@@ -543,7 +5517,7 @@ mod tests {
         let tmp = tempfile::TempDir::new().unwrap();
         setup_test_notes(tmp.path());
 
-        let output = list_notes(tmp.path(), None, None);
+        let output = list_to_string(tmp.path(), None, None);
         let expected = [
             ".",
             "├── daily/",
@@ -565,7 +5539,7 @@ mod tests {
         let tmp = tempfile::TempDir::new().unwrap();
         setup_test_notes(tmp.path());
 
-        let output = list_notes(tmp.path(), None, Some(1));
+        let output = list_to_string(tmp.path(), None, Some(1));
         assert_eq!(
             output,
             "\
@@ -581,7 +5555,7 @@ mod tests {
         let tmp = tempfile::TempDir::new().unwrap();
         setup_test_notes(tmp.path());
 
-        let output = list_notes(tmp.path(), Some("my-project"), None);
+        let output = list_to_string(tmp.path(), Some("my-project"), None);
         assert_eq!(
             output,
             "\
@@ -591,11 +5565,160 @@ my-project\n\
         );
     }
 
+    #[test]
+    fn test_list_with_attachments() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        setup_test_notes(tmp.path());
+        fs::write(tmp.path().join("my-project/diagram.PNG"), "").unwrap();
+        fs::write(tmp.path().join("my-project/spec.pdf"), "").unwrap();
+        fs::write(tmp.path().join("my-project/.DS_Store"), "").unwrap();
+
+        let mut out = Vec::new();
+        list_notes(tmp.path(), Some("my-project"), None, true, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+my-project\n\
+├── design-decisions.md\n\
+├── diagram.PNG [image]\n\
+├── ideas.md\n\
+└── spec.pdf [pdf]\n"
+        );
+    }
+
+    #[test]
+    fn test_filtered_notes_by_tag_and_since() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        setup_test_notes(tmp.path());
+        fs::write(
+            tmp.path().join("sql/joins.md"),
+            "---\ntags: [sql]\n---\n# Joins\n",
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("my-project/ideas.md"),
+            "---\ntags: [sql, ideas]\n---\n# Ideas\n",
+        )
+        .unwrap();
+
+        let notes = filtered_notes(tmp.path(), None, Some("#sql"), None, SortKey::Name);
+        assert_eq!(
+            notes,
+            [
+                PathBuf::from("my-project/ideas.md"),
+                PathBuf::from("sql/joins.md")
+            ]
+        );
+
+        let scoped = filtered_notes(tmp.path(), Some("sql"), Some("sql"), None, SortKey::Name);
+        assert_eq!(scoped, [PathBuf::from("sql/joins.md")]);
+
+        let tomorrow = Local::now().date_naive() + chrono::Days::new(1);
+        assert!(filtered_notes(tmp.path(), None, None, Some(tomorrow), SortKey::Name).is_empty());
+    }
+
+    #[test]
+    fn test_filtered_notes_sorted_by_modified() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        setup_test_notes(tmp.path());
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        for note in [
+            "daily/2026/2026-02-15.md",
+            "my-project/ideas.md",
+            "my-project/design-decisions.md",
+        ] {
+            fs::File::options()
+                .write(true)
+                .open(tmp.path().join(note))
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+        }
+
+        let notes = filtered_notes(tmp.path(), None, None, None, SortKey::Modified);
+        assert_eq!(notes[0], PathBuf::from("sql/joins.md"));
+    }
+
+    #[test]
+    fn test_render_tree_from_paths() {
+        let paths = [
+            PathBuf::from("my-project/ideas.md"),
+            PathBuf::from("sql/joins.md"),
+            PathBuf::from("inbox.md"),
+        ];
+        let expected = [
+            ".",
+            "├── my-project/",
+            "│   └── ideas.md",
+            "├── sql/",
+            "│   └── joins.md",
+            "└── inbox.md",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(render_tree(".", &paths, None), expected);
+        assert_eq!(
+            render_tree(".", &paths, Some(1)),
+            ".\n├── my-project/\n├── sql/\n└── inbox.md\n"
+        );
+    }
+
+    #[test]
+    fn test_saved_search_roundtrip() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let query = SearchQuery {
+            query: "-- TODO".to_string(),
+            path: Some("inbox".to_string()),
+            syntax: search::Syntax {
+                ignore_case: true,
+                ..search::Syntax::default()
+            },
+            since: query::parse_date("2024-01").ok(),
+            until: None,
+            filter: Some("tag:work OR path:inbox".to_string()),
+        };
+        config::save_search(tmp.path(), "inbox-actions", &query.to_args()).unwrap();
+
+        let saved = SearchQuery::saved(tmp.path(), "inbox-actions").unwrap();
+        assert_eq!(saved.to_args(), query.to_args());
+        assert_eq!(saved.query, "-- TODO");
+        assert_eq!(saved.path.as_deref(), Some("inbox"));
+        assert!(saved.syntax.ignore_case && !saved.syntax.regex);
+        assert!(SearchQuery::saved(tmp.path(), "missing").is_err());
+    }
+
+    #[test]
+    fn test_parse_until_arg() {
+        let until = |s: &str| parse_until_arg(s).unwrap().to_string();
+        assert_eq!(until("2024-06-15"), "2024-06-15");
+        assert_eq!(until("2024-06"), "2024-06-30");
+        assert_eq!(until("2024-02"), "2024-02-29");
+        assert_eq!(until("2024"), "2024-12-31");
+        assert!(parse_until_arg("june").is_err());
+    }
+
+    #[test]
+    fn test_cli_parses_list_filters() {
+        let cli = Cli::parse_from([
+            "kno", "list", "--tag", "rust", "--since", "2024-01", "--sort", "modified",
+        ]);
+        match &cli.command {
+            Some(Command::List {
+                tag, since, sort, ..
+            }) => {
+                assert_eq!(tag.as_deref(), Some("rust"));
+                assert_eq!(since.unwrap().to_string(), "2024-01-01");
+                assert_eq!(*sort, SortKey::Modified);
+            }
+            _ => panic!("expected List command"),
+        }
+    }
+
     #[test]
     fn test_list_empty_dir() {
         let tmp = tempfile::TempDir::new().unwrap();
 
-        let output = list_notes(tmp.path(), None, None);
+        let output = list_to_string(tmp.path(), None, None);
         assert_eq!(output, ".\n");
     }
 
@@ -621,7 +5744,7 @@ my-project\n\
     fn test_cli_parses_list_with_path() {
         let cli = Cli::parse_from(["kno", "list", "sql"]);
         match &cli.command {
-            Some(Command::List { path, level }) => {
+            Some(Command::List { path, level, .. }) => {
                 assert_eq!(path.as_deref(), Some("sql"));
                 assert_eq!(*level, None);
             }
@@ -680,6 +5803,334 @@ my-project\n\
         assert!(notes_dir.join(".git").exists());
     }
 
+    #[test]
+    fn test_cli_parses_meeting_attendees() {
+        let cli = Cli::parse_from(["kno", "meeting", "standup", "--attendees", "ana,bo"]);
+        match &cli.command {
+            Some(Command::Meeting { name, attendees }) => {
+                assert_eq!(name, "standup");
+                assert_eq!(attendees, &["ana", "bo"]);
+            }
+            _ => panic!("expected Meeting command"),
+        }
+    }
+
+    #[test]
+    fn test_meeting_note_created_and_linked_from_daily() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let today = Local::now().format("%Y-%m-%d").to_string();
+        let attendees = vec!["ana".to_string(), "bo".to_string()];
+
+        let (path, _) = create_meeting_note(tmp.path(), "standup", &attendees);
+
+        assert_eq!(
+            path,
+            tmp.path().join(format!("meetings/{today}-standup.md"))
+        );
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# Standup\n\nDate: "));
+        assert!(content.contains("- ana\n- bo"));
+        assert!(content.contains("## Agenda"));
+        assert!(content.contains("## Action Items"));
+
+        let daily = fs::read_to_string(open_note(tmp.path(), None)).unwrap();
+        assert!(daily.contains(&format!("- [Standup](../../meetings/{today}-standup.md)")));
+    }
+
+    #[test]
+    fn test_meeting_note_links_once() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        create_meeting_note(tmp.path(), "standup", &[]);
+        create_meeting_note(tmp.path(), "standup", &[]);
+
+        let daily = fs::read_to_string(open_note(tmp.path(), None)).unwrap();
+        assert_eq!(daily.matches("[Standup]").count(), 1);
+    }
+
+    #[test]
+    fn test_cli_parses_prev() {
+        let cli = Cli::parse_from(["kno", "prev", "-p"]);
+        assert!(matches!(cli.command, Some(Command::Prev { print: true })));
+    }
+
+    #[test]
+    fn test_cli_parses_last() {
+        let cli = Cli::parse_from(["kno", "last"]);
+        assert!(matches!(cli.command, Some(Command::Last { print: false })));
+    }
+
+    #[test]
+    fn test_cli_parses_history_opened() {
+        let cli = Cli::parse_from(["kno", "history", "--opened", "-n", "5"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::History {
+                opened: true,
+                limit: 5,
+                format: None
+            })
+        ));
+    }
+
+    #[test]
+    fn test_cli_parses_pick_print0_multi() {
+        let cli = Cli::parse_from(["kno", "pick", "--print0", "--multi"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Pick {
+                print0: true,
+                multi: true,
+                print: false,
+                ..
+            })
+        ));
+        assert!(Cli::try_parse_from(["kno", "pick", "--print", "--print0"]).is_err());
+        assert!(Cli::try_parse_from(["kno", "pick", "--send"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parses_migrate_map_or_daily() {
+        let cli = Cli::parse_from(["kno", "migrate", "--map", "moves.toml"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Migrate { target: None, map: Some(ref map) }) if map == std::path::Path::new("moves.toml")
+        ));
+        let cli = Cli::parse_from(["kno", "migrate", "daily", "--to", "flat"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Migrate {
+                target: Some(MigrateTarget::Daily { .. }),
+                map: None
+            })
+        ));
+        assert!(Cli::try_parse_from(["kno", "migrate"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parses_export_format_or_profile() {
+        let cli = Cli::parse_from(["kno", "export", "--profile", "thesis", "--in", "thesis"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Export { profile: Some(ref p), format: None, path: None, profile_path: Some(ref d), .. })
+                if p == "thesis" && d == "thesis"
+        ));
+        let cli = Cli::parse_from(["kno", "export", "org", "projects"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Export { format: Some(export::Format::Org), profile: None, path: Some(ref p), .. })
+                if p == "projects"
+        ));
+        assert!(Cli::try_parse_from(["kno", "export"]).is_err());
+        assert!(Cli::try_parse_from(["kno", "export", "pdf"]).is_err());
+        assert!(Cli::try_parse_from(["kno", "export", "--profile", "x", "thesis"]).is_err());
+        assert!(
+            Cli::try_parse_from(["kno", "export", "--profile", "x", "docx", "projects"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["kno", "export", "org", "--in", "projects"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parses_recent_format() {
+        let cli = Cli::parse_from(["kno", "recent", "--format", r"{path}\t{title}"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::History { format: Some(ref f), .. }) if f == r"{path}\t{title}"
+        ));
+        assert!(Cli::try_parse_from(["kno", "todo", "--format", "{title}"]).is_err());
+        assert!(Cli::try_parse_from(["kno", "search", "x", "-c", "--format", "{path}"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parses_multiple_paths() {
+        let cli = Cli::parse_from(["kno", "sql/joins", "sql/indexes"]);
+        assert_eq!(cli.paths, ["sql/joins", "sql/indexes"]);
+        assert!(cli.command.is_none());
+    }
+
+    #[test]
+    fn test_cli_parses_open_all() {
+        let cli = Cli::parse_from(["kno", "open", "--all", "projects/foo/*"]);
+        match &cli.command {
+            Some(Command::Open { paths, all, .. }) => {
+                assert!(paths.is_empty());
+                assert_eq!(all, &["projects/foo/*"]);
+            }
+            _ => panic!("expected Open command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parses_editor_args() {
+        let cli = Cli::parse_from(["kno", "daily", "--", "-O", "other.md"]);
+        assert_eq!(cli.paths, ["daily"]);
+        assert_eq!(cli.editor_args, ["-O", "other.md"]);
+    }
+
+    #[test]
+    fn test_cli_parses_editor_args_with_completer() {
+        let mut cmd = Cli::command()
+            .mut_arg("paths", |a| {
+                a.add(ArgValueCompleter::new(PathCompleter::any()))
+            })
+            .mut_arg("editor_args", |a| a);
+        let matches = cmd
+            .try_get_matches_from_mut(["kno", "foo", "--", "+3"])
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        assert_eq!(cli.paths, ["foo"]);
+        assert_eq!(cli.editor_args, ["+3"]);
+    }
+
+    #[test]
+    fn test_completers_keep_cli_valid() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        with_completers(Cli::command(), tmp.path()).debug_assert();
+        let mut cmd = with_completers(Cli::command(), tmp.path());
+
+        let matches = cmd
+            .try_get_matches_from_mut(["kno", "open", "a", "b", "--", "+3"])
+            .unwrap();
+        match Cli::from_arg_matches(&matches).unwrap().command {
+            Some(Command::Open {
+                paths, editor_args, ..
+            }) => {
+                assert_eq!(paths, ["a", "b"]);
+                assert_eq!(editor_args, ["+3"]);
+            }
+            _ => panic!("expected Open command"),
+        }
+
+        let matches = cmd
+            .try_get_matches_from_mut(["kno", "tag", "rename", "old", "new"])
+            .unwrap();
+        match Cli::from_arg_matches(&matches).unwrap().command {
+            Some(Command::Tag {
+                action: TagAction::Rename { old, new },
+            }) => assert_eq!((old.as_str(), new.as_str()), ("old", "new")),
+            _ => panic!("expected Tag Rename command"),
+        }
+    }
+
+    #[test]
+    fn test_note_paths_complete_in_subcommands() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("sql")).unwrap();
+        fs::write(tmp.path().join("sql/joins.md"), "").unwrap();
+        let mut cmd = with_completers(Cli::command(), tmp.path());
+
+        let complete = |cmd: &mut clap::Command, args: &[&str]| {
+            let args: Vec<std::ffi::OsString> = args.iter().map(Into::into).collect();
+            let index = args.len() - 1;
+            clap_complete::engine::complete(cmd, args, index, None)
+                .unwrap()
+                .iter()
+                .map(|c| c.get_value().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert!(complete(&mut cmd, &["kno", "share", "sql/j"]).contains(&"sql/joins.md".into()));
+        assert!(
+            complete(&mut cmd, &["kno", "tag", "add", "sql/j"]).contains(&"sql/joins.md".into())
+        );
+        assert_eq!(complete(&mut cmd, &["kno", "list", "s"]), ["sql/"]);
+        assert!(complete(&mut cmd, &["kno", "mv", "sql/j"]).contains(&"sql/joins.md".into()));
+        assert!(complete(&mut cmd, &["kno", "mv", "x", "s"]).contains(&"sql/".into()));
+    }
+
+    #[test]
+    fn test_subcommand_conflict() {
+        let conflict = |args: &[&str]| subcommand_conflict(&Cli::parse_from(args));
+        assert_eq!(conflict(&["kno", "foo", "list"]), None);
+        assert_eq!(conflict(&["kno", "--dry-run", "list"]), None);
+        assert_eq!(
+            conflict(&["kno", "foo", "--dry-run", "list"]).as_deref(),
+            Some("note path 'foo' can't be combined with a subcommand")
+        );
+        assert!(conflict(&["kno", "-p", "list"]).is_some());
+    }
+
+    #[test]
+    fn test_cli_rejects_zero_habit_days() {
+        assert!(Cli::try_parse_from(["kno", "habit", "status", "--days", "0"]).is_err());
+        assert!(Cli::try_parse_from(["kno", "habit", "status", "--days", "7"]).is_ok());
+    }
+
+    #[test]
+    fn test_cli_parses_editor_args_without_path() {
+        let cli = Cli::parse_from(["kno", "--", "+/pattern"]);
+        assert!(cli.paths.is_empty());
+        assert_eq!(cli.editor_args, ["+/pattern"]);
+    }
+
+    #[test]
+    fn test_cli_parses_open_with_app() {
+        let cli = Cli::parse_from(["kno", "open-with", "--app", "zathura", "docs/spec.pdf"]);
+        match &cli.command {
+            Some(Command::OpenWith { path, system, app }) => {
+                assert_eq!(path, "docs/spec.pdf");
+                assert!(!system);
+                assert_eq!(app.as_deref(), Some("zathura"));
+            }
+            _ => panic!("expected OpenWith command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_open_with_system_conflicts_with_app() {
+        let result = Cli::try_parse_from(["kno", "open-with", "--system", "--app", "x", "foo"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_resolve_existing_prefers_exact_file() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("assets")).unwrap();
+        fs::write(tmp.path().join("assets/diagram.png"), "").unwrap();
+        fs::write(tmp.path().join("foo.md"), "# Foo\n").unwrap();
+
+        assert_eq!(
+            resolve_existing(tmp.path(), "assets/diagram.png"),
+            Some(tmp.path().join("assets/diagram.png"))
+        );
+        assert_eq!(
+            resolve_existing(tmp.path(), "foo"),
+            Some(tmp.path().join("foo.md"))
+        );
+        assert_eq!(resolve_existing(tmp.path(), "missing"), None);
+    }
+
+    #[test]
+    fn test_opener_cmd_with_app() {
+        let cmd = opener_cmd(Some("zathura"), std::path::Path::new("/n/spec.pdf"));
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args.last().unwrap().to_str(), Some("/n/spec.pdf"));
+    }
+
+    #[test]
+    fn test_open_notes_multiple() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let paths = vec!["sql/joins".to_string(), "sql/indexes".to_string()];
+
+        let (files, cursor) = open_notes(tmp.path(), &paths, Local::now().date_naive());
+
+        assert_eq!(
+            files,
+            [
+                tmp.path().join("sql/joins.md"),
+                tmp.path().join("sql/indexes.md")
+            ]
+        );
+        assert!(files.iter().all(|f| f.exists()));
+        assert_eq!(cursor, None);
+    }
+
+    #[test]
+    fn test_open_notes_defaults_to_daily() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (files, _) = open_notes(tmp.path(), &[], Local::now().date_naive());
+        assert_eq!(files.len(), 1);
+        assert!(files[0].starts_with(tmp.path().join("daily")));
+    }
+
     #[test]
     fn test_list_hides_dot_dirs() {
         let tmp = tempfile::TempDir::new().unwrap();
@@ -689,7 +6140,7 @@ my-project\n\
         fs::create_dir_all(tmp.path().join(".git")).unwrap();
         fs::create_dir_all(tmp.path().join(".templates")).unwrap();
 
-        let output = list_notes(tmp.path(), None, None);
+        let output = list_to_string(tmp.path(), None, None);
         assert!(!output.contains(".git"));
         assert!(!output.contains(".templates"));
         // But regular dirs still show
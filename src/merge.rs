@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use crate::frontmatter;
+
+/// A note's lines before its first `## ` heading (`heading: None`), or one
+/// `## ` section.
+#[derive(Debug)]
+struct Block<'a> {
+    heading: Option<&'a str>,
+    lines: Vec<&'a str>,
+}
+
+/// Split a note body into its preamble and `## ` sections.
+fn blocks(body: &str) -> Vec<Block<'_>> {
+    let mut blocks = vec![Block {
+        heading: None,
+        lines: Vec::new(),
+    }];
+    for line in body.lines() {
+        match line.starts_with("## ") {
+            true => blocks.push(Block {
+                heading: Some(line),
+                lines: Vec::new(),
+            }),
+            false => blocks.last_mut().unwrap().lines.push(line),
+        }
+    }
+    blocks
+}
+
+fn lines_of<'a>(blocks: &[Block<'a>], heading: Option<&str>) -> Vec<&'a str> {
+    blocks
+        .iter()
+        .find(|b| b.heading == heading)
+        .map(|b| b.lines.clone())
+        .unwrap_or_default()
+}
+
+/// The `HH:MM` an entry starts with, after any bullet or checkbox
+/// (`- 14:32 text`, `- [ ] 09:05 call`, `14:32 text`).
+fn timestamp(line: &str) -> Option<&str> {
+    let rest = line.trim_start();
+    let rest = rest
+        .strip_prefix(['-', '*', '+'])
+        .map_or(rest, str::trim_start);
+    let rest = ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find_map(|b| rest.strip_prefix(b))
+        .unwrap_or(rest);
+    let time = rest.get(..5)?;
+    let bytes = time.as_bytes();
+    let valid = bytes[2] == b':'
+        && [0, 1, 3, 4].iter().all(|&i| bytes[i].is_ascii_digit())
+        && rest[5..].chars().next().is_none_or(char::is_whitespace);
+    valid.then_some(time)
+}
+
+/// Remove one occurrence of `line` from a line multiset, returning whether it
+/// was there.
+fn take(counts: &mut HashMap<&str, usize>, line: &str) -> bool {
+    match counts.get_mut(line) {
+        Some(n) if *n > 0 => {
+            *n -= 1;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn counts<'a>(lines: &[&'a str]) -> HashMap<&'a str, usize> {
+    let mut counts = HashMap::new();
+    for line in lines {
+        *counts.entry(*line).or_default() += 1;
+    }
+    counts
+}
+
+/// Merge one block: our lines, minus lines they deleted, plus lines they
+/// added. Added entries with a time go in time order among ours; the rest
+/// go at the end, before trailing blank lines.
+fn merge_lines<'a>(base: &[&'a str], ours: &[&'a str], theirs: &[&'a str]) -> Vec<&'a str> {
+    // Lines from base that they no longer have were deleted (or edited) by them
+    let mut theirs_left = counts(theirs);
+    let mut deleted = counts(base);
+    for line in base {
+        if take(&mut theirs_left, line) {
+            take(&mut deleted, line);
+        }
+    }
+    let mut merged: Vec<&str> = ours
+        .iter()
+        .copied()
+        .filter(|line| !take(&mut deleted, line))
+        .collect();
+
+    // Lines they have that neither we nor base do were added by them
+    let mut known = counts(ours);
+    for (line, n) in counts(base) {
+        *known.entry(line).or_default() += n;
+    }
+    let added: Vec<&str> = theirs
+        .iter()
+        .copied()
+        .filter(|line| !take(&mut known, line))
+        .collect();
+
+    // Keep trailing blank lines last, but a section's first blank line (after
+    // its heading) first
+    let content_end = merged
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(merged.len().min(1), |i| i + 1);
+    let mut trailing = merged.split_off(content_end);
+    for line in added {
+        // Their blank lines only matter in a block we don't have
+        if line.trim().is_empty() && !ours.is_empty() {
+            continue;
+        }
+        let position = timestamp(line).and_then(|time| {
+            let timed: Vec<_> = merged
+                .iter()
+                .enumerate()
+                .filter_map(|(i, l)| Some((i, timestamp(l)?)))
+                .collect();
+            let (first, _) = *timed.first()?;
+            Some(match timed.iter().rev().find(|(_, t)| *t <= time) {
+                Some((i, _)) => i + 1,
+                None => first,
+            })
+        });
+        match position {
+            Some(i) => merged.insert(i, line),
+            None => merged.push(line),
+        }
+    }
+    merged.append(&mut trailing);
+    merged
+}
+
+/// Merge two versions of an append-style note (a daily note) that both
+/// changed since `base`, keeping every entry either side added. Entries
+/// with an `HH:MM` time are ordered by it; our frontmatter wins.
+pub fn union(base: &str, ours: &str, theirs: &str) -> String {
+    use frontmatter::body;
+    let front = &ours[..ours.len() - body(ours).len()];
+    let (base, ours, theirs) = (blocks(body(base)), blocks(body(ours)), blocks(body(theirs)));
+
+    let mut headings: Vec<Option<&str>> = ours.iter().map(|b| b.heading).collect();
+    for block in &theirs {
+        if !headings.contains(&block.heading) {
+            headings.push(block.heading);
+        }
+    }
+
+    let mut out = front.to_string();
+    for heading in headings {
+        let lines = merge_lines(
+            &lines_of(&base, heading),
+            &lines_of(&ours, heading),
+            &lines_of(&theirs, heading),
+        );
+        if let Some(heading) = heading {
+            // A section only they added needs a blank line before it
+            if !out.is_empty() && !out.ends_with("\n\n") {
+                out.push('\n');
+            }
+            out.push_str(heading);
+            out.push('\n');
+        }
+        for line in lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "# 2025-03-01\n\n## Log\n\n- 09:00 standup\n\n## Tasks\n\n";
+
+    #[test]
+    fn test_union_orders_both_sides_appends_by_time() {
+        let ours = "# 2025-03-01\n\n## Log\n\n- 09:00 standup\n- 10:15 review\n- 12:00 lunch\n\n## Tasks\n\n";
+        let theirs = "# 2025-03-01\n\n## Log\n\n- 09:00 standup\n- 11:30 call with Bo\n\n## Tasks\n\n- [ ] send notes\n";
+        assert_eq!(
+            union(BASE, ours, theirs),
+            "# 2025-03-01\n\n## Log\n\n- 09:00 standup\n- 10:15 review\n- 11:30 call with Bo\n- 12:00 lunch\n\n## Tasks\n\n- [ ] send notes\n"
+        );
+    }
+
+    #[test]
+    fn test_union_appends_untimed_lines_and_new_sections() {
+        let ours = "# 2025-03-01\n\n## Log\n\n- 09:00 standup\nmine\n\n## Tasks\n\n";
+        let theirs = "# 2025-03-01\n\n## Log\n\n- 09:00 standup\ntheirs\n\n## Tasks\n\n## Links\n\nhttps://example.com\n";
+        assert_eq!(
+            union(BASE, ours, theirs),
+            "# 2025-03-01\n\n## Log\n\n- 09:00 standup\nmine\ntheirs\n\n## Tasks\n\n## Links\n\nhttps://example.com\n"
+        );
+    }
+
+    #[test]
+    fn test_union_honours_deletions_and_edits() {
+        let base = "# Day\n\n- [ ] a\n- [ ] b\n- [ ] c\n";
+        let ours = "# Day\n\n- [ ] a\n- [ ] c\n- [ ] d\n";
+        let theirs = "# Day\n\n- [ ] a\n- [x] b\n- [ ] c\n";
+        // We deleted b; they checked it off, which is also a change to b
+        assert_eq!(
+            union(base, ours, theirs),
+            "# Day\n\n- [ ] a\n- [ ] c\n- [ ] d\n- [x] b\n"
+        );
+        let theirs = "# Day\n\n- [ ] b\n- [ ] c\n";
+        assert_eq!(union(base, ours, theirs), "# Day\n\n- [ ] c\n- [ ] d\n");
+    }
+
+    #[test]
+    fn test_union_keeps_our_frontmatter() {
+        let base = "---\nupdated: 1\n---\n# Day\n";
+        let ours = "---\nupdated: 2\n---\n# Day\nmine\n";
+        let theirs = "---\nupdated: 3\n---\n# Day\ntheirs\n";
+        assert_eq!(
+            union(base, ours, theirs),
+            "---\nupdated: 2\n---\n# Day\nmine\ntheirs\n"
+        );
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp("- 14:32 text"), Some("14:32"));
+        assert_eq!(timestamp("- [ ] 09:05 call"), Some("09:05"));
+        assert_eq!(timestamp("14:32"), Some("14:32"));
+        assert_eq!(timestamp("- 14:32pm"), None);
+        assert_eq!(timestamp("- call at 14:32"), None);
+        assert_eq!(timestamp("- é"), None);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::{links, vault};
+
+/// Read a mapping file: a TOML table of old path prefixes to new ones, like
+/// `"work/acme" = "clients/acme"`.
+pub fn read_map(path: &Path) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+    let table: BTreeMap<String, String> =
+        toml::from_str(&content).map_err(|e| format!("{}: {e}", path.display()))?;
+    if table.is_empty() {
+        return Err(format!("{} maps nothing", path.display()));
+    }
+    table
+        .into_iter()
+        .map(|(from, to)| {
+            let (from, to) = (from.trim_end_matches('/'), to.trim_end_matches('/'));
+            for prefix in [from, to] {
+                let normal = Path::new(prefix)
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_)));
+                if prefix.is_empty() || !normal {
+                    return Err(format!(
+                        "{prefix:?} isn't a path inside the notes dir (in {})",
+                        path.display()
+                    ));
+                }
+            }
+            Ok((PathBuf::from(from), PathBuf::from(to)))
+        })
+        .collect()
+}
+
+/// Where `file` goes under `map`: the longest matching old prefix is swapped
+/// for its new one. A prefix naming a note without `.md` moves just that note.
+fn target(file: &Path, map: &[(PathBuf, PathBuf)]) -> Option<PathBuf> {
+    map.iter()
+        .filter_map(|(from, to)| {
+            let from_note = from.with_extension("md");
+            match file.strip_prefix(from) {
+                Ok(rest) => Some((from, to.join(rest))),
+                Err(_) if file == from_note => Some((from, to.with_extension("md"))),
+                Err(_) => None,
+            }
+        })
+        .max_by_key(|(from, _)| from.components().count())
+        .map(|(_, to)| to)
+        .filter(|to| to != file)
+}
+
+/// The moves (old path to new, relative to the notes dir) that apply `map`
+/// to the vault. Attachments under a mapped prefix move along with the notes
+/// so links to them keep working. Fails without moving anything if two files
+/// would end up at the same path, or one would land on an existing file.
+pub fn moves(
+    notes_dir: &Path,
+    map: &[(PathBuf, PathBuf)],
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    let moves: Vec<(PathBuf, PathBuf)> = vault::files(notes_dir)
+        .into_iter()
+        .filter_map(|file| {
+            let to = target(&file, map)?;
+            Some((file, to))
+        })
+        .collect();
+
+    let mut targets = HashSet::new();
+    for (from, to) in &moves {
+        if !targets.insert(to) {
+            return Err(format!("more than one file would move to {}", to.display()));
+        }
+        if notes_dir.join(to).exists() {
+            return Err(format!(
+                "can't move {}: {} already exists",
+                from.display(),
+                to.display()
+            ));
+        }
+    }
+    Ok(moves)
+}
+
+/// A note left at `old` pointing readers to where it moved.
+pub fn redirect_stub(old: &Path, new: &Path, title: &str) -> String {
+    format!(
+        "# {title}\n\nMoved to [{}]({})\n",
+        new.with_extension("").display(),
+        links::relative_link(old, new)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, rel: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    fn map(entries: &[(&str, &str)]) -> Vec<(PathBuf, PathBuf)> {
+        entries
+            .iter()
+            .map(|(from, to)| (PathBuf::from(from), PathBuf::from(to)))
+            .collect()
+    }
+
+    #[test]
+    fn test_read_map() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("map.toml");
+        fs::write(
+            &path,
+            "\"work/acme/\" = \"clients/acme\"\nwork = \"clients/misc\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_map(&path).unwrap(),
+            map(&[("work", "clients/misc"), ("work/acme", "clients/acme")])
+        );
+
+        fs::write(&path, "work = \"../elsewhere\"\n").unwrap();
+        assert!(read_map(&path).is_err());
+        fs::write(&path, "").unwrap();
+        assert!(read_map(&path).is_err());
+    }
+
+    #[test]
+    fn test_moves_use_longest_prefix() {
+        let tmp = TempDir::new().unwrap();
+        write(tmp.path(), "work/acme/kickoff.md");
+        write(tmp.path(), "work/globex/q3.md");
+        write(tmp.path(), "work/todo.md");
+        write(tmp.path(), "workshop.md");
+        write(tmp.path(), "inbox.md");
+
+        let map = map(&[
+            ("work", "clients/misc"),
+            ("work/acme", "clients/acme"),
+            ("inbox", "triage/inbox"),
+        ]);
+        assert_eq!(
+            moves(tmp.path(), &map).unwrap(),
+            [
+                (PathBuf::from("inbox.md"), PathBuf::from("triage/inbox.md")),
+                (
+                    PathBuf::from("work/acme/kickoff.md"),
+                    PathBuf::from("clients/acme/kickoff.md")
+                ),
+                (
+                    PathBuf::from("work/globex/q3.md"),
+                    PathBuf::from("clients/misc/globex/q3.md")
+                ),
+                (
+                    PathBuf::from("work/todo.md"),
+                    PathBuf::from("clients/misc/todo.md")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_moves_take_attachments_along() {
+        let tmp = TempDir::new().unwrap();
+        write(tmp.path(), "work/acme/kickoff.md");
+        write(tmp.path(), "work/acme/img/board.png");
+        write(tmp.path(), "work/acme.md");
+
+        assert_eq!(
+            moves(tmp.path(), &map(&[("work/acme", "clients/acme")])).unwrap(),
+            [
+                (
+                    PathBuf::from("work/acme/img/board.png"),
+                    PathBuf::from("clients/acme/img/board.png")
+                ),
+                (
+                    PathBuf::from("work/acme/kickoff.md"),
+                    PathBuf::from("clients/acme/kickoff.md")
+                ),
+                (
+                    PathBuf::from("work/acme.md"),
+                    PathBuf::from("clients/acme.md")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redirect_stub() {
+        assert_eq!(
+            redirect_stub(
+                Path::new("projects/old/plan.md"),
+                Path::new("projects/new/plan.md"),
+                "Plan"
+            ),
+            "# Plan\n\nMoved to [projects/new/plan](../new/plan.md)\n"
+        );
+    }
+
+    #[test]
+    fn test_moves_refuse_to_overwrite() {
+        let tmp = TempDir::new().unwrap();
+        write(tmp.path(), "work/a.md");
+        write(tmp.path(), "clients/a.md");
+        let err = moves(tmp.path(), &map(&[("work", "clients")])).unwrap_err();
+        assert_eq!(err, "can't move work/a.md: clients/a.md already exists");
+
+        write(tmp.path(), "notes/a.md");
+        let err = moves(tmp.path(), &map(&[("work", "all"), ("notes", "all")])).unwrap_err();
+        assert_eq!(err, "more than one file would move to all/a.md");
+    }
+}
@@ -0,0 +1,46 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::process;
+
+// kno is single-threaded; a thread-local keeps parallel tests independent.
+thread_local! {
+    static SERVER: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Open notes in the Neovim listening on `server` instead of a new editor.
+pub fn set_server(server: Option<PathBuf>) {
+    SERVER.with(|s| *s.borrow_mut() = server);
+}
+
+pub fn server() -> Option<PathBuf> {
+    SERVER.with(|s| s.borrow().clone())
+}
+
+/// `nvim --server SOCKET --remote`, to which `+N` and file arguments are
+/// added as for a new editor. The files open as buffers in that Neovim and
+/// the command returns right away.
+pub fn remote_cmd(server: &Path) -> process::Command {
+    let mut cmd = process::Command::new("nvim");
+    cmd.arg("--server").arg(server).arg("--remote");
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_cmd() {
+        let cmd = remote_cmd(Path::new("/tmp/nvim.sock"));
+        assert_eq!(cmd.get_program(), "nvim");
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["--server", "/tmp/nvim.sock", "--remote"]);
+    }
+
+    #[test]
+    fn test_set_server() {
+        assert_eq!(server(), None);
+        set_server(Some(PathBuf::from("/tmp/nvim.sock")));
+        assert_eq!(server(), Some(PathBuf::from("/tmp/nvim.sock")));
+    }
+}
@@ -0,0 +1,192 @@
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+use crate::{frontmatter, tags};
+
+/// An Org link target for a markdown link: notes become `file:` links to
+/// their exported `.org` file, other relative paths plain `file:` links.
+fn link_target(dest: &str) -> String {
+    if dest.contains(':') {
+        return dest.to_string();
+    }
+    let path = dest.split('#').next().unwrap_or_default();
+    match path.strip_suffix(".md") {
+        Some(stem) => format!("file:{stem}.org"),
+        None => format!("file:{path}"),
+    }
+}
+
+fn new_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Convert a note's markdown to Org: the title and tags become `#+title:` and
+/// `#+filetags:`, and headings, lists, checkboxes, code blocks, quotes,
+/// tables, emphasis and links get their Org syntax.
+pub fn to_org(content: &str, title: &str) -> String {
+    let mut out = format!("#+title: {title}\n");
+    // Org tags are words, so `work/acme` or `to-read` become `work_acme` and `to_read`
+    let note_tags: Vec<String> = tags::note_tags(content)
+        .iter()
+        .map(|tag| tag.replace(|c: char| !c.is_alphanumeric() && c != '_', "_"))
+        .collect();
+    if !note_tags.is_empty() {
+        out.push_str(&format!("#+filetags: :{}:\n", note_tags.join(":")));
+    }
+    out.push('\n');
+
+    // Each open list: the next number (ordered lists only) and the width of
+    // the current item's marker, which nested lines are indented by
+    let mut lists: Vec<(Option<u64>, usize)> = Vec::new();
+    let mut in_image = false;
+    // The line closing the code block being written, if any
+    let mut code_block_end: Option<&str> = None;
+    let indent = |lists: &[(Option<u64>, usize)]| -> String {
+        " ".repeat(lists.iter().map(|(_, width)| width).sum())
+    };
+    let end_block = |out: &mut String, lists: &[(Option<u64>, usize)]| {
+        new_line(out);
+        if lists.is_empty() {
+            out.push('\n');
+        }
+    };
+
+    for event in Parser::new_ext(frontmatter::body(content), Options::all()) {
+        if in_image {
+            in_image = !matches!(event, Event::End(TagEnd::Image));
+            continue;
+        }
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                let level = match level {
+                    HeadingLevel::H1 => 1,
+                    HeadingLevel::H2 => 2,
+                    HeadingLevel::H3 => 3,
+                    HeadingLevel::H4 => 4,
+                    HeadingLevel::H5 => 5,
+                    HeadingLevel::H6 => 6,
+                };
+                out.push_str(&format!("{} ", "*".repeat(level)));
+            }
+            Event::End(TagEnd::Heading(_)) => end_block(&mut out, &lists),
+            Event::Start(Tag::Paragraph) => {}
+            Event::End(TagEnd::Paragraph) => end_block(&mut out, &lists),
+            Event::Start(Tag::List(start)) => {
+                new_line(&mut out);
+                lists.push((start, 0));
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                end_block(&mut out, &lists);
+            }
+            Event::Start(Tag::Item) => {
+                new_line(&mut out);
+                let depth = lists.len() - 1;
+                out.push_str(&indent(&lists[..depth]));
+                let marker = match &mut lists[depth].0 {
+                    Some(n) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    None => "- ".to_string(),
+                };
+                lists[depth].1 = marker.len();
+                out.push_str(&marker);
+            }
+            Event::End(TagEnd::Item) => new_line(&mut out),
+            Event::TaskListMarker(checked) => out.push_str(match checked {
+                true => "[X] ",
+                false => "[ ] ",
+            }),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                new_line(&mut out);
+                match kind {
+                    CodeBlockKind::Fenced(lang) if !lang.is_empty() => {
+                        out.push_str(&format!("#+begin_src {lang}\n"));
+                        code_block_end = Some("#+end_src\n");
+                    }
+                    _ => {
+                        out.push_str("#+begin_example\n");
+                        code_block_end = Some("#+end_example\n");
+                    }
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                new_line(&mut out);
+                out.push_str(code_block_end.take().unwrap_or_default());
+                end_block(&mut out, &lists);
+            }
+            Event::Start(Tag::BlockQuote(_)) => {
+                new_line(&mut out);
+                out.push_str("#+begin_quote\n");
+            }
+            Event::End(TagEnd::BlockQuote(_)) => {
+                let trimmed = out.trim_end_matches('\n').len();
+                out.truncate(trimmed);
+                out.push_str("\n#+end_quote\n");
+                end_block(&mut out, &lists);
+            }
+            Event::Start(Tag::Emphasis) | Event::End(TagEnd::Emphasis) => out.push('/'),
+            Event::Start(Tag::Strong) | Event::End(TagEnd::Strong) => out.push('*'),
+            Event::Start(Tag::Strikethrough) | Event::End(TagEnd::Strikethrough) => out.push('+'),
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                out.push_str("[[");
+                out.push_str(&link_target(&dest_url));
+                out.push_str("][");
+            }
+            Event::End(TagEnd::Link) => out.push_str("]]"),
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                out.push_str(&format!("[[{}]]", link_target(&dest_url)));
+                in_image = true;
+            }
+            Event::End(TagEnd::TableHead) => {
+                out.push_str("|\n|-\n");
+            }
+            Event::End(TagEnd::TableRow) => out.push_str("|\n"),
+            Event::Start(Tag::TableCell) => out.push_str("| "),
+            Event::End(TagEnd::TableCell) => out.push(' '),
+            Event::End(TagEnd::Table) => out.push('\n'),
+            Event::Text(text) => match code_block_end {
+                Some(_) => out.push_str(&text),
+                None => out.push_str(&text.replace('\n', " ")),
+            },
+            Event::Code(code) => out.push_str(&format!("~{code}~")),
+            Event::Html(html) | Event::InlineHtml(html) => out.push_str(&html),
+            Event::SoftBreak => {
+                out.push('\n');
+                out.push_str(&indent(&lists));
+            }
+            Event::HardBreak => {
+                out.push_str("\\\\\n");
+                out.push_str(&indent(&lists));
+            }
+            Event::Rule => out.push_str("-----\n\n"),
+            _ => {}
+        }
+    }
+    format!("{}\n", out.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_org() {
+        let content = "---\ntags: [launch, work/acme]\n---\n# Launch\n\nShip **on time**, see [plan](plan.md#dates) and [site](https://x.io).\n\n## Tasks\n\n- [x] Book venue\n- [ ] Send invites\n  - draft *copy*\n1. First\n2. Second\n\n```sh\necho hi\n```\n\n> quoted `code`\n";
+        assert_eq!(
+            to_org(content, "Launch"),
+            "#+title: Launch\n#+filetags: :launch:work_acme:\n\n* Launch\n\nShip *on time*, see [[file:plan.org][plan]] and [[https://x.io][site]].\n\n** Tasks\n\n- [X] Book venue\n- [ ] Send invites\n  - draft /copy/\n\n1. First\n2. Second\n\n#+begin_src sh\necho hi\n#+end_src\n\n#+begin_quote\nquoted ~code~\n#+end_quote\n"
+        );
+    }
+
+    #[test]
+    fn test_to_org_tables_and_images() {
+        let content = "| a | b |\n|---|---|\n| 1 | 2 |\n\n![diagram](../assets/d.png)\n";
+        assert_eq!(
+            to_org(content, "T"),
+            "#+title: T\n\n| a | b |\n|-\n| 1 | 2 |\n\n[[file:../assets/d.png]]\n"
+        );
+    }
+}
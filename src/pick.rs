@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+
+use crate::{frontmatter, log, vault};
+
+/// Exit code of `kno pick` when nothing was picked, as fzf uses for a
+/// cancelled picker; errors exit 1.
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+/// One picker line per note: its path, a tab and its title, then another tab
+/// and its frontmatter aliases if it has any, so typing `k8s` finds
+/// `tech/kubernetes.md`. Notes opened through kno come first, most recent
+/// first, then the rest by path.
+pub fn candidates(notes_dir: &Path) -> String {
+    let notes = vault::visible_notes(notes_dir);
+    let visible: HashSet<&PathBuf> = notes.iter().collect();
+    let recent: Vec<PathBuf> = vault::opened_history(notes_dir)
+        .into_iter()
+        .filter(|rel| visible.contains(rel))
+        .collect();
+    let rest = notes.iter().filter(|rel| !recent.contains(rel)).cloned();
+    recent
+        .iter()
+        .cloned()
+        .chain(rest)
+        .map(|rel| {
+            let content = fs::read_to_string(notes_dir.join(&rel)).unwrap_or_default();
+            let mut line = format!("{}\t{}", rel.display(), vault::title(&content, &rel));
+            let aliases = frontmatter::get_list(&content, "aliases");
+            if !aliases.is_empty() {
+                line.push_str(&format!("\t{}", aliases.join(" ")));
+            }
+            line + "\n"
+        })
+        .collect()
+}
+
+/// fzf arguments for the picker. With `tmux`, fzf opens in a popup over the
+/// current pane (fzf 0.53 or later); with `multi`, tab marks several notes.
+fn fzf_args(tmux: bool, multi: bool) -> Vec<&'static str> {
+    let mut args = vec!["--delimiter=\t", "--prompt=note> ", "--preview=cat {1}"];
+    if tmux {
+        args.push("--tmux=center,80%");
+    }
+    if multi {
+        args.push("--multi");
+    }
+    args
+}
+
+/// Note paths from the picker lines fzf printed.
+fn selected(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .filter_map(|line| line.split('\t').next())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Let the user choose a note (several with `multi`) with fzf, returning
+/// paths relative to the notes dir. Empty when they cancel or nothing matches.
+pub fn pick(notes_dir: &Path, tmux: bool, multi: bool) -> Result<Vec<PathBuf>, String> {
+    let args = fzf_args(tmux, multi);
+    log::debug!("running fzf {}", args.join(" "));
+    let mut child = process::Command::new("fzf")
+        .args(&args)
+        .current_dir(notes_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run fzf (is it installed?): {e}"))?;
+    let mut stdin = child.stdin.take().expect("fzf stdin is piped");
+    // fzf may exit before reading everything, e.g. when cancelled early
+    let _ = stdin.write_all(candidates(notes_dir).as_bytes());
+    drop(stdin);
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("failed to run fzf: {e}"))?;
+    // 1 is no match and 130 is cancelled; anything else is fzf's own error
+    match output.status.code() {
+        Some(0) => Ok(selected(&String::from_utf8_lossy(&output.stdout))),
+        Some(1 | 130) => Ok(Vec::new()),
+        _ => Err("fzf failed".to_string()),
+    }
+}
+
+/// Open the note at `relative_path` with kno in a new tmux window named
+/// after it.
+pub fn open_in_tmux_window(notes_dir: &Path, relative_path: &Path) -> Result<(), String> {
+    let kno = std::env::current_exe().map_err(|e| format!("failed to find kno: {e}"))?;
+    let name = relative_path.with_extension("");
+    let window_name = name.file_name().unwrap_or_default();
+    tmux(
+        process::Command::new("tmux")
+            .arg("new-window")
+            .arg("-n")
+            .arg(window_name)
+            .arg("-c")
+            .arg(notes_dir)
+            .arg(kno)
+            .arg(&name),
+    )
+}
+
+/// Type `path` into the current tmux pane, without pressing enter.
+pub fn send_to_tmux_pane(path: &Path) -> Result<(), String> {
+    tmux(
+        process::Command::new("tmux")
+            .args(["send-keys", "-l"])
+            .arg(path),
+    )
+}
+
+fn tmux(cmd: &mut process::Command) -> Result<(), String> {
+    log::debug!("running {cmd:?}");
+    let output = cmd
+        .output()
+        .map_err(|e| format!("failed to run tmux: {e}"))?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(format!(
+            "tmux failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_candidates() {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("sql")).unwrap();
+        fs::write(tmp.path().join("sql/joins.md"), "# SQL Joins\n").unwrap();
+        fs::write(tmp.path().join("inbox.md"), "no heading\n").unwrap();
+
+        assert_eq!(
+            candidates(tmp.path()),
+            "inbox.md\tinbox\nsql/joins.md\tSQL Joins\n"
+        );
+    }
+
+    #[test]
+    fn test_candidates_recent_first_with_aliases() {
+        let tmp = TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("tech")).unwrap();
+        fs::write(tmp.path().join("a.md"), "# A\n").unwrap();
+        fs::write(tmp.path().join("b.md"), "# B\n").unwrap();
+        fs::write(
+            tmp.path().join("tech/kubernetes.md"),
+            "---\naliases: [k8s, kube]\n---\n# Kubernetes\n",
+        )
+        .unwrap();
+        vault::record_opened(tmp.path(), &tmp.path().join("b.md"));
+        vault::record_opened(tmp.path(), &tmp.path().join("tech/kubernetes.md"));
+
+        assert_eq!(
+            candidates(tmp.path()),
+            "tech/kubernetes.md\tKubernetes\tk8s kube\nb.md\tB\na.md\tA\n"
+        );
+        assert_eq!(
+            selected("tech/kubernetes.md\tKubernetes\tk8s kube\n"),
+            [PathBuf::from("tech/kubernetes.md")]
+        );
+    }
+
+    #[test]
+    fn test_selected() {
+        assert_eq!(
+            selected("sql/joins.md\tSQL Joins\nnotes with space.md\tx\n"),
+            [
+                PathBuf::from("sql/joins.md"),
+                PathBuf::from("notes with space.md")
+            ]
+        );
+        assert!(selected("").is_empty());
+    }
+
+    #[test]
+    fn test_fzf_args() {
+        assert!(
+            !fzf_args(false, false)
+                .iter()
+                .any(|a| a.starts_with("--tmux"))
+        );
+        assert!(fzf_args(true, false).contains(&"--tmux=center,80%"));
+        assert!(!fzf_args(false, false).contains(&"--multi"));
+        assert!(fzf_args(false, true).contains(&"--multi"));
+    }
+}
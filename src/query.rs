@@ -0,0 +1,316 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::{tags, vault};
+
+/// How a `modified` term compares a note's date.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cmp {
+    Before,
+    OnOrBefore,
+    On,
+    OnOrAfter,
+    After,
+}
+
+/// A parsed query like `tag:rust AND path:projects/* AND modified>2025-01-01`.
+#[derive(Debug, PartialEq)]
+pub enum Query {
+    /// Frontmatter or inline tag.
+    Tag(String),
+    /// Glob matched against the note's path or any directory above it.
+    Path(String),
+    /// Text in the title, ignoring case.
+    Title(String),
+    /// Text anywhere in the note, ignoring case. Bare words mean this too.
+    Content(String),
+    Modified(Cmp, NaiveDate),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+/// What a query is tested against, read once per note.
+struct Note<'a> {
+    rel: &'a Path,
+    content: String,
+    modified: Option<NaiveDate>,
+}
+
+/// Split a query into words, quoted strings (quotes kept, so `"AND"` stays a
+/// word) and parentheses.
+fn tokenize(input: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in input.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if quoted => current.push(c),
+            '(' | ')' => {
+                tokens.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => {
+                tokens.extend((!current.is_empty()).then(|| std::mem::take(&mut current)));
+            }
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        return Err("unclosed quote".to_string());
+    }
+    tokens.extend((!current.is_empty()).then_some(current));
+    Ok(tokens)
+}
+
+/// Parse a `YYYY-MM-DD` date, or the first day of a `YYYY-MM` month or `YYYY`
+/// year. Also used for `--since` and friends.
+pub fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(&format!("{s}-01"), "%Y-%m-%d"))
+        .or_else(|_| NaiveDate::parse_from_str(&format!("{s}-01-01"), "%Y-%m-%d"))
+        .map_err(|_| format!("invalid date `{s}` (expected YYYY-MM-DD, YYYY-MM or YYYY)"))
+}
+
+fn parse_term(token: &str) -> Result<Query, String> {
+    if let Some(rest) = token.strip_prefix("modified") {
+        let ops = [
+            (">=", Cmp::OnOrAfter),
+            ("<=", Cmp::OnOrBefore),
+            (">", Cmp::After),
+            ("<", Cmp::Before),
+            ("=", Cmp::On),
+            (":", Cmp::On),
+        ];
+        if let Some((date, cmp)) = ops
+            .iter()
+            .find_map(|(op, cmp)| Some((rest.strip_prefix(op)?, *cmp)))
+        {
+            return Ok(Query::Modified(cmp, parse_date(date)?));
+        }
+    }
+
+    let unquote = |s: &str| s.trim_matches('"').to_string();
+    let Some((field, value)) = token.split_once(':').filter(|_| !token.starts_with('"')) else {
+        return Ok(Query::Content(unquote(token)));
+    };
+    if value.is_empty() {
+        return Err(format!("`{field}:` needs a value"));
+    }
+    match field {
+        "tag" => Ok(Query::Tag(tags::normalize(&unquote(value)))),
+        "path" => Ok(Query::Path(
+            unquote(value).trim_end_matches('/').to_string(),
+        )),
+        "title" => Ok(Query::Title(unquote(value))),
+        "content" => Ok(Query::Content(unquote(value))),
+        _ => Err(format!(
+            "unknown field `{field}` (expected tag, path, title, content or modified)"
+        )),
+    }
+}
+
+/// Recursive descent over the tokens: OR binds loosest, then AND (also
+/// implied between adjacent terms), then NOT.
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Query, String> {
+        let mut query = self.and()?;
+        while self.peek() == Some("OR") {
+            self.pos += 1;
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut query = self.not()?;
+        loop {
+            match self.peek() {
+                Some("AND") => self.pos += 1,
+                None | Some("OR") | Some(")") => return Ok(query),
+                Some(_) => {}
+            }
+            query = Query::And(Box::new(query), Box::new(self.not()?));
+        }
+    }
+
+    fn not(&mut self) -> Result<Query, String> {
+        match self.next().as_deref() {
+            Some("NOT") => Ok(Query::Not(Box::new(self.not()?))),
+            Some("(") => {
+                let query = self.or()?;
+                match self.next().as_deref() {
+                    Some(")") => Ok(query),
+                    _ => Err("missing `)`".to_string()),
+                }
+            }
+            Some(op @ ("AND" | "OR" | ")")) => Err(format!("unexpected `{op}`")),
+            Some(term) => parse_term(term),
+            None => Err("query ends too soon".to_string()),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, String> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+        };
+        let query = parser.or()?;
+        match parser.peek() {
+            None => Ok(query),
+            Some(token) => Err(format!("unexpected `{token}`")),
+        }
+    }
+
+    fn matches(&self, note: &Note) -> bool {
+        match self {
+            Query::Tag(tag) => tags::all_tags(&note.content).contains(tag),
+            Query::Path(pattern) => note
+                .rel
+                .ancestors()
+                .filter(|p| !p.as_os_str().is_empty())
+                .any(|p| vault::glob_matches(pattern, p)),
+            Query::Title(text) => vault::title(&note.content, note.rel)
+                .to_lowercase()
+                .contains(&text.to_lowercase()),
+            Query::Content(text) => note.content.to_lowercase().contains(&text.to_lowercase()),
+            Query::Modified(cmp, date) => note.modified.is_some_and(|m| match cmp {
+                Cmp::Before => m < *date,
+                Cmp::OnOrBefore => m <= *date,
+                Cmp::On => m == *date,
+                Cmp::OnOrAfter => m >= *date,
+                Cmp::After => m > *date,
+            }),
+            Query::Not(query) => !query.matches(note),
+            Query::And(a, b) => a.matches(note) && b.matches(note),
+            Query::Or(a, b) => a.matches(note) || b.matches(note),
+        }
+    }
+
+    /// The notes in `notes` (relative to the notes dir) that match.
+    pub fn filter(&self, notes_dir: &Path, notes: Vec<PathBuf>) -> Vec<PathBuf> {
+        notes
+            .into_iter()
+            .filter(|rel| {
+                let path = notes_dir.join(rel);
+                let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+                let note = Note {
+                    rel,
+                    content: fs::read_to_string(&path).unwrap_or_default(),
+                    modified: modified.map(|m| DateTime::<Local>::from(m).date_naive()),
+                };
+                self.matches(&note)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let q = |s: &str| Query::parse(s).unwrap();
+        let tag = || Box::new(Query::Tag("rust".to_string()));
+        let content = || Box::new(Query::Content("async fn".to_string()));
+        assert_eq!(
+            q(r#"tag:rust AND content:"async fn""#),
+            Query::And(tag(), content())
+        );
+        // AND is implied, and binds tighter than OR
+        assert_eq!(
+            q(r#"path:inbox OR tag:rust "async fn""#),
+            Query::Or(
+                Box::new(Query::Path("inbox".to_string())),
+                Box::new(Query::And(tag(), content()))
+            )
+        );
+        assert_eq!(
+            q("NOT (tag:rust OR modified>=2025-01)"),
+            Query::Not(Box::new(Query::Or(
+                tag(),
+                Box::new(Query::Modified(Cmp::OnOrAfter, date("2025-01-01")))
+            )))
+        );
+        assert_eq!(q(r#""AND""#), Query::Content("AND".to_string()));
+
+        for bad in [
+            "",
+            "tag:",
+            "foo:bar",
+            "(tag:a",
+            "tag:a )",
+            "a AND",
+            "\"open",
+            "modified>soon",
+        ] {
+            assert!(Query::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2024-06-15").unwrap().to_string(), "2024-06-15");
+        assert_eq!(parse_date("2024-01").unwrap().to_string(), "2024-01-01");
+        assert_eq!(parse_date("2023").unwrap().to_string(), "2023-01-01");
+        assert!(parse_date("last spring").is_err());
+    }
+
+    #[test]
+    fn test_filter() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let write = |rel: &str, content: &str| {
+            let path = tmp.path().join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "projects/kno/async.md",
+            "---\ntags: [rust]\n---\n# Async\n\nasync fn\n",
+        );
+        write("projects/kno/plan.md", "# Plan\n\n#rust later\n");
+        write("rust/book.md", "---\ntags: [rust]\n---\n# The Book\n");
+        let notes = vault::notes(tmp.path());
+        let run = |s: &str| Query::parse(s).unwrap().filter(tmp.path(), notes.clone());
+
+        assert_eq!(
+            run(r#"tag:rust AND path:projects/* AND content:"async""#),
+            [PathBuf::from("projects/kno/async.md")]
+        );
+        assert_eq!(
+            run("tag:rust NOT path:projects"),
+            [PathBuf::from("rust/book.md")]
+        );
+        assert_eq!(run("title:book"), [PathBuf::from("rust/book.md")]);
+        assert_eq!(run("modified>2000-01-01").len(), 3);
+        assert!(run("modified<2000").is_empty());
+    }
+}
@@ -0,0 +1,571 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{self, Stdio};
+
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+
+use crate::config::RemoteConfig;
+use crate::{curl, dedupe, dry_run, log, vault};
+
+/// Object on the remote listing every synced note as `<sha256>\t<path>`, so
+/// syncing never needs to list a bucket or walk a WebDAV tree.
+pub const MANIFEST_NAME: &str = ".kno-manifest";
+
+/// The manifest as of the last sync from this machine, used to tell which
+/// side changed a note since.
+pub const STATE_FILE_NAME: &str = ".remote-sync";
+
+/// Object storage `kno sync --backend` talks to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    /// S3 or an S3-compatible service, with SigV4-signed requests
+    S3,
+    /// A WebDAV server such as Nextcloud
+    Webdav,
+}
+
+/// Where synced notes are kept: whole files by relative path.
+pub trait Store {
+    /// The object at `key`, or `None` if there isn't one.
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), String>;
+    fn delete(&self, key: &str) -> Result<(), String>;
+}
+
+/// A `Store` reached over HTTP with curl.
+pub struct Curl {
+    backend: Backend,
+    url: String,
+    /// A private curl config holding `user:password`, kept off the command
+    /// line (see `curl::secret_config`).
+    credentials: Option<NamedTempFile>,
+    region: String,
+    /// WebDAV collections known to exist, so each is only created once.
+    collections: RefCell<HashSet<String>>,
+}
+
+impl Curl {
+    pub fn new(backend: Backend, config: &RemoteConfig) -> Result<Curl, String> {
+        let url = config
+            .url
+            .as_deref()
+            .ok_or("remote.url is required for kno sync --backend")?;
+        if backend == Backend::S3 && (config.user.is_none() || config.password.is_none()) {
+            return Err(
+                "remote.user (access key) and remote.password (secret key) are required for S3"
+                    .to_string(),
+            );
+        }
+        let credentials = match &config.user {
+            Some(user) => {
+                let password = config.password.as_deref().unwrap_or_default();
+                Some(curl::secret_config(&[(
+                    "user",
+                    &format!("{user}:{password}"),
+                )])?)
+            }
+            None => None,
+        };
+        Ok(Curl {
+            backend,
+            url: url.trim_end_matches('/').to_string(),
+            credentials,
+            region: config.region.clone(),
+            collections: RefCell::new(HashSet::new()),
+        })
+    }
+
+    /// `curl` making a `method` request for `key`, printing the response body
+    /// followed by the three-digit status code.
+    fn cmd(&self, method: &str, key: &str) -> process::Command {
+        let mut cmd = process::Command::new("curl");
+        cmd.args(["--silent", "--show-error", "--request", method]);
+        cmd.args(["--write-out", "%{http_code}"]);
+        if let Some(credentials) = &self.credentials {
+            cmd.arg("--config").arg(credentials.path());
+        }
+        if self.backend == Backend::S3 {
+            cmd.args(["--aws-sigv4", &format!("aws:amz:{}:s3", self.region)]);
+        }
+        cmd.arg(format!("{}/{}", self.url, encode_key(key)));
+        cmd
+    }
+
+    /// Run a request, returning the status code and response body.
+    fn request(
+        &self,
+        method: &str,
+        key: &str,
+        body: Option<&[u8]>,
+    ) -> Result<(u16, Vec<u8>), String> {
+        let mut cmd = self.cmd(method, key);
+        if body.is_some() {
+            cmd.args(["--header", "Content-Type: application/octet-stream"]);
+            cmd.args(["--data-binary", "@-"]);
+        }
+        log::debug!("{method} {key}");
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to run curl: {e}"))?;
+        let mut stdin = child.stdin.take().expect("curl stdin is piped");
+        if let Some(body) = body {
+            stdin
+                .write_all(body)
+                .map_err(|e| format!("failed to upload {key}: {e}"))?;
+        }
+        drop(stdin);
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("failed to run curl: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "{method} {key} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let mut body = output.stdout;
+        let code = body.split_off(body.len().saturating_sub(3));
+        let code = String::from_utf8_lossy(&code).parse().unwrap_or_default();
+        Ok((code, body))
+    }
+
+    /// Create the WebDAV collections `key` lives in; servers won't do it on
+    /// PUT.
+    fn make_collections(&self, key: &str) -> Result<(), String> {
+        for (end, _) in key.match_indices('/') {
+            let dir = key[..end].to_string();
+            if self.collections.borrow().contains(&dir) {
+                continue;
+            }
+            // 405 means the collection is already there
+            match self.request("MKCOL", &format!("{dir}/"), None)? {
+                (200..=299 | 405, _) => {}
+                (code, _) => return Err(format!("creating {dir} failed with HTTP {code}")),
+            }
+            self.collections.borrow_mut().insert(dir.clone());
+        }
+        Ok(())
+    }
+}
+
+impl Store for Curl {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        match self.request("GET", key, None)? {
+            (200..=299, body) => Ok(Some(body)),
+            (404, _) => Ok(None),
+            (code, _) => Err(format!("downloading {key} failed with HTTP {code}")),
+        }
+    }
+
+    fn put(&self, key: &str, bytes: &[u8]) -> Result<(), String> {
+        if self.backend == Backend::Webdav {
+            self.make_collections(key)?;
+        }
+        match self.request("PUT", key, Some(bytes))? {
+            (200..=299, _) => Ok(()),
+            (code, _) => Err(format!("uploading {key} failed with HTTP {code}")),
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<(), String> {
+        match self.request("DELETE", key, None)? {
+            (200..=299 | 404, _) => Ok(()),
+            (code, _) => Err(format!("deleting {key} failed with HTTP {code}")),
+        }
+    }
+}
+
+/// Percent-encode a key for a URL path, keeping its `/` separators.
+fn encode_key(key: &str) -> String {
+    let mut out = String::new();
+    for byte in key.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+type Manifest = BTreeMap<PathBuf, String>;
+
+/// A manifest's entries; paths that could escape the notes dir are dropped.
+fn parse_manifest(content: &str) -> Manifest {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (hash, path) = line.split_once('\t')?;
+            let path = PathBuf::from(path);
+            let safe = path.components().all(|c| matches!(c, Component::Normal(_)));
+            safe.then(|| (path, hash.to_string()))
+        })
+        .collect()
+}
+
+fn format_manifest(manifest: &Manifest) -> String {
+    manifest
+        .iter()
+        .map(|(path, hash)| format!("{hash}\t{}\n", path.display()))
+        .collect()
+}
+
+fn key(relative_path: &Path) -> String {
+    relative_path.to_string_lossy().replace('\\', "/")
+}
+
+/// What a sync does with one note.
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Upload,
+    Download,
+    DeleteRemote,
+    DeleteLocal,
+    /// Both sides changed it: ours is uploaded and theirs saved next to it as
+    /// a conflict copy.
+    Conflict,
+}
+
+/// What to do with each note, given the local hashes, the remote manifest
+/// and the manifest as of the last sync.
+pub fn plan(local: &Manifest, remote: &Manifest, base: &Manifest) -> Vec<(PathBuf, Action)> {
+    let paths: BTreeSet<&PathBuf> = local.keys().chain(remote.keys()).collect();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let (l, r, b) = (local.get(path), remote.get(path), base.get(path));
+            let action = match () {
+                _ if l == r => return None,
+                _ if r == b => match l {
+                    Some(_) => Action::Upload,
+                    None => Action::DeleteRemote,
+                },
+                _ if l == b => match r {
+                    Some(_) => Action::Download,
+                    None => Action::DeleteLocal,
+                },
+                // An edit beats a delete on the other side
+                _ => match (l, r) {
+                    (Some(_), None) => Action::Upload,
+                    (None, _) => Action::Download,
+                    (Some(_), Some(_)) => Action::Conflict,
+                },
+            };
+            Some((path.clone(), action))
+        })
+        .collect()
+}
+
+/// `stem (conflicted copy from remote).md`, which `kno merge-daily` and
+/// other sync tooling recognise.
+fn conflict_copy_path(relative_path: &Path) -> PathBuf {
+    let stem = relative_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let mut path = relative_path.with_file_name(format!("{stem} (conflicted copy from remote)"));
+    if let Some(ext) = relative_path.extension() {
+        path.set_extension(ext);
+    }
+    path
+}
+
+fn write_note(notes_dir: &Path, relative_path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let path = notes_dir.join(relative_path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+    }
+    fs::write(&path, bytes).map_err(|e| format!("failed to write {}: {e}", relative_path.display()))
+}
+
+/// Download `key`, checking it against the manifest hash.
+fn fetch(store: &dyn Store, key: &str, hash: &str) -> Result<Vec<u8>, String> {
+    let bytes = store
+        .get(key)?
+        .ok_or_else(|| format!("{key} is in the remote manifest but missing"))?;
+    match sha256_hex(&bytes) == hash {
+        true => Ok(bytes),
+        false => Err(format!(
+            "{key} doesn't match the remote manifest; another sync may be running"
+        )),
+    }
+}
+
+/// Sync the notes in `notes_dir` with `store`, returning what was done to
+/// each note.
+pub fn sync(notes_dir: &Path, store: &dyn Store) -> Result<Vec<(PathBuf, Action)>, String> {
+    let mut local = Manifest::new();
+    for rel in vault::notes(notes_dir) {
+        let bytes = fs::read(notes_dir.join(&rel))
+            .map_err(|e| format!("failed to read {}: {e}", rel.display()))?;
+        local.insert(rel, sha256_hex(&bytes));
+    }
+    let remote = match store.get(MANIFEST_NAME)? {
+        Some(bytes) => parse_manifest(&String::from_utf8_lossy(&bytes)),
+        None => Manifest::new(),
+    };
+    let base =
+        parse_manifest(&fs::read_to_string(notes_dir.join(STATE_FILE_NAME)).unwrap_or_default());
+
+    let actions = plan(&local, &remote, &base);
+    let mut synced = remote.clone();
+    for (rel, action) in &actions {
+        let name = key(rel);
+        if dry_run::skip(format_args!("{action:?} {}", rel.display())) {
+            continue;
+        }
+        match action {
+            Action::Upload | Action::Conflict => {
+                if *action == Action::Conflict {
+                    let copy = conflict_copy_path(rel);
+                    let theirs = fetch(store, &name, &remote[rel])?;
+                    write_note(notes_dir, &copy, &theirs)?;
+                    store.put(&key(&copy), &theirs)?;
+                    synced.insert(copy, remote[rel].clone());
+                }
+                let bytes = fs::read(notes_dir.join(rel))
+                    .map_err(|e| format!("failed to read {}: {e}", rel.display()))?;
+                store.put(&name, &bytes)?;
+                synced.insert(rel.clone(), sha256_hex(&bytes));
+            }
+            Action::Download => {
+                let bytes = fetch(store, &name, &remote[rel])?;
+                write_note(notes_dir, rel, &bytes)?;
+                synced.insert(rel.clone(), remote[rel].clone());
+            }
+            Action::DeleteRemote => {
+                store.delete(&name)?;
+                synced.remove(rel);
+            }
+            Action::DeleteLocal => {
+                dedupe::trash(notes_dir, rel);
+                synced.remove(rel);
+            }
+        }
+    }
+    if dry_run::is_enabled() {
+        return Ok(actions);
+    }
+
+    let manifest = format_manifest(&synced);
+    if synced != remote {
+        store.put(MANIFEST_NAME, manifest.as_bytes())?;
+    }
+    fs::write(notes_dir.join(STATE_FILE_NAME), manifest)
+        .map_err(|e| format!("failed to record the sync: {e}"))?;
+    Ok(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A directory standing in for a bucket.
+    struct DirStore(PathBuf);
+
+    impl Store for DirStore {
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+            Ok(fs::read(self.0.join(key)).ok())
+        }
+
+        fn put(&self, key: &str, bytes: &[u8]) -> Result<(), String> {
+            let path = self.0.join(key);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, bytes).map_err(|e| e.to_string())
+        }
+
+        fn delete(&self, key: &str) -> Result<(), String> {
+            let _ = fs::remove_file(self.0.join(key));
+            Ok(())
+        }
+    }
+
+    fn manifest(entries: &[(&str, &str)]) -> Manifest {
+        entries
+            .iter()
+            .map(|(path, hash)| (PathBuf::from(path), hash.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan() {
+        let base = manifest(&[
+            ("same.md", "1"),
+            ("ours.md", "1"),
+            ("theirs.md", "1"),
+            ("both.md", "1"),
+            ("gone-here.md", "1"),
+            ("gone-there.md", "1"),
+        ]);
+        let local = manifest(&[
+            ("same.md", "1"),
+            ("ours.md", "2"),
+            ("theirs.md", "1"),
+            ("both.md", "2"),
+            ("gone-there.md", "1"),
+            ("new.md", "1"),
+        ]);
+        let remote = manifest(&[
+            ("same.md", "1"),
+            ("ours.md", "1"),
+            ("theirs.md", "2"),
+            ("both.md", "3"),
+            ("gone-here.md", "1"),
+        ]);
+        assert_eq!(
+            plan(&local, &remote, &base),
+            [
+                (PathBuf::from("both.md"), Action::Conflict),
+                (PathBuf::from("gone-here.md"), Action::DeleteRemote),
+                (PathBuf::from("gone-there.md"), Action::DeleteLocal),
+                (PathBuf::from("new.md"), Action::Upload),
+                (PathBuf::from("ours.md"), Action::Upload),
+                (PathBuf::from("theirs.md"), Action::Download),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_between_two_machines() {
+        let tmp = TempDir::new().unwrap();
+        let store = DirStore(tmp.path().join("bucket"));
+        let (laptop, desktop) = (tmp.path().join("laptop"), tmp.path().join("desktop"));
+        fs::create_dir_all(laptop.join("sql")).unwrap();
+        fs::create_dir_all(&desktop).unwrap();
+        fs::write(laptop.join("sql/joins.md"), "# Joins\n").unwrap();
+        fs::write(laptop.join("inbox.md"), "# Inbox\n").unwrap();
+
+        sync(&laptop, &store).unwrap();
+        let actions = sync(&desktop, &store).unwrap();
+        assert_eq!(actions.len(), 2);
+        assert_eq!(
+            fs::read_to_string(desktop.join("sql/joins.md")).unwrap(),
+            "# Joins\n"
+        );
+
+        // Nothing changed, nothing to do
+        assert!(sync(&laptop, &store).unwrap().is_empty());
+
+        fs::write(desktop.join("inbox.md"), "# Inbox\n\n- desktop\n").unwrap();
+        fs::remove_file(desktop.join("sql/joins.md")).unwrap();
+        fs::write(laptop.join("inbox.md"), "# Inbox\n\n- laptop\n").unwrap();
+        sync(&desktop, &store).unwrap();
+        let actions = sync(&laptop, &store).unwrap();
+        assert_eq!(
+            actions,
+            [
+                (PathBuf::from("inbox.md"), Action::Conflict),
+                (PathBuf::from("sql/joins.md"), Action::DeleteLocal),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(laptop.join("inbox.md")).unwrap(),
+            "# Inbox\n\n- laptop\n"
+        );
+        assert_eq!(
+            fs::read_to_string(laptop.join("inbox (conflicted copy from remote).md")).unwrap(),
+            "# Inbox\n\n- desktop\n"
+        );
+        assert!(laptop.join(".trash/sql/joins.md").is_file());
+
+        // The desktop gets the laptop's version and the conflict copy
+        sync(&desktop, &store).unwrap();
+        assert_eq!(
+            fs::read_to_string(desktop.join("inbox.md")).unwrap(),
+            "# Inbox\n\n- laptop\n"
+        );
+        assert!(
+            desktop
+                .join("inbox (conflicted copy from remote).md")
+                .is_file()
+        );
+    }
+
+    #[test]
+    fn test_sync_keeps_hidden_notes() {
+        let tmp = TempDir::new().unwrap();
+        let store = DirStore(tmp.path().join("bucket"));
+        let (laptop, desktop) = (tmp.path().join("laptop"), tmp.path().join("desktop"));
+        fs::create_dir_all(laptop.join(".journal")).unwrap();
+        fs::create_dir_all(&desktop).unwrap();
+        fs::write(laptop.join(".secret.md"), "# Secret\n").unwrap();
+        fs::write(laptop.join(".journal/monday.md"), "# Monday\n").unwrap();
+
+        // Whether `--hidden` is given doesn't change what a sync sees
+        vault::set_show_hidden(true);
+        let first = sync(&laptop, &store).unwrap();
+        vault::set_show_hidden(false);
+        assert_eq!(first.len(), 2);
+        assert!(sync(&laptop, &store).unwrap().is_empty());
+
+        sync(&desktop, &store).unwrap();
+        assert_eq!(
+            fs::read_to_string(desktop.join(".secret.md")).unwrap(),
+            "# Secret\n"
+        );
+        assert!(desktop.join(".journal/monday.md").is_file());
+        assert!(sync(&laptop, &store).unwrap().is_empty());
+        assert!(laptop.join(".secret.md").is_file());
+    }
+
+    #[test]
+    fn test_parse_manifest_drops_unsafe_paths() {
+        let parsed = parse_manifest("ab\tsql/joins.md\ncd\t../escape.md\nef\t/etc/passwd\n");
+        assert_eq!(parsed, manifest(&[("sql/joins.md", "ab")]));
+    }
+
+    #[test]
+    fn test_encode_key() {
+        assert_eq!(encode_key("sql/my joins.md"), "sql/my%20joins.md");
+        assert_eq!(encode_key("café.md"), "caf%C3%A9.md");
+    }
+
+    #[test]
+    fn test_curl_cmd() {
+        let config = RemoteConfig {
+            url: Some("https://s3.eu-west-1.amazonaws.com/notes/".to_string()),
+            user: Some("AKIA".to_string()),
+            password: Some("secret".to_string()),
+            region: "eu-west-1".to_string(),
+        };
+        let curl = Curl::new(Backend::S3, &config).unwrap();
+        let cmd = curl.cmd("GET", "sql/joins.md");
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy()).collect();
+        assert!(args.contains(&"aws:amz:eu-west-1:s3".into()));
+        assert!(!args.iter().any(|a| a.contains("secret")));
+        let credentials = curl.credentials.as_ref().unwrap().path();
+        assert!(args.contains(&credentials.to_string_lossy()));
+        assert_eq!(
+            fs::read_to_string(credentials).unwrap(),
+            "user = \"AKIA:secret\"\n"
+        );
+        assert_eq!(
+            args.last().unwrap(),
+            "https://s3.eu-west-1.amazonaws.com/notes/sql/joins.md"
+        );
+
+        let config = RemoteConfig {
+            user: None,
+            ..config
+        };
+        assert!(Curl::new(Backend::S3, &config).is_err());
+        assert!(Curl::new(Backend::Webdav, &config).is_ok());
+    }
+}
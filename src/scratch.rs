@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDateTime;
+
+use crate::dry_run;
+
+/// Directory inside the notes dir holding throwaway notes. Like every
+/// dot-directory it's left out of listings and searches.
+pub const SCRATCH_DIR_NAME: &str = ".scratch";
+
+/// Where a scratch note started at `now` goes, relative to the notes dir.
+pub fn new_path(notes_dir: &Path, now: NaiveDateTime) -> PathBuf {
+    let stem = now.format("%Y-%m-%d-%H%M%S").to_string();
+    let dir = PathBuf::from(SCRATCH_DIR_NAME);
+    let mut path = dir.join(format!("{stem}.md"));
+    let mut n = 1;
+    while notes_dir.join(&path).exists() {
+        n += 1;
+        path = dir.join(format!("{stem}-{n}.md"));
+    }
+    path
+}
+
+/// Scratch notes relative to the notes dir, oldest first.
+pub fn notes(notes_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(notes_dir.join(SCRATCH_DIR_NAME)) else {
+        return Vec::new();
+    };
+    let mut notes: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "md"))
+        .map(|p| p.strip_prefix(notes_dir).unwrap().to_path_buf())
+        .collect();
+    // Names are timestamps, so name order is creation order
+    notes.sort();
+    notes
+}
+
+/// Move scratch note `from` (the newest if `None`; a file name in the
+/// scratch dir, with or without `.md`) to `to`. Returns the note moved.
+pub fn promote(notes_dir: &Path, from: Option<&str>, to: &Path) -> Result<PathBuf, String> {
+    let source = match from {
+        Some(name) => {
+            let name = name.strip_suffix(".md").unwrap_or(name);
+            let path = PathBuf::from(SCRATCH_DIR_NAME).join(format!("{name}.md"));
+            match notes_dir.join(&path).is_file() {
+                true => path,
+                false => return Err(format!("no scratch note named {name}")),
+            }
+        }
+        None => notes(notes_dir)
+            .pop()
+            .ok_or_else(|| "no scratch notes to promote".to_string())?,
+    };
+    if notes_dir.join(to).exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+
+    if dry_run::skip(format_args!(
+        "move {} to {}",
+        source.display(),
+        to.display()
+    )) {
+        return Ok(source);
+    }
+    if let Some(parent) = notes_dir.join(to).parent() {
+        fs::create_dir_all(parent).expect("failed to create note directory");
+    }
+    fs::rename(notes_dir.join(&source), notes_dir.join(to)).expect("failed to move scratch note");
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_new_path_avoids_collisions() {
+        let tmp = TempDir::new().unwrap();
+        let now = at("2026-10-18 09:05:30");
+        let first = new_path(tmp.path(), now);
+        assert_eq!(first, PathBuf::from(".scratch/2026-10-18-090530.md"));
+
+        fs::create_dir_all(tmp.path().join(SCRATCH_DIR_NAME)).unwrap();
+        fs::write(tmp.path().join(&first), "").unwrap();
+        assert_eq!(
+            new_path(tmp.path(), now),
+            PathBuf::from(".scratch/2026-10-18-090530-2.md")
+        );
+    }
+
+    #[test]
+    fn test_promote() {
+        let tmp = TempDir::new().unwrap();
+        let scratch = tmp.path().join(SCRATCH_DIR_NAME);
+        fs::create_dir_all(&scratch).unwrap();
+        fs::write(scratch.join("2026-10-17-120000.md"), "older").unwrap();
+        fs::write(scratch.join("2026-10-18-080000.md"), "newer").unwrap();
+
+        let moved = promote(tmp.path(), None, Path::new("ideas/cache.md")).unwrap();
+        assert_eq!(moved, PathBuf::from(".scratch/2026-10-18-080000.md"));
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("ideas/cache.md")).unwrap(),
+            "newer"
+        );
+
+        let err = promote(
+            tmp.path(),
+            Some("2026-10-17-120000"),
+            Path::new("ideas/cache.md"),
+        );
+        assert_eq!(err, Err("ideas/cache.md already exists".to_string()));
+        promote(
+            tmp.path(),
+            Some("2026-10-17-120000.md"),
+            Path::new("old.md"),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("old.md")).unwrap(),
+            "older"
+        );
+
+        assert!(notes(tmp.path()).is_empty());
+        assert!(promote(tmp.path(), None, Path::new("x.md")).is_err());
+        assert!(promote(tmp.path(), Some("nope"), Path::new("x.md")).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::IsTerminal;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate};
+use regex::{Regex, RegexBuilder};
+
+use crate::daily;
+
+/// A line of a note that matches a search.
+#[derive(Debug, PartialEq)]
+pub struct Match {
+    pub path: PathBuf,
+    /// 1-based line number.
+    pub line: usize,
+    pub text: String,
+    /// Byte ranges of the matched text in `text`.
+    pub spans: Vec<Range<usize>>,
+}
+
+/// How a search query is matched. By default it's literal text and case
+/// matters, as in ripgrep.
+#[derive(Debug, Default)]
+pub struct Syntax {
+    /// The query is a regular expression.
+    pub regex: bool,
+    pub ignore_case: bool,
+    /// Only match whole words.
+    pub word: bool,
+}
+
+/// Compile `query` into a pattern, or say why it isn't a valid regex.
+pub fn compile(query: &str, syntax: &Syntax) -> Result<Regex, String> {
+    let mut pattern = match syntax.regex {
+        true => query.to_string(),
+        false => regex::escape(query),
+    };
+    if syntax.word {
+        pattern = format!(r"\b(?:{pattern})\b");
+    }
+    RegexBuilder::new(&pattern)
+        .case_insensitive(syntax.ignore_case)
+        .build()
+        .map_err(|e| format!("invalid regex: {e}"))
+}
+
+/// The notes in `notes` dated from `since` to `until`, inclusive: daily
+/// notes by their date, the rest by the day they were last modified.
+pub fn dated_within(
+    notes_dir: &Path,
+    notes: Vec<PathBuf>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+) -> Vec<PathBuf> {
+    if since.is_none() && until.is_none() {
+        return notes;
+    }
+    let dailies: HashMap<PathBuf, NaiveDate> = daily::daily_notes(notes_dir)
+        .into_iter()
+        .map(|(date, rel)| (rel, date))
+        .collect();
+    notes
+        .into_iter()
+        .filter(|rel| {
+            let date = dailies.get(rel).copied().or_else(|| {
+                let modified = fs::metadata(notes_dir.join(rel)).ok()?.modified().ok()?;
+                Some(DateTime::<Local>::from(modified).date_naive())
+            });
+            date.is_some_and(|d| since.is_none_or(|s| d >= s) && until.is_none_or(|u| d <= u))
+        })
+        .collect()
+}
+
+/// Lines of `notes` (relative to the notes dir) matching `pattern`, in note
+/// then line order.
+pub fn search(notes_dir: &Path, notes: &[PathBuf], pattern: &Regex) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for rel in notes {
+        let Ok(content) = fs::read_to_string(notes_dir.join(rel)) else {
+            continue;
+        };
+        for (i, text) in content.lines().enumerate() {
+            let spans: Vec<_> = pattern.find_iter(text).map(|m| m.range()).collect();
+            if !spans.is_empty() {
+                matches.push(Match {
+                    path: rel.clone(),
+                    line: i + 1,
+                    text: text.to_string(),
+                    spans,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// Whether to color output: stdout is a terminal and `NO_COLOR` isn't set.
+pub fn use_color() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && std::io::stdout().is_terminal()
+}
+
+/// `m`'s line with the matched text in bold red when `color` is set.
+pub fn highlight(m: &Match, color: bool) -> String {
+    if !color {
+        return m.text.clone();
+    }
+    let mut out = String::new();
+    let mut last = 0;
+    for span in &m.spans {
+        out.push_str(&m.text[last..span.start]);
+        out.push_str("\x1b[1;31m");
+        out.push_str(&m.text[span.clone()]);
+        out.push_str("\x1b[0m");
+        last = span.end;
+    }
+    out.push_str(&m.text[last..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literal(query: &str) -> Regex {
+        compile(query, &Syntax::default()).unwrap()
+    }
+
+    #[test]
+    fn test_search_and_highlight() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("sql")).unwrap();
+        fs::write(
+            tmp.path().join("sql/joins.md"),
+            "# Joins\n\nLEFT JOIN keeps rows; a join (a.b) joins\n",
+        )
+        .unwrap();
+        fs::write(tmp.path().join("inbox.md"), "nothing here\n").unwrap();
+
+        let notes = [PathBuf::from("inbox.md"), PathBuf::from("sql/joins.md")];
+        let matches = search(tmp.path(), &notes, &literal("join"));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, PathBuf::from("sql/joins.md"));
+        assert_eq!(matches[0].line, 3);
+        assert_eq!(matches[0].spans, [24..28, 35..39]);
+        assert_eq!(
+            highlight(&matches[0], true),
+            "LEFT JOIN keeps rows; a \x1b[1;31mjoin\x1b[0m (a.b) \x1b[1;31mjoin\x1b[0ms"
+        );
+        assert_eq!(highlight(&matches[0], false), matches[0].text);
+
+        // The query is literal, not a regex
+        assert_eq!(search(tmp.path(), &notes, &literal("(a.b)")).len(), 1);
+        assert!(search(tmp.path(), &notes, &literal("(a.c)")).is_empty());
+    }
+
+    #[test]
+    fn test_dated_within() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("daily/2024")).unwrap();
+        for rel in [
+            "daily/2024/2024-03-10.md",
+            "daily/2024/2024-07-01.md",
+            "kafka.md",
+        ] {
+            fs::write(tmp.path().join(rel), "kafka\n").unwrap();
+        }
+        let notes = crate::vault::notes(tmp.path());
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+
+        assert_eq!(
+            dated_within(
+                tmp.path(),
+                notes.clone(),
+                date("2024-01-01"),
+                date("2024-06-30")
+            ),
+            [PathBuf::from("daily/2024/2024-03-10.md")]
+        );
+        // Other notes go by modification time, which is today
+        let today = Local::now().date_naive();
+        assert_eq!(
+            dated_within(tmp.path(), notes.clone(), Some(today), None),
+            [PathBuf::from("kafka.md")]
+        );
+        assert_eq!(dated_within(tmp.path(), notes.clone(), None, None), notes);
+    }
+
+    #[test]
+    fn test_compile() {
+        let find = |query: &str, syntax: Syntax| -> Vec<String> {
+            let pattern = compile(query, &syntax).unwrap();
+            pattern
+                .find_iter("Join joins JOIN (a.b) a1b")
+                .map(|m| m.as_str().to_string())
+                .collect()
+        };
+        assert_eq!(find("join", Syntax::default()), ["join"]);
+        let ignore_case = Syntax {
+            ignore_case: true,
+            ..Syntax::default()
+        };
+        assert_eq!(find("join", ignore_case), ["Join", "join", "JOIN"]);
+        let word = Syntax {
+            ignore_case: true,
+            word: true,
+            ..Syntax::default()
+        };
+        assert_eq!(find("join", word), ["Join", "JOIN"]);
+        let regex = Syntax {
+            regex: true,
+            ..Syntax::default()
+        };
+        assert_eq!(find(r"a.b", regex), ["a.b", "a1b"]);
+        assert_eq!(find(r"a.b", Syntax::default()), ["a.b"]);
+
+        let invalid = Syntax {
+            regex: true,
+            ..Syntax::default()
+        };
+        assert!(compile("(unclosed", &invalid).is_err());
+    }
+}
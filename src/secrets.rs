@@ -0,0 +1,70 @@
+use keyring::{Entry, Error};
+
+use crate::dry_run;
+
+/// Keychain service that kno's secrets are stored under.
+const SERVICE: &str = "kno";
+
+/// Secret names kno looks up, and the config keys they stand in for.
+pub const KNOWN: &[(&str, &str)] = &[
+    ("github_token", "share.github_token"),
+    ("smtp_password", "mail.password"),
+    ("remote_password", "remote.password"),
+];
+
+fn entry(name: &str) -> Result<Entry, String> {
+    if name.trim().is_empty() {
+        return Err("secret name can't be empty".to_string());
+    }
+    Entry::new(SERVICE, name).map_err(|e| format!("keychain: {e}"))
+}
+
+/// The secret stored as `name`, or `None` if there isn't one.
+pub fn get(name: &str) -> Result<Option<String>, String> {
+    match entry(name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("keychain: {e}")),
+    }
+}
+
+/// `get`, for fallbacks where an unavailable keychain means no secret.
+pub fn lookup(name: &str) -> Option<String> {
+    get(name).unwrap_or_else(|e| {
+        crate::log::debug!("no {name} from the keychain: {e}");
+        None
+    })
+}
+
+pub fn set(name: &str, value: &str) -> Result<(), String> {
+    let entry = entry(name)?;
+    if dry_run::skip(format_args!("store {name} in the keychain")) {
+        return Ok(());
+    }
+    entry
+        .set_password(value)
+        .map_err(|e| format!("keychain: {e}"))
+}
+
+pub fn remove(name: &str) -> Result<(), String> {
+    let entry = entry(name)?;
+    if dry_run::skip(format_args!("remove {name} from the keychain")) {
+        return Ok(());
+    }
+    match entry.delete_credential() {
+        Ok(()) => Ok(()),
+        Err(Error::NoEntry) => Err(format!("no secret named {name}")),
+        Err(e) => Err(format!("keychain: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_empty_name() {
+        assert!(get(" ").is_err());
+        assert!(KNOWN.iter().all(|(name, _)| entry(name).is_ok()));
+    }
+}
@@ -0,0 +1,178 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+
+use crate::{curl, dry_run, log};
+
+/// File mapping shared notes to their gists, one `<path>\t<id>\t<url>` per line.
+pub const GISTS_FILE_NAME: &str = ".gists";
+
+const GISTS_API: &str = "https://api.github.com/gists";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gist {
+    pub path: PathBuf,
+    pub id: String,
+    pub url: String,
+}
+
+pub fn read_gists(notes_dir: &Path) -> Vec<Gist> {
+    let content = fs::read_to_string(notes_dir.join(GISTS_FILE_NAME)).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            Some(Gist {
+                path: PathBuf::from(parts.next()?),
+                id: parts.next()?.to_string(),
+                url: parts.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The gist a note was last shared to, if any.
+pub fn find(notes_dir: &Path, relative_path: &Path) -> Option<Gist> {
+    read_gists(notes_dir)
+        .into_iter()
+        .find(|g| g.path == relative_path)
+}
+
+/// Remember `gist` as the one for its note, replacing any earlier entry.
+pub fn record(notes_dir: &Path, gist: &Gist) {
+    let mut gists = read_gists(notes_dir);
+    gists.retain(|g| g.path != gist.path);
+    gists.push(gist.clone());
+
+    let content: String = gists
+        .iter()
+        .map(|g| format!("{}\t{}\t{}\n", g.path.display(), g.id, g.url))
+        .collect();
+    fs::write(notes_dir.join(GISTS_FILE_NAME), content).expect("failed to write gists file");
+}
+
+fn payload(relative_path: &Path, content: &str) -> String {
+    let filename = relative_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    serde_json::json!({
+        "description": relative_path.to_string_lossy(),
+        "public": false,
+        "files": { filename: { "content": content } },
+    })
+    .to_string()
+}
+
+/// Pull the gist id and URL out of a GitHub API response, or its error message.
+fn parse_response(body: &str) -> Result<(String, String), String> {
+    let value: serde_json::Value =
+        serde_json::from_str(body).map_err(|_| format!("unexpected response: {body}"))?;
+    match (value["id"].as_str(), value["html_url"].as_str()) {
+        (Some(id), Some(url)) => Ok((id.to_string(), url.to_string())),
+        _ => Err(value["message"]
+            .as_str()
+            .unwrap_or("unexpected response")
+            .to_string()),
+    }
+}
+
+/// Create a secret gist for the note, or update `existing` in place.
+/// The request is sent with `curl` so kno needs no HTTP stack of its own.
+/// Returns `None` in dry-run mode.
+pub fn share(
+    notes_dir: &Path,
+    relative_path: &Path,
+    token: &str,
+    existing: Option<&Gist>,
+) -> Result<Option<Gist>, String> {
+    let content = fs::read_to_string(notes_dir.join(relative_path))
+        .map_err(|e| format!("failed to read {}: {e}", relative_path.display()))?;
+    let (method, url) = match existing {
+        Some(gist) => ("PATCH", format!("{GISTS_API}/{}", gist.id)),
+        None => ("POST", GISTS_API.to_string()),
+    };
+
+    if dry_run::skip(format_args!(
+        "{method} {} to {url}",
+        relative_path.display()
+    )) {
+        return Ok(None);
+    }
+
+    log::debug!("{method} {url}");
+    let auth = curl::secret_config(&[("header", &format!("Authorization: Bearer {token}"))])?;
+    let mut child = process::Command::new("curl")
+        .args(["--silent", "--show-error", "-X", method, &url])
+        .args(["-H", "Accept: application/vnd.github+json"])
+        .arg("--config")
+        .arg(auth.path())
+        .args(["--data-binary", "@-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run curl: {e}"))?;
+    child
+        .stdin
+        .take()
+        .expect("curl stdin is piped")
+        .write_all(payload(relative_path, &content).as_bytes())
+        .map_err(|e| format!("failed to send note to curl: {e}"))?;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("curl failed: {e}"))?;
+    if !output.status.success() {
+        return Err("curl request failed".to_string());
+    }
+
+    let (id, url) = parse_response(&String::from_utf8_lossy(&output.stdout))?;
+    let gist = Gist {
+        path: relative_path.to_path_buf(),
+        id,
+        url,
+    };
+    record(notes_dir, &gist);
+    Ok(Some(gist))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_is_secret_gist() {
+        let value: serde_json::Value =
+            serde_json::from_str(&payload(Path::new("sql/joins.md"), "# Joins\n")).unwrap();
+        assert_eq!(value["public"], false);
+        assert_eq!(value["files"]["joins.md"]["content"], "# Joins\n");
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(
+            parse_response(r#"{"id": "abc", "html_url": "https://gist.github.com/abc"}"#),
+            Ok(("abc".into(), "https://gist.github.com/abc".into()))
+        );
+        assert_eq!(
+            parse_response(r#"{"message": "Bad credentials"}"#),
+            Err("Bad credentials".into())
+        );
+    }
+
+    #[test]
+    fn test_record_replaces_previous_gist() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let gist = |id: &str| Gist {
+            path: PathBuf::from("sql/joins.md"),
+            id: id.into(),
+            url: format!("https://gist.github.com/{id}"),
+        };
+        record(tmp.path(), &gist("a"));
+        record(tmp.path(), &gist("b"));
+
+        assert_eq!(read_gists(tmp.path()), [gist("b")]);
+        assert_eq!(find(tmp.path(), Path::new("sql/joins.md")), Some(gist("b")));
+        assert_eq!(find(tmp.path(), Path::new("other.md")), None);
+    }
+}
@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use crate::config::Config;
+use crate::{daily, tasks};
+
+/// What goes into a standup: the last working day's finished tasks and log,
+/// and what's still open.
+#[derive(Debug, PartialEq)]
+pub struct Standup {
+    /// The day reported on, the most recent daily note before today.
+    pub previous: Option<NaiveDate>,
+    pub done: Vec<String>,
+    pub log: Vec<String>,
+    pub open: Vec<String>,
+}
+
+fn push_unique(list: &mut Vec<String>, text: String) {
+    if !text.is_empty() && !list.contains(&text) {
+        list.push(text);
+    }
+}
+
+/// A log line without its list marker and `**14:32**` time.
+fn log_text(line: &str) -> String {
+    let line = line.trim_start();
+    let line = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .unwrap_or(line);
+    let line = match line.strip_prefix("**").and_then(|l| l.split_once("** ")) {
+        Some((time, rest)) if time.contains(':') => rest,
+        _ => line,
+    };
+    line.trim().to_string()
+}
+
+/// Gather the standup for `today`: tasks checked off in the previous daily
+/// note or dated that day (`✅ YYYY-MM-DD`) anywhere, entries in that note's
+/// log section, and open tasks from today's and that daily note and the
+/// `[tasks]` note.
+pub fn gather(notes_dir: &Path, today: NaiveDate, config: &Config) -> Standup {
+    let read = |rel: &Path| fs::read_to_string(notes_dir.join(rel)).unwrap_or_default();
+    let previous = daily::previous_before(notes_dir, today);
+    let today_note = daily::daily_notes(notes_dir)
+        .into_iter()
+        .find(|(date, _)| *date == today)
+        .map(|(_, path)| path);
+
+    let mut standup = Standup {
+        previous: previous.as_ref().map(|(date, _)| *date),
+        done: Vec::new(),
+        log: Vec::new(),
+        open: Vec::new(),
+    };
+
+    if let Some((date, path)) = &previous {
+        let content = read(path);
+        let completed = format!("✅ {date}");
+        let dated = tasks::scan(notes_dir)
+            .into_iter()
+            .filter(|t| t.done && t.text.contains(&completed));
+        let in_note = tasks::parse(path, &content).into_iter().filter(|t| t.done);
+        for task in in_note.chain(dated) {
+            let text = task.text.split(" ✅ ").next().unwrap_or_default();
+            push_unique(&mut standup.done, text.trim().to_string());
+        }
+        for line in daily::section_lines(&content, &config.daily.log_section) {
+            if tasks::parse_line(line).is_none() {
+                push_unique(&mut standup.log, log_text(line));
+            }
+        }
+    }
+
+    let tasks_note = config
+        .tasks
+        .note
+        .as_ref()
+        .map(|n| PathBuf::from(format!("{n}.md")));
+    let sources = [today_note, previous.map(|(_, path)| path), tasks_note];
+    for path in sources.iter().flatten() {
+        for task in tasks::parse(path, &read(path)) {
+            if !task.done {
+                push_unique(&mut standup.open, task.text);
+            }
+        }
+    }
+    standup
+}
+
+/// The standup as a snippet ready to paste into chat.
+pub fn format(standup: &Standup, today: NaiveDate) -> String {
+    let heading = match standup.previous {
+        Some(date) if today.pred_opt() == Some(date) => "Yesterday".to_string(),
+        Some(date) => date.format("%A").to_string(),
+        None => "Yesterday".to_string(),
+    };
+    let list = |items: &[String]| match items.is_empty() {
+        true => "• nothing noted\n".to_string(),
+        false => items.iter().map(|item| format!("• {item}\n")).collect(),
+    };
+    let mut done = standup.done.clone();
+    done.extend(standup.log.iter().cloned());
+    format!(
+        "*{heading}*\n{}\n*Today*\n{}",
+        list(&done),
+        list(&standup.open)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, rel: &str, content: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_log_text() {
+        assert_eq!(log_text("- **14:32** deployed"), "deployed");
+        assert_eq!(log_text("* plain note"), "plain note");
+        assert_eq!(log_text("- **bold** start"), "**bold** start");
+    }
+
+    #[test]
+    fn test_standup_since_friday() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(
+            tmp.path(),
+            "daily/2026/2026-10-16.md",
+            "# Fri\n\n## Log\n\n- **09:10** paired on auth\n- [ ] not a log line\n\n## Tasks\n\n- [x] review PR\n- [ ] write migration\n",
+        );
+        write(
+            tmp.path(),
+            "daily/2026/2026-10-19.md",
+            "# Mon\n\n## Tasks\n\n- [ ] ship release\n",
+        );
+        write(
+            tmp.path(),
+            "projects/launch.md",
+            "- [x] book venue ✅ 2026-10-16\n- [x] old ✅ 2026-10-01\n",
+        );
+        write(tmp.path(), "tasks.md", "- [ ] renew passport\n");
+        let mut config = Config::default();
+        config.tasks.note = Some("tasks".to_string());
+
+        let monday = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let standup = gather(tmp.path(), monday, &config);
+        assert_eq!(
+            format(&standup, monday),
+            "*Friday*\n• review PR\n• book venue\n• paired on auth\n\n*Today*\n• ship release\n• not a log line\n• write migration\n• renew passport\n"
+        );
+    }
+}
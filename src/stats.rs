@@ -0,0 +1,195 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local};
+
+use crate::{frontmatter, tasks, vault};
+
+/// Size and age of one note.
+#[derive(Debug)]
+pub struct NoteStat {
+    /// Note path relative to the notes dir.
+    pub path: PathBuf,
+    pub words: usize,
+    pub modified: DateTime<Local>,
+}
+
+/// Statistics for the notes under one directory.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Notes in path order.
+    pub notes: Vec<NoteStat>,
+    pub tasks_done: usize,
+    pub tasks_total: usize,
+}
+
+/// Words in a note's body, ignoring frontmatter.
+pub fn word_count(content: &str) -> usize {
+    frontmatter::body(content).split_whitespace().count()
+}
+
+/// Gather statistics for the notes under `dir` (the whole vault if `None`).
+pub fn collect(notes_dir: &Path, dir: Option<&Path>) -> Report {
+    let mut report = Report::default();
+    for rel in vault::notes(notes_dir) {
+        if dir.is_some_and(|dir| !rel.starts_with(dir)) {
+            continue;
+        }
+        let file_path = notes_dir.join(&rel);
+        let (Ok(content), Some(modified)) =
+            (fs::read_to_string(&file_path), vault::modified(&file_path))
+        else {
+            continue;
+        };
+        let tasks = tasks::parse(&rel, &content);
+        report.tasks_done += tasks.iter().filter(|t| t.done).count();
+        report.tasks_total += tasks.len();
+        report.notes.push(NoteStat {
+            path: rel,
+            words: word_count(&content),
+            modified: modified.into(),
+        });
+    }
+    report
+}
+
+impl Report {
+    pub fn words(&self) -> usize {
+        self.notes.iter().map(|n| n.words).sum()
+    }
+
+    /// The most recently modified note.
+    pub fn newest(&self) -> Option<&NoteStat> {
+        self.notes.iter().max_by_key(|n| n.modified)
+    }
+
+    /// The note left unmodified the longest.
+    pub fn oldest(&self) -> Option<&NoteStat> {
+        self.notes.iter().min_by_key(|n| n.modified)
+    }
+
+    /// The `n` notes with the most words, biggest first.
+    pub fn largest(&self, n: usize) -> Vec<&NoteStat> {
+        let mut notes: Vec<_> = self.notes.iter().collect();
+        notes.sort_by_key(|n| std::cmp::Reverse(n.words));
+        notes.truncate(n);
+        notes
+    }
+
+    /// The `n` notes left unmodified the longest, stalest first.
+    pub fn stalest(&self, n: usize) -> Vec<&NoteStat> {
+        let mut notes: Vec<_> = self.notes.iter().collect();
+        notes.sort_by_key(|n| n.modified);
+        notes.truncate(n);
+        notes
+    }
+
+    /// The summary printed by `kno stats`.
+    pub fn render(&self) -> String {
+        let mut out = format!("notes   {}\nwords   {}\n", self.notes.len(), self.words());
+        let mut note_line = |label: &str, note: Option<&NoteStat>| {
+            if let Some(note) = note {
+                out.push_str(&format!(
+                    "{label}  {} ({})\n",
+                    note.path.display(),
+                    note.modified.date_naive()
+                ));
+            }
+        };
+        note_line("newest", self.newest());
+        note_line("oldest", self.oldest());
+        let tasks = match self.tasks_total {
+            0 => "none".to_string(),
+            total => format!(
+                "{}/{total} done ({}%)",
+                self.tasks_done,
+                self.tasks_done * 100 / total
+            ),
+        };
+        out.push_str(&format!("tasks   {tasks}\n"));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn write(root: &Path, rel: &str, content: &str, days_ago: u64) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        let time = SystemTime::now() - Duration::from_secs(days_ago * 24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_word_count_skips_frontmatter() {
+        assert_eq!(
+            word_count("---\ntags: [a, b]\n---\n# Joins\n\ninner  join\n"),
+            4
+        );
+        assert_eq!(word_count(""), 0);
+    }
+
+    #[test]
+    fn test_collect_subtree() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(
+            tmp.path(),
+            "projects/foo/plan.md",
+            "# Plan\n\n- [x] a\n- [ ] b\n",
+            30,
+        );
+        write(tmp.path(), "projects/foo/log.md", "# Log\n\n- [x] c\n", 1);
+        write(tmp.path(), "projects/foobar.md", "# Other\n", 0);
+        write(tmp.path(), "sql/joins.md", "# Joins\n", 0);
+
+        let report = collect(tmp.path(), Some(Path::new("projects/foo")));
+        assert_eq!(report.notes.len(), 2);
+        assert_eq!(report.words(), 14);
+        assert_eq!(report.tasks_done, 2);
+        assert_eq!(report.tasks_total, 3);
+        assert_eq!(
+            report.newest().unwrap().path,
+            Path::new("projects/foo/log.md")
+        );
+        assert_eq!(
+            report.oldest().unwrap().path,
+            Path::new("projects/foo/plan.md")
+        );
+
+        let rendered = report.render();
+        assert!(rendered.starts_with("notes   2\nwords   14\nnewest  projects/foo/log.md ("));
+        assert!(rendered.ends_with("tasks   2/3 done (66%)\n"));
+        assert!(Report::default().render().ends_with("tasks   none\n"));
+    }
+
+    #[test]
+    fn test_largest_and_stalest() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "a.md", "one two three\n", 5);
+        write(tmp.path(), "b.md", "one\n", 50);
+        write(tmp.path(), "c.md", "one two\n", 20);
+        let report = collect(tmp.path(), None);
+
+        let paths = |notes: Vec<&NoteStat>| -> Vec<PathBuf> {
+            notes.into_iter().map(|n| n.path.clone()).collect()
+        };
+        assert_eq!(
+            paths(report.largest(2)),
+            ["a.md", "c.md"].map(PathBuf::from)
+        );
+        assert_eq!(
+            paths(report.stalest(2)),
+            ["b.md", "c.md"].map(PathBuf::from)
+        );
+        assert_eq!(report.largest(10).len(), 3);
+    }
+}
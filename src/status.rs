@@ -0,0 +1,248 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, TimeZone};
+
+use crate::commit::{self, git};
+use crate::sync::{self, Pending};
+
+/// The notes repo's sync state, as `kno status` reports it.
+#[derive(Debug, PartialEq)]
+pub struct Status {
+    /// Files with uncommitted changes.
+    pub changed: Vec<PathBuf>,
+    /// The upstream branch with commits ahead of and behind it, if one is set.
+    pub upstream: Option<Upstream>,
+    /// When the upstream branch was last fetched or pushed to.
+    pub last_sync: Option<DateTime<Local>>,
+    /// Commits `kno sync` couldn't push because the remote was unreachable.
+    pub pending: Option<Pending>,
+    /// Files with unresolved merge conflicts.
+    pub conflicts: Vec<PathBuf>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Upstream {
+    pub name: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+/// Read the sync state of the git repo in `notes_dir`.
+pub fn read(notes_dir: &Path) -> Result<Status, String> {
+    let changed = commit::changed(notes_dir)?;
+    let conflicts = git(notes_dir, &["diff", "--name-only", "--diff-filter=U"])?
+        .lines()
+        .map(PathBuf::from)
+        .collect();
+
+    let upstream_ref = git(
+        notes_dir,
+        &["rev-parse", "--symbolic-full-name", "--abbrev-ref", "@{u}"],
+    )
+    .ok()
+    .map(|name| name.trim().to_string());
+    let upstream = upstream_ref.as_ref().and_then(|name| {
+        let counts = git(
+            notes_dir,
+            &["rev-list", "--left-right", "--count", "HEAD...@{u}"],
+        )
+        .ok()?;
+        let (ahead, behind) = counts.trim().split_once('\t')?;
+        Some(Upstream {
+            name: name.clone(),
+            ahead: ahead.parse().ok()?,
+            behind: behind.parse().ok()?,
+        })
+    });
+    // Fetches and pushes both move the remote-tracking ref; its reflog says
+    // when that last happened
+    let last_sync = upstream_ref.and_then(|name| {
+        let entry = git(
+            notes_dir,
+            &[
+                "log",
+                "-g",
+                "-n1",
+                "--date=unix",
+                "--format=%gd",
+                &format!("refs/remotes/{name}"),
+            ],
+        )
+        .ok()?;
+        let secs = entry.trim().rsplit_once("@{")?.1.strip_suffix('}')?;
+        Local.timestamp_opt(secs.parse().ok()?, 0).single()
+    });
+
+    Ok(Status {
+        changed,
+        upstream,
+        last_sync,
+        pending: sync::pending(notes_dir),
+        conflicts,
+    })
+}
+
+/// `3 hours ago`, in the largest whole unit.
+fn ago(then: DateTime<Local>, now: DateTime<Local>) -> String {
+    let minutes = (now - then).num_minutes().max(0);
+    let (n, unit) = match minutes {
+        0 => return "just now".to_string(),
+        m if m < 60 => (m, "minute"),
+        m if m < 60 * 24 => (m / 60, "hour"),
+        m => (m / (60 * 24), "day"),
+    };
+    match n {
+        1 => format!("1 {unit} ago"),
+        n => format!("{n} {unit}s ago"),
+    }
+}
+
+/// `1 file`, `3 files`.
+fn files(n: usize) -> String {
+    match n {
+        1 => "1 file".to_string(),
+        n => format!("{n} files"),
+    }
+}
+
+/// The report `kno status` prints.
+pub fn format(status: &Status, now: DateTime<Local>) -> String {
+    let mut out = match status.changed.len() {
+        0 => "Uncommitted: none\n".to_string(),
+        n => format!("Uncommitted: {}\n", files(n)),
+    };
+    for file in &status.changed {
+        out.push_str(&format!("  {}\n", file.display()));
+    }
+    out.push_str(&match &status.upstream {
+        Some(u) => format!(
+            "Remote: {}, {} ahead, {} behind\n",
+            u.name, u.ahead, u.behind
+        ),
+        None => "Remote: no upstream branch\n".to_string(),
+    });
+    out.push_str(&match status.last_sync {
+        Some(t) => format!(
+            "Last sync: {} ({})\n",
+            t.format("%Y-%m-%d %H:%M"),
+            ago(t, now)
+        ),
+        None => "Last sync: never\n".to_string(),
+    });
+    if let Some(p) = &status.pending {
+        out.push_str(&format!(
+            "Pending push: queued {} ({} attempts; {})\n",
+            ago(p.since, now),
+            p.attempts,
+            p.error
+        ));
+    }
+    match status.conflicts.len() {
+        0 => out.push_str("Conflicts: none\n"),
+        n => {
+            out.push_str(&format!("Conflicts: {}\n", files(n)));
+            for file in &status.conflicts {
+                out.push_str(&format!("  {}\n", file.display()));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn repo(dir: &Path) {
+        git(dir, &["init", "-q"]).unwrap();
+        git(dir, &["config", "user.name", "t"]).unwrap();
+        git(dir, &["config", "user.email", "t@t"]).unwrap();
+    }
+
+    #[test]
+    fn test_read_with_upstream() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let remote = tmp.path().join("remote");
+        let local = tmp.path().join("local");
+        fs::create_dir_all(&remote).unwrap();
+        git(&remote, &["init", "-q", "--bare"]).unwrap();
+        fs::create_dir_all(&local).unwrap();
+        repo(&local);
+        fs::write(local.join("a.md"), "# A\n").unwrap();
+        commit::commit_all(&local, "first").unwrap();
+        git(
+            &local,
+            &["remote", "add", "origin", remote.to_str().unwrap()],
+        )
+        .unwrap();
+        git(&local, &["push", "-q", "-u", "origin", "HEAD"]).unwrap();
+
+        fs::write(local.join("b.md"), "# B\n").unwrap();
+        commit::commit_all(&local, "second").unwrap();
+        fs::write(local.join("a.md"), "# A\n\nmore\n").unwrap();
+
+        let status = read(&local).unwrap();
+        assert_eq!(status.changed, [PathBuf::from("a.md")]);
+        let upstream = status.upstream.unwrap();
+        assert!(upstream.name.starts_with("origin/"));
+        assert_eq!((upstream.ahead, upstream.behind), (1, 0));
+        assert!(status.last_sync.is_some());
+        assert!(status.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_read_without_upstream() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        repo(tmp.path());
+        fs::write(tmp.path().join("a.md"), "# A\n").unwrap();
+        commit::commit_all(tmp.path(), "first").unwrap();
+
+        let status = read(tmp.path()).unwrap();
+        assert!(status.changed.is_empty());
+        assert_eq!(status.upstream, None);
+        assert_eq!(status.last_sync, None);
+        assert_eq!(status.pending, None);
+    }
+
+    #[test]
+    fn test_format() {
+        let now = Local.with_ymd_and_hms(2025, 3, 1, 15, 0, 0).unwrap();
+        let status = Status {
+            changed: vec![PathBuf::from("a.md"), PathBuf::from("daily/x.md")],
+            upstream: Some(Upstream {
+                name: "origin/main".to_string(),
+                ahead: 2,
+                behind: 1,
+            }),
+            last_sync: Some(Local.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap()),
+            pending: Some(Pending {
+                since: Local.with_ymd_and_hms(2025, 3, 1, 14, 30, 0).unwrap(),
+                attempts: 3,
+                error: "git pull failed: could not resolve host".to_string(),
+            }),
+            conflicts: vec![PathBuf::from("b.md")],
+        };
+        assert_eq!(
+            format(&status, now),
+            "Uncommitted: 2 files\n  a.md\n  daily/x.md\n\
+             Remote: origin/main, 2 ahead, 1 behind\n\
+             Last sync: 2025-03-01 12:00 (3 hours ago)\n\
+             Pending push: queued 30 minutes ago (3 attempts; git pull failed: could not resolve host)\n\
+             Conflicts: 1 file\n  b.md\n"
+        );
+    }
+
+    #[test]
+    fn test_ago() {
+        let now = Local.with_ymd_and_hms(2025, 3, 1, 15, 0, 0).unwrap();
+        let at = |h, m| Local.with_ymd_and_hms(2025, 3, 1, h, m, 0).unwrap();
+        assert_eq!(ago(at(15, 0), now), "just now");
+        assert_eq!(ago(at(14, 59), now), "1 minute ago");
+        assert_eq!(ago(at(13, 0), now), "2 hours ago");
+        assert_eq!(
+            ago(Local.with_ymd_and_hms(2025, 2, 27, 15, 0, 0).unwrap(), now),
+            "2 days ago"
+        );
+    }
+}
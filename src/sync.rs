@@ -0,0 +1,223 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeZone};
+
+use crate::commit::git;
+use crate::log;
+
+/// File in `.git` recording commits that couldn't be pushed. It lives in the
+/// repo's git dir so it never gets committed or synced itself.
+pub const PENDING_FILE_NAME: &str = "kno-sync-pending";
+
+/// Commits waiting for the remote to be reachable again.
+#[derive(Debug, PartialEq)]
+pub struct Pending {
+    /// When the first push failed.
+    pub since: DateTime<Local>,
+    /// Failed pull or push attempts so far.
+    pub attempts: u32,
+    /// The first line of git's error from the last attempt.
+    pub error: String,
+}
+
+/// How `sync` left the repo.
+#[derive(Debug, PartialEq)]
+pub enum Synced {
+    /// Pulled and pushed; nothing is waiting.
+    Pushed,
+    /// The remote couldn't be reached; commits stay queued locally.
+    Queued(Pending),
+}
+
+fn pending_path(notes_dir: &Path) -> PathBuf {
+    notes_dir.join(".git").join(PENDING_FILE_NAME)
+}
+
+/// The queued push, if the last sync couldn't reach the remote.
+pub fn pending(notes_dir: &Path) -> Option<Pending> {
+    let content = fs::read_to_string(pending_path(notes_dir)).ok()?;
+    let mut fields = content.trim_end().splitn(3, '\t');
+    let since = Local
+        .timestamp_opt(fields.next()?.parse().ok()?, 0)
+        .single()?;
+    Some(Pending {
+        since,
+        attempts: fields.next()?.parse().ok()?,
+        error: fields.next().unwrap_or_default().to_string(),
+    })
+}
+
+fn write_pending(notes_dir: &Path, pending: &Pending) {
+    let content = format!(
+        "{}\t{}\t{}\n",
+        pending.since.timestamp(),
+        pending.attempts,
+        pending.error
+    );
+    // Best effort: the commits are safe either way, this only feeds `kno status`
+    let _ = fs::write(pending_path(notes_dir), content);
+}
+
+/// Whether a pull stopped on conflicts and left a rebase for the user.
+pub fn rebase_in_progress(notes_dir: &Path) -> bool {
+    let git_dir = notes_dir.join(".git");
+    git_dir.join("rebase-merge").exists() || git_dir.join("rebase-apply").exists()
+}
+
+/// Pull (rebasing local commits) and push, retrying `retries` times with
+/// the wait doubling from `delay`. When the remote stays unreachable the
+/// commits are queued for the next sync instead of failing; conflicts are
+/// still an error.
+pub fn sync(
+    notes_dir: &Path,
+    retries: u32,
+    delay: Duration,
+    now: DateTime<Local>,
+) -> Result<Synced, String> {
+    if git(notes_dir, &["rev-parse", "--abbrev-ref", "@{u}"]).is_err() {
+        return Err(
+            "no upstream branch to sync with. Set one with `kno git push -u origin <branch>`"
+                .to_string(),
+        );
+    }
+    let mut error = String::new();
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            let wait = delay * 2u32.pow(attempt - 1);
+            log::debug!("retrying sync in {}s", wait.as_secs());
+            thread::sleep(wait);
+        }
+        let result = git(notes_dir, &["pull", "--rebase", "--quiet"])
+            .and_then(|_| git(notes_dir, &["push", "--quiet"]));
+        match result {
+            Ok(_) => {
+                let _ = fs::remove_file(pending_path(notes_dir));
+                return Ok(Synced::Pushed);
+            }
+            Err(_) if rebase_in_progress(notes_dir) => {
+                return Err(
+                    "pull stopped on conflicts. Resolve them, run `kno git rebase --continue`, then `kno sync`"
+                        .to_string(),
+                );
+            }
+            Err(e) => error = e,
+        }
+    }
+
+    let previous = pending(notes_dir);
+    let pending = Pending {
+        since: previous.as_ref().map_or(now, |p| p.since),
+        attempts: previous.map_or(0, |p| p.attempts) + retries + 1,
+        error: error.lines().next().unwrap_or_default().to_string(),
+    };
+    write_pending(notes_dir, &pending);
+    Ok(Synced::Queued(pending))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit;
+
+    fn setup(tmp: &Path) -> (PathBuf, PathBuf) {
+        let remote = tmp.join("remote");
+        let local = tmp.join("local");
+        fs::create_dir_all(&remote).unwrap();
+        fs::create_dir_all(&local).unwrap();
+        git(&remote, &["init", "-q", "--bare"]).unwrap();
+        git(&local, &["init", "-q"]).unwrap();
+        git(&local, &["config", "user.name", "t"]).unwrap();
+        git(&local, &["config", "user.email", "t@t"]).unwrap();
+        fs::write(local.join("a.md"), "# A\n").unwrap();
+        commit::commit_all(&local, "first").unwrap();
+        git(
+            &local,
+            &["remote", "add", "origin", remote.to_str().unwrap()],
+        )
+        .unwrap();
+        git(&local, &["push", "-q", "-u", "origin", "HEAD"]).unwrap();
+        (remote, local)
+    }
+
+    #[test]
+    fn test_sync_queues_while_offline() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (remote, local) = setup(tmp.path());
+        let now = Local.with_ymd_and_hms(2025, 3, 1, 8, 0, 0).unwrap();
+
+        fs::write(local.join("b.md"), "# B\n").unwrap();
+        commit::commit_all(&local, "second").unwrap();
+        let away = tmp.path().join("away");
+        fs::rename(&remote, &away).unwrap();
+
+        let Synced::Queued(first) = sync(&local, 1, Duration::ZERO, now).unwrap() else {
+            panic!("expected the push to be queued");
+        };
+        assert_eq!(first.since, now);
+        assert_eq!(first.attempts, 2);
+        assert!(first.error.starts_with("git pull failed"));
+
+        // A later failed sync keeps the original queue time
+        let later = now + chrono::Duration::hours(1);
+        sync(&local, 0, Duration::ZERO, later).unwrap();
+        let queued = pending(&local).unwrap();
+        assert_eq!((queued.since, queued.attempts), (now, 3));
+
+        fs::rename(&away, &remote).unwrap();
+        assert_eq!(sync(&local, 0, Duration::ZERO, later), Ok(Synced::Pushed));
+        assert_eq!(pending(&local), None);
+        let log = git(&remote, &["log", "--format=%s"]).unwrap();
+        assert_eq!(log, "second\nfirst\n");
+    }
+
+    #[test]
+    fn test_sync_leaves_out_local_state() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let (remote, laptop) = setup(tmp.path());
+        // An older kno committed the history file
+        fs::write(laptop.join(".history"), "1\ta.md\n").unwrap();
+        commit::commit_all(&laptop, "history").unwrap();
+        git(&laptop, &["push", "-q"]).unwrap();
+        let desktop = tmp.path().join("desktop");
+        git(
+            tmp.path(),
+            &["clone", "-q", remote.to_str().unwrap(), "desktop"],
+        )
+        .unwrap();
+        git(&desktop, &["config", "user.name", "t"]).unwrap();
+        git(&desktop, &["config", "user.email", "t@t"]).unwrap();
+
+        // Both machines open a note and change another, then sync
+        let now = Local::now();
+        for (dir, note) in [(&laptop, "b.md"), (&desktop, "c.md")] {
+            fs::write(dir.join(".history"), format!("2\t{note}\n")).unwrap();
+            fs::write(dir.join(".cursors"), format!("{note}\t3\t1\n")).unwrap();
+            fs::write(dir.join(note), "# Note\n").unwrap();
+            assert!(commit::ignore_local_files(dir).unwrap());
+            commit::commit_all(dir, note).unwrap();
+            assert_eq!(sync(dir, 0, Duration::ZERO, now), Ok(Synced::Pushed));
+        }
+        fs::write(laptop.join(".history"), "3\ta.md\n").unwrap();
+        assert!(!commit::ignore_local_files(&laptop).unwrap());
+        assert!(commit::changed(&laptop).unwrap().is_empty());
+        assert_eq!(sync(&laptop, 0, Duration::ZERO, now), Ok(Synced::Pushed));
+
+        let files = git(&remote, &["ls-tree", "--name-only", "HEAD"]).unwrap();
+        assert_eq!(files, "a.md\nb.md\nc.md\n");
+        assert_eq!(
+            fs::read_to_string(desktop.join(".history")).unwrap(),
+            "2\tc.md\n"
+        );
+    }
+
+    #[test]
+    fn test_sync_needs_upstream() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        git(tmp.path(), &["init", "-q"]).unwrap();
+        let now = Local::now();
+        assert!(sync(tmp.path(), 0, Duration::ZERO, now).is_err());
+    }
+}
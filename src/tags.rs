@@ -0,0 +1,335 @@
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate};
+
+use crate::{dry_run, frontmatter, vault};
+
+/// How often a tag is used and when a note carrying it last changed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TagStat {
+    pub tag: String,
+    pub count: usize,
+    pub last_used: Option<NaiveDate>,
+}
+
+/// Normalize user input (`#rust`, ` rust `) to a bare tag.
+pub fn normalize(tag: &str) -> String {
+    tag.trim().trim_start_matches('#').to_string()
+}
+
+/// Tags from a note's frontmatter.
+pub fn note_tags(content: &str) -> Vec<String> {
+    frontmatter::get_list(content, "tags")
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | '/')
+}
+
+/// Byte ranges (excluding the `#`) of inline `#hashtags` in `body`. Headings,
+/// code fences, inline code and purely numeric tags (`#123`) are ignored.
+fn hashtag_spans(body: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut in_fence = false;
+    let mut offset = 0;
+
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence {
+            let mut in_code = false;
+            let mut prev: Option<char> = None;
+            let mut chars = line.char_indices().peekable();
+            while let Some((i, c)) = chars.next() {
+                let at_boundary = prev.is_none_or(char::is_whitespace);
+                prev = Some(c);
+                match c {
+                    '`' => in_code = !in_code,
+                    '#' if !in_code && at_boundary => {
+                        let start = i + 1;
+                        let mut end = start;
+                        while let Some(&(j, c)) = chars.peek().filter(|(_, c)| is_tag_char(*c)) {
+                            end = j + c.len_utf8();
+                            prev = Some(c);
+                            chars.next();
+                        }
+                        let tag = &line[start..end];
+                        if !tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit()) {
+                            spans.push(offset + start..offset + end);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        offset += line.len();
+    }
+
+    spans
+}
+
+/// Inline `#hashtags` in a note's body, in order of first appearance.
+pub fn inline_tags(content: &str) -> Vec<String> {
+    let body = frontmatter::body(content);
+    let mut tags: Vec<String> = Vec::new();
+    for span in hashtag_spans(body) {
+        let tag = &body[span];
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Frontmatter tags followed by any inline hashtags not already listed.
+pub fn all_tags(content: &str) -> Vec<String> {
+    let mut tags = note_tags(content);
+    for tag in inline_tags(content) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Rewrite inline `#old` hashtags in the body to `#new`.
+fn rename_inline(content: &str, old: &str, new: &str) -> String {
+    let body = frontmatter::body(content);
+    let body_start = content.len() - body.len();
+    let mut out = content[..body_start].to_string();
+    let mut last = 0;
+    for span in hashtag_spans(body) {
+        if &body[span.clone()] == old {
+            out.push_str(&body[last..span.start]);
+            out.push_str(new);
+            last = span.end;
+        }
+    }
+    out.push_str(&body[last..]);
+    out
+}
+
+/// Every tag used in the vault (frontmatter and inline), sorted.
+pub fn names(notes_dir: &Path) -> Vec<String> {
+    stats(notes_dir).into_iter().map(|s| s.tag).collect()
+}
+
+fn write_tags(file_path: &Path, content: &str, tags: &[String]) {
+    if dry_run::skip(format_args!(
+        "set tags [{}] on {}",
+        tags.join(", "),
+        file_path.display()
+    )) {
+        return;
+    }
+    fs::write(file_path, frontmatter::set_list(content, "tags", tags))
+        .expect("failed to update note");
+}
+
+/// Add `new_tags` to the note's frontmatter. Returns the tags actually added.
+pub fn add(file_path: &Path, new_tags: &[String]) -> Vec<String> {
+    let content = fs::read_to_string(file_path).expect("failed to read note");
+    let mut tags = note_tags(&content);
+    let mut added = Vec::new();
+    for tag in new_tags.iter().map(|t| normalize(t)) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag.clone());
+            added.push(tag);
+        }
+    }
+    if !added.is_empty() {
+        write_tags(file_path, &content, &tags);
+    }
+    added
+}
+
+/// Remove `old_tags` from the note's frontmatter. Returns the tags actually removed.
+pub fn remove(file_path: &Path, old_tags: &[String]) -> Vec<String> {
+    let content = fs::read_to_string(file_path).expect("failed to read note");
+    let old: Vec<_> = old_tags.iter().map(|t| normalize(t)).collect();
+    let (removed, kept): (Vec<_>, Vec<_>) = note_tags(&content)
+        .into_iter()
+        .partition(|t| old.contains(t));
+    if !removed.is_empty() {
+        write_tags(file_path, &content, &kept);
+    }
+    removed
+}
+
+/// Rename a tag across the whole vault, in frontmatter and inline hashtags.
+/// Returns the notes that changed.
+pub fn rename(notes_dir: &Path, old: &str, new: &str) -> Vec<PathBuf> {
+    let (old, new) = (normalize(old), normalize(new));
+    let mut changed = Vec::new();
+
+    for rel in vault::notes(notes_dir) {
+        let file_path = notes_dir.join(&rel);
+        let content = fs::read_to_string(&file_path).unwrap_or_default();
+        if !all_tags(&content).contains(&old) {
+            continue;
+        }
+
+        let mut renamed: Vec<String> = Vec::new();
+        for tag in note_tags(&content) {
+            let tag = if tag == old { new.clone() } else { tag };
+            if !renamed.contains(&tag) {
+                renamed.push(tag);
+            }
+        }
+        let updated = rename_inline(&content, &old, &new);
+        let updated = match note_tags(&content).is_empty() {
+            true => updated,
+            false => frontmatter::set_list(&updated, "tags", &renamed),
+        };
+        changed.push(rel);
+
+        if dry_run::skip(format_args!(
+            "rename tag {old} to {new} in {}",
+            file_path.display()
+        )) {
+            continue;
+        }
+        fs::write(&file_path, updated).expect("failed to update note");
+    }
+
+    changed
+}
+
+/// Usage statistics for every tag in the vault, sorted by tag name.
+pub fn stats(notes_dir: &Path) -> Vec<TagStat> {
+    let mut stats: Vec<TagStat> = Vec::new();
+
+    for rel in vault::notes(notes_dir) {
+        let file_path = notes_dir.join(&rel);
+        let content = fs::read_to_string(&file_path).unwrap_or_default();
+        let modified = fs::metadata(&file_path)
+            .and_then(|m| m.modified())
+            .ok()
+            .map(|t| DateTime::<Local>::from(t).date_naive());
+
+        for tag in all_tags(&content) {
+            let idx = match stats.iter().position(|s| s.tag == tag) {
+                Some(idx) => idx,
+                None => {
+                    stats.push(TagStat {
+                        tag,
+                        count: 0,
+                        last_used: None,
+                    });
+                    stats.len() - 1
+                }
+            };
+            stats[idx].count += 1;
+            stats[idx].last_used = stats[idx].last_used.max(modified);
+        }
+    }
+
+    stats.sort_by(|a, b| a.tag.cmp(&b.tag));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(" #rust "), "rust");
+    }
+
+    #[test]
+    fn test_add_and_remove() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let file = tmp.path().join("joins.md");
+        fs::write(&file, "# Joins\n").unwrap();
+
+        assert_eq!(add(&file, &["#sql".into(), "db".into()]), ["sql", "db"]);
+        assert!(add(&file, &["sql".into()]).is_empty());
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "---\ntags: [sql, db]\n---\n# Joins\n"
+        );
+
+        assert_eq!(remove(&file, &["sql".into(), "nope".into()]), ["sql"]);
+        assert_eq!(
+            fs::read_to_string(&file).unwrap(),
+            "---\ntags: [db]\n---\n# Joins\n"
+        );
+    }
+
+    #[test]
+    fn test_inline_tags() {
+        let content = "---\ntags: [a]\n---\n# Heading\n## Sub\nWorking on #rust and #cli/tools, \
+            issue #123, url http://x.io/#frag, `#code`\n```\n#fenced\n```\nagain #rust #é-tag\n";
+        assert_eq!(inline_tags(content), ["rust", "cli/tools", "é-tag"]);
+        assert_eq!(all_tags(content), ["a", "rust", "cli/tools", "é-tag"]);
+    }
+
+    #[test]
+    fn test_rename_inline() {
+        let content = "---\ntags: [x]\n---\n#rust-lang and #rust-lang-old\n`#rust-lang`\n";
+        assert_eq!(
+            rename_inline(content, "rust-lang", "rust"),
+            "---\ntags: [x]\n---\n#rust and #rust-lang-old\n`#rust-lang`\n"
+        );
+    }
+
+    #[test]
+    fn test_rename_inline_only_note() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(tmp.path().join("a.md"), "# A\n\nlearning #rust-lang\n").unwrap();
+
+        assert_eq!(
+            rename(tmp.path(), "rust-lang", "rust"),
+            [PathBuf::from("a.md")]
+        );
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("a.md")).unwrap(),
+            "# A\n\nlearning #rust\n"
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(tmp.path().join("a.md"), "---\ntags: [rust, cli]\n---\n").unwrap();
+        fs::write(
+            tmp.path().join("b.md"),
+            "---\ntags: [rust-lang, rust]\n---\n",
+        )
+        .unwrap();
+        fs::write(tmp.path().join("c.md"), "# untagged\n\nabout #cli\n").unwrap();
+
+        let stats = stats(tmp.path());
+        let counts: Vec<_> = stats.iter().map(|s| (s.tag.as_str(), s.count)).collect();
+        assert_eq!(counts, [("cli", 2), ("rust", 2), ("rust-lang", 1)]);
+        assert_eq!(stats[0].last_used, Some(Local::now().date_naive()));
+    }
+
+    #[test]
+    fn test_rename_vault_wide_dedupes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(tmp.path().join("a.md"), "---\ntags: [rust-lang]\n---\n").unwrap();
+        fs::write(
+            tmp.path().join("b.md"),
+            "---\ntags:\n  - rust\n  - rust-lang\n---\nbody\n",
+        )
+        .unwrap();
+        fs::write(tmp.path().join("c.md"), "---\ntags: [go]\n---\n").unwrap();
+
+        let changed = rename(tmp.path(), "rust-lang", "rust");
+
+        assert_eq!(changed, [PathBuf::from("a.md"), PathBuf::from("b.md")]);
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("a.md")).unwrap(),
+            "---\ntags: [rust]\n---\n"
+        );
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("b.md")).unwrap(),
+            "---\ntags: [rust]\n---\nbody\n"
+        );
+    }
+}
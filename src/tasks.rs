@@ -0,0 +1,466 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{format, vault};
+
+/// Task priority, most important first so sorting puts it on top.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum)]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl Priority {
+    /// Parse priority markers from task text: `(A)`/`(B)`/`(C)` at the start
+    /// (todo.txt style), or `!!`, `!high`, `!medium`, `!low` anywhere.
+    pub fn parse(text: &str) -> Option<Priority> {
+        match text.get(..3) {
+            Some("(A)") => return Some(Priority::High),
+            Some("(B)") => return Some(Priority::Medium),
+            Some("(C)") => return Some(Priority::Low),
+            _ => {}
+        }
+        text.split_whitespace()
+            .find_map(|word| match word.to_lowercase().as_str() {
+                "!!" | "!high" => Some(Priority::High),
+                "!medium" | "!med" => Some(Priority::Medium),
+                "!low" => Some(Priority::Low),
+                _ => None,
+            })
+    }
+}
+
+/// A markdown checklist item (`- [ ] text` / `- [x] text`) found in a note.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Task {
+    /// Note path relative to the notes dir.
+    pub path: PathBuf,
+    /// 1-based line number.
+    pub line: usize,
+    pub text: String,
+    pub done: bool,
+    pub priority: Option<Priority>,
+}
+
+impl Task {
+    /// Values of `format::TASK_FIELDS` for this task.
+    fn format_values(&self) -> Vec<(&'static str, String)> {
+        let priority = self
+            .priority
+            .map(|p| format!("{p:?}").to_lowercase())
+            .unwrap_or_default();
+        vec![
+            ("path", self.path.display().to_string()),
+            ("line", self.line.to_string()),
+            ("text", self.text.clone()),
+            ("priority", priority),
+        ]
+    }
+}
+
+/// Parse a checklist line into `(done, text)`. Accepts `-`, `*` and `+` bullets
+/// at any indentation.
+pub fn parse_line(line: &str) -> Option<(bool, &str)> {
+    let rest = line.trim_start().strip_prefix(['-', '*', '+'])?;
+    let rest = rest.strip_prefix(' ')?;
+    let (done, text) = match rest.get(..3)? {
+        "[ ]" => (false, &rest[3..]),
+        "[x]" | "[X]" => (true, &rest[3..]),
+        _ => return None,
+    };
+    Some((done, text.trim()))
+}
+
+/// Every task in a single note's content.
+pub fn parse(path: &Path, content: &str) -> Vec<Task> {
+    let mut in_fence = false;
+    let mut tasks = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some((done, text)) = parse_line(line) {
+            tasks.push(Task {
+                path: path.to_path_buf(),
+                line: i + 1,
+                text: text.to_string(),
+                done,
+                priority: Priority::parse(text),
+            });
+        }
+    }
+
+    tasks
+}
+
+/// Every task in the vault, in note path order.
+pub fn scan(notes_dir: &Path) -> Vec<Task> {
+    vault::notes(notes_dir)
+        .into_iter()
+        .flat_map(|rel| {
+            let content = fs::read_to_string(notes_dir.join(&rel)).unwrap_or_default();
+            parse(&rel, &content)
+        })
+        .collect()
+}
+
+/// Open tasks in the vault, highest priority first, then in note path order.
+pub fn open_tasks(notes_dir: &Path) -> Vec<Task> {
+    let mut open: Vec<_> = scan(notes_dir).into_iter().filter(|t| !t.done).collect();
+    open.sort_by_key(|t| (t.priority.is_none(), t.priority));
+    open
+}
+
+/// Open tasks in the vault, one `N. path:line: text` per line, or per a
+/// `--format` template (with `{number}` for N). N is the task's number in
+/// `kno done`, kept when filtering. With `min_priority`, only tasks at that
+/// priority or above are listed; with `notes`, only tasks in those notes.
+pub fn list_open(
+    notes_dir: &Path,
+    min_priority: Option<Priority>,
+    notes: Option<&[PathBuf]>,
+    template: Option<&str>,
+) -> String {
+    open_tasks(notes_dir)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, t)| min_priority.is_none_or(|min| t.priority.is_some_and(|p| p <= min)))
+        .filter(|(_, t)| notes.is_none_or(|notes| notes.contains(&t.path)))
+        .map(|(i, t)| match template {
+            Some(template) => {
+                let mut values = t.format_values();
+                values.push(("number", (i + 1).to_string()));
+                format::render(template, &values) + "\n"
+            }
+            None => format!(
+                "{:>3}. {}:{}: {}\n",
+                i + 1,
+                t.path.display(),
+                t.line,
+                t.text
+            ),
+        })
+        .collect()
+}
+
+/// Width of the bars drawn by `progress`.
+const PROGRESS_BAR_WIDTH: usize = 10;
+
+fn progress_bar(done: usize, total: usize) -> String {
+    let filled = (done * PROGRESS_BAR_WIDTH + total / 2) / total;
+    format!(
+        "{}{}",
+        "▓".repeat(filled),
+        "░".repeat(PROGRESS_BAR_WIDTH - filled)
+    )
+}
+
+/// Checklist completion per note with tasks, plus totals for each directory
+/// above them, one aligned `path  done/total  ▓▓▓░░` row each in path order.
+pub fn progress(tasks: &[Task]) -> String {
+    let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for task in tasks {
+        let mut rows = vec![task.path.display().to_string()];
+        for dir in task.path.ancestors().skip(1) {
+            if !dir.as_os_str().is_empty() {
+                rows.push(format!("{}/", dir.display()));
+            }
+        }
+        for row in rows {
+            let (done, total) = counts.entry(row).or_default();
+            *done += usize::from(task.done);
+            *total += 1;
+        }
+    }
+
+    let width = counts.keys().map(|k| k.chars().count()).max().unwrap_or(0);
+    let count_width = counts
+        .values()
+        .map(|(done, total)| format!("{done}/{total}").len())
+        .max()
+        .unwrap_or(0);
+    counts
+        .iter()
+        .map(|(path, &(done, total))| {
+            let count = format!("{done}/{total}");
+            format!(
+                "{path:<width$}  {count:>count_width$}  {}\n",
+                progress_bar(done, total)
+            )
+        })
+        .collect()
+}
+
+/// Parse a selection like `1 3-5,7` into 0-based indices into a list of `len` items.
+pub fn parse_selection(input: &str, len: usize) -> Result<Vec<usize>, String> {
+    let mut picked = Vec::new();
+    for part in input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|p| !p.is_empty())
+    {
+        let (start, end) = match part.split_once('-') {
+            Some((a, b)) => (a, b),
+            None => (part, part),
+        };
+        let parse = |s: &str| match s.parse::<usize>() {
+            Ok(n) if (1..=len).contains(&n) => Ok(n),
+            _ => Err(format!("invalid task number: {part}")),
+        };
+        for n in parse(start)?..=parse(end)? {
+            if !picked.contains(&(n - 1)) {
+                picked.push(n - 1);
+            }
+        }
+    }
+    Ok(picked)
+}
+
+/// Mark the checklist item on `line` as done, optionally appending a date.
+fn check_off(line: &str, date: Option<&str>) -> String {
+    let checked = line.replacen("[ ]", "[x]", 1);
+    match date {
+        Some(date) => format!("{} ✅ {date}", checked.trim_end()),
+        None => checked,
+    }
+}
+
+/// Check off the tasks at `lines` (1-based) in `content`. With `move_to_done`,
+/// completed tasks are moved under a `## Done` heading, created if missing.
+pub fn complete_in(
+    content: &str,
+    lines: &[usize],
+    date: Option<&str>,
+    move_to_done: bool,
+) -> String {
+    let mut kept = Vec::new();
+    let mut completed = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        match lines.contains(&(i + 1)) {
+            true if move_to_done => completed.push(check_off(line.trim_start(), date)),
+            true => kept.push(check_off(line, date)),
+            false => kept.push(line.to_string()),
+        }
+    }
+
+    if !completed.is_empty() {
+        match kept.iter().position(|l| l.trim() == "## Done") {
+            Some(heading) => {
+                let insert_at = heading
+                    + 1
+                    + usize::from(kept.get(heading + 1).is_some_and(|l| l.trim().is_empty()));
+                kept.splice(insert_at..insert_at, completed);
+            }
+            None => {
+                while kept.last().is_some_and(|l| l.trim().is_empty()) {
+                    kept.pop();
+                }
+                kept.extend(["".to_string(), "## Done".to_string(), "".to_string()]);
+                kept.extend(completed);
+            }
+        }
+    }
+
+    kept.join("\n") + "\n"
+}
+
+/// Check off `tasks` across the vault, rewriting each affected note once.
+pub fn complete(notes_dir: &Path, tasks: &[&Task], date: Option<&str>, move_to_done: bool) {
+    let mut paths: Vec<&PathBuf> = tasks.iter().map(|t| &t.path).collect();
+    paths.sort();
+    paths.dedup();
+
+    for path in paths {
+        let lines: Vec<usize> = tasks
+            .iter()
+            .filter(|t| &t.path == path)
+            .map(|t| t.line)
+            .collect();
+        let file_path = notes_dir.join(path);
+        if crate::dry_run::skip(format_args!(
+            "check off {} task(s) in {}",
+            lines.len(),
+            file_path.display()
+        )) {
+            continue;
+        }
+        let content = fs::read_to_string(&file_path).expect("failed to read note");
+        fs::write(
+            &file_path,
+            complete_in(&content, &lines, date, move_to_done),
+        )
+        .expect("failed to update note");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line("- [ ] call bo"), Some((false, "call bo")));
+        assert_eq!(parse_line("  * [x] shipped"), Some((true, "shipped")));
+        assert_eq!(parse_line("+ [X] done"), Some((true, "done")));
+        assert_eq!(parse_line("- plain item"), None);
+        assert_eq!(parse_line("[ ] no bullet"), None);
+        assert_eq!(parse_line("-[ ] no space"), None);
+    }
+
+    #[test]
+    fn test_parse_skips_code_fences() {
+        let content = "# T\n\n- [ ] real\n```\n- [ ] example\n```\n- [x] old\n";
+        let tasks = parse(Path::new("t.md"), content);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].line, 3);
+        assert_eq!(tasks[0].text, "real");
+        assert!(tasks[1].done);
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut tasks = parse(
+            Path::new("projects/launch.md"),
+            "- [x] a\n- [x] b\n- [ ] c\n- [ ] d\n",
+        );
+        tasks.extend(parse(
+            Path::new("projects/site/redesign.md"),
+            "- [x] a\n- [ ] b\n",
+        ));
+        tasks.extend(parse(Path::new("inbox.md"), "- [x] a\n"));
+        assert_eq!(
+            progress(&tasks),
+            "inbox.md                   1/1  ▓▓▓▓▓▓▓▓▓▓\n\
+             projects/                  3/6  ▓▓▓▓▓░░░░░\n\
+             projects/launch.md         2/4  ▓▓▓▓▓░░░░░\n\
+             projects/site/             1/2  ▓▓▓▓▓░░░░░\n\
+             projects/site/redesign.md  1/2  ▓▓▓▓▓░░░░░\n"
+        );
+        assert_eq!(progress(&[]), "");
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        assert_eq!(parse_selection("1 3-4,2", 5), Ok(vec![0, 2, 3, 1]));
+        assert_eq!(parse_selection("2 2\n", 5), Ok(vec![1]));
+        assert!(parse_selection("0", 5).is_err());
+        assert!(parse_selection("6", 5).is_err());
+        assert!(parse_selection("x", 5).is_err());
+    }
+
+    #[test]
+    fn test_complete_in_place_with_date() {
+        let content = "# T\n\n- [ ] a\n  - [ ] b\n";
+        assert_eq!(
+            complete_in(content, &[4], Some("2026-02-15"), false),
+            "# T\n\n- [ ] a\n  - [x] b ✅ 2026-02-15\n"
+        );
+    }
+
+    #[test]
+    fn test_complete_moves_to_new_done_section() {
+        let content = "# T\n\n- [ ] a\n- [ ] b\n\n";
+        assert_eq!(
+            complete_in(content, &[3], None, true),
+            "# T\n\n- [ ] b\n\n## Done\n\n- [x] a\n"
+        );
+    }
+
+    #[test]
+    fn test_complete_moves_to_existing_done_section() {
+        let content = "# T\n\n- [ ] a\n\n## Done\n\n- [x] old\n";
+        assert_eq!(
+            complete_in(content, &[3], None, true),
+            "# T\n\n\n## Done\n\n- [x] a\n- [x] old\n"
+        );
+    }
+
+    #[test]
+    fn test_complete_across_notes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(tmp.path().join("a.md"), "- [ ] one\n- [ ] two\n").unwrap();
+        fs::write(tmp.path().join("b.md"), "- [ ] three\n").unwrap();
+
+        let open = open_tasks(tmp.path());
+        complete(tmp.path(), &[&open[1], &open[2]], None, false);
+
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("a.md")).unwrap(),
+            "- [ ] one\n- [x] two\n"
+        );
+        assert_eq!(
+            fs::read_to_string(tmp.path().join("b.md")).unwrap(),
+            "- [x] three\n"
+        );
+    }
+
+    #[test]
+    fn test_list_open() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("sql")).unwrap();
+        fs::write(
+            tmp.path().join("sql/joins.md"),
+            "# Joins\n\n- [ ] add examples\n",
+        )
+        .unwrap();
+        fs::write(tmp.path().join("inbox.md"), "- [x] done\n- [ ] triage\n").unwrap();
+
+        assert_eq!(
+            list_open(tmp.path(), None, None, None),
+            "  1. inbox.md:2: triage\n  2. sql/joins.md:3: add examples\n"
+        );
+    }
+
+    #[test]
+    fn test_priority_parse() {
+        assert_eq!(Priority::parse("(A) call bo"), Some(Priority::High));
+        assert_eq!(Priority::parse("(C) later"), Some(Priority::Low));
+        assert_eq!(Priority::parse("fix prod !!"), Some(Priority::High));
+        assert_eq!(Priority::parse("review !Medium"), Some(Priority::Medium));
+        assert_eq!(Priority::parse("tidy !low"), Some(Priority::Low));
+        assert_eq!(Priority::parse("wow! nice"), None);
+        assert_eq!(Priority::parse("plain"), None);
+    }
+
+    #[test]
+    fn test_list_open_sorted_and_filtered_by_priority() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::write(
+            tmp.path().join("a.md"),
+            "- [ ] plain\n- [ ] tidy !low\n- [ ] (A) urgent\n- [ ] review !medium\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            list_open(tmp.path(), None, None, None),
+            "  1. a.md:3: (A) urgent\n  2. a.md:4: review !medium\n  3. a.md:2: tidy !low\n  4. a.md:1: plain\n"
+        );
+        assert_eq!(
+            list_open(tmp.path(), Some(Priority::Medium), None, None),
+            "  1. a.md:3: (A) urgent\n  2. a.md:4: review !medium\n"
+        );
+        assert_eq!(
+            list_open(
+                tmp.path(),
+                Some(Priority::Low),
+                None,
+                Some(r"{priority}\t{text}")
+            ),
+            "high\t(A) urgent\nmedium\treview !medium\nlow\ttidy !low\n"
+        );
+
+        // Numbers match `kno done` even when tasks are filtered out
+        let notes = [PathBuf::from("b.md")];
+        fs::write(tmp.path().join("b.md"), "- [ ] elsewhere\n").unwrap();
+        assert_eq!(
+            list_open(tmp.path(), None, Some(&notes), Some("{number} {text}")),
+            "5 elsewhere\n"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, Locale};
+
+/// Directory inside the notes dir holding user-provided templates.
+pub const TEMPLATES_DIR_NAME: &str = ".templates";
+
+/// Values available to a template when a note is created.
+///
+/// Built-in variables:
+/// - `{{date}}` / `{{date:<strftime>}}` — creation date in `date_format` (from
+///   `[dates] format`, `%Y-%m-%d` by default) or the given format, with month
+///   and day names in `locale`
+/// - `{{time}}` — creation time as `%H:%M`
+/// - `{{title}}` — note title
+/// - `{{path}}` — note path relative to the notes dir
+/// - `{{env:VAR}}` — environment variable, empty if unset
+/// - `{{cursor}}` — removed from the output; marks where the editor should start
+///
+/// `extra` holds command-specific variables (e.g. `attendees`) and takes
+/// precedence over the built-ins.
+pub struct Vars<'a> {
+    pub title: &'a str,
+    pub path: &'a str,
+    pub now: DateTime<Local>,
+    pub date_format: &'a str,
+    pub locale: Locale,
+    pub extra: &'a [(&'a str, &'a str)],
+}
+
+/// A rendered template along with the 1-based line of `{{cursor}}`, if present.
+pub struct Rendered {
+    pub content: String,
+    pub cursor: Option<usize>,
+}
+
+/// Load `.templates/<name>.md` from the notes dir, falling back to `default`
+/// when the user hasn't provided one.
+pub fn load(notes_dir: &Path, name: &str, default: &str) -> String {
+    let path = notes_dir
+        .join(TEMPLATES_DIR_NAME)
+        .join(format!("{name}.md"));
+    fs::read_to_string(path).unwrap_or_else(|_| default.to_string())
+}
+
+/// Format `now` with a strftime string, naming months and days in `locale`.
+/// `None` if the format is invalid.
+pub fn format_date(now: &DateTime<Local>, fmt: &str, locale: Locale) -> Option<String> {
+    let items: Vec<_> = StrftimeItems::new_with_locale(fmt, locale).collect();
+    if items.iter().any(|i| matches!(i, Item::Error)) {
+        return None;
+    }
+    let mut out = String::new();
+    write!(
+        out,
+        "{}",
+        now.format_localized_with_items(items.into_iter(), locale)
+    )
+    .ok()?;
+    Some(out)
+}
+
+fn lookup(key: &str, vars: &Vars) -> Option<String> {
+    if let Some((_, value)) = vars.extra.iter().find(|(k, _)| *k == key) {
+        return Some(value.to_string());
+    }
+
+    match key.split_once(':') {
+        Some(("date", fmt)) => format_date(&vars.now, fmt, vars.locale),
+        Some(("env", name)) => Some(env::var(name).unwrap_or_default()),
+        Some(_) => None,
+        None => match key {
+            "date" => format_date(&vars.now, vars.date_format, vars.locale),
+            "time" => format_date(&vars.now, "%H:%M", vars.locale),
+            "title" => Some(vars.title.to_string()),
+            "path" => Some(vars.path.to_string()),
+            _ => None,
+        },
+    }
+}
+
+/// Replace `{{key}}` placeholders using `vars`.
+/// Unknown keys are left untouched so typos are visible in the note.
+pub fn render(template: &str, vars: &Vars) -> Rendered {
+    let mut output = String::with_capacity(template.len());
+    let mut cursor_offset = None;
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let key = after[..end].trim();
+        match key {
+            "cursor" => {
+                cursor_offset.get_or_insert(output.len());
+            }
+            _ => match lookup(key, vars) {
+                Some(value) => output.push_str(&value),
+                None => output.push_str(&rest[start..start + 2 + end + 2]),
+            },
+        }
+        rest = &after[end + 2..];
+    }
+
+    output.push_str(rest);
+    let cursor = cursor_offset.map(|offset| output[..offset].matches('\n').count() + 1);
+    Rendered {
+        content: output,
+        cursor,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn vars<'a>(extra: &'a [(&'a str, &'a str)]) -> Vars<'a> {
+        Vars {
+            title: "Joins",
+            path: "sql/joins.md",
+            now: Local.with_ymd_and_hms(2026, 2, 15, 9, 5, 0).unwrap(),
+            date_format: "%Y-%m-%d",
+            locale: Locale::POSIX,
+            extra,
+        }
+    }
+
+    #[test]
+    fn test_render_substitutes_vars() {
+        let out = render("# {{title}} on {{ date }}", &vars(&[]));
+        assert_eq!(out.content, "# Joins on 2026-02-15");
+        assert_eq!(out.cursor, None);
+    }
+
+    #[test]
+    fn test_render_extra_vars_take_precedence() {
+        let out = render(
+            "{{title}}: {{attendees}}",
+            &vars(&[("title", "Sync"), ("attendees", "ana")]),
+        );
+        assert_eq!(out.content, "Sync: ana");
+    }
+
+    #[test]
+    fn test_render_date_format_and_time() {
+        let out = render("{{date:%A %d %B}} {{time}}", &vars(&[]));
+        assert_eq!(out.content, "Sunday 15 February 09:05");
+    }
+
+    #[test]
+    fn test_render_localized_date() {
+        let mut vars = vars(&[]);
+        vars.date_format = "%A, %-d. %B %Y";
+        vars.locale = Locale::de_DE;
+        let out = render("{{date}} / {{date:%a %d %b}}", &vars);
+        assert_eq!(out.content, "Sonntag, 15. Februar 2026 / So 15 Feb");
+    }
+
+    #[test]
+    fn test_render_invalid_date_format_kept() {
+        let out = render("{{date:%Q}}", &vars(&[]));
+        assert_eq!(out.content, "{{date:%Q}}");
+    }
+
+    #[test]
+    fn test_render_path_and_env() {
+        let path = env::var("PATH").unwrap();
+        let out = render("{{path}} {{env:PATH}} [{{env:KNO_UNSET_VAR}}]", &vars(&[]));
+        assert_eq!(out.content, format!("sql/joins.md {path} []"));
+    }
+
+    #[test]
+    fn test_render_cursor_line() {
+        let out = render("# {{title}}\n\n{{cursor}}\n", &vars(&[]));
+        assert_eq!(out.content, "# Joins\n\n\n");
+        assert_eq!(out.cursor, Some(3));
+    }
+
+    #[test]
+    fn test_render_keeps_unknown_vars() {
+        assert_eq!(render("{{nope}} x", &vars(&[])).content, "{{nope}} x");
+    }
+
+    #[test]
+    fn test_render_unterminated_placeholder() {
+        assert_eq!(render("a {{title", &vars(&[])).content, "a {{title");
+    }
+
+    #[test]
+    fn test_load_falls_back_to_default() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        assert_eq!(load(tmp.path(), "meeting", "default"), "default");
+    }
+
+    #[test]
+    fn test_load_prefers_user_template() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join(TEMPLATES_DIR_NAME)).unwrap();
+        fs::write(tmp.path().join(".templates/meeting.md"), "custom").unwrap();
+        assert_eq!(load(tmp.path(), "meeting", "default"), "custom");
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use sha2::{Digest, Sha256};
+
+use crate::{dry_run, log};
+
+const RELEASES_API: &str = "https://api.github.com/repos/farmeroy/kno/releases/latest";
+
+/// A downloadable release for this platform.
+#[derive(Debug, PartialEq)]
+pub struct Release {
+    pub version: String,
+    pub binary_url: String,
+    pub checksum_url: String,
+}
+
+/// Release asset name for the running platform, e.g. `kno-x86_64-linux`.
+fn asset_name() -> String {
+    format!("kno-{}-{}", std::env::consts::ARCH, std::env::consts::OS)
+}
+
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-'])
+        .map_while(|p| p.parse().ok())
+        .collect()
+}
+
+/// Whether release `tag` (e.g. `v0.2.0`) is newer than `current`.
+pub fn is_newer(current: &str, tag: &str) -> bool {
+    version_parts(tag) > version_parts(current)
+}
+
+fn parse_release(body: &str, asset: &str) -> Result<Release, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(body).map_err(|_| "unexpected response from GitHub".to_string())?;
+    let Some(version) = value["tag_name"].as_str() else {
+        return Err(value["message"]
+            .as_str()
+            .unwrap_or("no release found")
+            .to_string());
+    };
+
+    let url_of = |name: &str| {
+        value["assets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|a| a["name"] == name)
+            .and_then(|a| a["browser_download_url"].as_str())
+            .map(str::to_string)
+    };
+    let checksum_name = format!("{asset}.sha256");
+    match (url_of(asset), url_of(&checksum_name)) {
+        (Some(binary_url), Some(checksum_url)) => Ok(Release {
+            version: version.to_string(),
+            binary_url,
+            checksum_url,
+        }),
+        (None, _) => Err(format!("release {version} has no {asset} binary")),
+        (Some(_), None) => Err(format!("release {version} has no {checksum_name}")),
+    }
+}
+
+fn curl(url: &str, out: Option<&Path>) -> Result<Vec<u8>, String> {
+    log::debug!("fetching {url}");
+    let mut cmd = process::Command::new("curl");
+    cmd.args(["--silent", "--show-error", "--fail", "--location", url]);
+    if let Some(out) = out {
+        cmd.arg("--output").arg(out);
+    }
+    let output = cmd
+        .output()
+        .map_err(|e| format!("failed to run curl: {e}"))?;
+    match output.status.success() {
+        true => Ok(output.stdout),
+        false => Err(format!(
+            "download failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+/// The latest release, fetched from GitHub.
+pub fn latest() -> Result<Release, String> {
+    let body = curl(RELEASES_API, None)?;
+    parse_release(&String::from_utf8_lossy(&body), &asset_name())
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Check `bytes` against a `sha256sum`-style checksum file (`<hex>  <name>`).
+fn verify(bytes: &[u8], checksum_file: &str) -> Result<(), String> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .ok_or("empty checksum file")?
+        .to_lowercase();
+    let actual = sha256_hex(bytes);
+    match actual == expected {
+        true => Ok(()),
+        false => Err(format!(
+            "checksum mismatch (expected {expected}, got {actual})"
+        )),
+    }
+}
+
+/// Download `release`, verify it and replace the running executable with it.
+pub fn install(release: &Release) -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("can't find the kno binary: {e}"))?;
+    if dry_run::skip(format_args!(
+        "replace {} with {}",
+        exe.display(),
+        release.version
+    )) {
+        return Ok(exe);
+    }
+    replace(release, &exe)?;
+    Ok(exe)
+}
+
+/// Download the release over `exe`, removing the partial download if any
+/// step fails.
+fn replace(release: &Release, exe: &Path) -> Result<(), String> {
+    // Download next to the executable so the final rename stays on one filesystem
+    let staged = exe.with_extension("download");
+    let result = stage(release, &staged).and_then(|()| {
+        fs::rename(&staged, exe).map_err(|e| format!("failed to replace {}: {e}", exe.display()))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+    result
+}
+
+/// Download the release binary to `staged`, check it and make it executable.
+fn stage(release: &Release, staged: &Path) -> Result<(), String> {
+    curl(&release.binary_url, Some(staged))?;
+    let checksum = curl(&release.checksum_url, None)?;
+    let bytes = fs::read(staged).map_err(|e| format!("failed to read download: {e}"))?;
+    verify(&bytes, &String::from_utf8_lossy(&checksum))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(staged, fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("failed to make download executable: {e}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_cleans_up_failed_download() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let exe = tmp.path().join("kno");
+        fs::write(&exe, "old").unwrap();
+        let binary = tmp.path().join("release-kno");
+        fs::write(&binary, "new").unwrap();
+        let url = |path: &Path| format!("file://{}", path.display());
+        let mut release = Release {
+            version: "v0.2.0".into(),
+            binary_url: url(&binary),
+            checksum_url: url(&tmp.path().join("missing.sha256")),
+        };
+
+        // The checksum can't be fetched after the binary was
+        assert!(replace(&release, &exe).is_err());
+        assert!(!exe.with_extension("download").exists());
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "old");
+
+        let checksum = tmp.path().join("kno.sha256");
+        fs::write(&checksum, format!("{}  kno\n", sha256_hex(b"new"))).unwrap();
+        release.checksum_url = url(&checksum);
+        replace(&release, &exe).unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("0.1.0", "v0.2.0"));
+        assert!(is_newer("0.1.9", "0.1.10"));
+        assert!(!is_newer("0.2.0", "v0.2.0"));
+        assert!(!is_newer("1.0.0", "v0.9.0"));
+    }
+
+    #[test]
+    fn test_parse_release() {
+        let body = r#"{"tag_name": "v0.2.0", "assets": [
+            {"name": "kno-x86_64-linux", "browser_download_url": "https://dl/kno"},
+            {"name": "kno-x86_64-linux.sha256", "browser_download_url": "https://dl/kno.sha256"}
+        ]}"#;
+        assert_eq!(
+            parse_release(body, "kno-x86_64-linux"),
+            Ok(Release {
+                version: "v0.2.0".into(),
+                binary_url: "https://dl/kno".into(),
+                checksum_url: "https://dl/kno.sha256".into(),
+            })
+        );
+        assert_eq!(
+            parse_release(body, "kno-aarch64-macos"),
+            Err("release v0.2.0 has no kno-aarch64-macos binary".into())
+        );
+        assert_eq!(
+            parse_release(r#"{"message": "Not Found"}"#, "x"),
+            Err("Not Found".into())
+        );
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let hex = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify(b"hello", &format!("{hex}  kno-x86_64-linux\n")).is_ok());
+        assert!(verify(b"hellO", hex).is_err());
+        assert!(verify(b"hello", "").is_err());
+    }
+}
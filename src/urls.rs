@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+use crate::config::LintConfig;
+use crate::verify::Issue;
+use crate::{dry_run, frontmatter, log, vault};
+
+/// File recording URLs that answered, one `<unix secs>\t<url>` per line, so
+/// `kno lint --urls` doesn't check them again on every run.
+pub const URL_CACHE_FILE_NAME: &str = ".url-cache";
+
+/// The `http(s)` links and images in `content`, with the line each is on.
+pub fn extract(content: &str) -> Vec<(usize, String)> {
+    let body = frontmatter::body(content);
+    let offset = content.len() - body.len();
+    let mut urls = Vec::new();
+    for (event, range) in Parser::new_ext(body, Options::all()).into_offset_iter() {
+        let (Event::Start(Tag::Link { dest_url, .. }) | Event::Start(Tag::Image { dest_url, .. })) =
+            event
+        else {
+            continue;
+        };
+        if dest_url.starts_with("http://") || dest_url.starts_with("https://") {
+            let line = content[..offset + range.start].matches('\n').count() + 1;
+            urls.push((line, dest_url.into_string()));
+        }
+    }
+    urls
+}
+
+fn read_cache(notes_dir: &Path) -> HashMap<String, u64> {
+    let content = fs::read_to_string(notes_dir.join(URL_CACHE_FILE_NAME)).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let (secs, url) = line.split_once('\t')?;
+            Some((url.to_string(), secs.parse().ok()?))
+        })
+        .collect()
+}
+
+fn write_cache(notes_dir: &Path, cache: &HashMap<String, u64>) {
+    let mut entries: Vec<_> = cache.iter().collect();
+    entries.sort();
+    let content: String = entries
+        .iter()
+        .map(|(url, secs)| format!("{secs}\t{url}\n"))
+        .collect();
+    fs::write(notes_dir.join(URL_CACHE_FILE_NAME), content).expect("failed to write URL cache");
+}
+
+/// The `curl` command fetching `url`. Brackets and braces in the URL are
+/// taken literally rather than as curl globs, and neither the URL nor a
+/// redirect may switch to a protocol other than http(s), e.g. `file://`.
+fn curl_cmd(url: &str, timeout_secs: u64) -> process::Command {
+    let null = match cfg!(windows) {
+        true => "NUL",
+        false => "/dev/null",
+    };
+    let mut cmd = process::Command::new("curl");
+    cmd.args(["--silent", "--show-error", "--location", "--globoff"])
+        .args(["--proto", "=http,https", "--proto-redir", "=http,https"])
+        .args([
+            "--output",
+            null,
+            "--write-out",
+            "%{http_code}",
+            "--max-time",
+        ])
+        .arg(timeout_secs.to_string())
+        .arg(url);
+    cmd
+}
+
+/// Fetch `url` with `curl`, following redirects. Any response below 400
+/// counts as alive.
+fn check_url(url: &str, timeout_secs: u64) -> Result<(), String> {
+    let output = curl_cmd(url, timeout_secs)
+        .output()
+        .map_err(|e| format!("failed to run curl: {e}"))?;
+    match String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<u16>()
+    {
+        Ok(1..400) => Ok(()),
+        Ok(0) | Err(_) => {
+            // `curl: (7) Failed to connect ...`
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let error = stderr.trim().trim_start_matches("curl: ");
+            let error = error
+                .split_once(") ")
+                .filter(|(code, _)| code.starts_with('('))
+                .map_or(error, |(_, message)| message);
+            Err(error.to_string())
+        }
+        Ok(code) => Err(format!("HTTP {code}")),
+    }
+}
+
+/// Check `urls` with up to `concurrency` requests in flight.
+fn check_all(
+    urls: &[&str],
+    timeout_secs: u64,
+    concurrency: usize,
+) -> HashMap<String, Result<(), String>> {
+    let queue = Mutex::new(urls.iter());
+    let results = Mutex::new(HashMap::new());
+    let level = log::level();
+    thread::scope(|s| {
+        for _ in 0..concurrency.clamp(1, urls.len().max(1)) {
+            s.spawn(|| {
+                log::set_level(level);
+                loop {
+                    let next = queue.lock().unwrap().next();
+                    let Some(url) = next else {
+                        break;
+                    };
+                    log::debug!("checking {url}");
+                    let result = check_url(url, timeout_secs);
+                    results.lock().unwrap().insert(url.to_string(), result);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap()
+}
+
+/// Check every external link in the vault, skipping URLs that answered
+/// within the last `cache_hours`. Returns an issue for each link, by note and
+/// line, whose URL is dead.
+pub fn check(notes_dir: &Path, config: &LintConfig) -> Vec<Issue> {
+    let mut links: Vec<(PathBuf, usize, String)> = Vec::new();
+    for rel in vault::notes(notes_dir) {
+        let content = fs::read_to_string(notes_dir.join(&rel)).unwrap_or_default();
+        for (line, url) in extract(&content) {
+            links.push((rel.clone(), line, url));
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut cache = read_cache(notes_dir);
+    cache.retain(|_, checked| now.saturating_sub(*checked) < config.cache_hours * 3600);
+
+    let mut distinct: Vec<&str> = links.iter().map(|(_, _, url)| url.as_str()).collect();
+    distinct.sort();
+    distinct.dedup();
+    let pending: Vec<&str> = distinct
+        .iter()
+        .copied()
+        .filter(|url| !cache.contains_key(*url))
+        .collect();
+    log::debug!(
+        "checking {} URLs ({} cached)",
+        pending.len(),
+        distinct.len() - pending.len()
+    );
+    let results = check_all(&pending, config.timeout_secs, config.concurrency);
+
+    for (url, result) in &results {
+        if result.is_ok() {
+            cache.insert(url.clone(), now);
+        }
+    }
+    if !dry_run::is_enabled() {
+        write_cache(notes_dir, &cache);
+    }
+
+    links
+        .iter()
+        .filter_map(|(rel, line, url)| {
+            let Some(Err(e)) = results.get(url) else {
+                return None;
+            };
+            Some(Issue::new(
+                rel,
+                format!("line {line}: dead link {url} ({e})"),
+                "update the link, or remove it if the page is gone",
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract() {
+        let content = "---\nurl: https://in.frontmatter\n---\n# Links\n\n\
+                       See [docs](https://docs.rs/x) and <http://example.com>.\n\
+                       ![chart](https://img.example.com/c.png) [local](sql/joins.md)\n\n\
+                       `[code](https://not.a.link)`\n";
+        assert_eq!(
+            extract(content),
+            [
+                (6, "https://docs.rs/x".to_string()),
+                (6, "http://example.com".to_string()),
+                (7, "https://img.example.com/c.png".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_curl_cmd() {
+        let cmd = curl_cmd("https://example.com/a[1]{b}", 5);
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy()).collect();
+        assert!(args.contains(&"--globoff".into()));
+        let proto = args.iter().position(|a| a == "--proto").unwrap();
+        assert_eq!(args[proto + 1], "=http,https");
+        let redir = args.iter().position(|a| a == "--proto-redir").unwrap();
+        assert_eq!(args[redir + 1], "=http,https");
+        assert_eq!(args.last().unwrap(), "https://example.com/a[1]{b}");
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        assert!(read_cache(tmp.path()).is_empty());
+        let cache = HashMap::from([("https://docs.rs/x".to_string(), 1_700_000_000)]);
+        write_cache(tmp.path(), &cache);
+        assert_eq!(read_cache(tmp.path()), cache);
+    }
+}
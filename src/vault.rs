@@ -0,0 +1,757 @@
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::NaiveDate;
+
+use crate::dedupe::TRASH_DIR_NAME;
+use crate::locks::LOCKS_DIR_NAME;
+use crate::scratch::SCRATCH_DIR_NAME;
+use crate::template::TEMPLATES_DIR_NAME;
+use crate::{frontmatter, log};
+
+/// File recording notes opened through kno, one `<unix secs>\t<path>` per line.
+pub const HISTORY_FILE_NAME: &str = ".history";
+
+/// Entries kept in the history file; older ones are dropped on write.
+const HISTORY_LIMIT: usize = 1000;
+
+/// Dot-directories holding kno's own files, never walked even with `--hidden`.
+const INTERNAL_DIRS: &[&str] = &[
+    ".git",
+    TEMPLATES_DIR_NAME,
+    SCRATCH_DIR_NAME,
+    TRASH_DIR_NAME,
+    LOCKS_DIR_NAME,
+];
+
+// Set once from `--follow-symlinks` and `--hidden`; thread-local like the
+// dry-run flag.
+thread_local! {
+    static FOLLOW_SYMLINKS: Cell<bool> = const { Cell::new(false) };
+    static SHOW_HIDDEN: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn set_follow_symlinks(enabled: bool) {
+    FOLLOW_SYMLINKS.with(|f| f.set(enabled));
+}
+
+pub fn set_show_hidden(enabled: bool) {
+    SHOW_HIDDEN.with(|h| h.set(enabled));
+}
+
+fn show_hidden() -> bool {
+    SHOW_HIDDEN.with(|h| h.get())
+}
+
+/// Whether a listing of the vault skips the entry `name`: dot-prefixed notes
+/// and directories are hidden unless `--hidden` is given, and kno's own
+/// directories always are. Hidden notes can still be opened by path.
+pub fn is_skipped(name: &str, is_dir: bool) -> bool {
+    (is_dir && INTERNAL_DIRS.contains(&name)) || (name.starts_with('.') && !show_hidden())
+}
+
+/// Whether the note at `rel` is hidden, being dot-prefixed or inside a
+/// dot-prefixed directory.
+pub fn is_hidden(rel: &Path) -> bool {
+    rel.components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+/// The real paths of the directories a walk is inside, root first, used to
+/// catch symlinks that lead back up the tree.
+pub fn walk_root(dir: &Path) -> Vec<PathBuf> {
+    match FOLLOW_SYMLINKS.with(|f| f.get()) {
+        true => fs::canonicalize(dir).into_iter().collect(),
+        false => Vec::new(),
+    }
+}
+
+/// Whether a walk inside `ancestors` (see `walk_root`) should descend into
+/// the directory `path`, returning the entry to push onto `ancestors` while
+/// it does. Symlinked directories are only entered with `--follow-symlinks`,
+/// and never when they lead back into a directory the walk is already in.
+pub fn descend(path: &Path, ancestors: &[PathBuf]) -> Option<PathBuf> {
+    if !FOLLOW_SYMLINKS.with(|f| f.get()) {
+        return (!path.is_symlink()).then(|| path.to_path_buf());
+    }
+    let real = fs::canonicalize(path).ok()?;
+    if ancestors.contains(&real) {
+        log::debug!(
+            "not following {}: it loops back to {}",
+            path.display(),
+            real.display()
+        );
+        return None;
+    }
+    Some(real)
+}
+
+fn collect(
+    notes_dir: &Path,
+    dir: &Path,
+    all_files: bool,
+    ancestors: &mut Vec<PathBuf>,
+    out: &mut Vec<PathBuf>,
+) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let is_dir = path.is_dir();
+        if is_dir && INTERNAL_DIRS.contains(&&*entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        match is_dir {
+            true => {
+                if let Some(real) = descend(&path, ancestors) {
+                    ancestors.push(real);
+                    collect(notes_dir, &path, all_files, ancestors, out);
+                    ancestors.pop();
+                }
+            }
+            false if all_files || path.extension().is_some_and(|ext| ext == "md") => {
+                out.push(path.strip_prefix(notes_dir).unwrap().to_path_buf());
+            }
+            false => {}
+        }
+    }
+}
+
+/// Every note in the vault as a path relative to the notes dir, sorted,
+/// hidden ones included. kno's own directories (`.git`, `.templates`, ...)
+/// are skipped, as are symlinked directories unless `--follow-symlinks` is
+/// given.
+pub fn notes(notes_dir: &Path) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    collect(
+        notes_dir,
+        notes_dir,
+        false,
+        &mut walk_root(notes_dir),
+        &mut out,
+    );
+    out.sort();
+    out
+}
+
+/// Every file in the vault, notes and attachments alike, walked like `notes`.
+pub fn files(notes_dir: &Path) -> Vec<PathBuf> {
+    let mut out = Vec::new();
+    collect(
+        notes_dir,
+        notes_dir,
+        true,
+        &mut walk_root(notes_dir),
+        &mut out,
+    );
+    out.sort();
+    out
+}
+
+/// The notes that listings, search and the picker show: `notes` without
+/// hidden ones (see `is_hidden`) unless `--hidden` is given. Anything that
+/// changes the vault should walk `notes` instead.
+pub fn visible_notes(notes_dir: &Path) -> Vec<PathBuf> {
+    let mut notes = notes(notes_dir);
+    if !show_hidden() {
+        notes.retain(|rel| !is_hidden(rel));
+    }
+    notes
+}
+
+/// A short label for the kind of a file that isn't a note, like `image` or
+/// `pdf`, or `None` for notes.
+pub fn file_kind(path: &Path) -> Option<&'static str> {
+    let ext = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    Some(match ext.as_str() {
+        "md" => return None,
+        "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" | "bmp" | "heic" => "image",
+        "pdf" => "pdf",
+        "mp3" | "wav" | "ogg" | "flac" | "m4a" => "audio",
+        "mp4" | "mov" | "mkv" | "webm" => "video",
+        "zip" | "gz" | "tgz" | "tar" | "7z" => "archive",
+        "txt" | "csv" | "json" | "yaml" | "yml" | "toml" => "text",
+        _ => "file",
+    })
+}
+
+/// Shell-style match where `*` and `?` stay within one path segment and `**`
+/// spans any number of segments.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            let rest = rest.strip_prefix(&['/']).unwrap_or(rest);
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match(rest, &text[i..])),
+        ['?', rest @ ..] => text
+            .split_first()
+            .is_some_and(|(c, tail)| *c != '/' && glob_match(rest, tail)),
+        [p, rest @ ..] => text
+            .split_first()
+            .is_some_and(|(c, tail)| c == p && glob_match(rest, tail)),
+    }
+}
+
+/// Whether the relative path `rel` matches `pattern`, with or without its
+/// `.md` extension.
+pub fn glob_matches(pattern: &str, rel: &Path) -> bool {
+    let pattern: Vec<char> = pattern.trim_start_matches("./").chars().collect();
+    let full = rel.to_string_lossy();
+    let stem = full.trim_end_matches(".md");
+    [&*full, stem]
+        .iter()
+        .any(|candidate| glob_match(&pattern, &candidate.chars().collect::<Vec<_>>()))
+}
+
+/// Notes whose relative path matches `pattern`. The `.md` extension is
+/// optional in the pattern, so `sql/*` and `sql/*.md` are equivalent.
+pub fn glob(notes_dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    notes(notes_dir)
+        .into_iter()
+        .filter(|rel| glob_matches(pattern, rel))
+        .collect()
+}
+
+/// Edit distance between two strings, counted in chars.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            let next = (row[j] + 1).min(row[j + 1] + 1).min(prev + cost);
+            prev = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// Notes whose path is a likely typo of `relative_path` (a few edits away),
+/// closest first.
+pub fn similar(notes_dir: &Path, relative_path: &Path, limit: usize) -> Vec<PathBuf> {
+    let target = relative_path
+        .with_extension("")
+        .to_string_lossy()
+        .into_owned();
+    let max_distance = (target.chars().count() / 3).max(2);
+    let mut matches: Vec<(usize, PathBuf)> = notes(notes_dir)
+        .into_iter()
+        .map(|rel| {
+            (
+                levenshtein(&target, &rel.with_extension("").to_string_lossy()),
+                rel,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    matches.sort();
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, rel)| rel)
+        .collect()
+}
+
+/// Modification time of `path`, if it can be read.
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Raw history entries, oldest first, including notes that no longer exist.
+pub fn read_history(notes_dir: &Path) -> Vec<(SystemTime, PathBuf)> {
+    let content = fs::read_to_string(notes_dir.join(HISTORY_FILE_NAME)).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let (secs, path) = line.split_once('\t')?;
+            let time = UNIX_EPOCH + Duration::from_secs(secs.parse().ok()?);
+            Some((time, PathBuf::from(path)))
+        })
+        .collect()
+}
+
+/// Record `file_path` as opened now in the history file.
+pub fn record_opened(notes_dir: &Path, file_path: &Path) {
+    if crate::dry_run::is_enabled() {
+        return;
+    }
+    let relative = file_path.strip_prefix(notes_dir).unwrap_or(file_path);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let history_file = notes_dir.join(HISTORY_FILE_NAME);
+    let content = fs::read_to_string(&history_file).unwrap_or_default();
+    let mut lines: Vec<_> = content.lines().map(str::to_string).collect();
+    lines.push(format!("{now}\t{}", relative.display()));
+    let start = lines.len().saturating_sub(HISTORY_LIMIT);
+
+    // Best effort: failing to record history should never block opening a note
+    let _ = fs::write(&history_file, lines[start..].join("\n") + "\n");
+}
+
+/// Point history entries for moved notes at their new paths.
+pub fn rename_in_history(notes_dir: &Path, moves: &HashMap<PathBuf, PathBuf>) {
+    let history_file = notes_dir.join(HISTORY_FILE_NAME);
+    let Ok(content) = fs::read_to_string(&history_file) else {
+        return;
+    };
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| match line.split_once('\t') {
+            Some((secs, path)) => match moves.get(Path::new(path)) {
+                Some(new) => format!("{secs}\t{}", new.display()),
+                None => line.to_string(),
+            },
+            None => line.to_string(),
+        })
+        .collect();
+    let _ = fs::write(&history_file, lines.join("\n") + "\n");
+}
+
+/// Notes opened through kno, most recent first, without duplicates.
+/// Notes that no longer exist are skipped.
+pub fn opened_history(notes_dir: &Path) -> Vec<PathBuf> {
+    let mut seen = Vec::new();
+    for (_, path) in read_history(notes_dir).into_iter().rev() {
+        if !seen.contains(&path) && notes_dir.join(&path).is_file() {
+            seen.push(path);
+        }
+    }
+    seen
+}
+
+/// Notes ordered by modification time, most recent first.
+pub fn recently_modified(notes_dir: &Path) -> Vec<PathBuf> {
+    let mut notes: Vec<_> = notes(notes_dir)
+        .into_iter()
+        .filter_map(|rel| Some((modified(&notes_dir.join(&rel))?, rel)))
+        .collect();
+    notes.sort_by_key(|(time, _)| std::cmp::Reverse(*time));
+    notes.into_iter().map(|(_, rel)| rel).collect()
+}
+
+/// The note to return to: whichever is newer of the most recently modified
+/// note and the last note opened through kno.
+pub fn last_note(notes_dir: &Path) -> Option<PathBuf> {
+    let modified_note = notes(notes_dir)
+        .into_iter()
+        .map(|rel| notes_dir.join(rel))
+        .filter_map(|path| Some((modified(&path)?, path)))
+        .max();
+
+    let opened_note = read_history(notes_dir)
+        .into_iter()
+        .rev()
+        .map(|(time, rel)| (time, notes_dir.join(rel)))
+        .find(|(_, path)| path.is_file());
+
+    match (modified_note, opened_note) {
+        (Some(m), Some(o)) => Some(if o.0 >= m.0 { o.1 } else { m.1 }),
+        (m, o) => m.or(o).map(|(_, path)| path),
+    }
+}
+
+/// The date in a note's `expires: YYYY-MM-DD` frontmatter. `None` if the
+/// note has none, `Some(Err)` with the raw value if it isn't a date.
+pub fn expires(content: &str) -> Option<Result<NaiveDate, String>> {
+    let value = frontmatter::get(content, "expires")?;
+    Some(NaiveDate::parse_from_str(&value, "%Y-%m-%d").map_err(|_| value))
+}
+
+/// A note's title: its frontmatter `title`, else its first `# ` heading,
+/// else its file name.
+pub fn title(content: &str, relative_path: &Path) -> String {
+    frontmatter::get(content, "title")
+        .or_else(|| {
+            frontmatter::body(content)
+                .lines()
+                .find_map(|l| l.strip_prefix("# "))
+                .map(|h| h.trim().to_string())
+        })
+        .unwrap_or_else(|| {
+            relative_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+}
+
+/// Notes whose title contains `query`, ignoring case, with their titles. A
+/// note titled exactly `query` is returned alone.
+pub fn find_by_title(notes_dir: &Path, query: &str) -> Vec<(PathBuf, String)> {
+    let query = query.trim().to_lowercase();
+    let matches: Vec<_> = notes(notes_dir)
+        .into_iter()
+        .filter_map(|rel| {
+            let content = fs::read_to_string(notes_dir.join(&rel)).ok()?;
+            let title = title(&content, &rel);
+            title
+                .to_lowercase()
+                .contains(&query)
+                .then_some((rel, title))
+        })
+        .collect();
+    let exact: Vec<_> = matches
+        .iter()
+        .filter(|(_, title)| title.to_lowercase() == query)
+        .cloned()
+        .collect();
+    match exact.len() {
+        1 => exact,
+        _ => matches,
+    }
+}
+
+/// Every name in the vault's `aliases: [k8s, kube]` frontmatter, with the
+/// note it stands for. If two notes claim a name, the first by path keeps it.
+pub fn aliases(notes_dir: &Path) -> BTreeMap<String, PathBuf> {
+    let mut aliases = BTreeMap::new();
+    for rel in notes(notes_dir) {
+        let content = fs::read_to_string(notes_dir.join(&rel)).unwrap_or_default();
+        for alias in frontmatter::get_list(&content, "aliases") {
+            aliases.entry(alias).or_insert_with(|| rel.clone());
+        }
+    }
+    aliases
+}
+
+/// The note `name` is an alias for. A note actually called `name` wins, so
+/// an alias never hides one.
+pub fn resolve_alias(notes_dir: &Path, name: &str) -> Option<PathBuf> {
+    if name.ends_with('/') || notes_dir.join(format!("{name}.md")).is_file() {
+        return None;
+    }
+    aliases(notes_dir).remove(name)
+}
+
+/// Notes whose `expires` date is `today` or earlier, with that date.
+pub fn expired(notes_dir: &Path, today: NaiveDate) -> Vec<(PathBuf, NaiveDate)> {
+    notes(notes_dir)
+        .into_iter()
+        .filter_map(|rel| {
+            let content = fs::read_to_string(notes_dir.join(&rel)).ok()?;
+            let date = expires(&content)?.ok()?;
+            (date <= today).then_some((rel, date))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, rel: &str) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "# x\n").unwrap();
+    }
+
+    fn set_mtime(path: &Path, secs_ago: u64) {
+        let time = SystemTime::now() - Duration::from_secs(secs_ago);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("sql/jions", "sql/joins"), 2);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn test_similar() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "sql/joins.md");
+        write(tmp.path(), "sql/join.md");
+        write(tmp.path(), "sql/indexes.md");
+
+        assert_eq!(
+            similar(tmp.path(), Path::new("sql/jions.md"), 5),
+            [PathBuf::from("sql/joins.md"), PathBuf::from("sql/join.md")]
+        );
+        assert!(similar(tmp.path(), Path::new("rust/ownership.md"), 5).is_empty());
+    }
+
+    #[test]
+    fn test_find_by_title() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(tmp.path().join("proj")).unwrap();
+        fs::write(
+            tmp.path().join("proj/adr.md"),
+            "---\ntitle: Design Decisions\n---\n# ADRs\n",
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("proj/old.md"),
+            "# Design decisions (2024)\n",
+        )
+        .unwrap();
+        fs::write(tmp.path().join("design-notes.md"), "no heading\n").unwrap();
+
+        let found = |q: &str| -> Vec<PathBuf> {
+            find_by_title(tmp.path(), q)
+                .into_iter()
+                .map(|(rel, _)| rel)
+                .collect()
+        };
+        assert_eq!(found("design decisions"), [PathBuf::from("proj/adr.md")]);
+        assert_eq!(
+            found("DESIGN"),
+            ["design-notes.md", "proj/adr.md", "proj/old.md"].map(PathBuf::from)
+        );
+        assert_eq!(found("2024"), [PathBuf::from("proj/old.md")]);
+        assert!(found("kafka").is_empty());
+    }
+
+    #[test]
+    fn test_aliases() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let write = |rel: &str, content: &str| {
+            let path = tmp.path().join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "tech/kubernetes.md",
+            "---\naliases: [k8s, kube]\n---\n# K\n",
+        );
+        write("tech/kubectl.md", "---\naliases:\n  - kube\n  - ctl\n---\n");
+        write("ctl.md", "# Control\n");
+
+        assert_eq!(
+            aliases(tmp.path()).into_iter().collect::<Vec<_>>(),
+            [
+                ("ctl".to_string(), PathBuf::from("tech/kubectl.md")),
+                ("k8s".to_string(), PathBuf::from("tech/kubernetes.md")),
+                ("kube".to_string(), PathBuf::from("tech/kubectl.md")),
+            ]
+        );
+        assert_eq!(
+            resolve_alias(tmp.path(), "k8s"),
+            Some(PathBuf::from("tech/kubernetes.md"))
+        );
+        assert_eq!(resolve_alias(tmp.path(), "ctl"), None);
+        assert_eq!(resolve_alias(tmp.path(), "nope"), None);
+    }
+
+    #[test]
+    fn test_notes_skips_hidden_and_non_markdown() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "sql/joins.md");
+        write(tmp.path(), "inbox.md");
+        write(tmp.path(), ".templates/note.md");
+        write(tmp.path(), "assets/diagram.png");
+
+        assert_eq!(
+            notes(tmp.path()),
+            [PathBuf::from("inbox.md"), PathBuf::from("sql/joins.md")]
+        );
+    }
+
+    #[test]
+    fn test_notes_with_hidden() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "inbox.md");
+        write(tmp.path(), ".secret.md");
+        write(tmp.path(), ".private/diary.md");
+        write(tmp.path(), ".trash/old.md");
+        write(tmp.path(), ".git/x.md");
+        let all = [
+            PathBuf::from(".private/diary.md"),
+            PathBuf::from(".secret.md"),
+            PathBuf::from("inbox.md"),
+        ];
+        assert_eq!(notes(tmp.path()), all);
+        assert_eq!(visible_notes(tmp.path()), [PathBuf::from("inbox.md")]);
+
+        set_show_hidden(true);
+        let shown = visible_notes(tmp.path());
+        set_show_hidden(false);
+        assert_eq!(shown, all);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_notes_follow_symlinks() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let vault = tmp.path().join("vault");
+        write(&vault, "index.md");
+        write(tmp.path(), "team/shared.md");
+        std::os::unix::fs::symlink(tmp.path().join("team"), vault.join("team")).unwrap();
+        // A link back up the tree
+        std::os::unix::fs::symlink(&vault, tmp.path().join("team/vault")).unwrap();
+
+        assert_eq!(notes(&vault), [PathBuf::from("index.md")]);
+
+        set_follow_symlinks(true);
+        let followed = notes(&vault);
+        set_follow_symlinks(false);
+        assert_eq!(followed, ["index.md", "team/shared.md"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn test_glob_single_segment() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "projects/foo/a.md");
+        write(tmp.path(), "projects/foo/b.md");
+        write(tmp.path(), "projects/foo/deep/c.md");
+        write(tmp.path(), "projects/bar/d.md");
+
+        assert_eq!(
+            glob(tmp.path(), "projects/foo/*"),
+            [
+                PathBuf::from("projects/foo/a.md"),
+                PathBuf::from("projects/foo/b.md")
+            ]
+        );
+        assert_eq!(
+            glob(tmp.path(), "projects/*/?"),
+            [
+                PathBuf::from("projects/bar/d.md"),
+                PathBuf::from("projects/foo/a.md"),
+                PathBuf::from("projects/foo/b.md")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_glob_double_star() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "projects/foo/a.md");
+        write(tmp.path(), "projects/foo/deep/c.md");
+        write(tmp.path(), "other.md");
+
+        assert_eq!(
+            glob(tmp.path(), "projects/**/*.md"),
+            [
+                PathBuf::from("projects/foo/a.md"),
+                PathBuf::from("projects/foo/deep/c.md")
+            ]
+        );
+        assert_eq!(glob(tmp.path(), "**").len(), 3);
+    }
+
+    #[test]
+    fn test_last_note_most_recently_modified() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "a.md");
+        write(tmp.path(), "b.md");
+        set_mtime(&tmp.path().join("a.md"), 100);
+        set_mtime(&tmp.path().join("b.md"), 10);
+
+        assert_eq!(last_note(tmp.path()), Some(tmp.path().join("b.md")));
+    }
+
+    #[test]
+    fn test_last_note_prefers_recently_opened() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "a.md");
+        write(tmp.path(), "b.md");
+        set_mtime(&tmp.path().join("a.md"), 100);
+        set_mtime(&tmp.path().join("b.md"), 50);
+
+        record_opened(tmp.path(), &tmp.path().join("a.md"));
+
+        assert_eq!(last_note(tmp.path()), Some(tmp.path().join("a.md")));
+    }
+
+    #[test]
+    fn test_opened_history_most_recent_first_deduplicated() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "a.md");
+        write(tmp.path(), "b.md");
+        write(tmp.path(), "gone.md");
+
+        record_opened(tmp.path(), &tmp.path().join("a.md"));
+        record_opened(tmp.path(), &tmp.path().join("gone.md"));
+        record_opened(tmp.path(), &tmp.path().join("b.md"));
+        record_opened(tmp.path(), &tmp.path().join("a.md"));
+        fs::remove_file(tmp.path().join("gone.md")).unwrap();
+
+        assert_eq!(
+            opened_history(tmp.path()),
+            [PathBuf::from("a.md"), PathBuf::from("b.md")]
+        );
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "a.md");
+        for _ in 0..HISTORY_LIMIT + 5 {
+            record_opened(tmp.path(), &tmp.path().join("a.md"));
+        }
+        assert_eq!(read_history(tmp.path()).len(), HISTORY_LIMIT);
+    }
+
+    #[test]
+    fn test_recently_modified_order() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "a.md");
+        write(tmp.path(), "b.md");
+        set_mtime(&tmp.path().join("a.md"), 10);
+        set_mtime(&tmp.path().join("b.md"), 100);
+
+        assert_eq!(
+            recently_modified(tmp.path()),
+            [PathBuf::from("a.md"), PathBuf::from("b.md")]
+        );
+    }
+
+    #[test]
+    fn test_last_note_empty_vault() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        assert_eq!(last_note(tmp.path()), None);
+    }
+
+    #[test]
+    fn test_expired() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let note = |rel: &str, content: &str| {
+            let path = tmp.path().join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        note("trip.md", "---\nexpires: 2026-06-01\n---\n# Trip\n");
+        note("prep/interview.md", "---\nexpires: '2026-10-18'\n---\n");
+        note("later.md", "---\nexpires: 2026-10-19\n---\n");
+        note("bad.md", "---\nexpires: soon\n---\n");
+        note("plain.md", "# expires: 2020-01-01\n");
+
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        assert_eq!(
+            expired(tmp.path(), date("2026-10-18")),
+            [
+                (PathBuf::from("prep/interview.md"), date("2026-10-18")),
+                (PathBuf::from("trip.md"), date("2026-06-01")),
+            ]
+        );
+        assert_eq!(
+            expires("---\nexpires: soon\n---\n"),
+            Some(Err("soon".to_string()))
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commit::git;
+use crate::{links, share, vault};
+
+/// Extensions of files that look like notes but that kno ignores.
+const NOTE_LIKE_EXTENSIONS: &[&str] = &["markdown", "mdown", "mkd", "txt"];
+
+/// File names Windows reserves for devices, with or without an extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Characters Windows doesn't allow in file names.
+const RESERVED_CHARS: &[char] = &['<', '>', ':', '"', '|', '?', '*', '\\'];
+
+/// Longest path, relative to the notes dir, that stays under Windows' 260
+/// character limit with room for the notes dir itself.
+const MAX_PATH_CHARS: usize = 200;
+
+/// Longest file name most file systems allow, in bytes.
+const MAX_NAME_BYTES: usize = 255;
+
+/// A problem found in the vault, with a suggestion for fixing it.
+#[derive(Debug, PartialEq)]
+pub struct Issue {
+    pub path: PathBuf,
+    pub problem: String,
+    pub fix: String,
+    /// Where `kno verify --portable --fix` renames the file to.
+    pub rename: Option<PathBuf>,
+}
+
+impl Issue {
+    pub fn new(path: &Path, problem: impl Into<String>, fix: impl Into<String>) -> Issue {
+        Issue {
+            path: path.to_path_buf(),
+            problem: problem.into(),
+            fix: fix.into(),
+            rename: None,
+        }
+    }
+}
+
+/// Every file in the vault outside dot-directories, relative to the notes dir.
+fn files(notes_dir: &Path, dir: &Path, ancestors: &mut Vec<PathBuf>, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        match path.is_dir() {
+            true => {
+                if let Some(real) = vault::descend(&path, ancestors) {
+                    ancestors.push(real);
+                    files(notes_dir, &path, ancestors, out);
+                    ancestors.pop();
+                }
+            }
+            false => out.push(path.strip_prefix(notes_dir).unwrap().to_path_buf()),
+        }
+    }
+}
+
+fn has_conflict_markers(content: &str) -> bool {
+    let mut lines = content.lines();
+    lines.any(|l| l.starts_with("<<<<<<< "))
+        && lines.any(|l| l == "=======")
+        && lines.any(|l| l.starts_with(">>>>>>> "))
+}
+
+fn check_note(notes_dir: &Path, rel: &Path, issues: &mut Vec<Issue>) {
+    let Ok(bytes) = fs::read(notes_dir.join(rel)) else {
+        issues.push(Issue::new(
+            rel,
+            "unreadable",
+            "check the file's permissions",
+        ));
+        return;
+    };
+    if bytes.is_empty() {
+        issues.push(Issue::new(
+            rel,
+            "empty note (zero bytes)",
+            "delete it or add content",
+        ));
+        return;
+    }
+    match String::from_utf8(bytes) {
+        Err(e) => issues.push(Issue::new(
+            rel,
+            format!("invalid UTF-8 at byte {}", e.utf8_error().valid_up_to()),
+            "re-save the file as UTF-8 (e.g. iconv -t UTF-8)",
+        )),
+        Ok(content) if has_conflict_markers(&content) => issues.push(Issue::new(
+            rel,
+            "unresolved merge conflict markers",
+            "edit the note to resolve the conflict, then commit",
+        )),
+        Ok(content) => {
+            if let Some(Err(value)) = vault::expires(&content) {
+                issues.push(Issue::new(
+                    rel,
+                    format!("expires is not a date: {value}"),
+                    "use YYYY-MM-DD so kno gc can trash the note when it expires",
+                ));
+            }
+            check_links(notes_dir, rel, &content, issues);
+        }
+    }
+}
+
+fn check_links(notes_dir: &Path, rel: &Path, content: &str, issues: &mut Vec<Issue>) {
+    for target in links::targets(rel, content) {
+        if !notes_dir.join(&target).exists() {
+            issues.push(Issue::new(
+                rel,
+                format!("broken link to {}", target.display()),
+                "fix the link, or create the note it points to",
+            ));
+        }
+    }
+}
+
+fn all_files(notes_dir: &Path) -> Vec<PathBuf> {
+    let mut all = Vec::new();
+    files(
+        notes_dir,
+        notes_dir,
+        &mut vault::walk_root(notes_dir),
+        &mut all,
+    );
+    all.sort();
+    all
+}
+
+/// Problems with one file or directory name on other platforms, and the name
+/// that avoids them.
+fn portable_name(name: &str, problems: &mut Vec<String>) -> String {
+    let mut fixed: String = name
+        .chars()
+        .map(|c| match RESERVED_CHARS.contains(&c) || c.is_control() {
+            true => '-',
+            false => c,
+        })
+        .collect();
+    if fixed != name {
+        problems.push(format!("{name:?} has characters Windows doesn't allow"));
+    }
+    if fixed.ends_with(['.', ' ']) {
+        problems.push(format!("{name:?} ends in a dot or space"));
+        fixed = fixed.trim_end_matches(['.', ' ']).to_string();
+    }
+    let (stem, rest) = fixed.split_at(fixed.find('.').unwrap_or(fixed.len()));
+    if RESERVED_NAMES.contains(&stem.to_uppercase().as_str()) {
+        problems.push(format!("{name:?} is a reserved name on Windows"));
+        fixed = format!("{stem}_{rest}");
+    }
+    if name.len() > MAX_NAME_BYTES {
+        problems.push(format!("{name:?} is longer than {MAX_NAME_BYTES} bytes"));
+    }
+    fixed
+}
+
+/// `path` with its file stem `cut` characters shorter.
+fn shortened(path: &Path, cut: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let keep = stem.chars().count().saturating_sub(cut).max(1);
+    let mut out = path.with_file_name(stem.chars().take(keep).collect::<String>());
+    if let Some(ext) = path.extension() {
+        out.set_extension(ext);
+    }
+    out
+}
+
+/// `path` with `-n` added to its file stem.
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut out = path.with_file_name(format!("{stem}-{n}"));
+    if let Some(ext) = path.extension() {
+        out.set_extension(ext);
+    }
+    out
+}
+
+/// Check file names for things that break on other platforms or sync
+/// services: names differing only in case, trailing dots or spaces, reserved
+/// Windows names and characters, and overly long names and paths. Each issue
+/// suggests a rename that avoids the problem.
+pub fn portable(notes_dir: &Path) -> Vec<Issue> {
+    let all = all_files(notes_dir);
+    let lowercase = |p: &Path| p.to_string_lossy().to_lowercase();
+    let mut taken: HashSet<String> = all.iter().map(|p| lowercase(p)).collect();
+    let mut first_with_case: HashMap<String, &PathBuf> = HashMap::new();
+
+    let mut issues = Vec::new();
+    for rel in &all {
+        let mut problems = Vec::new();
+        let mut fixed: PathBuf = rel
+            .iter()
+            .map(|name| portable_name(&name.to_string_lossy(), &mut problems))
+            .collect();
+        let chars = fixed.to_string_lossy().chars().count();
+        if chars > MAX_PATH_CHARS {
+            problems.push(format!("path is {chars} characters, over {MAX_PATH_CHARS}"));
+            fixed = shortened(&fixed, chars - MAX_PATH_CHARS);
+        }
+        match first_with_case.get(&lowercase(rel)) {
+            Some(other) => problems.push(format!("differs only in case from {}", other.display())),
+            None => {
+                first_with_case.insert(lowercase(rel), rel);
+            }
+        }
+        if problems.is_empty() {
+            continue;
+        }
+
+        let mut n = 1;
+        let base = fixed.clone();
+        while fixed == *rel || taken.contains(&lowercase(&fixed)) {
+            n += 1;
+            fixed = numbered(&base, n);
+        }
+        taken.insert(lowercase(&fixed));
+        issues.push(Issue {
+            path: rel.clone(),
+            problem: problems.join("; "),
+            fix: format!(
+                "rename it to {}, or run kno verify --portable --fix",
+                fixed.display()
+            ),
+            rename: Some(fixed),
+        });
+    }
+    issues
+}
+
+/// Check every file in the vault, and kno's own state files, for problems.
+pub fn check(notes_dir: &Path) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let all = all_files(notes_dir);
+    for rel in &all {
+        let ext = rel.extension().map(|e| e.to_string_lossy().to_lowercase());
+        match ext.as_deref() {
+            Some("md") => check_note(notes_dir, rel, &mut issues),
+            Some(ext) if NOTE_LIKE_EXTENSIONS.contains(&ext) => issues.push(Issue::new(
+                rel,
+                format!("note-like .{ext} file that kno ignores"),
+                format!("rename it to {}", rel.with_extension("md").display()),
+            )),
+            _ => {}
+        }
+    }
+
+    for gist in share::read_gists(notes_dir) {
+        if !notes_dir.join(&gist.path).is_file() {
+            issues.push(Issue::new(
+                Path::new(share::GISTS_FILE_NAME),
+                format!(
+                    "gist {} is for missing note {}",
+                    gist.id,
+                    gist.path.display()
+                ),
+                "remove the line, or restore the note to keep updating the gist",
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Entries in the history file for notes that no longer exist. Harmless, so
+/// they're reported but never fail `kno verify`.
+pub fn stale_history(notes_dir: &Path) -> usize {
+    vault::read_history(notes_dir)
+        .into_iter()
+        .filter(|(_, rel)| !notes_dir.join(rel).is_file())
+        .count()
+}
+
+/// Check the notes staged for commit, as staged, for what shouldn't be
+/// committed: conflict markers and broken links. Run by the pre-commit hook.
+pub fn check_staged(notes_dir: &Path) -> Result<Vec<Issue>, String> {
+    let staged = git(
+        notes_dir,
+        &[
+            "-c",
+            "core.quotePath=false",
+            "diff",
+            "--cached",
+            "--name-only",
+            "--diff-filter=ACMR",
+            "--",
+            "*.md",
+        ],
+    )?;
+    let mut issues = Vec::new();
+    for rel in staged.lines().map(PathBuf::from) {
+        let content = git(notes_dir, &["show", &format!(":{}", rel.display())])?;
+        match has_conflict_markers(&content) {
+            true => issues.push(Issue::new(
+                &rel,
+                "unresolved merge conflict markers",
+                "edit the note to resolve the conflict, then commit",
+            )),
+            false => check_links(notes_dir, &rel, &content, &mut issues),
+        }
+    }
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, rel: &str, content: &[u8]) {
+        let path = root.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_conflict_markers() {
+        assert!(has_conflict_markers(
+            "<<<<<<< HEAD\na\n=======\nb\n>>>>>>> origin/main\n"
+        ));
+        assert!(!has_conflict_markers("# Title\n=======\n"));
+    }
+
+    #[test]
+    fn test_check_reports_problems() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "ok.md", b"# Ok\n");
+        write(tmp.path(), "my note.md", b"# Mine\n");
+        write(
+            tmp.path(),
+            "index.md",
+            b"[ok](ok.md) [mine](my%20note.md) [gone](sql/gone.md#x)\n",
+        );
+        write(tmp.path(), "empty.md", b"");
+        write(tmp.path(), "latin1.md", b"caf\xe9\n");
+        write(
+            tmp.path(),
+            "sql/joins.md",
+            b"<<<<<<< a\nx\n=======\ny\n>>>>>>> b\n",
+        );
+        write(tmp.path(), "old.txt", b"text");
+        write(tmp.path(), "trip.md", b"---\nexpires: soon\n---\n");
+        write(tmp.path(), "assets/diagram.png", b"\x89PNG");
+        write(tmp.path(), ".git/objects/x", b"");
+        write(tmp.path(), ".history", b"1\tok.md\n2\tgone.md\n");
+
+        let issues = check(tmp.path());
+        let problems: Vec<_> = issues
+            .iter()
+            .map(|i| (i.path.to_str().unwrap(), i.problem.as_str()))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                ("empty.md", "empty note (zero bytes)"),
+                ("index.md", "broken link to sql/gone.md"),
+                ("latin1.md", "invalid UTF-8 at byte 3"),
+                ("old.txt", "note-like .txt file that kno ignores"),
+                ("sql/joins.md", "unresolved merge conflict markers"),
+                ("trip.md", "expires is not a date: soon"),
+            ]
+        );
+        assert_eq!(issues[3].fix, "rename it to old.md");
+        assert_eq!(stale_history(tmp.path()), 1);
+    }
+
+    #[test]
+    fn test_check_staged() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let dir = tmp.path();
+        git(dir, &["init", "-q"]).unwrap();
+        write(dir, "ok.md", b"# Ok\n");
+        write(dir, "index.md", b"[ok](ok.md) [gone](gone.md)\n");
+        write(dir, "merge.md", b"<<<<<<< a\nx\n=======\ny\n>>>>>>> b\n");
+        write(dir, "unstaged.md", b"[gone](gone.md)\n");
+        write(dir, ".history", b"1\tmissing.md\n");
+        git(dir, &["add", "ok.md", "index.md", "merge.md"]).unwrap();
+        // What's staged counts, not later edits
+        write(dir, "index.md", b"[ok](ok.md)\n");
+
+        let issues = check_staged(dir).unwrap();
+        let problems: Vec<_> = issues
+            .iter()
+            .map(|i| (i.path.to_str().unwrap(), i.problem.as_str()))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                ("index.md", "broken link to gone.md"),
+                ("merge.md", "unresolved merge conflict markers"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_portable() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let long = "x".repeat(210);
+        write(tmp.path(), "ok.md", b"");
+        write(tmp.path(), "Joins.md", b"");
+        write(tmp.path(), "joins.md", b"");
+        write(tmp.path(), "ideas. /list.md", b"");
+        write(tmp.path(), "con.md", b"");
+        write(tmp.path(), "what?.md", b"");
+        write(tmp.path(), &format!("{long}.md"), b"");
+
+        let issues = portable(tmp.path());
+        let found: Vec<_> = issues
+            .iter()
+            .map(|i| {
+                (
+                    i.path.to_string_lossy().into_owned(),
+                    i.problem.as_str(),
+                    i.rename.clone().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "con.md".to_string(),
+                    "\"con.md\" is a reserved name on Windows",
+                    PathBuf::from("con_.md")
+                ),
+                (
+                    "ideas. /list.md".to_string(),
+                    "\"ideas. \" ends in a dot or space",
+                    PathBuf::from("ideas/list.md")
+                ),
+                (
+                    "joins.md".to_string(),
+                    "differs only in case from Joins.md",
+                    PathBuf::from("joins-2.md")
+                ),
+                (
+                    "what?.md".to_string(),
+                    "\"what?.md\" has characters Windows doesn't allow",
+                    PathBuf::from("what-.md")
+                ),
+                (
+                    format!("{long}.md"),
+                    "path is 213 characters, over 200",
+                    PathBuf::from(format!("{}.md", "x".repeat(197)))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_clean_vault() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        write(tmp.path(), "ok.md", b"# Ok\n");
+        assert!(check(tmp.path()).is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::Deserialize;
+
+/// Directory inside the notes dir holding weekly notes.
+pub const WEEKLY_DIR_NAME: &str = "weekly";
+
+/// How weeks are numbered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Numbering {
+    /// ISO 8601: week 1 is the week with the year's first Thursday, and a
+    /// week is numbered by its middle day.
+    #[default]
+    Iso,
+    /// US: week 1 is the week containing January 1st, and a week belongs to
+    /// the year its last day falls in.
+    Us,
+}
+
+/// The week of `date`: the first day of its week given `start`, and its
+/// (year, number) under `numbering`.
+#[derive(Debug, PartialEq)]
+pub struct Week {
+    pub start: NaiveDate,
+    pub year: i32,
+    pub number: u32,
+}
+
+impl Week {
+    pub fn of(date: NaiveDate, start: Weekday, numbering: Numbering) -> Week {
+        let first = date - Days::new(days_since(start, date.weekday()));
+        let (year, number) = match numbering {
+            Numbering::Iso => {
+                let iso = (first + Days::new(3)).iso_week();
+                (iso.year(), iso.week())
+            }
+            Numbering::Us => {
+                let last = first + Days::new(6);
+                let jan1 = NaiveDate::from_ymd_opt(last.year(), 1, 1).unwrap();
+                let week1 = jan1 - Days::new(days_since(start, jan1.weekday()));
+                (last.year(), ((last - week1).num_days() / 7 + 1) as u32)
+            }
+        };
+        Week {
+            start: first,
+            year,
+            number,
+        }
+    }
+
+    pub fn end(&self) -> NaiveDate {
+        self.start + Days::new(6)
+    }
+
+    /// `2026-W07`
+    pub fn label(&self) -> String {
+        format!("{}-W{:02}", self.year, self.number)
+    }
+
+    /// Where the week's note lives, relative to the notes dir.
+    pub fn note_path(&self) -> PathBuf {
+        PathBuf::from(WEEKLY_DIR_NAME).join(format!("{}.md", self.label()))
+    }
+}
+
+/// Days from the most recent `start` up to `day` (0-6).
+fn days_since(start: Weekday, day: Weekday) -> u64 {
+    u64::from((day.num_days_from_monday() + 7 - start.num_days_from_monday()) % 7)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_iso_weeks() {
+        let week = Week::of(date("2026-02-15"), Weekday::Mon, Numbering::Iso);
+        assert_eq!(week.start, date("2026-02-09"));
+        assert_eq!(week.end(), date("2026-02-15"));
+        assert_eq!(week.label(), "2026-W07");
+
+        // Dec 31 2024 is in ISO week 1 of 2025
+        let week = Week::of(date("2024-12-31"), Weekday::Mon, Numbering::Iso);
+        assert_eq!(week.label(), "2025-W01");
+        assert_eq!(week.note_path(), PathBuf::from("weekly/2025-W01.md"));
+    }
+
+    #[test]
+    fn test_us_weeks() {
+        // Sunday Feb 15 2026 starts a new US week
+        let week = Week::of(date("2026-02-15"), Weekday::Sun, Numbering::Us);
+        assert_eq!(week.start, date("2026-02-15"));
+        assert_eq!(week.label(), "2026-W08");
+
+        // The week of Sunday Dec 28 2025 contains Jan 1 2026: week 1
+        let week = Week::of(date("2025-12-29"), Weekday::Sun, Numbering::Us);
+        assert_eq!(week.start, date("2025-12-28"));
+        assert_eq!(week.label(), "2026-W01");
+
+        let week = Week::of(date("2026-01-04"), Weekday::Sun, Numbering::Us);
+        assert_eq!(week.label(), "2026-W02");
+    }
+}